@@ -8,6 +8,7 @@ use hir::{
 };
 
 use crate::indent::IndentWriter;
+use crate::source_map::SourcePos;
 use crate::BodyCtx;
 
 #[allow(dead_code)]
@@ -78,6 +79,15 @@ pub enum JsExpr {
     Throw {
         expr: Box<JsExpr>,
     },
+    Loop {
+        body: Box<JsExpr>,
+    },
+    Continue,
+    /// An expression which is mapped to `pos` in the source map.
+    Source {
+        pos: SourcePos,
+        expr: Box<JsExpr>,
+    },
 }
 
 pub enum Arg {
@@ -86,8 +96,17 @@ pub enum Arg {
 }
 
 impl JsExpr {
+    /// The expression without the source positions it is wrapped in.
+    pub fn unmapped(&self) -> &JsExpr {
+        match self {
+            | Self::Source { expr, .. } => expr.unmapped(),
+            | _ => self,
+        }
+    }
+
     pub fn is_inline(&self) -> bool {
         match self {
+            | Self::Source { expr, .. } => expr.is_inline(),
             | Self::Undefined | Self::Ident { .. } | Self::Literal { .. } => true,
             | Self::Field { base, .. } | Self::Index { base, .. } => base.is_inline(),
             | Self::Array { exprs } => exprs.iter().all(Self::is_inline),
@@ -112,7 +131,7 @@ impl JsExpr {
             return true;
         }
 
-        match self {
+        match self.unmapped() {
             | Self::Ident { .. } | Self::Field { .. } | Self::Index { .. } => true,
             | _ => false,
         }
@@ -125,7 +144,9 @@ impl JsExpr {
             | Self::Var { .. }
             | Self::Lambda { .. }
             | Self::Return { .. }
-            | Self::Throw { .. } => true,
+            | Self::Throw { .. }
+            | Self::Loop { .. }
+            | Self::Continue => true,
             | Self::Field { base, .. } | Self::Index { base, .. } => base.is_effectful(),
             | Self::BinOp { lhs, rhs, .. } => lhs.is_effectful() || rhs.is_effectful(),
             | Self::UnOp { rhs, .. } => rhs.is_effectful(),
@@ -142,17 +163,61 @@ impl JsExpr {
                 else_: None,
             } => cond.is_effectful() || then.is_effectful(),
             | Self::Block { exprs } => exprs.iter().any(Self::is_effectful),
+            | Self::Source { expr, .. } => expr.is_effectful(),
             | _ => false,
         }
     }
 
     pub fn is_terminator(&self) -> bool {
         match self {
-            | Self::Return { .. } | Self::Throw { .. } => true,
+            | Self::Return { .. } | Self::Throw { .. } | Self::Continue => true,
+            | Self::Source { expr, .. } => expr.is_terminator(),
             | _ => false,
         }
     }
 
+    pub fn walk(&self, mut f: impl FnMut(&JsExpr)) {
+        match self {
+            | Self::Undefined | Self::Ident { .. } | Self::Literal { .. } | Self::Continue => {},
+            | Self::Field { base, .. } => f(base),
+            | Self::Index { base, idx } => {
+                f(base);
+                f(idx);
+            },
+            | Self::Call { base, args } => {
+                f(base);
+                args.iter().for_each(f);
+            },
+            | Self::Assign { place, expr } => {
+                f(place);
+                f(expr);
+            },
+            | Self::BinOp { lhs, rhs, .. } => {
+                f(lhs);
+                f(rhs);
+            },
+            | Self::UnOp { rhs, .. } => f(rhs),
+            | Self::New { args, .. } => args.iter().for_each(f),
+            | Self::Array { exprs } | Self::Block { exprs } => exprs.iter().for_each(f),
+            | Self::Object { fields } => fields.iter().for_each(|(_, e)| f(e)),
+            | Self::If { cond, then, else_ } => {
+                f(cond);
+                f(then);
+
+                if let Some(else_) = else_ {
+                    f(else_);
+                }
+            },
+            | Self::Var { expr, .. } => {
+                if let Some(expr) = expr {
+                    f(expr);
+                }
+            },
+            | Self::Lambda { body, .. } | Self::Loop { body } => f(body),
+            | Self::Return { expr } | Self::Throw { expr } | Self::Source { expr, .. } => f(expr),
+        }
+    }
+
     pub fn write<W: Write>(&self, out: &mut IndentWriter<W>, in_block: bool) -> io::Result<()> {
         if !self.is_inline() && !in_block {
             writeln!(out, "(function() {{")?;
@@ -169,12 +234,29 @@ impl JsExpr {
         Ok(())
     }
 
+    /// Write an operand of a binary operator, in parentheses when it is an operator itself.
+    fn write_operand<W: Write>(&self, out: &mut IndentWriter<W>) -> io::Result<()> {
+        match self.unmapped() {
+            | JsExpr::BinOp { .. } | JsExpr::If { .. } => {
+                write!(out, "(")?;
+                self.write_inner(out, false)?;
+                write!(out, ")")
+            },
+            | _ => self.write_inner(out, false),
+        }
+    }
+
     fn write_inner<W: Write>(&self, out: &mut IndentWriter<W>, in_block: bool) -> io::Result<()> {
         match self {
             | JsExpr::Undefined => write!(out, "undefined"),
             | JsExpr::Literal { lit } => match lit {
                 | Literal::Int(v) => write!(out, "{}", v),
-                | Literal::Float(v) => write!(out, "{}", v),
+                | Literal::Float(v) => match f64::from_bits(*v) {
+                    | v if v.is_nan() => write!(out, "NaN"),
+                    | v if v.is_infinite() && v > 0.0 => write!(out, "Infinity"),
+                    | v if v.is_infinite() => write!(out, "-Infinity"),
+                    | v => write!(out, "{:?}", v),
+                },
                 | Literal::Char(v) => write!(out, "{:?}", v),
                 | Literal::String(v) => write!(out, "{:?}", v),
             },
@@ -195,9 +277,9 @@ impl JsExpr {
                 expr.write_inner(out, false)
             },
             | JsExpr::BinOp { lhs, rhs, op } => {
-                lhs.write_inner(out, false)?;
+                lhs.write_operand(out)?;
                 write!(out, " {} ", op)?;
-                rhs.write_inner(out, false)
+                rhs.write_operand(out)
             },
             | JsExpr::UnOp { op, rhs } => {
                 write!(out, "{}", op)?;
@@ -381,6 +463,18 @@ impl JsExpr {
                 write!(out, "throw ")?;
                 expr.write_inner(out, false)
             },
+            | JsExpr::Loop { body } => {
+                writeln!(out, "while (true) {{")?;
+                out.indent();
+                body.write_inner(out, true)?;
+                out.dedent();
+                write!(out, ";\n}}")
+            },
+            | JsExpr::Continue => write!(out, "continue"),
+            | JsExpr::Source { pos, expr } => {
+                out.map(*pos);
+                expr.write_inner(out, in_block)
+            },
         }
     }
 }
@@ -397,7 +491,7 @@ impl BodyCtx<'_, '_> {
                 let lib = self.owner.module(self.db.upcast()).lib;
                 let unit = self.db.lang_item(lib, "unit-type").unwrap();
                 let unit = TypeCtor::from(unit.as_type_ctor().unwrap());
-                let unit = self.mangle((unit.ctors(self.db)[0].path(self.db).to_string(), true));
+                let unit = self.ctor_ref(unit.ctors(self.db)[0]);
 
                 JsExpr::Ident { name: unit }
             },
@@ -419,9 +513,16 @@ impl BodyCtx<'_, '_> {
                 }
 
                 args.reverse();
-                self.lower_app(Arg::ExprId(base), args, block)
+
+                let res = self.lower_app(Arg::ExprId(base), args, block);
+
+                self.mapped(expr, res)
+            },
+            | Expr::Infix { ref exprs, ref ops } => {
+                let res = self.lower_expr_infix(expr, exprs, ops, block);
+
+                self.mapped(expr, res)
             },
-            | Expr::Infix { ref exprs, ref ops } => self.lower_expr_infix(expr, exprs, ops, block),
             | Expr::Field { base, ref field } => JsExpr::Field {
                 base: Box::new(self.lower_expr(base, block)),
                 field: field.to_string(),
@@ -463,15 +564,16 @@ impl BodyCtx<'_, '_> {
 
                 self.in_lambda.push(name);
 
-                let expr = Box::new(self.lower_expr(body, &mut exprs));
-                let _ = exprs.push(JsExpr::Return { expr });
-                let body = Box::new(JsExpr::Block { exprs });
+                let expr = self.lower_expr(body, &mut exprs);
+                let _ = exprs.push(expr);
+                let body = self.wrap_return(JsExpr::Block { exprs });
                 let name = self.in_lambda.pop().unwrap();
+                let (params, body) = self.optimize_recur(&name, params, body);
 
                 block.push(JsExpr::Lambda {
                     name: Some(name.clone()),
                     params,
-                    body,
+                    body: Box::new(body),
                 });
 
                 JsExpr::Ident { name }
@@ -504,7 +606,7 @@ impl BodyCtx<'_, '_> {
         let lib = self.owner.module(self.db.upcast()).lib;
         let bool = self.db.lang_item(lib, "bool-type").unwrap();
         let bool = TypeCtor::from(bool.as_type_ctor().unwrap());
-        let true_ = self.mangle((bool.ctors(self.db)[1].path(self.db).to_string(), true));
+        let true_ = self.ctor_ref(bool.ctors(self.db)[1]);
 
         JsExpr::BinOp {
             op: "instanceof",
@@ -681,6 +783,7 @@ impl BodyCtx<'_, '_> {
                 },
                 | Stmt::Expr { expr } => {
                     let res = self.lower_expr(expr, exprs);
+                    let res = self.mapped(expr, res);
 
                     exprs.push(res);
                 },
@@ -786,7 +889,7 @@ impl BodyCtx<'_, '_> {
     ) -> JsExpr {
         let lower = self.db.lower_member(member.into());
         let record = JsExpr::Ident {
-            name: self.mangle((member.link_name(self.db), true)),
+            name: self.member_name(member),
         };
 
         let skip = lower
@@ -946,7 +1049,9 @@ impl BodyCtx<'_, '_> {
                 let func = Func::from(id);
 
                 if func.is_intrinsic(self.db) {
-                    return self.lower_intrinsic(&func.name(self.db).to_string(), args, block);
+                    let types = self.infer.instances.get(&expr).cloned().unwrap_or_default();
+
+                    return self.lower_intrinsic(&func.name(self.db).to_string(), &types, args, block);
                 }
 
                 (
@@ -958,7 +1063,7 @@ impl BodyCtx<'_, '_> {
                 let args = args.into_iter().map(|a| self.lower_arg(a, block)).collect();
 
                 return JsExpr::New {
-                    class: Cow::Owned(self.mangle((Ctor::from(id).path(self.db).to_string(), true))),
+                    class: Cow::Owned(self.ctor_ref(Ctor::from(id))),
                     args,
                 };
             },
@@ -1017,7 +1122,7 @@ impl BodyCtx<'_, '_> {
                     }
                 } else {
                     JsExpr::Ident {
-                        name: self.mangle(Func::from(id).link_name(self.db)),
+                        name: self.func_ref(Func::from(id)),
                     }
                 };
 
@@ -1068,13 +1173,13 @@ impl BodyCtx<'_, '_> {
                 base
             },
             | ValueNs::Const(id) => JsExpr::Ident {
-                name: self.mangle((Const::from(id).path(self.db).to_string(), true)),
+                name: self.const_ref(Const::from(id)),
             },
             | ValueNs::Static(id) => JsExpr::Ident {
-                name: self.mangle(Static::from(id).link_name(self.db)),
+                name: self.static_ref(Static::from(id)),
             },
             | ValueNs::Ctor(id) => JsExpr::New {
-                class: Cow::Owned(self.mangle((Ctor::from(id).path(self.db).to_string(), true))),
+                class: Cow::Owned(self.ctor_ref(Ctor::from(id))),
                 args: Vec::new(),
             },
             | ValueNs::Fixity(id) => {
@@ -1093,6 +1198,22 @@ impl BodyCtx<'_, '_> {
         }
     }
 
+    /// Map calls and assignments to the source of `expr`, other expressions either have no
+    /// effect or are mapped by the expressions they contain.
+    pub fn mapped(&mut self, expr: hir::ExprId, js: JsExpr) -> JsExpr {
+        if !matches!(js, JsExpr::Call { .. } | JsExpr::New { .. } | JsExpr::Assign { .. }) {
+            return js;
+        }
+
+        match self.ctx.source_pos(self.owner, expr) {
+            | Some(pos) => JsExpr::Source {
+                pos,
+                expr: Box::new(js),
+            },
+            | None => js,
+        }
+    }
+
     pub fn placed(&mut self, id: hir::ExprId, expr: JsExpr, block: &mut Vec<JsExpr>) -> JsExpr {
         if expr.is_place() {
             return expr;
@@ -1,9 +1,14 @@
 use std::io::{Result, Write};
 
+use crate::source_map::SourcePos;
+
 pub struct IndentWriter<W: Write> {
     writer: W,
     indent: usize,
     should_indent: bool,
+    line: u32,
+    col: u32,
+    mappings: Vec<((u32, u32), SourcePos)>,
 }
 
 impl<W: Write> IndentWriter<W> {
@@ -12,6 +17,9 @@ impl<W: Write> IndentWriter<W> {
             writer,
             indent: 0,
             should_indent: false,
+            line: 0,
+            col: 0,
+            mappings: Vec::new(),
         }
     }
 
@@ -22,12 +30,50 @@ impl<W: Write> IndentWriter<W> {
     pub fn dedent(&mut self) {
         self.indent -= 1;
     }
+
+    /// The zero-based line and column at which the next character will be written.
+    pub fn position(&self) -> (u32, u32) {
+        if self.should_indent {
+            (self.line, self.indent as u32 * 4)
+        } else {
+            (self.line, self.col)
+        }
+    }
+
+    /// Map the current position to `pos` in the source map.
+    pub fn map(&mut self, pos: SourcePos) {
+        let gen = self.position();
+
+        self.mappings.push((gen, pos));
+    }
+
+    pub fn take_mappings(&mut self) -> Vec<((u32, u32), SourcePos)> {
+        std::mem::take(&mut self.mappings)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn emit(&mut self, buf: &[u8]) -> Result<()> {
+        self.writer.write_all(buf)?;
+
+        for &b in buf {
+            if b == b'\n' {
+                self.line += 1;
+                self.col = 0;
+            } else {
+                self.col += 1;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<W: Write> Write for IndentWriter<W> {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
         let mut start = 0;
-        let mut written = 0;
 
         for cur in 0..buf.len() {
             if buf[cur] == b'\n' {
@@ -36,21 +82,21 @@ impl<W: Write> Write for IndentWriter<W> {
             }
 
             if self.should_indent {
-                written += self.writer.write(&buf[start..cur])?;
+                self.emit(&buf[start..cur])?;
                 start = cur;
                 self.should_indent = false;
 
                 for _ in 0..self.indent {
-                    self.writer.write(&[b' ', b' ', b' ', b' '])?;
+                    self.emit(&[b' ', b' ', b' ', b' '])?;
                 }
             }
         }
 
         if start < buf.len() {
-            written += self.writer.write(&buf[start..])?;
+            self.emit(&buf[start..])?;
         }
 
-        Ok(written)
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> Result<()> {
@@ -1,5 +1,5 @@
 use hir::id::HasModule;
-use hir::ty::{TyKind, TypeVar};
+use hir::ty::{Ty, TyKind, TypeVar};
 use hir::{Literal, TypeCtor};
 use tracing::warn;
 
@@ -11,7 +11,10 @@ impl BodyCtx<'_, '_> {
         self.type_vars[var.idx() as usize].clone()
     }
 
-    pub fn lower_intrinsic(&mut self, name: &str, mut args: Vec<Arg>, block: &mut Vec<JsExpr>) -> JsExpr {
+    /// Lower a call to the intrinsic `name`, where `types` are the type arguments of the
+    /// intrinsic. Float32 results are rounded with `Math.fround` so that they are the same as
+    /// on native targets.
+    pub fn lower_intrinsic(&mut self, name: &str, types: &[Ty], mut args: Vec<Arg>, block: &mut Vec<JsExpr>) -> JsExpr {
         match name {
             | "transmute" => self.lower_arg(args.remove(0), block),
             | "partial" => self.lower_arg(args.remove(0), block),
@@ -74,8 +77,46 @@ impl BodyCtx<'_, '_> {
             | "imul" => self.intrinsic_binop("*", args, block),
             | "idiv" => self.intrinsic_binop("/", args, block),
             | "irem" => self.intrinsic_binop("%", args, block),
-            | "ieq" => self.intrinsic_ieq(args, block),
+            | "ieq" => self.intrinsic_cmp("==", args, block),
             | "icmp" => self.intrinsic_icmp(args, block),
+            | "fadd" | "fsub" | "fmul" | "fdiv" | "frem" => {
+                let op = match name {
+                    | "fadd" => "+",
+                    | "fsub" => "-",
+                    | "fmul" => "*",
+                    | "fdiv" => "/",
+                    | _ => "%",
+                };
+
+                let res = self.intrinsic_binop(op, args, block);
+
+                self.fround(types.first(), res)
+            },
+            | "feq" => self.intrinsic_cmp("===", args, block),
+            | "fne" => self.intrinsic_cmp("!==", args, block),
+            | "flt" => self.intrinsic_cmp("<", args, block),
+            | "fle" => self.intrinsic_cmp("<=", args, block),
+            | "fgt" => self.intrinsic_cmp(">", args, block),
+            | "fge" => self.intrinsic_cmp(">=", args, block),
+            | "fconvert" | "itof" => {
+                let arg = self.lower_arg(args.remove(0), block);
+
+                self.fround(types.get(1), arg)
+            },
+            | "ftoi" => self.intrinsic_ftoi(types.get(1), args, block),
+            | "round" => self.intrinsic_round(args, block),
+            | "floor" | "ceil" => self.intrinsic_math(name, args, block),
+            | "sqrt" => {
+                let res = self.intrinsic_math(name, args, block);
+
+                self.fround(types.first(), res)
+            },
+            | "float_to_str" => JsExpr::Call {
+                base: Box::new(JsExpr::Ident { name: "String".into() }),
+                args: vec![self.lower_arg(args.remove(0), block)],
+            },
+            // strings are garbage collected
+            | "str_free" => JsExpr::Undefined,
             | _ => {
                 warn!(target: "lower_intrinsic", "todo: {:?}", name);
                 JsExpr::Undefined
@@ -92,7 +133,98 @@ impl BodyCtx<'_, '_> {
         JsExpr::BinOp { op, lhs, rhs }
     }
 
-    fn intrinsic_ieq(&mut self, mut args: Vec<Arg>, block: &mut Vec<JsExpr>) -> JsExpr {
+    fn intrinsic_math(&mut self, func: &str, mut args: Vec<Arg>, block: &mut Vec<JsExpr>) -> JsExpr {
+        let arg = self.lower_arg(args.remove(0), block);
+
+        math(func, vec![arg])
+    }
+
+    /// `Math.round` rounds halfway cases towards positive infinity, while native targets round
+    /// them away from zero. So this rounds the absolute value and restores the sign.
+    fn intrinsic_round(&mut self, mut args: Vec<Arg>, block: &mut Vec<JsExpr>) -> JsExpr {
+        let arg = self.lower_arg(args.remove(0), block);
+        let round = |x: JsExpr| JsExpr::BinOp {
+            op: "*",
+            lhs: Box::new(math("sign", vec![x.clone()])),
+            rhs: Box::new(math("round", vec![math("abs", vec![x])])),
+        };
+
+        if arg.is_place() {
+            return round(arg);
+        }
+
+        // evaluate the argument only once
+        JsExpr::Call {
+            base: Box::new(JsExpr::Lambda {
+                name: None,
+                params: vec![String::from("x")],
+                body: Box::new(round(JsExpr::Ident { name: "x".into() })),
+            }),
+            args: vec![arg],
+        }
+    }
+
+    /// Truncate towards zero and saturate at the bounds of the integer type `int`, with NaN
+    /// converted to zero, like on native targets.
+    fn intrinsic_ftoi(&mut self, int: Option<&Ty>, mut args: Vec<Arg>, block: &mut Vec<JsExpr>) -> JsExpr {
+        let arg = self.lower_arg(args.remove(0), block);
+        let (min, max) = match self.scalar_repr(int).as_deref() {
+            | Some("u8") => (0.0, u8::MAX as f64),
+            | Some("u16") => (0.0, u16::MAX as f64),
+            | Some("u32") => (0.0, u32::MAX as f64),
+            | Some("u64" | "usize") => (0.0, u64::MAX as f64),
+            | Some("u128") => (0.0, u128::MAX as f64),
+            | Some("i8") => (i8::MIN as f64, i8::MAX as f64),
+            | Some("i16") => (i16::MIN as f64, i16::MAX as f64),
+            | Some("i32") => (i32::MIN as f64, i32::MAX as f64),
+            | Some("i128") => (i128::MIN as f64, i128::MAX as f64),
+            | _ => (i64::MIN as f64, i64::MAX as f64),
+        };
+
+        let bound = |v: f64| JsExpr::Literal {
+            lit: Literal::Float(v.to_bits()),
+        };
+
+        let trunc = math("trunc", vec![arg]);
+        let clamped = math("min", vec![math("max", vec![trunc, bound(min)]), bound(max)]);
+
+        // NaN is falsy
+        JsExpr::BinOp {
+            op: "||",
+            lhs: Box::new(clamped),
+            rhs: Box::new(JsExpr::Literal { lit: Literal::Int(0) }),
+        }
+    }
+
+    /// Round `expr` to single precision when `ty` is a 32 bit float.
+    fn fround(&self, ty: Option<&Ty>, expr: JsExpr) -> JsExpr {
+        match self.scalar_repr(ty).as_deref() {
+            | Some("f32") => math("fround", vec![expr]),
+            | _ => expr,
+        }
+    }
+
+    /// The scalar of the `@repr` attribute of the type constructor of `ty`.
+    fn scalar_repr(&self, ty: Option<&Ty>) -> Option<String> {
+        let id = match ty?.lookup(self.db) {
+            | TyKind::Ctor(id) => id,
+            | _ => return None,
+        };
+
+        let attrs = self.db.attrs(id.into());
+        let scalar = attrs
+            .by_key("repr")
+            .attrs()
+            .next()?
+            .group()?
+            .field("scalar")?
+            .string()?
+            .to_string();
+
+        Some(scalar)
+    }
+
+    fn intrinsic_cmp(&mut self, op: &'static str, mut args: Vec<Arg>, block: &mut Vec<JsExpr>) -> JsExpr {
         let rhs = args.remove(1);
         let lhs = args.remove(0);
         let lhs = Box::new(self.lower_arg(lhs, block));
@@ -100,11 +232,11 @@ impl BodyCtx<'_, '_> {
         let lib = self.owner.module(self.db.upcast()).lib;
         let bool = self.db.lang_item(lib, "bool-type").unwrap();
         let bool = TypeCtor::from(bool.as_type_ctor().unwrap());
-        let true_ = self.mangle((bool.ctors(self.db)[1].path(self.db).to_string(), true));
-        let false_ = self.mangle((bool.ctors(self.db)[0].path(self.db).to_string(), true));
+        let true_ = self.ctor_ref(bool.ctors(self.db)[1]);
+        let false_ = self.ctor_ref(bool.ctors(self.db)[0]);
 
         JsExpr::If {
-            cond: Box::new(JsExpr::BinOp { op: "==", lhs, rhs }),
+            cond: Box::new(JsExpr::BinOp { op, lhs, rhs }),
             then: Box::new(JsExpr::UnOp {
                 op: "new ",
                 rhs: Box::new(JsExpr::Ident { name: true_ }),
@@ -137,3 +269,13 @@ impl BodyCtx<'_, '_> {
         }
     }
 }
+
+fn math(func: &str, args: Vec<JsExpr>) -> JsExpr {
+    JsExpr::Call {
+        base: Box::new(JsExpr::Field {
+            base: Box::new(JsExpr::Ident { name: "Math".into() }),
+            field: func.into(),
+        }),
+        args,
+    }
+}
@@ -3,25 +3,56 @@ mod indent;
 mod intrinsic;
 mod optimize;
 mod pat;
+mod reachable;
+mod source_map;
+mod tail;
 
-use std::io::{self, BufWriter, Write};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
 use std::sync::Arc;
 
 use arena::ArenaMap;
+use base_db::input::{FileId, LineIndex};
 use hir::db::HirDatabase;
 use hir::id::{DefWithBodyId, HasModule};
 use hir::ty::{Ty, TyKind};
 use rustc_hash::FxHashMap;
 
-pub fn codegen(db: &dyn HirDatabase, module: hir::Module, file: &mut dyn Write) {
-    let mut ctx = Ctx::new(db, file);
+pub use self::reachable::Reachable;
 
+/// The name of the runtime module of a library, containing the files it links with.
+pub const RUNTIME_FILE_NAME: &str = "runtime.js";
+
+/// Emit the file containing `module` as an ES module. The first line of the output is a
+/// `//- ` comment naming the file the module should be written to.
+/// Only definitions in `reachable` are emitted.
+pub fn codegen(db: &dyn HirDatabase, module: hir::Module, reachable: &Reachable, file: &mut dyn Write) {
+    let mut ctx = Ctx::new(db, module, reachable);
+
+    ctx.collect_trampolines();
+    ctx.write_trampoline_runtime().unwrap();
     ctx.codegen(module).unwrap();
+    ctx.finish(file).unwrap();
+}
+
+/// The name of the file a top level module is emitted to.
+pub fn module_file_name(db: &dyn HirDatabase, module: hir::Module) -> String {
+    let name = module.name(db).to_string().replace(hir::Path::SEPARATOR, ".");
+
+    format!("{}.js", name)
 }
 
 struct Ctx<'a> {
     db: &'a dyn HirDatabase,
-    out: indent::IndentWriter<BufWriter<&'a mut dyn Write>>,
+    file: hir::Module,
+    reachable: &'a Reachable,
+    out: indent::IndentWriter<Vec<u8>>,
+    imports: RefCell<BTreeMap<String, BTreeSet<String>>>,
+    source_map: source_map::SourceMap,
+    line_indices: FxHashMap<FileId, Arc<LineIndex>>,
+    /// The mangled names and parameter counts of the trampolined functions in this file.
+    trampolines: FxHashMap<String, usize>,
 }
 
 struct BodyCtx<'a, 'b> {
@@ -37,13 +68,53 @@ struct BodyCtx<'a, 'b> {
 }
 
 impl<'a> Ctx<'a> {
-    pub fn new(db: &'a dyn HirDatabase, out: &'a mut dyn Write) -> Self {
+    pub fn new(db: &'a dyn HirDatabase, file: hir::Module, reachable: &'a Reachable) -> Self {
         Self {
             db,
-            out: indent::IndentWriter::new(BufWriter::new(out)),
+            file,
+            reachable,
+            out: indent::IndentWriter::new(Vec::new()),
+            imports: RefCell::default(),
+            source_map: source_map::SourceMap::default(),
+            line_indices: FxHashMap::default(),
+            trampolines: FxHashMap::default(),
         }
     }
 
+    pub fn finish(self, file: &mut dyn Write) -> io::Result<()> {
+        let name = module_file_name(self.db, self.file);
+        let imports = self.imports.into_inner();
+        let mut out = self.out;
+        let mut source_map = self.source_map;
+
+        for (gen, pos) in out.take_mappings() {
+            source_map.add(gen, pos);
+        }
+
+        writeln!(file, "//- {}", name)?;
+
+        for (from, names) in &imports {
+            let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+
+            writeln!(file, "import {{ {} }} from {:?};", names.join(", "), from)?;
+        }
+
+        file.write_all(&out.into_inner())?;
+        source_map.offset_lines(imports.len() as u32);
+
+        if !source_map.is_empty() {
+            let json = source_map.to_json(&name);
+
+            writeln!(
+                file,
+                "\n//# sourceMappingURL=data:application/json;base64,{}",
+                source_map::encode_base64(json.as_bytes())
+            )?;
+        }
+
+        file.flush()
+    }
+
     fn mangle(&self, (name, mangle): (impl AsRef<str>, bool)) -> String {
         let name = name.as_ref();
 
@@ -51,23 +122,120 @@ impl<'a> Ctx<'a> {
             return name.to_string();
         }
 
-        let mut res = String::with_capacity(name.len());
+        mangle_name(name)
+    }
 
-        for c in name.chars() {
-            match c {
-                | '\'' => res.push('$'),
-                | '.' => res.push('_'),
-                | _ => res.push(c),
-            }
+    /// Refer to a definition from `module`, importing it when it is emitted to another file.
+    fn item_ref(&self, module: hir::Module, name: String) -> String {
+        let mut file = module;
+
+        while let Some(parent) = file.parent(self.db) {
+            file = parent;
         }
 
-        res
+        if file != self.file {
+            let from = format!("../{}/{}", file.lib().name(self.db), module_file_name(self.db, file));
+
+            self.imports.borrow_mut().entry(from).or_default().insert(name.clone());
+        }
+
+        name
+    }
+
+    /// Refer to a foreign definition. Definitions with a `@link_name` refer to a global,
+    /// others are imported from the runtime module of the library they are declared in.
+    fn foreign_ref(&self, module: hir::Module, has_link_name: bool, name: String) -> String {
+        let lib = module.lib();
+
+        if !has_link_name && !self.db.libs()[lib.into()].links.is_empty() {
+            let from = format!("../{}/{}", lib.name(self.db), RUNTIME_FILE_NAME);
+
+            self.imports.borrow_mut().entry(from).or_default().insert(name.clone());
+        }
+
+        name
+    }
+
+    fn func_ref(&self, func: hir::Func) -> String {
+        let name = self.mangle(func.link_name(self.db));
+
+        if func.is_foreign(self.db) {
+            let id = hir::id::FuncId::from(func);
+            let has_link_name = self.db.attrs(id.into()).by_key("link_name").exists();
+
+            self.foreign_ref(func.module(self.db), has_link_name, name)
+        } else {
+            self.item_ref(func.module(self.db), name)
+        }
+    }
+
+    fn static_ref(&self, static_: hir::Static) -> String {
+        let name = self.mangle(static_.link_name(self.db));
+
+        if static_.is_foreign(self.db) {
+            let id = hir::id::StaticId::from(static_);
+            let has_link_name = self.db.attrs(id.into()).by_key("link_name").exists();
+
+            self.foreign_ref(static_.module(self.db), has_link_name, name)
+        } else {
+            self.item_ref(static_.module(self.db), name)
+        }
+    }
+
+    fn const_ref(&self, const_: hir::Const) -> String {
+        let name = self.mangle((const_.path(self.db).to_string(), true));
+
+        self.item_ref(const_.module(self.db), name)
+    }
+
+    fn ctor_ref(&self, ctor: hir::Ctor) -> String {
+        let name = self.mangle((ctor.path(self.db).to_string(), true));
+
+        self.item_ref(ctor.module(self.db), name)
+    }
+
+    fn member_name(&self, member: hir::Member) -> String {
+        let name = self.mangle((member.link_name(self.db), true));
+
+        self.item_ref(member.module(self.db), name)
+    }
+
+    /// Map the current position in the output to the start of the body of `def`.
+    fn map_source(&mut self, def: DefWithBodyId) {
+        let body = self.db.body(def);
+
+        if let Some(pos) = self.source_pos(def, body.body_expr()) {
+            self.out.map(pos);
+        }
+    }
+
+    /// The position of the start of `expr` in its source file.
+    fn source_pos(&mut self, def: DefWithBodyId, expr: hir::ExprId) -> Option<source_map::SourcePos> {
+        let (_, source_map) = self.db.body_source_map(def);
+        let src = source_map.expr_syntax(expr).left()?;
+        let root = self.db.source_root(self.db.file_source_root(src.file_id));
+        let path = root.path_for_file(src.file_id)?.to_string();
+        let db = self.db;
+        let line_index = self
+            .line_indices
+            .entry(src.file_id)
+            .or_insert_with(|| Arc::new(LineIndex::new(&db.file_text(src.file_id))));
+        let line_col = line_index.line_col(src.value.syntax_node_ptr().range().start());
+
+        Some(source_map::SourcePos {
+            source: self.source_map.source_id(&path),
+            line: line_col.line,
+            col: line_col.col,
+        })
     }
 
     pub fn codegen(&mut self, module: hir::Module) -> io::Result<()> {
         let modules = module.children(self.db);
-        let decls = module.declarations(self.db);
-        let members = module.members(self.db);
+        let mut decls = module.declarations(self.db);
+        let mut members = module.members(self.db);
+
+        decls.retain(|&def| self.reachable.has_def(def));
+        members.retain(|&member| self.reachable.has_member(member));
 
         for module in modules {
             self.codegen(module)?;
@@ -95,23 +263,23 @@ impl<'a> Ctx<'a> {
     pub fn register_def(&mut self, def: hir::ModuleDef) -> io::Result<()> {
         match def {
             | hir::ModuleDef::Func(f) if !f.is_foreign(self.db) && f.has_body(self.db) => {
-                writeln!(self, "var {};", self.mangle(f.link_name(self.db)))
+                writeln!(self, "export var {};", self.mangle(f.link_name(self.db)))
             },
             | hir::ModuleDef::Static(s) if !s.is_foreign(self.db) => {
-                writeln!(self, "var {};", self.mangle(s.link_name(self.db)))
+                writeln!(self, "export var {};", self.mangle(s.link_name(self.db)))
             },
             | hir::ModuleDef::Const(c) => {
-                writeln!(self, "var {};", self.mangle((c.path(self.db).to_string(), true)))
+                writeln!(self, "export var {};", self.mangle((c.path(self.db).to_string(), true)))
             },
             | hir::ModuleDef::Ctor(c) => {
-                writeln!(self, "var {};", self.mangle((c.path(self.db).to_string(), true)))
+                writeln!(self, "export var {};", self.mangle((c.path(self.db).to_string(), true)))
             },
             | _ => Ok(()),
         }
     }
 
     pub fn register_member(&mut self, def: hir::Member) -> io::Result<()> {
-        writeln!(self, "var {};", self.mangle((def.link_name(self.db), true)))
+        writeln!(self, "export var {};", self.mangle((def.link_name(self.db), true)))
     }
 
     pub fn codegen_def(&mut self, def: hir::ModuleDef) -> io::Result<()> {
@@ -128,9 +296,22 @@ impl<'a> Ctx<'a> {
         let id = hir::id::FuncId::from(func);
 
         if !func.is_foreign(self.db) && func.has_body(self.db) {
-            write!(self, "{} = ", self.mangle(func.link_name(self.db)))?;
-            self.codegen_body(id.into(), false)?;
-            writeln!(self, ";")?;
+            let name = self.mangle(func.link_name(self.db));
+
+            self.map_source(id.into());
+
+            if self.trampolines.contains_key(&name) {
+                let tc_name = tail::trampoline_name(&name);
+
+                write!(self, "var {} = ", tc_name)?;
+                self.codegen_body(id.into(), false)?;
+                writeln!(self, ";")?;
+                writeln!(self, "{} = $trampoline({});", name, tc_name)?;
+            } else {
+                write!(self, "{} = ", name)?;
+                self.codegen_body(id.into(), false)?;
+                writeln!(self, ";")?;
+            }
         }
 
         if self.db.attrs(id.into()).by_key("main").exists() {
@@ -141,6 +322,7 @@ impl<'a> Ctx<'a> {
     }
 
     pub fn codegen_const(&mut self, const_: hir::Const) -> io::Result<()> {
+        self.map_source(hir::id::ConstId::from(const_).into());
         write!(self, "{} = ", self.mangle((const_.path(self.db).to_string(), true)))?;
         self.codegen_body_expr(hir::id::ConstId::from(const_).into())?;
         writeln!(self, ";")
@@ -148,6 +330,7 @@ impl<'a> Ctx<'a> {
 
     pub fn codegen_static(&mut self, static_: hir::Static) -> io::Result<()> {
         if !static_.is_foreign(self.db) {
+            self.map_source(hir::id::StaticId::from(static_).into());
             write!(self, "{} = ", self.mangle(static_.link_name(self.db)))?;
             self.codegen_body_expr(hir::id::StaticId::from(static_).into())?;
             writeln!(self, ";")?;
@@ -263,7 +446,7 @@ impl<'a> Ctx<'a> {
                 match c {
                     | hir::MethodSource::Member(id) => {
                         let member = hir::Member::from(id);
-                        write!(self, "{}", self.mangle((member.link_name(self.db), true)))?;
+                        write!(self, "{}", self.member_name(member))?;
                     },
                     | hir::MethodSource::Record(idx, p) => {
                         write!(self, "record{}", idx)?;
@@ -278,12 +461,32 @@ impl<'a> Ctx<'a> {
                 i += 1;
             }
 
+            let mut trampolines = Vec::new();
+
             for item in items {
-                write!(self, "{}: ", self.mangle((item.name(self.db), true)))?;
+                let def = match item {
+                    | hir::AssocItem::Func(id) => DefWithBodyId::from(hir::id::FuncId::from(id)),
+                    | hir::AssocItem::Static(id) => DefWithBodyId::from(hir::id::StaticId::from(id)),
+                };
+
+                let mut name = self.mangle((item.name(self.db), true));
+
+                if let hir::AssocItem::Func(func) = item {
+                    match self.func_key(func) {
+                        | Some(key) if self.trampolines.contains_key(&key) => {
+                            name = tail::trampoline_name(&name);
+                            trampolines.push(key);
+                        },
+                        | _ => {},
+                    }
+                }
+
+                self.map_source(def);
+                write!(self, "{}: ", name)?;
 
                 match item {
-                    | hir::AssocItem::Func(id) => self.codegen_body(hir::id::FuncId::from(id).into(), true)?,
-                    | hir::AssocItem::Static(id) => self.codegen_body_expr(hir::id::StaticId::from(id).into())?,
+                    | hir::AssocItem::Func(_) => self.codegen_body(def, true)?,
+                    | hir::AssocItem::Static(_) => self.codegen_body_expr(def)?,
                 }
 
                 writeln!(self, ",")?;
@@ -296,6 +499,11 @@ impl<'a> Ctx<'a> {
                 self.out.dedent();
                 writeln!(self, "}}")?;
             }
+
+            // Trampolined items can only be wrapped once the record exists.
+            for key in trampolines {
+                writeln!(self, "{} = $trampoline({});", key, tail::trampoline_name(&key))?;
+            }
         }
 
         Ok(())
@@ -383,7 +591,7 @@ impl<'a> Ctx<'a> {
             field: bcx.mangle(("report", true)),
         };
 
-        writeln!(bcx, "const $main = () => {{")?;
+        writeln!(bcx, "export const $main = () => {{")?;
         bcx.out.indent();
 
         for var in block {
@@ -417,6 +625,7 @@ impl<'a, 'b> BodyCtx<'a, 'b> {
     pub fn lower(&mut self, in_block: bool) -> io::Result<()> {
         let expr = self.lower_expr_inline(self.body.body_expr());
         let ret = self.wrap_return(expr);
+        let ret = self.optimize_tail_calls(ret);
 
         ret.write(&mut self.out, in_block)
     }
@@ -483,6 +692,27 @@ fn for_each_record(db: &dyn HirDatabase, ty: Ty, mut f: impl FnMut() -> io::Resu
     Ok(ty)
 }
 
+/// Turns a path into a javascript identifier. The separators of the path become `$` and the
+/// other characters which can't be part of an identifier become `$` followed by a digit. A
+/// segment of a path never starts with a digit, so no two paths get the same identifier.
+/// `$9` is left for the names generated from mangled names, see [`tail::trampoline_name`].
+fn mangle_name(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        match c {
+            | hir::Path::SEPARATOR => res.push('$'),
+            | '\'' => res.push_str("$0"),
+            | '.' => res.push_str("$1"),
+            | '@' => res.push_str("$2"),
+            | '$' => res.push_str("$3"),
+            | _ => res.push(c),
+        }
+    }
+
+    res
+}
+
 impl<'a> Write for Ctx<'a> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.out.write(buf)
@@ -506,3 +736,24 @@ impl<'a, 'b> std::ops::DerefMut for BodyCtx<'a, 'b> {
         &mut self.ctx
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mangled_paths_do_not_collide() {
+        assert_eq!(mangle_name("A:b_c"), "A$b_c");
+        assert_eq!(mangle_name("A_b:c"), "A_b$c");
+        assert_ne!(mangle_name("A:b'"), mangle_name("A:b$0"));
+        assert_ne!(mangle_name("A:b.c"), mangle_name("A:b:c"));
+    }
+
+    #[test]
+    fn trampolines_do_not_collide_with_mangled_paths() {
+        let name = mangle_name("Main:go");
+
+        assert_ne!(tail::trampoline_name(&name), mangle_name("Main:go:tc"));
+        assert_ne!(tail::trampoline_name(&name), mangle_name("Main:go'tc"));
+    }
+}
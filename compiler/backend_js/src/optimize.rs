@@ -118,3 +118,232 @@ impl BodyCtx<'_, '_> {
         }
     }
 }
+
+impl BodyCtx<'_, '_> {
+    /// Turn the self tail calls of the function being lowered into a loop and, when the
+    /// function is trampolined, make its other tail calls to trampolined functions return a
+    /// `$TailCall` instead.
+    pub fn optimize_tail_calls(&self, body: JsExpr) -> JsExpr {
+        let func = match self.owner {
+            | DefWithBodyId::FuncId(id) => hir::Func::from(id),
+            | _ => return body,
+        };
+
+        let name = match self.func_key(func) {
+            | Some(name) => name,
+            | None => return body,
+        };
+
+        let params = self
+            .body
+            .params()
+            .iter()
+            .map(|pat| format!("param{}", u32::from(pat.into_raw())))
+            .collect::<Vec<_>>();
+        let wrapper = self.type_vars.iter().chain(&self.records).cloned().collect::<Vec<_>>();
+        let (body, _) = loop_tail_calls(body, &params, |expr| match expr {
+            | JsExpr::Call { base, args } if args.len() == params.len() => match &**base {
+                | JsExpr::Call { base, args: w } if *w == wrapper && !wrapper.is_empty() => {
+                    (callee_key(base).as_ref() == Some(&name)).then(|| args.clone())
+                },
+                | base if wrapper.is_empty() && callee_key(base).as_ref() == Some(&name) => Some(args.clone()),
+                | _ => None,
+            },
+            | _ => None,
+        });
+
+        if !self.trampolines.contains_key(&name) {
+            return body;
+        }
+
+        replace_tail_calls(body, &mut |expr| match expr {
+            | JsExpr::Call { base, args } => match callee_key(base) {
+                | Some(name) if self.trampolines.get(&name) == Some(&args.len()) => Some(JsExpr::Return {
+                    expr: Box::new(JsExpr::New {
+                        class: "$TailCall".into(),
+                        args: vec![
+                            JsExpr::Ident {
+                                name: tail::trampoline_name(&name),
+                            },
+                            JsExpr::Array { exprs: args.clone() },
+                        ],
+                    }),
+                }),
+                | _ => None,
+            },
+            | _ => None,
+        })
+    }
+
+    /// Turn the `recur` tail calls of the lambda `name` into a loop.
+    pub fn optimize_recur(&self, name: &str, params: Vec<String>, body: JsExpr) -> (Vec<String>, JsExpr) {
+        let (body, looped) = loop_tail_calls(body, &params, |expr| {
+            let mut args = Vec::new();
+            let mut base = expr;
+
+            // `recur` is curried, so every argument is applied separately.
+            while let JsExpr::Call { base: b, args: a } = base {
+                if a.len() != 1 {
+                    return None;
+                }
+
+                args.push(a[0].clone());
+                base = b;
+            }
+
+            match base {
+                | JsExpr::Ident { name: n } if n == name && !args.is_empty() && args.len() == params.len() => {
+                    args.reverse();
+                    Some(args)
+                },
+                | _ => None,
+            }
+        });
+
+        if !looped || params.len() == 1 {
+            return (params, body);
+        }
+
+        // The outer parameters of a curried lambda are shared by every call of its partial
+        // applications, so the loop reassigns local copies of them instead.
+        let args = params.iter().map(|p| format!("{}$arg", p)).collect::<Vec<_>>();
+        let mut exprs = params
+            .into_iter()
+            .zip(&args)
+            .map(|(name, arg)| JsExpr::Var {
+                name,
+                expr: Some(Box::new(JsExpr::Ident { name: arg.clone() })),
+            })
+            .collect::<Vec<_>>();
+
+        exprs.push(body);
+        (args, JsExpr::Block { exprs })
+    }
+}
+
+/// Replace the tail calls matched by `is_self_call` with a reassignment of `params` and wrap
+/// `body` in a loop. Bodies creating closures are left alone, as the closures could capture
+/// the reassigned parameters.
+fn loop_tail_calls(
+    body: JsExpr,
+    params: &[String],
+    is_self_call: impl Fn(&JsExpr) -> Option<Vec<JsExpr>>,
+) -> (JsExpr, bool) {
+    if contains_closure(&body) {
+        return (body, false);
+    }
+
+    let mut looped = false;
+    let body = replace_tail_calls(body, &mut |expr| {
+        let args = is_self_call(expr)?;
+
+        looped = true;
+        Some(reassign_params(params, args))
+    });
+
+    if !looped {
+        return (body, false);
+    }
+
+    let body = if always_exits(&body) {
+        body
+    } else {
+        JsExpr::Block {
+            exprs: vec![body, JsExpr::Return {
+                expr: Box::new(JsExpr::Undefined),
+            }],
+        }
+    };
+
+    (JsExpr::Loop { body: Box::new(body) }, true)
+}
+
+/// Replace every `return` of `body` for which `f` returns a replacement.
+fn replace_tail_calls(body: JsExpr, f: &mut impl FnMut(&JsExpr) -> Option<JsExpr>) -> JsExpr {
+    match body {
+        | JsExpr::Return { expr } => match f(expr.unmapped()) {
+            | Some(replacement) => replacement,
+            | None => JsExpr::Return { expr },
+        },
+        | JsExpr::Block { exprs } => JsExpr::Block {
+            exprs: exprs.into_iter().map(|e| replace_tail_calls(e, f)).collect(),
+        },
+        | JsExpr::If { cond, then, else_ } => JsExpr::If {
+            cond,
+            then: Box::new(replace_tail_calls(*then, f)),
+            else_: else_.map(|e| Box::new(replace_tail_calls(*e, f))),
+        },
+        | _ => body,
+    }
+}
+
+/// The name of a function or member item called as `base`, see [`Ctx::func_key`].
+fn callee_key(base: &JsExpr) -> Option<String> {
+    match base {
+        | JsExpr::Ident { name } => Some(name.clone()),
+        | JsExpr::Field { base, field } => match &**base {
+            | JsExpr::Ident { name } => Some(format!("{}.{}", name, field)),
+            | _ => None,
+        },
+        | _ => None,
+    }
+}
+
+fn reassign_params(params: &[String], args: Vec<JsExpr>) -> JsExpr {
+    let changed = params
+        .iter()
+        .zip(args)
+        .filter(|(param, arg)| !matches!(arg, JsExpr::Ident { name } if name == *param))
+        .collect::<Vec<_>>();
+    let mut exprs = Vec::new();
+
+    if let [(param, arg)] = &changed[..] {
+        exprs.push(JsExpr::Assign {
+            place: Box::new(JsExpr::Ident {
+                name: param.to_string(),
+            }),
+            expr: Box::new(arg.clone()),
+        });
+    } else {
+        // All arguments are evaluated before any of the parameters are reassigned.
+        for (param, arg) in &changed {
+            exprs.push(JsExpr::Var {
+                name: format!("{}$next", param),
+                expr: Some(Box::new(arg.clone())),
+            });
+        }
+
+        for (param, _) in &changed {
+            exprs.push(JsExpr::Assign {
+                place: Box::new(JsExpr::Ident {
+                    name: param.to_string(),
+                }),
+                expr: Box::new(JsExpr::Ident {
+                    name: format!("{}$next", param),
+                }),
+            });
+        }
+    }
+
+    exprs.push(JsExpr::Continue);
+    JsExpr::Block { exprs }
+}
+
+fn contains_closure(expr: &JsExpr) -> bool {
+    let mut found = matches!(expr, JsExpr::Lambda { .. });
+
+    expr.walk(|e| found |= contains_closure(e));
+    found
+}
+
+fn always_exits(expr: &JsExpr) -> bool {
+    match expr {
+        | JsExpr::Block { exprs } => exprs.iter().any(always_exits),
+        | JsExpr::If {
+            then,
+            else_: Some(else_),
+            ..
+        } => always_exits(then) && always_exits(else_),
+        | _ => expr.is_terminator(),
+    }
+}
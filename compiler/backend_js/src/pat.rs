@@ -219,7 +219,7 @@ impl BodyCtx<'_, '_> {
                     op: "instanceof",
                     lhs: Box::new(place),
                     rhs: Box::new(JsExpr::Ident {
-                        name: self.mangle((ctor.path(self.db).to_string(), true)),
+                        name: self.ctor_ref(ctor),
                     }),
                 }),
             );
@@ -0,0 +1,215 @@
+use base_db::libs::LibKind;
+use hir::db::HirDatabase;
+use hir::id::DefWithBodyId;
+use hir::{AssocItem, Expr, HasResolver, MethodSource, Pat, Path, Resolver, ValueNs};
+use rustc_hash::FxHashSet;
+
+/// The definitions, constructors and members which can be reached from the main functions
+/// and the exported items of the libraries no other library depends on.
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct Reachable {
+    defs: FxHashSet<DefWithBodyId>,
+    ctors: FxHashSet<hir::Ctor>,
+    members: FxHashSet<hir::Member>,
+}
+
+enum Item {
+    Def(DefWithBodyId),
+    Ctor(hir::Ctor),
+    Member(hir::Member),
+}
+
+impl Reachable {
+    pub fn new(db: &dyn HirDatabase) -> Self {
+        let libs = db.libs();
+        let mut ctx = ReachableCtx {
+            db,
+            reachable: Self::default(),
+            worklist: Vec::new(),
+        };
+
+        for lib in hir::Lib::all(db) {
+            let data = &libs[lib.into()];
+            let is_root = libs.iter().all(|other| !libs[other].deps.contains(&data.id));
+
+            // The backend refers to these when lowering units and conditions.
+            for lang in ["unit-type", "bool-type"] {
+                if let Some(ty) = db.lang_item(lib.into(), lang).and_then(|l| l.as_type_ctor()) {
+                    for ctor in hir::TypeCtor::from(ty).ctors(db) {
+                        ctx.push(Item::Ctor(ctor));
+                    }
+                }
+            }
+
+            for module in all_modules(db, lib) {
+                for def in module.declarations(db) {
+                    match def {
+                        | hir::ModuleDef::Func(func)
+                            if data.kind == LibKind::Executable
+                                && db.attrs(hir::id::FuncId::from(func).into()).by_key("main").exists() =>
+                        {
+                            ctx.push(Item::Def(hir::id::FuncId::from(func).into()));
+                        },
+                        | _ if is_root && def.is_exported(db) => ctx.push_def(def),
+                        | _ => {},
+                    }
+                }
+
+                if is_root {
+                    for member in module.members(db) {
+                        ctx.push(Item::Member(member));
+                    }
+                }
+            }
+        }
+
+        while let Some(item) = ctx.worklist.pop() {
+            match item {
+                | Item::Def(def) => ctx.visit_def(def),
+                | Item::Ctor(_) => {},
+                | Item::Member(member) => ctx.visit_member(member),
+            }
+        }
+
+        ctx.reachable
+    }
+
+    pub fn has_def(&self, def: hir::ModuleDef) -> bool {
+        match def {
+            | hir::ModuleDef::Func(it) => self.defs.contains(&hir::id::FuncId::from(it).into()),
+            | hir::ModuleDef::Static(it) => self.defs.contains(&hir::id::StaticId::from(it).into()),
+            | hir::ModuleDef::Const(it) => self.defs.contains(&hir::id::ConstId::from(it).into()),
+            | hir::ModuleDef::Ctor(it) => self.ctors.contains(&it),
+            | _ => true,
+        }
+    }
+
+    pub fn has_member(&self, member: hir::Member) -> bool {
+        self.members.contains(&member)
+    }
+}
+
+struct ReachableCtx<'a> {
+    db: &'a dyn HirDatabase,
+    reachable: Reachable,
+    worklist: Vec<Item>,
+}
+
+impl ReachableCtx<'_> {
+    fn push(&mut self, item: Item) {
+        let new = match item {
+            | Item::Def(def) => self.reachable.defs.insert(def),
+            | Item::Ctor(ctor) => self.reachable.ctors.insert(ctor),
+            | Item::Member(member) => self.reachable.members.insert(member),
+        };
+
+        if new {
+            self.worklist.push(item);
+        }
+    }
+
+    fn push_def(&mut self, def: hir::ModuleDef) {
+        match def {
+            | hir::ModuleDef::Func(it) if it.has_body(self.db) && !it.is_foreign(self.db) => {
+                self.push(Item::Def(hir::id::FuncId::from(it).into()))
+            },
+            | hir::ModuleDef::Static(it) if !it.is_foreign(self.db) => {
+                self.push(Item::Def(hir::id::StaticId::from(it).into()))
+            },
+            | hir::ModuleDef::Const(it) => self.push(Item::Def(hir::id::ConstId::from(it).into())),
+            | hir::ModuleDef::Ctor(it) => self.push(Item::Ctor(it)),
+            | _ => {},
+        }
+    }
+
+    fn visit_def(&mut self, def: DefWithBodyId) {
+        let body = self.db.body(def);
+        let infer = self.db.infer(def);
+
+        for (id, expr) in body.exprs() {
+            match expr {
+                | Expr::Path { path } => {
+                    self.visit_path(&Resolver::for_expr(self.db.upcast(), def, id), path);
+                },
+                | Expr::Infix { ops, .. } => {
+                    let resolver = Resolver::for_expr(self.db.upcast(), def, id);
+
+                    for op in ops.iter() {
+                        self.visit_path(&resolver, op);
+                    }
+                },
+                | _ => {},
+            }
+        }
+
+        let resolver = def.resolver(self.db.upcast());
+
+        for (_, pat) in body.pats() {
+            match pat {
+                | Pat::Path { path } => self.visit_path(&resolver, path),
+                | Pat::Infix { ops, .. } => {
+                    for op in ops.iter() {
+                        self.visit_path(&resolver, op);
+                    }
+                },
+                | _ => {},
+            }
+        }
+
+        for source in infer.methods.values().flatten() {
+            if let MethodSource::Member(id) = *source {
+                self.push(Item::Member(id.into()));
+            }
+        }
+    }
+
+    fn visit_member(&mut self, member: hir::Member) {
+        for item in member.items(self.db) {
+            match item {
+                | AssocItem::Func(it) => self.push(Item::Def(hir::id::FuncId::from(it).into())),
+                | AssocItem::Static(it) => self.push(Item::Def(hir::id::StaticId::from(it).into())),
+            }
+        }
+
+        for source in self.db.verify_member(member.into()).constraints.iter() {
+            if let MethodSource::Member(id) = *source {
+                self.push(Item::Member(id.into()));
+            }
+        }
+    }
+
+    fn visit_path(&mut self, resolver: &Resolver, path: &Path) {
+        let resolved = match resolver.resolve_value_fully(self.db.upcast(), path) {
+            | Some((resolved, _)) => resolved,
+            | None => return,
+        };
+
+        match resolved {
+            | ValueNs::Func(id) => self.push_def(hir::Func::from(id).into()),
+            | ValueNs::Static(id) => self.push_def(hir::Static::from(id).into()),
+            | ValueNs::Const(id) => self.push_def(hir::Const::from(id).into()),
+            | ValueNs::Ctor(id) => self.push(Item::Ctor(id.into())),
+            | ValueNs::Fixity(id) => {
+                let resolver = id.resolver(self.db.upcast());
+                let data = self.db.fixity_data(id);
+
+                self.visit_path(&resolver, &data.func);
+            },
+            | _ => {},
+        }
+    }
+}
+
+fn all_modules(db: &dyn HirDatabase, lib: hir::Lib) -> Vec<hir::Module> {
+    let mut modules = lib.modules(db);
+    let mut i = 0;
+
+    while i < modules.len() {
+        let children = modules[i].children(db);
+
+        modules.extend(children);
+        i += 1;
+    }
+
+    modules
+}
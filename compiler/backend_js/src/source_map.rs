@@ -0,0 +1,220 @@
+use std::fmt::Write;
+
+use rustc_hash::FxHashMap;
+
+/// Builds a version 3 source map mapping positions in the generated code to the fi sources.
+#[derive(Default)]
+pub struct SourceMap {
+    sources: Vec<String>,
+    source_ids: FxHashMap<String, u32>,
+    mappings: Vec<Mapping>,
+}
+
+/// A zero-based line and column in one of the sources of a `SourceMap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourcePos {
+    pub source: u32,
+    pub line: u32,
+    pub col: u32,
+}
+
+struct Mapping {
+    gen_line: u32,
+    gen_col: u32,
+    pos: SourcePos,
+}
+
+impl SourceMap {
+    /// The id of `source`, adding it to the sources of the map when it is new.
+    pub fn source_id(&mut self, source: &str) -> u32 {
+        let next = self.sources.len() as u32;
+
+        *self.source_ids.entry(source.to_string()).or_insert_with(|| {
+            self.sources.push(source.to_string());
+            next
+        })
+    }
+
+    /// Map the generated position to `pos`.
+    pub fn add(&mut self, (gen_line, gen_col): (u32, u32), pos: SourcePos) {
+        self.mappings.push(Mapping { gen_line, gen_col, pos });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Shift all generated positions down by `lines`, as happens when a header is written
+    /// before the code the map was built for.
+    pub fn offset_lines(&mut self, lines: u32) {
+        for mapping in &mut self.mappings {
+            mapping.gen_line += lines;
+        }
+    }
+
+    pub fn to_json(&self, file: &str) -> String {
+        let mut json = String::new();
+
+        write!(json, "{{\"version\":3,\"file\":{:?},\"sources\":[", file).unwrap();
+
+        for (i, source) in self.sources.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            write!(json, "{:?}", source).unwrap();
+        }
+
+        write!(json, "],\"names\":[],\"mappings\":\"{}\"}}", self.encode_mappings()).unwrap();
+        json
+    }
+
+    fn encode_mappings(&self) -> String {
+        let mut out = String::new();
+        let mut mappings = self.mappings.iter().collect::<Vec<_>>();
+        let (mut line, mut prev_col, mut prev_source, mut prev_line, mut prev_src_col) = (0, 0, 0, 0, 0);
+
+        mappings.sort_by_key(|m| (m.gen_line, m.gen_col));
+
+        for (i, m) in mappings.into_iter().enumerate() {
+            if m.gen_line != line {
+                while line < m.gen_line {
+                    out.push(';');
+                    line += 1;
+                }
+
+                prev_col = 0;
+            } else if i > 0 {
+                out.push(',');
+            }
+
+            encode_vlq(&mut out, m.gen_col as i64 - prev_col);
+            encode_vlq(&mut out, m.pos.source as i64 - prev_source);
+            encode_vlq(&mut out, m.pos.line as i64 - prev_line);
+            encode_vlq(&mut out, m.pos.col as i64 - prev_src_col);
+            prev_col = m.gen_col as i64;
+            prev_source = m.pos.source as i64;
+            prev_line = m.pos.line as i64;
+            prev_src_col = m.pos.col as i64;
+        }
+
+        out
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_vlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 { ((-value) << 1) | 1 } else { value << 1 };
+
+    loop {
+        let mut digit = vlq & 0b11111;
+
+        vlq >>= 5;
+
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+
+        out.push(BASE64[digit as usize] as char);
+
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        out.push(BASE64[(n >> 18) as usize & 63] as char);
+        out.push(BASE64[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            BASE64[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            BASE64[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(value: i64) -> String {
+        let mut out = String::new();
+
+        encode_vlq(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn vlq_encodes_sign_in_lowest_bit() {
+        assert_eq!(vlq(0), "A");
+        assert_eq!(vlq(1), "C");
+        assert_eq!(vlq(-1), "D");
+        assert_eq!(vlq(15), "e");
+    }
+
+    #[test]
+    fn vlq_continues_large_values() {
+        assert_eq!(vlq(16), "gB");
+        assert_eq!(vlq(-16), "hB");
+        assert_eq!(vlq(123), "2H");
+        assert_eq!(vlq(1000), "w+B");
+    }
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn mappings_are_relative_to_previous() {
+        let mut map = SourceMap::default();
+        let main = map.source_id("src/main.fi");
+        let lib = map.source_id("src/lib.fi");
+        let pos = |source, line, col| SourcePos { source, line, col };
+
+        assert_eq!(map.source_id("src/main.fi"), main);
+
+        map.add((2, 0), pos(lib, 0, 0));
+        map.add((0, 4), pos(main, 1, 2));
+        map.add((0, 0), pos(main, 0, 0));
+
+        assert_eq!(map.encode_mappings(), "AAAA,IACE;;ACDF");
+    }
+
+    #[test]
+    fn offset_lines_prepends_empty_lines() {
+        let mut map = SourceMap::default();
+        let main = map.source_id("src/main.fi");
+
+        map.add((0, 0), SourcePos {
+            source: main,
+            line: 0,
+            col: 0,
+        });
+        map.offset_lines(2);
+
+        assert_eq!(
+            map.to_json("Main.js"),
+            r#"{"version":3,"file":"Main.js","sources":["src/main.fi"],"names":[],"mappings":";;AAAA"}"#
+        );
+    }
+}
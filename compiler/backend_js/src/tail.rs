@@ -0,0 +1,237 @@
+use hir::id::FuncId;
+use hir::{CaseValue, Expr, ExprId, MethodSource, Resolver, Stmt, ValueNs};
+use rustc_hash::FxHashSet;
+
+use super::*;
+
+/// The name of the function returning `$TailCall`s instead of making the tail calls of `name`.
+pub fn trampoline_name(name: &str) -> String {
+    format!("{}$9tc", name)
+}
+
+impl Ctx<'_> {
+    /// Find the functions in the current file which make mutually recursive tail calls.
+    /// These are emitted as trampolines so that the recursion does not grow the stack.
+    pub fn collect_trampolines(&mut self) {
+        let mut funcs = Vec::new();
+        let mut modules = vec![self.file];
+
+        while let Some(module) = modules.pop() {
+            modules.extend(module.children(self.db));
+
+            for def in module.declarations(self.db) {
+                if let hir::ModuleDef::Func(func) = def {
+                    if self.reachable.has_def(def) && self.can_trampoline(func) {
+                        funcs.push(FuncId::from(func));
+                    }
+                }
+            }
+
+            for member in module.members(self.db) {
+                if !self.reachable.has_member(member) {
+                    continue;
+                }
+
+                for item in member.items(self.db) {
+                    if let hir::AssocItem::Func(func) = item {
+                        if self.can_trampoline(func) {
+                            funcs.push(FuncId::from(func));
+                        }
+                    }
+                }
+            }
+        }
+
+        let candidates = funcs.iter().copied().collect::<FxHashSet<_>>();
+        let calls = funcs
+            .iter()
+            .map(|&id| {
+                let mut calls = Vec::new();
+                let body = self.db.body(id.into());
+
+                tail_calls(self.db, id.into(), &body, body.body_expr(), &mut calls);
+                calls.retain(|callee| *callee != id && candidates.contains(callee));
+                (id, calls)
+            })
+            .collect::<FxHashMap<_, _>>();
+
+        for &id in &funcs {
+            let mut seen = FxHashSet::default();
+            let mut stack = calls[&id].clone();
+
+            while let Some(callee) = stack.pop() {
+                if callee == id {
+                    let name = self.func_key(hir::Func::from(id)).unwrap();
+                    let params = self.db.body(id.into()).params().len();
+
+                    self.trampolines.insert(name, params);
+                    break;
+                }
+
+                if seen.insert(callee) {
+                    stack.extend(calls[&callee].iter().copied());
+                }
+            }
+        }
+    }
+
+    /// The expression referring to `func` in the output, which is used as its name in
+    /// `trampolines`. Items of members are fields of the record of their member. Members
+    /// with constraints are functions creating their record, these have no fixed name.
+    pub fn func_key(&self, func: hir::Func) -> Option<String> {
+        match func.as_assoc_item(self.db).map(|item| item.container(self.db)) {
+            | None => Some(self.mangle(func.link_name(self.db))),
+            | Some(hir::AssocItemContainer::Member(member)) if !self.has_records(member) => Some(format!(
+                "{}.{}",
+                self.member_name(member),
+                self.mangle((func.name(self.db), true))
+            )),
+            | Some(_) => None,
+        }
+    }
+
+    /// Whether the record of `member` is created from the records of its constraints.
+    fn has_records(&self, member: hir::Member) -> bool {
+        let lower = self.db.lower_member(member.into());
+
+        lower
+            .member
+            .where_clause
+            .constraints
+            .iter()
+            .any(|c| !self.db.class_data(c.class).items.is_empty())
+    }
+
+    /// Only functions which are called with all of their arguments at once can be
+    /// trampolined, functions taking type symbols or class records are curried.
+    fn can_trampoline(&self, func: hir::Func) -> bool {
+        if !func.has_body(self.db)
+            || func.is_foreign(self.db)
+            || func.is_intrinsic(self.db)
+            || self.func_key(func).is_none()
+        {
+            return false;
+        }
+
+        let id = FuncId::from(func);
+        let ty = self.db.infer(id.into()).self_type.ty;
+        let mut extra = 0;
+        let ty = for_each_type_var(self.db, id.into(), ty, || {
+            extra += 1;
+            Ok(())
+        })
+        .unwrap();
+
+        for_each_record(self.db, ty, || {
+            extra += 1;
+            Ok(())
+        })
+        .unwrap();
+
+        extra == 0
+    }
+
+    pub fn write_trampoline_runtime(&mut self) -> io::Result<()> {
+        if self.trampolines.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(self, "class $TailCall {{")?;
+        self.out.indent();
+        writeln!(self, "constructor(func, args) {{")?;
+        self.out.indent();
+        writeln!(self, "this.func = func;")?;
+        writeln!(self, "this.args = args;")?;
+        self.out.dedent();
+        writeln!(self, "}}")?;
+        self.out.dedent();
+        writeln!(self, "}}")?;
+        writeln!(self, "function $trampoline(func) {{")?;
+        self.out.indent();
+        writeln!(self, "return function(...args) {{")?;
+        self.out.indent();
+        writeln!(self, "let res = func(...args);")?;
+        writeln!(self, "while (res instanceof $TailCall) res = res.func(...res.args);")?;
+        writeln!(self, "return res;")?;
+        self.out.dedent();
+        writeln!(self, "}};")?;
+        self.out.dedent();
+        writeln!(self, "}}")
+    }
+}
+
+/// Collect the functions which are fully applied in a tail position of `expr`.
+fn tail_calls(db: &dyn HirDatabase, owner: DefWithBodyId, body: &hir::Body, expr: ExprId, calls: &mut Vec<FuncId>) {
+    match body[expr] {
+        | Expr::Typed { expr, .. } => tail_calls(db, owner, body, expr, calls),
+        | Expr::If { then, else_, .. } => {
+            tail_calls(db, owner, body, then, calls);
+
+            if let Some(else_) = else_ {
+                tail_calls(db, owner, body, else_, calls);
+            }
+        },
+        | Expr::Case { ref arms, .. } => {
+            for arm in arms.iter() {
+                match arm.value {
+                    | CaseValue::Normal(expr) => tail_calls(db, owner, body, expr, calls),
+                    | CaseValue::Guarded(_, ref exprs) => {
+                        for &expr in exprs.iter() {
+                            tail_calls(db, owner, body, expr, calls);
+                        }
+                    },
+                }
+            }
+        },
+        | Expr::Do { ref stmts } => {
+            if let Some(&Stmt::Expr { expr }) = stmts.last() {
+                tail_calls(db, owner, body, expr, calls);
+            }
+        },
+        | Expr::App { .. } => {
+            let mut base = expr;
+            let mut args = 0;
+
+            while let Expr::App { base: b, .. } = body[base] {
+                base = b;
+                args += 1;
+            }
+
+            if let Expr::Path { ref path } = body[base] {
+                let resolver = Resolver::for_expr(db.upcast(), owner, base);
+
+                if let Some((ValueNs::Func(id), _)) = resolver.resolve_value_fully(db.upcast(), path) {
+                    if let Some(id) = callee(db, owner, base, id) {
+                        if db.func_data(id).has_body && db.body(id.into()).params().len() == args {
+                            calls.push(id);
+                        }
+                    }
+                }
+            }
+        },
+        | _ => {},
+    }
+}
+
+/// The function called by the path `expr` resolving to `func`. Calls of class methods resolve
+/// to the item of a member when the member is known statically.
+fn callee(db: &dyn HirDatabase, owner: DefWithBodyId, expr: ExprId, func: FuncId) -> Option<FuncId> {
+    let item = match hir::Func::from(func).as_assoc_item(db) {
+        | Some(item) => item,
+        | None => return Some(func),
+    };
+
+    let name = item.name(db);
+    let infer = db.infer(owner);
+
+    match infer.methods.get(&(expr, 0))?.first()? {
+        | MethodSource::Member(id) => hir::Member::from(*id)
+            .items(db)
+            .into_iter()
+            .find_map(|item| match item {
+                | hir::AssocItem::Func(f) if f.name(db) == name => Some(FuncId::from(f)),
+                | _ => None,
+            }),
+        | MethodSource::Record(..) => None,
+    }
+}
@@ -25,6 +25,9 @@ pub enum EmptySinglePair<T> {
 pub struct FnAbi<'ctx> {
     pub args: Box<[ArgAbi<'ctx>]>,
     pub ret: ArgAbi<'ctx>,
+    /// Whether the last argument is not passed directly, but holds the `va_list` of the
+    /// variadic arguments of the call.
+    pub variadic: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -67,7 +70,11 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
             })
             .collect();
 
-        FnAbi { args, ret }
+        FnAbi {
+            args,
+            ret,
+            variadic: false,
+        }
     }
 
     pub fn compute_layout_abi(&self, layout: Arc<ReprAndLayout>) -> ArgAbi<'ctx> {
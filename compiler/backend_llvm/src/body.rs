@@ -3,8 +3,8 @@ use std::sync::Arc;
 use arena::Idx;
 use hir::HirDisplay;
 use inkwell::values::{self, BasicValue, BasicValueEnum, CallableValue};
-use inkwell::IntPredicate;
-use mir::instance::InstanceDef;
+use inkwell::{AddressSpace, FloatPredicate, IntPredicate};
+use mir::instance::{Instance, InstanceDef};
 use mir::layout::ReprAndLayout;
 use mir::repr::Repr;
 use mir::syntax::{
@@ -28,6 +28,7 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
         let entry = self.context.append_basic_block(self.func, "entry");
         let first_block = Idx::from_raw(0u32.into());
         self.builder.position_at_end(entry);
+        self.create_subprogram();
 
         for (arg, local_ref) in body.blocks[first_block]
             .params
@@ -92,6 +93,7 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
 
         let first_block = self.blocks[first_block];
 
+        self.declare_vars(entry);
         self.builder.position_at_end(entry);
         self.builder.build_unconditional_branch(first_block);
 
@@ -99,9 +101,7 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
             self.codegen_block(Block(block), data);
         }
 
-        if self.func.verify(true) {
-            self.fpm.run_on(&self.func);
-        } else {
+        if !self.func.verify(true) {
             eprintln!();
             self.func.print_to_stderr();
             eprintln!();
@@ -119,10 +119,12 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
 
         self.builder.position_at_end(bb);
 
-        for stmt in &data.stmts {
+        for (i, stmt) in data.stmts.iter().enumerate() {
+            self.set_debug_loc(data.sources.get(i).copied().flatten());
             self.codegen_stmt(stmt);
         }
 
+        self.set_debug_loc(data.sources.get(data.stmts.len()).copied().flatten());
         self.codegen_term(&data.term);
     }
 
@@ -147,7 +149,7 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
                 },
                 | PassMode::ByValPair(_, _) => {
                     let op = self.codegen_operand(op);
-                    let (a, b) = op.pair();
+                    let (a, b) = op.load_pair(self.cx);
 
                     self.builder.build_aggregate_return(&[a, b]);
                 },
@@ -253,11 +255,7 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
 
                 // self.locals[local.0].ptr = ptr;
             },
-            | Stmt::Drop(_local) => {
-                // let ptr = self.locals[local.0].ptr;
-
-                // self.builder.build_free(ptr);
-            },
+            | Stmt::Drop(local) => self.codegen_drop(*local),
             | Stmt::Assign(place, rvalue) => {
                 if place.projection.is_empty() {
                     match &self.locals[place.local.0] {
@@ -292,15 +290,31 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
             },
             | Stmt::Call { place, func, args } => {
                 let func = self.codegen_operand(func);
-                let func_sig = match &func.layout.repr {
-                    | Repr::Func(sig, _) => sig,
+                let (func_sig, thick) = match &func.layout.repr {
+                    | Repr::Func(sig, thick) => (sig, *thick),
                     | _ => unreachable!(),
                 };
 
                 let func_abi = self.compute_fn_abi(&func_sig);
-                let func = match func.val {
-                    | OperandValue::Ref(ptr, None) => self.builder.build_load(ptr, ""),
-                    | _ => func.immediate(),
+                let (func, env) = if thick {
+                    // closures receive a pointer to their environment as the first argument
+                    let (func, env) = func.load_pair(self.cx);
+                    let env_abi = self.compute_layout_abi(self.db.layout_of(Repr::closure_env()));
+                    let mut closure_abi = func_abi.clone();
+
+                    closure_abi.args = std::iter::once(env_abi).chain(func_abi.args.iter().cloned()).collect();
+
+                    let ty = self.fn_type_for_abi(&closure_abi).ptr_type(AddressSpace::default());
+                    let func = self.builder.build_pointer_cast(func.into_pointer_value(), ty, "");
+
+                    (func.as_basic_value_enum(), Some(env.into()))
+                } else {
+                    let func = match func.val {
+                        | OperandValue::Ref(ptr, None) => self.builder.build_load(ptr, ""),
+                        | _ => func.immediate(),
+                    };
+
+                    (func, None)
                 };
 
                 let func = match func {
@@ -316,6 +330,7 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
 
                 let args = ret_ptr
                     .into_iter()
+                    .chain(env)
                     .chain(
                         args.iter()
                             .zip(func_abi.args.iter())
@@ -350,7 +365,7 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
             | PassMode::ByValPair(_, _) => {
                 tracing::debug!("{}", arg.display(self.db.upcast()));
                 let op = self.codegen_operand(arg);
-                let (a, b) = op.pair();
+                let (a, b) = op.load_pair(self.cx);
                 EmptySinglePair::Pair(a.into(), b.into())
             },
             | PassMode::ByRef { size: Some(_) } => {
@@ -401,6 +416,13 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
                 assert_eq!(value.layout.size, layout.size);
                 value.bitcast(self.cx, layout)
             },
+            | Rvalue::Cast(CastKind::Pointer, op) => {
+                let ty = self.basic_type_for_ral(&layout).into_pointer_type();
+                let value = self.codegen_operand(op).load(self.cx).into_pointer_value();
+                let value = self.builder.build_pointer_cast(value, ty, "");
+
+                OperandRef::new_imm(layout, value.as_basic_value_enum())
+            },
             | Rvalue::Cast(CastKind::IntToInt, op) => {
                 let ty = self.basic_type_for_ral(&layout).into_int_type();
                 let value = self.codegen_operand(op);
@@ -410,6 +432,42 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
 
                 OperandRef::new_imm(layout, value.as_basic_value_enum())
             },
+            | Rvalue::Cast(CastKind::FloatToFloat, op) => {
+                let ty = self.basic_type_for_ral(&layout).into_float_type();
+                let value = self.codegen_operand(op).load(self.cx).into_float_value();
+                let value = self.builder.build_float_cast(value, ty, "");
+
+                OperandRef::new_imm(layout, value.as_basic_value_enum())
+            },
+            | Rvalue::Cast(CastKind::IntToFloat, op) => {
+                let ty = self.basic_type_for_ral(&layout).into_float_type();
+                let value = self.codegen_operand(op);
+                let is_signed = value.layout.is_signed();
+                let value = value.load(self.cx).into_int_value();
+                let value = if is_signed {
+                    self.builder.build_signed_int_to_float(value, ty, "")
+                } else {
+                    self.builder.build_unsigned_int_to_float(value, ty, "")
+                };
+
+                OperandRef::new_imm(layout, value.as_basic_value_enum())
+            },
+            | Rvalue::Cast(CastKind::FloatToInt, op) => {
+                // saturate out of range values and convert NaN to zero, like javascript
+                let width = self.operand_layout(op).size.bits();
+                let sign = if layout.is_signed() { 's' } else { 'u' };
+                let name = format!("llvm.fpto{}i.sat.i{}.f{}", sign, layout.size.bits(), width);
+                let func = self.cx.get_intrinsic(&name);
+                let value = self.codegen_operand(op).load(self.cx);
+                let value = self
+                    .builder
+                    .build_call(func, &[value.into()], "")
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap();
+
+                OperandRef::new_imm(layout, value)
+            },
             | Rvalue::Discriminant(place) => {
                 let place = self.codegen_place(place);
                 let repr = Repr::Discr(Box::new(place.layout.repr.clone()));
@@ -421,7 +479,23 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
             | Rvalue::InstanceRef(instance) => {
                 let instance = self.instance.subst_instance(self.db, instance);
                 let value = match instance.def {
-                    | InstanceDef::Def(hir::DefWithBody::Func(_)) => {
+                    | InstanceDef::Def(hir::DefWithBody::Func(f)) => {
+                        if f.is_variadic(self.db.upcast()) {
+                            base_db::Error::throw(format!(
+                                "`{}` is `@variadic` and can only be called from C",
+                                f.name(self.db.upcast())
+                            ));
+                        }
+
+                        let func = self.cx.declare_or_codegen_func(instance).0;
+                        func.as_global_value().as_basic_value_enum()
+                    },
+                    | InstanceDef::Body(_) => {
+                        // like closures, bodies share the type parameters of the body they are defined in
+                        let instance = Instance {
+                            def: instance.def,
+                            subst: self.instance.subst.clone(),
+                        };
                         let func = self.cx.declare_or_codegen_func(instance).0;
                         func.as_global_value().as_basic_value_enum()
                     },
@@ -439,12 +513,86 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
 
                 OperandRef::new_imm(layout, value)
             },
+            | Rvalue::Closure(instance, captures) => self.codegen_closure(layout, instance, captures),
             | Rvalue::BinOp(op, lhs, rhs) => self.codegen_binop(layout, op, lhs, rhs),
             | Rvalue::NullOp(op, repr) => self.codegen_nullop(layout, op, repr),
             | _ => todo!(),
         }
     }
 
+    /// A closure is a pair of a function pointer and a pointer to its environment, which holds
+    /// the captured values. Closures without captures have a null environment.
+    ///
+    /// Environments are allocated with `malloc` and freed when the closure is dropped, which
+    /// only happens for closures that can't outlive the body that created them.
+    pub fn codegen_closure(
+        &mut self,
+        layout: Arc<ReprAndLayout>,
+        instance: &Instance,
+        captures: &[Operand],
+    ) -> OperandRef<'ctx> {
+        // closure bodies share the type parameters of the body they are defined in
+        let instance = Instance {
+            def: instance.def,
+            subst: self.instance.subst.clone(),
+        };
+
+        let func = self.cx.declare_or_codegen_func(instance).0;
+        let func_ty = self.basic_type_for_ral(&layout.field(self.db, 0).unwrap());
+        let func = func.as_global_value().as_pointer_value();
+        let func = self.builder.build_pointer_cast(func, func_ty.into_pointer_type(), "");
+        let env_ty = self.basic_type_for_ral(&layout.field(self.db, 1).unwrap());
+        let env_ty = env_ty.into_pointer_type();
+        let env = if captures.is_empty() {
+            env_ty.const_null()
+        } else {
+            let reprs = captures
+                .iter()
+                .map(|c| self.instance.subst_repr(self.db, &self.body.operand_repr(c)))
+                .collect();
+            let env_layout = self.db.layout_of(Repr::Struct(reprs));
+            let usize = self.context.ptr_sized_int_type(&self.target_data, None);
+            let size = usize.const_int(env_layout.size.bytes(), false);
+            let malloc = self.cx.get_intrinsic("malloc");
+            let ptr = self
+                .builder
+                .build_call(malloc, &[size.into()], "")
+                .try_as_basic_value()
+                .left()
+                .unwrap()
+                .into_pointer_value();
+            let ty = self.basic_type_for_ral(&env_layout).ptr_type(AddressSpace::default());
+            let env = PlaceRef::new(env_layout, self.builder.build_pointer_cast(ptr, ty, ""), None);
+
+            for (i, capture) in captures.iter().enumerate() {
+                let value = self.codegen_operand(capture);
+                let field = env.field(self.cx, i);
+
+                value.store(self.cx, &field);
+            }
+
+            self.builder.build_pointer_cast(ptr, env_ty, "")
+        };
+
+        OperandRef::new_pair(layout, func.as_basic_value_enum(), env.as_basic_value_enum())
+    }
+
+    /// Free the environment of a closure. Other values don't own any memory yet.
+    pub fn codegen_drop(&mut self, local: Local) {
+        let repr = self.instance.subst_repr(self.db, &self.body.locals[local.0].repr);
+
+        if let Repr::Func(_, true) = repr {
+            let (_, env) = self
+                .codegen_operand(&Operand::Copy(Place::new(local)))
+                .load_pair(self.cx);
+            let free = self.cx.get_intrinsic("free");
+            let ptr = self.context.i8_type().ptr_type(AddressSpace::default());
+            let env = self.builder.build_pointer_cast(env.into_pointer_value(), ptr, "");
+
+            self.builder.build_call(free, &[env.into()], "");
+        }
+    }
+
     pub fn codegen_binop(
         &mut self,
         layout: Arc<ReprAndLayout>,
@@ -452,8 +600,9 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
         lhs: &Operand,
         rhs: &Operand,
     ) -> OperandRef<'ctx> {
-        let is_float = layout.is_float();
-        let is_signed = layout.is_signed();
+        let lhs_layout = self.operand_layout(lhs);
+        let is_float = lhs_layout.is_float();
+        let is_signed = lhs_layout.is_signed();
         let lhs = self.codegen_operand(lhs).load(self.cx);
         let rhs = self.codegen_operand(rhs).load(self.cx);
 
@@ -467,21 +616,30 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
             let lhs = lhs.into_float_value();
             let rhs = rhs.into_float_value();
             let val = match op {
-                | BinOp::Add => self.builder.build_float_add(lhs, rhs, ""),
-                | BinOp::Sub => self.builder.build_float_sub(lhs, rhs, ""),
-                | BinOp::Mul => self.builder.build_float_mul(lhs, rhs, ""),
-                | BinOp::Div => self.builder.build_float_div(lhs, rhs, ""),
-                | BinOp::Rem => self.builder.build_float_rem(lhs, rhs, ""),
-                // | BinOp::Eq => self.builder.build_float_compare(FloatPredicate::OEQ, lhs, rhs, ""),
-                // | BinOp::Ne => self.builder.build_float_compare(FloatPredicate::UNE, lhs, rhs, ""),
-                // | BinOp::Lt => self.builder.build_float_compare(FloatPredicate::OLT, lhs, rhs, ""),
-                // | BinOp::Le => self.builder.build_float_compare(FloatPredicate::OLE, lhs, rhs, ""),
-                // | BinOp::Gt => self.builder.build_float_compare(FloatPredicate::OGT, lhs, rhs, ""),
-                // | BinOp::Ge => self.builder.build_float_compare(FloatPredicate::OGE, lhs, rhs, ""),
+                | BinOp::Add => self.builder.build_float_add(lhs, rhs, "").as_basic_value_enum(),
+                | BinOp::Sub => self.builder.build_float_sub(lhs, rhs, "").as_basic_value_enum(),
+                | BinOp::Mul => self.builder.build_float_mul(lhs, rhs, "").as_basic_value_enum(),
+                | BinOp::Div => self.builder.build_float_div(lhs, rhs, "").as_basic_value_enum(),
+                | BinOp::Rem => self.builder.build_float_rem(lhs, rhs, "").as_basic_value_enum(),
+                | BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                    let pred = match op {
+                        | BinOp::Eq => FloatPredicate::OEQ,
+                        | BinOp::Ne => FloatPredicate::UNE,
+                        | BinOp::Lt => FloatPredicate::OLT,
+                        | BinOp::Le => FloatPredicate::OLE,
+                        | BinOp::Gt => FloatPredicate::OGT,
+                        | BinOp::Ge => FloatPredicate::OGE,
+                        | _ => unreachable!(),
+                    };
+
+                    self.builder
+                        .build_float_compare(pred, lhs, rhs, "")
+                        .as_basic_value_enum()
+                },
                 | _ => unreachable!(),
             };
 
-            OperandRef::new_imm(layout, val.as_basic_value_enum())
+            OperandRef::new_imm(layout, val)
         } else {
             let lhs = lhs.into_int_value();
             let rhs = rhs.into_int_value();
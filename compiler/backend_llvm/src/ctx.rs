@@ -2,26 +2,31 @@ use std::cell::{Cell, RefCell};
 use std::sync::Arc;
 
 use arena::{ArenaMap, Idx};
-use base_db::target::CompilerTarget;
+use base_db::target::{CompilerTarget, Optimization};
 use hir::attrs::HasAttrs;
 use hir::id::DefWithBodyId;
+use hir::DefWithBody;
+use inkwell::attributes::AttributeLoc;
 use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
+use inkwell::debug_info::DISubprogram;
 use inkwell::module::{Linkage, Module};
-use inkwell::passes::{PassManager, PassManagerBuilder};
+use inkwell::passes::PassBuilderOptions;
 use inkwell::targets::{
     CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetData, TargetMachine, TargetTriple,
 };
-use inkwell::{types, values, OptimizationLevel};
+use inkwell::values::BasicValue;
+use inkwell::{types, values, AddressSpace, IntPredicate, OptimizationLevel};
 use mir::db::MirDatabase;
-use mir::instance::Instance;
+use mir::instance::{Instance, InstanceDef};
 use mir::layout::ReprAndLayout;
 use mir::repr::{Repr, Signature};
-use mir::syntax::{BlockData, LocalData};
+use mir::syntax::{BlockData, Const, LocalData};
 use rustc_hash::FxHashMap;
 
 use crate::abi::FnAbi;
+use crate::debug::DebugCtx;
 use crate::local::LocalRef;
 use crate::place::PlaceRef;
 
@@ -33,11 +38,11 @@ pub struct CodegenCtx<'a, 'ctx> {
     pub builder: &'a Builder<'ctx>,
     pub target_machine: TargetMachine,
     pub target_data: TargetData,
-    pub fpm: &'a PassManager<values::FunctionValue<'ctx>>,
     pub intrinsics: FxHashMap<&'static str, values::FunctionValue<'ctx>>,
     pub funcs: FxHashMap<Instance, (values::FunctionValue<'ctx>, FnAbi<'ctx>)>,
     pub types: RefCell<FxHashMap<Arc<ReprAndLayout>, types::BasicTypeEnum<'ctx>>>,
     pub consts: Cell<usize>,
+    pub debug: Option<DebugCtx<'ctx>>,
 }
 
 pub struct BodyCtx<'a, 'b, 'ctx> {
@@ -49,6 +54,7 @@ pub struct BodyCtx<'a, 'b, 'ctx> {
     pub ret_ptr: Option<PlaceRef<'ctx>>,
     pub blocks: ArenaMap<Idx<BlockData>, BasicBlock<'ctx>>,
     pub locals: ArenaMap<Idx<LocalData>, LocalRef<'ctx>>,
+    pub debug_scope: Option<(DISubprogram<'ctx>, u32)>,
 }
 
 impl<'a, 'b, 'ctx> std::ops::Deref for BodyCtx<'a, 'b, 'ctx> {
@@ -59,47 +65,52 @@ impl<'a, 'b, 'ctx> std::ops::Deref for BodyCtx<'a, 'b, 'ctx> {
     }
 }
 
-pub fn with_codegen_ctx<T>(db: &dyn MirDatabase, hir: hir::Module, f: impl FnOnce(CodegenCtx) -> T) -> T {
-    Target::initialize_native(&InitializationConfig::default()).unwrap();
-
+/// Create a codegen context for an LLVM module named `name`. Debug info is only emitted for
+/// modules generated from a `hir` module.
+pub fn with_codegen_ctx<T>(
+    db: &dyn MirDatabase,
+    name: &str,
+    hir: Option<hir::Module>,
+    f: impl FnOnce(CodegenCtx) -> T,
+) -> T {
+    let is_wasm = db.target().is_wasm();
     let triple = match db.target() {
         | CompilerTarget::Native(triple) => triple,
         | _ => unreachable!(),
     };
 
     let target_triple = TargetTriple::create(&triple.to_string());
-    let host_cpu = TargetMachine::get_host_cpu_name();
-    let host_features = TargetMachine::get_host_cpu_features();
+    let (cpu, features, reloc) = if is_wasm {
+        Target::initialize_webassembly(&InitializationConfig::default());
+
+        // bulk-memory lowers memcpy and memset to instructions instead of libc calls.
+        (String::from("generic"), String::from("+bulk-memory"), RelocMode::Static)
+    } else {
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+        (
+            TargetMachine::get_host_cpu_name().to_string(),
+            TargetMachine::get_host_cpu_features().to_string(),
+            RelocMode::PIC,
+        )
+    };
+
     let target = Target::from_triple(&target_triple).unwrap();
     let target_machine = target
         .create_target_machine(
             &target_triple,
-            host_cpu.to_str().unwrap(),
-            host_features.to_str().unwrap(),
-            OptimizationLevel::Default,
-            RelocMode::PIC,
+            &cpu,
+            &features,
+            optimization_level(db.optimization()),
+            reloc,
             CodeModel::Default,
         )
         .unwrap();
 
     let target_data = target_machine.get_target_data();
     let context = Context::create();
-    let module = context.create_module(hir.name(db.upcast()).as_ref());
+    let module = context.create_module(name);
     let builder = context.create_builder();
-    let pmb = PassManagerBuilder::create();
-    let fpm = PassManager::create(&module);
-
-    pmb.set_optimization_level(OptimizationLevel::None);
-    pmb.populate_function_pass_manager(&fpm);
-    // fpm.add_instruction_combining_pass();
-    // fpm.add_reassociate_pass();
-    // fpm.add_gvn_pass();
-    // fpm.add_cfg_simplification_pass();
-    // fpm.add_basic_alias_analysis_pass();
-    // fpm.add_promote_memory_to_register_pass();
-    // fpm.add_instruction_combining_pass();
-    // fpm.add_reassociate_pass();
-    fpm.initialize();
 
     let ctx = CodegenCtx {
         db,
@@ -109,23 +120,57 @@ pub fn with_codegen_ctx<T>(db: &dyn MirDatabase, hir: hir::Module, f: impl FnOnc
         module: &module,
         builder: &builder,
         context: &context,
-        fpm: &fpm,
         intrinsics: FxHashMap::default(),
         funcs: FxHashMap::default(),
         types: RefCell::default(),
         consts: Cell::new(0),
+        debug: hir
+            .filter(|_| db.debug_info())
+            .map(|hir| DebugCtx::new(db, &module, hir)),
     };
 
     f(ctx)
 }
 
+fn optimization_level(level: Optimization) -> OptimizationLevel {
+    match level {
+        | Optimization::None => OptimizationLevel::None,
+        | Optimization::Less => OptimizationLevel::Less,
+        | Optimization::Default | Optimization::Size | Optimization::SizeMin => OptimizationLevel::Default,
+        | Optimization::Aggressive => OptimizationLevel::Aggressive,
+    }
+}
+
+/// The pipeline of the new pass manager used for the optimization level.
+fn pass_pipeline(level: Optimization) -> &'static str {
+    match level {
+        | Optimization::None => "default<O0>",
+        | Optimization::Less => "default<O1>",
+        | Optimization::Default => "default<O2>",
+        | Optimization::Aggressive => "default<O3>",
+        | Optimization::Size => "default<Os>",
+        | Optimization::SizeMin => "default<Oz>",
+    }
+}
+
 impl<'ctx> CodegenCtx<'_, 'ctx> {
     pub fn write(&mut self, file: &mut dyn std::io::Write) {
         tracing::debug!("{}", self.module.to_string());
+        self.write_file_type(file, FileType::Object);
+    }
 
+    pub fn write_asm(&mut self, file: &mut dyn std::io::Write) {
+        self.write_file_type(file, FileType::Assembly);
+    }
+
+    pub fn write_ir(&mut self, file: &mut dyn std::io::Write) {
+        file.write_all(self.module.print_to_string().to_bytes()).unwrap();
+    }
+
+    fn write_file_type(&mut self, file: &mut dyn std::io::Write, file_type: FileType) {
         let buffer = self
             .target_machine
-            .write_to_memory_buffer(self.module, FileType::Object)
+            .write_to_memory_buffer(self.module, file_type)
             .unwrap();
 
         file.write_all(buffer.as_slice()).unwrap();
@@ -134,17 +179,26 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
     pub fn codegen(&mut self, module: hir::Module) {
         self.codegen_module(module);
 
-        let mpm = PassManager::create(());
+        if let Some(debug) = &self.debug {
+            debug.finalize();
+        }
+
+        let pipeline = pass_pipeline(self.db.optimization());
+        let options = PassBuilderOptions::create();
 
-        mpm.add_always_inliner_pass();
-        // mpm.add_function_inlining_pass();
-        mpm.run_on(self.module);
+        options.set_verify_each(cfg!(debug_assertions));
+        if let Err(e) = self.module.run_passes(pipeline, &self.target_machine, options) {
+            base_db::Error::throw(format!("failed to optimize module: {}", e));
+        }
     }
 
     pub fn codegen_module(&mut self, module: hir::Module) {
         for def in module.declarations(self.db.upcast()) {
             match def {
                 | hir::ModuleDef::Func(func) => self.codegen_func(func),
+                | hir::ModuleDef::Static(static_) if static_.is_c_export(self.db.upcast()) => {
+                    self.codegen_exported_static(static_)
+                },
                 | _ => {},
             }
         }
@@ -163,6 +217,46 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
         }
     }
 
+    /// Exported statics are global variables initialized with the value of their body, which is
+    /// evaluated at compile time.
+    pub fn codegen_exported_static(&mut self, static_: hir::Static) {
+        if !static_.has_body(self.db.upcast()) {
+            return;
+        }
+
+        let name = static_.link_name(self.db.upcast()).0.to_string();
+        let layout = self.db.layout_of(self.db.repr_of(static_.ty(self.db.upcast())));
+        let ty = self.basic_type_for_ral(&layout);
+        let value = match self.db.eval(Instance::mono(static_.into()), Arc::new([])) {
+            | Ok(Const::Int(i)) if ty.is_int_type() => {
+                ty.into_int_type().const_int(i as u64, true).as_basic_value_enum()
+            },
+            | Ok(Const::Char(c)) if ty.is_int_type() => {
+                ty.into_int_type().const_int(c as u64, false).as_basic_value_enum()
+            },
+            | Ok(Const::Float(f)) if ty.is_float_type() => ty
+                .into_float_type()
+                .const_float(f64::from_bits(f))
+                .as_basic_value_enum(),
+            | Ok(Const::Ctor(ctor)) if ty.is_int_type() => {
+                let ctors = ctor.type_ctor().ctors(self.db.upcast());
+                let idx = ctors.iter().position(|&c| c == ctor).unwrap();
+
+                ty.into_int_type().const_int(idx as u64, false).as_basic_value_enum()
+            },
+            | _ => base_db::Error::throw(format!(
+                "the value of the exported static `{}` must be a constant number, character or boolean",
+                name
+            )),
+        };
+
+        let global = self.module.add_global(ty, None, &name);
+
+        global.set_initializer(&value);
+        global.set_constant(true);
+        global.set_linkage(Linkage::External);
+    }
+
     pub fn declare_func(&mut self, func: Instance) -> (values::FunctionValue<'ctx>, FnAbi<'ctx>) {
         if let Some(value) = self.funcs.get(&func) {
             return value.clone();
@@ -177,10 +271,34 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
         let name = func.link_name(self.db);
         let sig = self.db.func_signature(func.clone());
         tracing::debug!("{} :: {}", name, hir::HirDisplay::display(&sig, self.db.upcast()));
-        let abi = self.compute_fn_abi(&sig);
+        let mut abi = self.compute_fn_abi(&sig);
+
+        if let InstanceDef::Def(DefWithBody::Func(f)) = func.def {
+            abi.variadic = f.is_variadic(self.db.upcast());
+
+            if abi.variadic && (abi.args.is_empty() || !self.db.target().is_wasm()) {
+                base_db::Error::throw(format!(
+                    "`{}` can't be `@variadic`, variadic functions need a parameter for their arguments and are only supported on wasm targets",
+                    name
+                ));
+            }
+        }
+
         let ty = self.fn_type_for_abi(&abi);
         let value = self.module.add_function(&name, ty, Some(linkage));
 
+        if let InstanceDef::Def(DefWithBody::Func(f)) = func.def {
+            if let Some(import_module) = f.wasm_import_module(self.db.upcast()) {
+                let module = self
+                    .context
+                    .create_string_attribute("wasm-import-module", &import_module);
+                let import_name = self.context.create_string_attribute("wasm-import-name", &name);
+
+                value.add_attribute(AttributeLoc::Function, module);
+                value.add_attribute(AttributeLoc::Function, import_name);
+            }
+        }
+
         self.funcs.insert(func, (value, abi.clone()));
 
         (value, abi)
@@ -193,7 +311,7 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
 
         let (value, abi) = self.declare_func(func.clone());
 
-        if func.subst.is_none() {
+        if func.subst.is_none() && !matches!(func.def, InstanceDef::Body(_)) {
             return (value, abi);
         }
 
@@ -203,7 +321,8 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
 
         if func.has_body(self.db) {
             let insert_block = self.builder.get_insert_block();
-            let body = func.body(self.db);
+            let debug_loc = self.builder.get_current_debug_location();
+            let body = self.db.optimized_body(func.body(self.db));
             let mut ctx = BodyCtx {
                 body: self.db.lookup_intern_body(body),
                 instance: func,
@@ -212,6 +331,7 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
                 ret_ptr: None,
                 blocks: ArenaMap::default(),
                 locals: ArenaMap::default(),
+                debug_scope: None,
                 cx: self,
             };
 
@@ -220,6 +340,11 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
             if let Some(block) = insert_block {
                 self.builder.position_at_end(block);
             }
+
+            match debug_loc {
+                | Some(loc) => self.builder.set_current_debug_location(loc),
+                | None => self.builder.unset_current_debug_location(),
+            }
         }
 
         (value, abi)
@@ -238,6 +363,7 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
             let instance = Instance::mono(func.into());
             let (value, abi) = self.declare_func(instance.clone());
             let body = self.db.body_mir(DefWithBodyId::FuncId(func.into()));
+            let body = self.db.optimized_body(body);
             let mut ctx = BodyCtx {
                 body: self.db.lookup_intern_body(body),
                 instance,
@@ -246,12 +372,17 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
                 ret_ptr: None,
                 blocks: ArenaMap::default(),
                 locals: ArenaMap::default(),
+                debug_scope: None,
                 cx: self,
             };
 
             ctx.codegen();
 
-            if func.attrs(self.db.upcast()).by_key("main").exists() {
+            let attrs = func.attrs(self.db.upcast());
+
+            if attrs.by_key("test").exists() {
+                self.codegen_test_shim(func);
+            } else if attrs.by_key("main").exists() && !func.lib(self.db.upcast()).is_test(self.db.upcast()) {
                 self.codegen_main_shim(func);
             }
         }
@@ -263,10 +394,25 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
             ret: Repr::i32(),
         };
 
+        self.codegen_shim(main_func, "main", sig);
+    }
+
+    /// Generate a function reporting the result of `test_func` as an exit code, which is called
+    /// by the test harness.
+    pub fn codegen_test_shim(&mut self, test_func: hir::Func) {
+        let sig = Signature {
+            params: Box::new([]),
+            ret: Repr::i32(),
+        };
+
+        self.codegen_shim(test_func, &test_shim_name(self.db, test_func), sig);
+    }
+
+    fn codegen_shim(&mut self, func: hir::Func, name: &str, sig: Signature) {
         let abi = self.compute_fn_abi(&sig);
-        let main_type = self.fn_type_for_abi(&abi);
-        let value = self.module.add_function("main", main_type, None);
-        let main_shim = self.db.mir_main_shim(main_func);
+        let shim_type = self.fn_type_for_abi(&abi);
+        let value = self.module.add_function(name, shim_type, None);
+        let main_shim = self.db.optimized_body(self.db.mir_main_shim(func));
         let instance = Instance::mono(main_shim.into());
         let mut ctx = BodyCtx {
             body: self.db.lookup_intern_body(main_shim),
@@ -276,9 +422,83 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
             ret_ptr: None,
             blocks: ArenaMap::default(),
             locals: ArenaMap::default(),
+            debug_scope: None,
             cx: self,
         };
 
         ctx.codegen();
     }
+
+    /// Generate the `main` function of the test harness. The harness runs the test at the index
+    /// given as its first argument and exits with the result of the test, or with 2 when the
+    /// index is missing or out of range.
+    pub fn codegen_test_harness(&mut self, tests: &[hir::Func]) {
+        let i32_type = self.context.i32_type();
+        let str_type = self.context.i8_type().ptr_type(AddressSpace::default());
+        let main_type = i32_type.fn_type(
+            &[i32_type.into(), str_type.ptr_type(AddressSpace::default()).into()],
+            false,
+        );
+        let main = self.module.add_function("main", main_type, None);
+        let atoi_type = i32_type.fn_type(&[str_type.into()], false);
+        let atoi = self.module.add_function("atoi", atoi_type, Some(Linkage::External));
+        let entry = self.context.append_basic_block(main, "");
+        let select = self.context.append_basic_block(main, "");
+        let invalid = self.context.append_basic_block(main, "");
+        let argc = main.get_nth_param(0).unwrap().into_int_value();
+        let argv = main.get_nth_param(1).unwrap().into_pointer_value();
+
+        self.builder.position_at_end(entry);
+
+        let has_index = self
+            .builder
+            .build_int_compare(IntPredicate::SGT, argc, i32_type.const_int(1, false), "");
+
+        self.builder.build_conditional_branch(has_index, select, invalid);
+        self.builder.position_at_end(select);
+
+        let arg = unsafe { self.builder.build_gep(argv, &[i32_type.const_int(1, false)], "") };
+        let arg = self.builder.build_load(arg, "");
+        let index = self
+            .builder
+            .build_call(atoi, &[arg.into()], "")
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let shim_type = i32_type.fn_type(&[], false);
+        let cases = tests
+            .iter()
+            .enumerate()
+            .map(|(i, &test)| {
+                let shim = self
+                    .module
+                    .add_function(&test_shim_name(self.db, test), shim_type, Some(Linkage::External));
+                let block = self.context.append_basic_block(main, "");
+
+                self.builder.position_at_end(block);
+
+                let res = self
+                    .builder
+                    .build_call(shim, &[], "")
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap();
+
+                self.builder.build_return(Some(&res));
+                (i32_type.const_int(i as u64, false), block)
+            })
+            .collect::<Vec<_>>();
+
+        self.builder.position_at_end(select);
+        self.builder.build_switch(index, invalid, &cases);
+        self.builder.position_at_end(invalid);
+        self.builder.build_return(Some(&i32_type.const_int(2, false)));
+    }
+}
+
+/// The name of the function running `test` in the test harness.
+fn test_shim_name(db: &dyn MirDatabase, test: hir::Func) -> String {
+    format!("{}$test", Instance::mono(test.into()).link_name(db))
 }
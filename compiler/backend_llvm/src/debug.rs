@@ -0,0 +1,295 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use base_db::input::{FileId, LineCol, LineIndex};
+use hir::id::DefWithBodyId;
+use hir::{ExprId, HirDisplay, PatId};
+use inkwell::basic_block::BasicBlock;
+use inkwell::debug_info::{
+    AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DISubprogram, DIType, DWARFEmissionKind,
+    DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::{FlagBehavior, Linkage, Module};
+use mir::db::MirDatabase;
+use mir::layout::{Abi, ReprAndLayout};
+use mir::repr::{Integer, Primitive};
+use rustc_hash::FxHashMap;
+
+use crate::ctx::BodyCtx;
+use crate::local::LocalRef;
+
+const DW_ATE_ADDRESS: u32 = 0x01;
+const DW_ATE_BOOLEAN: u32 = 0x02;
+const DW_ATE_FLOAT: u32 = 0x04;
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_UNSIGNED: u32 = 0x07;
+
+pub struct DebugCtx<'ctx> {
+    pub builder: DebugInfoBuilder<'ctx>,
+    pub unit: DICompileUnit<'ctx>,
+    files: RefCell<FxHashMap<FileId, (DIFile<'ctx>, Arc<LineIndex>)>>,
+    types: RefCell<FxHashMap<Arc<ReprAndLayout>, DIType<'ctx>>>,
+}
+
+impl<'ctx> DebugCtx<'ctx> {
+    pub fn new(db: &dyn MirDatabase, module: &Module<'ctx>, hir: hir::Module) -> Self {
+        let version = module.get_context().i32_type().const_int(3, false);
+
+        module.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version);
+
+        let (name, dir) = file_name_and_dir(db, hir.file_id(db.upcast()));
+        let (builder, unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::C,
+            &name,
+            &dir,
+            concat!("fi ", env!("CARGO_PKG_VERSION")),
+            db.optimization().is_enabled(),
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+
+        Self {
+            builder,
+            unit,
+            files: RefCell::default(),
+            types: RefCell::default(),
+        }
+    }
+
+    pub fn finalize(&self) {
+        self.builder.finalize();
+    }
+
+    fn file(&self, db: &dyn MirDatabase, file: FileId) -> (DIFile<'ctx>, Arc<LineIndex>) {
+        if let Some(entry) = self.files.borrow().get(&file) {
+            return entry.clone();
+        }
+
+        let (name, dir) = file_name_and_dir(db, file);
+        let entry = (
+            self.builder.create_file(&name, &dir),
+            Arc::new(LineIndex::new(&db.file_text(file))),
+        );
+
+        self.files.borrow_mut().insert(file, entry.clone());
+        entry
+    }
+
+    fn debug_type(&self, db: &dyn MirDatabase, layout: &Arc<ReprAndLayout>) -> DIType<'ctx> {
+        if let Some(ty) = self.types.borrow().get(layout) {
+            return *ty;
+        }
+
+        let name = layout.repr.display(db.upcast()).to_string();
+        let size = layout.size.bits();
+        let ty = match layout.abi {
+            | Abi::Scalar(ref scalar) => {
+                let encoding = match scalar.value {
+                    | Primitive::Int(Integer::I8, false) if scalar.valid_range == (0..=1) => DW_ATE_BOOLEAN,
+                    | Primitive::Int(_, true) => DW_ATE_SIGNED,
+                    | Primitive::Int(_, false) => DW_ATE_UNSIGNED,
+                    | Primitive::Float | Primitive::Double => DW_ATE_FLOAT,
+                    | Primitive::Pointer => DW_ATE_ADDRESS,
+                };
+
+                self.builder
+                    .create_basic_type(&name, size, encoding, DIFlags::ZERO)
+                    .unwrap()
+                    .as_type()
+            },
+            | _ => self
+                .builder
+                .create_struct_type(
+                    self.unit.as_debug_info_scope(),
+                    &name,
+                    self.unit.get_file(),
+                    0,
+                    size,
+                    layout.align.bits() as u32,
+                    DIFlags::ZERO,
+                    None,
+                    &[],
+                    0,
+                    None,
+                    &name,
+                )
+                .as_type(),
+        };
+
+        self.types.borrow_mut().insert(layout.clone(), ty);
+        ty
+    }
+}
+
+impl<'ctx> BodyCtx<'_, '_, 'ctx> {
+    /// Attach a subprogram to the function and set the initial debug location to the start
+    /// of its body.
+    pub fn create_subprogram(&mut self) {
+        let debug = match &self.debug {
+            | Some(debug) => debug,
+            | None => return,
+        };
+
+        self.builder.unset_current_debug_location();
+
+        let origin = self.body.origin;
+        let expr = origin.expr.unwrap_or_else(|| self.db.body(origin.def).body_expr());
+        let (file, line_col) = match self.expr_line_col(origin.def, expr) {
+            | Some(loc) => loc,
+            | None => return,
+        };
+
+        let (di_file, _) = debug.file(self.db, file);
+        let name = self.instance.display(self.db.upcast()).to_string();
+        let link_name = self.func.get_name().to_string_lossy();
+        let ty = debug.builder.create_subroutine_type(di_file, None, &[], DIFlags::ZERO);
+        let line = line_col.line + 1;
+        let subprogram = debug.builder.create_function(
+            di_file.as_debug_info_scope(),
+            &name,
+            Some(link_name.as_ref()),
+            di_file,
+            line,
+            ty,
+            self.func.get_linkage() == Linkage::Internal,
+            true,
+            line,
+            DIFlags::ZERO,
+            self.db.optimization().is_enabled(),
+        );
+
+        self.func.set_subprogram(subprogram);
+        self.debug_scope = Some((subprogram, line));
+        self.set_debug_loc(None);
+    }
+
+    /// Set the debug location of the following instructions to the source expression,
+    /// or to the start of the function if there is none.
+    pub fn set_debug_loc(&self, source: Option<ExprId>) {
+        let (debug, (scope, line)) = match (&self.debug, self.debug_scope) {
+            | (Some(debug), Some(scope)) => (debug, scope),
+            | _ => return,
+        };
+
+        let (line, col) = source
+            .and_then(|expr| self.expr_line_col(self.body.origin.def, expr))
+            .map(|(_, lc)| (lc.line + 1, lc.col + 1))
+            .unwrap_or((line, 0));
+
+        let loc = debug
+            .builder
+            .create_debug_location(self.context, line, col, scope.as_debug_info_scope(), None);
+
+        self.builder.set_current_debug_location(loc);
+    }
+
+    /// Describe the variables of the body which are stored in memory.
+    pub fn declare_vars(&self, entry: BasicBlock<'ctx>) {
+        let (debug, (scope, _)) = match (&self.debug, self.debug_scope) {
+            | (Some(debug), Some(scope)) => (debug, scope),
+            | _ => return,
+        };
+
+        let def = self.body.origin.def;
+        let params = &self.body.blocks[mir::syntax::Block::ENTRY.0].params;
+
+        for &(local, pat) in &self.body.vars {
+            let place = match self.locals.get(local.0) {
+                | Some(LocalRef::Place(place)) => place,
+                | _ => continue,
+            };
+
+            let (file, line_col) = match self.pat_line_col(def, pat) {
+                | Some(loc) => loc,
+                | None => continue,
+            };
+
+            let (di_file, _) = debug.file(self.db, file);
+            let name = hir::Local::from((def, pat)).name(self.db.upcast()).to_string();
+            let ty = debug.debug_type(self.db, &place.layout);
+            let line = line_col.line + 1;
+            let var = match params.iter().position(|&p| p == local) {
+                | Some(arg) => debug.builder.create_parameter_variable(
+                    scope.as_debug_info_scope(),
+                    &name,
+                    arg as u32 + 1,
+                    di_file,
+                    line,
+                    ty,
+                    true,
+                    DIFlags::ZERO,
+                ),
+                | None => debug.builder.create_auto_variable(
+                    scope.as_debug_info_scope(),
+                    &name,
+                    di_file,
+                    line,
+                    ty,
+                    true,
+                    DIFlags::ZERO,
+                    place.layout.align.bits() as u32,
+                ),
+            };
+
+            let loc = debug.builder.create_debug_location(
+                self.context,
+                line,
+                line_col.col + 1,
+                scope.as_debug_info_scope(),
+                None,
+            );
+
+            debug
+                .builder
+                .insert_declare_at_end(place.ptr, Some(var), None, loc, entry);
+        }
+    }
+
+    fn expr_line_col(&self, def: DefWithBodyId, expr: ExprId) -> Option<(FileId, LineCol)> {
+        let (_, source_map) = self.db.body_source_map(def);
+        let src = source_map.expr_syntax(expr).left()?;
+        let (_, line_index) = self.debug.as_ref()?.file(self.db, src.file_id);
+
+        Some((
+            src.file_id,
+            line_index.line_col(src.value.syntax_node_ptr().range().start()),
+        ))
+    }
+
+    fn pat_line_col(&self, def: DefWithBodyId, pat: PatId) -> Option<(FileId, LineCol)> {
+        let (_, source_map) = self.db.body_source_map(def);
+        let src = source_map.pat_syntax(pat).left()?;
+        let (_, line_index) = self.debug.as_ref()?.file(self.db, src.file_id);
+
+        Some((
+            src.file_id,
+            line_index.line_col(src.value.syntax_node_ptr().range().start()),
+        ))
+    }
+}
+
+fn file_name_and_dir(db: &dyn MirDatabase, file: FileId) -> (String, String) {
+    let root = db.source_root(db.file_source_root(file));
+
+    match root.path_for_file(file) {
+        | Some(path) => {
+            let dir = path.parent().map(|p| p.to_string()).unwrap_or_default();
+            let name = match path.name_and_extension() {
+                | Some((name, Some(ext))) => format!("{}.{}", name, ext),
+                | Some((name, None)) => name.to_string(),
+                | None => path.to_string(),
+            };
+
+            (name, dir)
+        },
+        | None => (String::from("<unknown>"), String::new()),
+    }
+}
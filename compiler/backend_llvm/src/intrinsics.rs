@@ -1,5 +1,6 @@
 use inkwell::types::{self, BasicType};
-use inkwell::values;
+use inkwell::values::{self, BasicValue};
+use inkwell::AddressSpace;
 use mir::syntax::{Operand, Place};
 
 use crate::ctx::{BodyCtx, CodegenCtx};
@@ -17,6 +18,12 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
                     | _ => unreachable!(),
                 }
             },
+            | "floor" | "ceil" | "round" | "sqrt" => {
+                let width = self.operand_layout(&args[0]).size.bits();
+                self.codegen_intrinsic_call(&format!("llvm.{func}.f{width}"), args)
+            },
+            | "format_float" => self.codegen_format_float(args),
+            | "str_free" => return self.codegen_str_free(place, &args[0]),
             | _ => self.codegen_intrinsic_call(func, args),
         };
 
@@ -26,6 +33,35 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
         self.store_return(place, res);
     }
 
+    /// `snprintf(buf, size, "%.*g", precision, x)`, which `Data:Float:to_str` of core formats
+    /// floats with, since variadic functions can only be called from C.
+    fn codegen_format_float(&mut self, args: &[Operand]) -> values::BasicValueEnum<'ctx> {
+        let snprintf = self.cx.get_intrinsic("snprintf");
+        let fmt = self.alloc_const_name("fmt");
+        let fmt = self.builder.build_global_string_ptr("%.*g", &fmt).as_basic_value_enum();
+        let mut args = args
+            .iter()
+            .map(|a| self.codegen_operand(a).load(self.cx).into())
+            .collect::<Vec<_>>();
+
+        args.insert(2, fmt.into());
+        self.builder
+            .build_call(snprintf, &args, "")
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+    }
+
+    /// Frees a string allocated by `Data:Float:to_str`.
+    fn codegen_str_free(&mut self, place: &Place, arg: &Operand) {
+        let free = self.cx.get_intrinsic("free");
+        let ptr = self.codegen_operand(arg).field(self.cx, 0).load(self.cx);
+        let layout = self.place_layout(place);
+
+        self.builder.build_call(free, &[ptr.into()], "");
+        self.store_return(place, OperandRef::new_zst(self.cx, layout));
+    }
+
     fn codegen_intrinsic_call(&mut self, func: &str, args: &[Operand]) -> values::BasicValueEnum<'ctx> {
         let func = self.cx.get_intrinsic(func);
         let args = args
@@ -42,11 +78,15 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
 }
 
 impl<'ctx> CodegenCtx<'_, 'ctx> {
-    fn get_intrinsic(&mut self, name: &str) -> values::FunctionValue<'ctx> {
+    pub fn get_intrinsic(&mut self, name: &str) -> values::FunctionValue<'ctx> {
         if let Some(v) = self.intrinsics.get(name) {
             return v.clone();
         }
 
+        if let Some(v) = self.module.get_function(name) {
+            return v;
+        }
+
         self.declare_intrinsic(name)
     }
 
@@ -54,9 +94,13 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
         &mut self,
         name: &'static str,
         args: &[types::BasicMetadataTypeEnum<'ctx>],
-        ret: types::BasicTypeEnum<'ctx>,
+        ret: Option<types::BasicTypeEnum<'ctx>>,
+        is_var_args: bool,
     ) -> values::FunctionValue<'ctx> {
-        let ty = ret.fn_type(args, false);
+        let ty = match ret {
+            | Some(ret) => ret.fn_type(args, is_var_args),
+            | None => self.context.void_type().fn_type(args, is_var_args),
+        };
         let f = self.module.add_function(name, ty, None);
 
         self.intrinsics.insert(name, f);
@@ -67,12 +111,22 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
         macro_rules! ifn {
             ($name:literal,fn() -> $ret:expr) => {
                 if name == $name {
-                    return self.insert_intrinsic($name, &[], $ret.into());
+                    return self.insert_intrinsic($name, &[], Some($ret.into()), false);
+                }
+            };
+            ($name:literal,fn($($arg:expr),*)) => {
+                if name == $name {
+                    return self.insert_intrinsic($name, &[$($arg.into()),*], None, false);
                 }
             };
             ($name:literal,fn($($arg:expr),*) -> $ret:expr) => {
                 if name == $name {
-                    return self.insert_intrinsic($name, &[$($arg.into()),*], $ret.into());
+                    return self.insert_intrinsic($name, &[$($arg.into()),*], Some($ret.into()), false);
+                }
+            };
+            ($name:literal,fn($($arg:expr),*; ...) -> $ret:expr) => {
+                if name == $name {
+                    return self.insert_intrinsic($name, &[$($arg.into()),*], Some($ret.into()), true);
                 }
             };
         }
@@ -94,6 +148,48 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
         ifn!("llvm.bitreverse.i64", fn(t_i64) -> t_i64);
         ifn!("llvm.bitreverse.i128", fn(t_i128) -> t_i128);
 
+        let t_f32 = self.context.f32_type();
+        let t_f64 = self.context.f64_type();
+
+        ifn!("llvm.floor.f32", fn(t_f32) -> t_f32);
+        ifn!("llvm.floor.f64", fn(t_f64) -> t_f64);
+        ifn!("llvm.ceil.f32", fn(t_f32) -> t_f32);
+        ifn!("llvm.ceil.f64", fn(t_f64) -> t_f64);
+        ifn!("llvm.round.f32", fn(t_f32) -> t_f32);
+        ifn!("llvm.round.f64", fn(t_f64) -> t_f64);
+        ifn!("llvm.sqrt.f32", fn(t_f32) -> t_f32);
+        ifn!("llvm.sqrt.f64", fn(t_f64) -> t_f64);
+
+        // `llvm.fptosi.sat.i<N>.f<M>` and `llvm.fptoui.sat.i<N>.f<M>`
+        if let Some(rest) = name
+            .strip_prefix("llvm.fptosi.sat.i")
+            .or_else(|| name.strip_prefix("llvm.fptoui.sat.i"))
+        {
+            let (bits, float) = rest.split_once(".f").unwrap();
+            let int = self.context.custom_width_int_type(bits.parse().unwrap());
+            let float = if float == "32" { t_f32 } else { t_f64 };
+
+            return self.module.add_function(name, int.fn_type(&[float.into()], false), None);
+        }
+
+        let t_ptr = t_i8.ptr_type(AddressSpace::default());
+        let t_usize = self.context.ptr_sized_int_type(&self.target_data, None);
+
+        ifn!("llvm.va_start", fn(t_ptr));
+
+        // Closures and float formatting depend on these, which are provided by the C library.
+        if !self.db.target().has_libc() {
+            base_db::Error::throw(format!(
+                "`{}` is not available on the target `{}`, which has no C library, use `wasm32-wasi` instead",
+                name,
+                self.db.target().triple()
+            ));
+        }
+
+        ifn!("malloc", fn(t_usize) -> t_ptr);
+        ifn!("free", fn(t_ptr));
+        ifn!("snprintf", fn(t_ptr, t_usize, t_ptr; ...) -> t_i32);
+
         panic!("unknown intrinsic {}", name);
     }
 }
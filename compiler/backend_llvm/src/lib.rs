@@ -3,6 +3,7 @@
 mod abi;
 mod body;
 mod ctx;
+mod debug;
 mod intrinsics;
 mod local;
 mod operand;
@@ -15,8 +16,40 @@ use std::io::Write;
 use mir::db::MirDatabase;
 
 pub fn codegen(db: &dyn MirDatabase, module: hir::Module, file: &mut dyn Write) {
-    ctx::with_codegen_ctx(db, module, |mut ctx| {
+    let name = module.name(db.upcast());
+
+    ctx::with_codegen_ctx(db, name.as_ref(), Some(module), |mut ctx| {
+        ctx.codegen(module);
+        ctx.write(file);
+    })
+}
+
+/// Write the optimized LLVM IR of `module` as text.
+pub fn codegen_ir(db: &dyn MirDatabase, module: hir::Module, file: &mut dyn Write) {
+    let name = module.name(db.upcast());
+
+    ctx::with_codegen_ctx(db, name.as_ref(), Some(module), |mut ctx| {
+        ctx.codegen(module);
+        ctx.write_ir(file);
+    })
+}
+
+/// Write the assembly of `module`.
+pub fn codegen_asm(db: &dyn MirDatabase, module: hir::Module, file: &mut dyn Write) {
+    let name = module.name(db.upcast());
+
+    ctx::with_codegen_ctx(db, name.as_ref(), Some(module), |mut ctx| {
         ctx.codegen(module);
+        ctx.write_asm(file);
+    })
+}
+
+/// Generate the object file containing the `main` function of the test harness of `lib`.
+pub fn codegen_test_harness(db: &dyn MirDatabase, lib: hir::Lib, file: &mut dyn Write) {
+    let tests = lib.tests(db.upcast());
+
+    ctx::with_codegen_ctx(db, "test_harness", None, |mut ctx| {
+        ctx.codegen_test_harness(&tests);
         ctx.write(file);
     })
 }
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use inkwell::AddressSpace;
 use mir::layout::ReprAndLayout;
 use mir::syntax::Local;
 use rustc_hash::FxHashSet;
@@ -30,6 +31,10 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
         let body = self.body.clone();
         let block = body.blocks.iter().next().unwrap().1;
         let mut index = self.fn_abi.ret.is_indirect() as u32;
+        let va_list = match self.fn_abi.variadic {
+            | true => block.params.last().copied(),
+            | false => None,
+        };
 
         block
             .params
@@ -39,6 +44,19 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
                 let layout = self.db.layout_of(repr);
                 let pass_mode = self.pass_mode(&layout);
 
+                if Some(*arg) == va_list {
+                    let list = self.codegen_va_list(layout.clone());
+
+                    if !by_ref_locals.contains(arg) {
+                        return LocalRef::Operand(Some(list));
+                    }
+
+                    let tmp = PlaceRef::new_alloca(self.cx, layout);
+
+                    list.val.store(self.cx, &tmp);
+                    return LocalRef::Place(tmp);
+                }
+
                 if !by_ref_locals.contains(arg) {
                     let local = |op| LocalRef::Operand(Some(op));
 
@@ -101,4 +119,20 @@ impl<'ctx> BodyCtx<'_, '_, 'ctx> {
             })
             .collect()
     }
+
+    /// The `va_list` of a variadic function, which is a pointer to its variadic arguments on the
+    /// targets supporting them. Ending the list is a no-op on these targets, so it is not ended.
+    fn codegen_va_list(&mut self, layout: Arc<ReprAndLayout>) -> OperandRef<'ctx> {
+        let va_start = self.cx.get_intrinsic("llvm.va_start");
+        let ty = self.basic_type_for_ral(&layout);
+        let list = self.builder.build_alloca(ty, "");
+        let i8_ptr = self.context.i8_type().ptr_type(AddressSpace::default());
+        let ptr = self.builder.build_pointer_cast(list, i8_ptr, "");
+
+        self.builder.build_call(va_start, &[ptr.into()], "");
+
+        let value = self.builder.build_load(list, "");
+
+        OperandRef::new_imm(layout, value)
+    }
 }
@@ -97,6 +97,25 @@ impl<'ctx> OperandRef<'ctx> {
         }
     }
 
+    pub fn load_pair(
+        &self,
+        ctx: &mut CodegenCtx<'_, 'ctx>,
+    ) -> (values::BasicValueEnum<'ctx>, values::BasicValueEnum<'ctx>) {
+        let val = match self.val {
+            | OperandValue::Pair(a, b) => return (a, b),
+            | OperandValue::Ref(ptr, _) => {
+                return PlaceRef::new(self.layout.clone(), ptr, None).load_operand(ctx).pair();
+            },
+            | OperandValue::Phi(phi) => phi.as_basic_value().into_struct_value(),
+            | OperandValue::Imm(imm) => imm.into_struct_value(),
+        };
+
+        let a = ctx.builder.build_extract_value(val, 0, "").unwrap();
+        let b = ctx.builder.build_extract_value(val, 1, "").unwrap();
+
+        (a, b)
+    }
+
     pub fn load(&self, ctx: &mut CodegenCtx<'_, 'ctx>) -> values::BasicValueEnum<'ctx> {
         match self.val {
             | OperandValue::Ref(ptr, _) => ctx.builder.build_load(ptr, ""),
@@ -1,8 +1,8 @@
 use std::sync::Arc;
 
-use inkwell::types::BasicType;
+use inkwell::types::{self, BasicType};
 use inkwell::values::BasicValue;
-use inkwell::{values, AddressSpace};
+use inkwell::{values, AddressSpace, IntPredicate};
 use mir::layout::{Abi, ReprAndLayout, TagEncoding, Variants};
 use mir::repr::Repr;
 
@@ -158,16 +158,64 @@ impl<'ctx> PlaceRef<'ctx> {
             } => (tag, tag_encoding, tag_field),
         };
 
-        let tag = self.field(ctx, tag_field);
-        let tag_op = tag.load_operand(ctx);
-        let tag_imm = tag_op.immediate().into_int_value();
-
         match *tag_encoding {
-            | TagEncoding::Direct => ctx.builder.build_int_cast(tag_imm, discr_ty, ""),
-            | TagEncoding::Niche { .. } => todo!(),
+            | TagEncoding::Direct => {
+                let tag = self.field(ctx, tag_field);
+                let tag_op = tag.load_operand(ctx);
+                let tag_imm = tag_op.immediate().into_int_value();
+
+                ctx.builder.build_int_cast(tag_imm, discr_ty, "")
+            },
+            | TagEncoding::Niche {
+                dataful_variant,
+                ref niche_variants,
+                niche_start,
+            } => {
+                let (tag_ty, tag_ptr) = self.niche_ptr(ctx, tag_field);
+                let tag = ctx.builder.build_load(tag_ptr, "").into_int_value();
+                let relative_max = (*niche_variants.end() - *niche_variants.start()) as u64;
+                let relative = ctx
+                    .builder
+                    .build_int_sub(tag, tag_ty.const_int(niche_start as u64, false), "");
+                let is_niche = ctx.builder.build_int_compare(
+                    IntPredicate::ULE,
+                    relative,
+                    tag_ty.const_int(relative_max, false),
+                    "",
+                );
+
+                let relative = ctx.builder.build_int_cast_sign_flag(relative, discr_ty, false, "");
+                let niche_discr =
+                    ctx.builder
+                        .build_int_add(relative, discr_ty.const_int(*niche_variants.start() as u64, false), "");
+
+                ctx.builder
+                    .build_select(
+                        is_niche,
+                        niche_discr,
+                        discr_ty.const_int(dataful_variant as u64, false),
+                        "",
+                    )
+                    .into_int_value()
+            },
         }
     }
 
+    /// Get a pointer to the niche field of an enum as an integer of the same size.
+    /// This allows the niche to be read and written even if it is a pointer or a bool.
+    fn niche_ptr(
+        &self,
+        ctx: &mut CodegenCtx<'_, 'ctx>,
+        tag_field: usize,
+    ) -> (types::IntType<'ctx>, values::PointerValue<'ctx>) {
+        let niche = self.field(ctx, tag_field);
+        let niche_ty = ctx.context.custom_width_int_type(niche.layout.size.bits() as u32);
+        let ptr_ty = niche_ty.ptr_type(AddressSpace::default());
+        let ptr = ctx.builder.build_pointer_cast(niche.ptr, ptr_ty, "");
+
+        (niche_ty, ptr)
+    }
+
     pub fn set_discr(&self, ctx: &mut CodegenCtx<'_, 'ctx>, idx: usize) {
         if self.layout.variant(idx).abi.is_uninhabited() {
             return;
@@ -200,13 +248,12 @@ impl<'ctx> PlaceRef<'ctx> {
                 ..
             } => {
                 if idx != dataful_variant {
-                    let niche = self.field(ctx, tag_field);
-                    let niche_ty = ctx.basic_type_for_ral(&niche.layout).into_int_type();
+                    let (niche_ty, niche_ptr) = self.niche_ptr(ctx, tag_field);
                     let niche_val = idx - *niche_variants.start();
                     let niche_val = (niche_val as u128).wrapping_add(niche_start);
                     let niche_val = niche_ty.const_int(niche_val as u64, false);
 
-                    ctx.builder.build_store(niche.ptr, niche_val);
+                    ctx.builder.build_store(niche_ptr, niche_val);
                 }
             },
         }
@@ -28,6 +28,9 @@ pub fn analyze(ctx: &BodyCtx<'_, '_, '_>) -> FxHashSet<Local> {
         let layout = ctx.db.layout_of(repr);
         let kind = if layout.is_zst() {
             LocalKind::ZST
+        } else if ctx.debug.is_some() && ctx.body.vars.iter().any(|&(l, _)| l.0 == id) {
+            // Variables are kept in memory so their values can be inspected by a debugger.
+            LocalKind::Memory
         } else if let Abi::Scalar(_) | Abi::ScalarPair(_, _) = layout.abi {
             LocalKind::Unused
         } else {
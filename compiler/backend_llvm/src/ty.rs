@@ -24,7 +24,9 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
             | PassMode::ByRef { size: None } => todo!(),
         };
 
-        for arg in fn_abi.args.iter() {
+        let fixed = fn_abi.args.len() - fn_abi.variadic as usize;
+
+        for arg in fn_abi.args[..fixed].iter() {
             match arg.mode {
                 | PassMode::NoPass => {},
                 | PassMode::ByVal(ty) => args.push(ty.into()),
@@ -41,8 +43,8 @@ impl<'ctx> CodegenCtx<'_, 'ctx> {
         }
 
         match ret {
-            | Ok(ret) => ret.fn_type(&args, false),
-            | Err(ret) => ret.fn_type(&args, false),
+            | Ok(ret) => ret.fn_type(&args, fn_abi.variadic),
+            | Err(ret) => ret.fn_type(&args, fn_abi.variadic),
         }
     }
 
@@ -1,5 +1,6 @@
 pub mod input;
 pub mod libs;
+pub mod lints;
 pub mod target;
 
 use std::sync::Arc;
@@ -10,7 +11,7 @@ use libs::LibId;
 use rustc_hash::FxHashSet;
 pub use salsa::Cancelled;
 use syntax::{ast, Parsed};
-use target::CompilerTarget;
+use target::{CompilerTarget, Optimization};
 
 pub trait Upcast<T: ?Sized> {
     fn upcast(&self) -> &T;
@@ -23,6 +24,12 @@ pub trait SourceDatabase: FileLoader {
     #[salsa::input]
     fn target(&self) -> CompilerTarget;
 
+    #[salsa::input]
+    fn optimization(&self) -> Optimization;
+
+    #[salsa::input]
+    fn debug_info(&self) -> bool;
+
     #[salsa::input]
     fn libs(&self) -> Arc<libs::LibSet>;
 
@@ -1,11 +1,13 @@
 use std::fmt;
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
 use std::path::PathBuf;
 
 use cfg::CfgOptions;
 use rustc_hash::{FxHashMap, FxHashSet};
 use vfs::FileId;
 
+use crate::lints::LintLevels;
+
 #[derive(Debug, Clone)]
 pub struct LibData {
     pub id: LibId,
@@ -14,6 +16,7 @@ pub struct LibData {
     pub deps: Vec<LibId>,
     pub links: Vec<PathBuf>,
     pub cfg_options: CfgOptions,
+    pub lints: LintLevels,
 
     /// Fi projects don't have a root file
     /// However, source roots are only created after loading projects
@@ -49,6 +52,7 @@ impl LibSet {
         kind: LibKind,
         links: Vec<PathBuf>,
         cfg_options: CfgOptions,
+        lints: LintLevels,
         root_file: FileId,
     ) -> LibId {
         let name = name.into();
@@ -59,6 +63,7 @@ impl LibSet {
             kind,
             links,
             cfg_options,
+            lints,
             root_file,
             deps: Vec::new(),
         };
@@ -176,6 +181,12 @@ impl Index<LibId> for LibSet {
     }
 }
 
+impl IndexMut<LibId> for LibSet {
+    fn index_mut(&mut self, index: LibId) -> &mut Self::Output {
+        self.libs.get_mut(&index).unwrap()
+    }
+}
+
 #[derive(Debug)]
 pub struct CyclicDependenciesError {
     from: (LibId, String),
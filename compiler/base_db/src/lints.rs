@@ -0,0 +1,97 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rustc_hash::FxHashMap;
+
+/// A check for code that compiles but is likely a mistake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedImports,
+    UnusedBindings,
+    UnusedItems,
+    ShadowedNames,
+}
+
+/// What to do when a lint is triggered, set with `@allow(..)`, `@warn(..)` and `@deny(..)`
+/// attributes or in the `[lints]` table of the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// The lint levels of a library, lints that are not set are warnings.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct LintLevels {
+    levels: FxHashMap<Lint, LintLevel>,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 4] = [
+        Lint::UnusedImports,
+        Lint::UnusedBindings,
+        Lint::UnusedItems,
+        Lint::ShadowedNames,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            | Lint::UnusedImports => "unused_imports",
+            | Lint::UnusedBindings => "unused_bindings",
+            | Lint::UnusedItems => "unused_items",
+            | Lint::ShadowedNames => "shadowed_names",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|l| l.name() == name)
+    }
+}
+
+impl LintLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            | LintLevel::Allow => "allow",
+            | LintLevel::Warn => "warn",
+            | LintLevel::Deny => "deny",
+        }
+    }
+}
+
+impl LintLevels {
+    pub fn get(&self, lint: Lint) -> LintLevel {
+        self.levels.get(&lint).copied().unwrap_or(LintLevel::Warn)
+    }
+
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s).ok_or_else(|| format!("unknown lint '{}'", s))
+    }
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            | "allow" => Ok(Self::Allow),
+            | "warn" => Ok(Self::Warn),
+            | "deny" => Ok(Self::Deny),
+            | _ => Err(format!("invalid lint level '{}', expected allow, warn or deny", s)),
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
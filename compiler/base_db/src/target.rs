@@ -8,10 +8,23 @@ pub enum CompilerTarget {
     Native(Triple),
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Optimization {
     #[default]
     None,
+    Less,
+    Default,
+    Aggressive,
+    /// Optimize for size.
+    Size,
+    /// Optimize for size, even at the cost of speed.
+    SizeMin,
+}
+
+impl Optimization {
+    pub fn is_enabled(self) -> bool {
+        self != Self::None
+    }
 }
 
 impl Default for CompilerTarget {
@@ -42,6 +55,41 @@ impl CompilerTarget {
             | _ => false,
         }
     }
+
+    pub fn is_wasm(&self) -> bool {
+        match self {
+            | Self::Native(triple) => triple.binary_format == target_lexicon::BinaryFormat::Wasm,
+            | _ => false,
+        }
+    }
+
+    /// Whether programs for this target can use a C library. Wasm modules without an
+    /// operating system only have the imports provided by their host.
+    pub fn has_libc(&self) -> bool {
+        match self {
+            | Self::Native(triple) if self.is_wasm() => {
+                triple.operating_system == target_lexicon::OperatingSystem::Wasi
+            },
+            | Self::Native(_) => true,
+            | Self::Javascript => false,
+        }
+    }
+}
+
+impl FromStr for Optimization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            | "0" | "none" => Ok(Self::None),
+            | "1" | "less" => Ok(Self::Less),
+            | "2" | "default" => Ok(Self::Default),
+            | "3" | "aggressive" => Ok(Self::Aggressive),
+            | "s" | "size" => Ok(Self::Size),
+            | "z" | "size-min" => Ok(Self::SizeMin),
+            | _ => Err(format!("`{}` isn't a valid optimization", s)),
+        }
+    }
 }
 
 impl FromStr for CompilerTarget {
@@ -5,6 +5,8 @@ use smol_str::SmolStr;
 pub struct CfgOptions {
     flags: FxHashSet<SmolStr>,
     keys: FxHashMap<SmolStr, CfgValue>,
+    /// Keys which can have multiple values at once, like `feature`.
+    values: FxHashMap<SmolStr, FxHashSet<CfgValue>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,7 +34,10 @@ impl CfgOptions {
     pub fn check(&self, cfg: &Cfg) -> Option<bool> {
         cfg.fold(&|atom| match atom {
             | CfgAtom::Flag(flag) => self.flags.contains(flag),
-            | CfgAtom::Key(key, value) => self.keys.get(key).map(|v| v == value).unwrap_or(false),
+            | CfgAtom::Key(key, value) => {
+                self.keys.get(key).map(|v| v == value).unwrap_or(false)
+                    || self.values.get(key).map(|v| v.contains(value)).unwrap_or(false)
+            },
         })
     }
 
@@ -44,6 +49,10 @@ impl CfgOptions {
         &self.keys
     }
 
+    pub fn values(&self) -> &FxHashMap<SmolStr, FxHashSet<CfgValue>> {
+        &self.values
+    }
+
     pub fn enable(&mut self, flag: impl Into<SmolStr>) {
         self.flags.insert(flag.into());
     }
@@ -52,6 +61,11 @@ impl CfgOptions {
         self.keys.insert(key.into(), value);
     }
 
+    /// Add `value` to the values of `key`, keeping the values it already has.
+    pub fn insert(&mut self, key: impl Into<SmolStr>, value: CfgValue) {
+        self.values.entry(key.into()).or_default().insert(value);
+    }
+
     pub fn merge(&self, other: &Self) -> Self {
         Self {
             flags: self.flags.union(&other.flags).cloned().collect(),
@@ -61,6 +75,15 @@ impl CfgOptions {
                 .chain(&other.keys)
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
+            values: {
+                let mut values = self.values.clone();
+
+                for (key, other) in &other.values {
+                    values.entry(key.clone()).or_default().extend(other.iter().cloned());
+                }
+
+                values
+            },
         }
     }
 }
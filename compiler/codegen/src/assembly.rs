@@ -1,3 +1,5 @@
+use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -5,14 +7,15 @@ use base_db::libs::LibKind;
 use base_db::target::CompilerTarget;
 use paths::{AbsPath, AbsPathBuf};
 use project::Workspace;
-use tempfile::NamedTempFile;
+use rustc_hash::FxHasher;
 
 use crate::db::CodegenDatabase;
-use crate::linker::Linker;
+use crate::linker::{Linker, ModuleKind};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct ObjectFile {
-    path: tempfile::TempPath,
+    path: AbsPathBuf,
+    cached: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -21,25 +24,28 @@ pub struct Assembly {
     objects: Vec<Arc<ObjectFile>>,
 }
 
-impl PartialEq for ObjectFile {
-    fn eq(&self, other: &Self) -> bool {
-        self.tmp_path().eq(other.tmp_path())
-    }
-}
-
-impl Eq for ObjectFile {
-}
-
 impl ObjectFile {
-    pub fn new(file: NamedTempFile) -> Self {
-        let path = file.into_temp_path();
-        Self { path }
+    pub fn new(path: AbsPathBuf, cached: bool) -> Self {
+        Self { path, cached }
     }
 
     #[inline(always)]
-    pub fn tmp_path(&self) -> &Path {
+    pub fn path(&self) -> &Path {
         self.path.as_ref()
     }
+
+    /// Whether the object file was reused from an earlier build.
+    pub fn is_cached(&self) -> bool {
+        self.cached
+    }
+}
+
+/// The file recording the inputs `out` was last linked from.
+fn inputs_path(out: &AbsPath) -> AbsPathBuf {
+    let mut path = out.as_os_str().to_owned();
+
+    path.push(".inputs");
+    AbsPathBuf::assert(path.into())
 }
 
 fn is_local_link(path: &Path) -> bool {
@@ -64,28 +70,91 @@ impl Assembly {
         }
     }
 
+    /// Whether none of the modules of the assembly had to be recompiled.
+    pub fn is_cached(&self) -> bool {
+        self.objects.iter().all(|obj| obj.is_cached())
+    }
+
     pub fn path(&self, db: &dyn CodegenDatabase, target_dir: &AbsPath) -> AbsPathBuf {
+        // Javascript libraries are emitted as a package directory containing one file per module.
+        if db.target() == CompilerTarget::Javascript {
+            let entry = match db.libs()[self.lib.into()].kind {
+                | LibKind::Executable => "main.js",
+                | LibKind::Dynamic | LibKind::Static => "index.js",
+            };
+
+            return target_dir.join(self.lib.name(db.upcast()).to_string()).join(entry);
+        }
+
         target_dir
             .join(format!("{}{}", self.prefix(db), self.lib.name(db.upcast())))
             .with_extension(self.extension(db))
     }
 
     pub fn link(&self, db: &dyn CodegenDatabase, ws: &Workspace, target_dir: &AbsPath) -> AbsPathBuf {
+        let out = self.path(db, target_dir);
+        let (mut linker, inputs) = self.linker(db, ws, target_dir);
+
+        if let Err(e) = linker.run() {
+            base_db::Error::throw(format!("error while linking:\n{}", e));
+        }
+
+        if let CompilerTarget::Native(_) = db.target() {
+            crate::header::write_header(db, self.lib, target_dir);
+        }
+
+        // The inputs are only recorded once linking succeeded, so a failed link is retried.
+        if let Err(e) = fs::write(inputs_path(&out), inputs) {
+            base_db::Error::throw(format!("failed to write {}: {}", inputs_path(&out).display(), e));
+        }
+
+        out
+    }
+
+    /// Whether the output of the last link is still up to date: it exists and was linked from
+    /// the same object files, libraries and flags. Object files are named after a hash of what
+    /// they were generated from, so recompiled modules change the inputs as well.
+    pub fn is_linked(&self, db: &dyn CodegenDatabase, ws: &Workspace, target_dir: &AbsPath) -> bool {
+        let out = self.path(db, target_dir);
+        let (_, inputs) = self.linker(db, ws, target_dir);
+
+        fs::metadata(&out).is_ok() && fs::read_to_string(inputs_path(&out)).ok() == Some(inputs)
+    }
+
+    /// Configure the linker for this assembly. Also returns a hash of everything passed to the
+    /// linker and of the modification times of the linked files.
+    fn linker(&self, db: &dyn CodegenDatabase, ws: &Workspace, target_dir: &AbsPath) -> (Box<dyn Linker>, String) {
         let mut linker = crate::linker::create(db.target());
+        let mut hasher = FxHasher::default();
         let out = self.path(db, target_dir);
         let libs = db.libs();
         let lib = &libs[self.lib.into()];
-        let pkg = ws.find_file_package(lib.root_file).unwrap();
-        let pkg_root = match pkg {
-            | Some(pkg) => ws[pkg].manifest_path.parent().unwrap(),
-            | None => ws.root_dir(),
+        // Doctests are not part of any package.
+        let pkg_root = match ws.find_file_package(lib.root_file) {
+            | Some(Some(pkg)) => ws[pkg].manifest_path.parent().unwrap(),
+            | Some(None) | None => ws.root_dir(),
         };
 
         for obj in self.objects.iter() {
-            linker.add_module(obj.tmp_path());
+            linker.add_module(obj.path(), ModuleKind::Object);
         }
 
         add_exports(db, &mut *linker, self.lib);
+
+        // Wasm libraries are relocatable objects which can't export anything themselves, so
+        // the functions they `@export` are exported from the executable they are linked into.
+        if db.target().is_wasm() && lib.kind == LibKind::Executable {
+            for dep in transitive_deps(db.upcast(), self.lib) {
+                for func in crate::header::c_exports(db, dep) {
+                    add_export(&mut *linker, func.link_name(db.upcast()));
+                }
+
+                for static_ in crate::header::c_export_statics(db, dep) {
+                    add_export(&mut *linker, static_.link_name(db.upcast()));
+                }
+            }
+        }
+
         linker.runtime_path(target_dir.as_ref());
         linker.add_path(target_dir.as_ref());
 
@@ -119,7 +188,8 @@ impl Assembly {
                 path = std::borrow::Cow::Owned(pkg_root.join(path).into());
             }
 
-            linker.add_module(&path);
+            linker.add_module(&path, ModuleKind::Link);
+            format!("{:?}", fs::metadata(&path).and_then(|m| m.modified()).ok()).hash(&mut hasher);
             // linker.add_lib(
             //     base_db::libs::LibKind::Dynamic,
             //     path.to_str().unwrap(),
@@ -129,42 +199,37 @@ impl Assembly {
 
         if db.target().is_windows() {
             linker.subsystem("console");
-            linker.add_module(Path::new("kernel32.lib"));
-            linker.add_module(Path::new("libvcruntime.lib"));
-            linker.add_module(Path::new("msvcrt.lib"));
-            linker.add_module(Path::new("ucrt.lib"));
+            linker.add_module(Path::new("kernel32.lib"), ModuleKind::Link);
+            linker.add_module(Path::new("libvcruntime.lib"), ModuleKind::Link);
+            linker.add_module(Path::new("msvcrt.lib"), ModuleKind::Link);
+            linker.add_module(Path::new("ucrt.lib"), ModuleKind::Link);
         }
 
         linker.out_kind(db.libs()[self.lib.into()].kind, out.as_ref());
         linker.build(out.as_ref());
 
         tracing::debug!("{:?}", linker);
+        format!("{:?}", linker).hash(&mut hasher);
 
-        if let Err(e) = linker.run() {
-            base_db::Error::throw(format!("error while linking:\n{}", e));
-        }
-
-        out
+        (linker, format!("{:016x}", hasher.finish()))
     }
 
     fn extension(&self, db: &dyn CodegenDatabase) -> &'static str {
         match db.target() {
             | CompilerTarget::Javascript => "js",
+            | target if target.is_wasm() => "wasm",
             | CompilerTarget::Native(triple) => match db.libs()[self.lib.into()].kind {
                 | LibKind::Executable => match triple.operating_system {
                     | target_lexicon::OperatingSystem::Windows => "exe",
-                    | target_lexicon::OperatingSystem::Wasi => "wasm",
                     | _ => "",
                 },
                 | LibKind::Dynamic => match triple.operating_system {
                     | target_lexicon::OperatingSystem::Windows => "dll",
                     | target_lexicon::OperatingSystem::MacOSX { .. } => "dylib",
-                    | target_lexicon::OperatingSystem::Wasi => "wasm",
                     | _ => "so",
                 },
                 | LibKind::Static => match triple.operating_system {
                     | target_lexicon::OperatingSystem::Windows => "lib",
-                    | target_lexicon::OperatingSystem::Wasi => "wasm",
                     | _ => "a",
                 },
             },
@@ -174,11 +239,11 @@ impl Assembly {
     fn prefix(&self, db: &dyn CodegenDatabase) -> &'static str {
         match db.target() {
             | CompilerTarget::Javascript => "",
+            | target if target.is_wasm() => "",
             | CompilerTarget::Native(triple) => match db.libs()[self.lib.into()].kind {
                 | LibKind::Executable => "",
                 | LibKind::Dynamic | LibKind::Static => match triple.operating_system {
                     | target_lexicon::OperatingSystem::Windows => "",
-                    | target_lexicon::OperatingSystem::Wasi => "",
                     | _ => "lib",
                 },
             },
@@ -187,6 +252,14 @@ impl Assembly {
 }
 
 fn add_exports(db: &dyn CodegenDatabase, linker: &mut dyn Linker, lib: hir::Lib) {
+    for func in crate::header::c_exports(db, lib) {
+        add_export(linker, func.link_name(db.upcast()));
+    }
+
+    for static_ in crate::header::c_export_statics(db, lib) {
+        add_export(linker, static_.link_name(db.upcast()));
+    }
+
     let db = db.upcast();
 
     for module in lib.modules(db) {
@@ -194,11 +267,11 @@ fn add_exports(db: &dyn CodegenDatabase, linker: &mut dyn Linker, lib: hir::Lib)
             match export {
                 | hir::ModuleDef::Func(it) if it.has_body(db) => {
                     // for now ignore generic function
-                    if !it.is_generic(db) {
+                    if !it.is_generic(db) && !it.is_c_export(db) {
                         add_export(linker, it.link_name(db));
                     }
                 },
-                | hir::ModuleDef::Static(it) if it.has_body(db) => {
+                | hir::ModuleDef::Static(it) if it.has_body(db) && !it.is_c_export(db) => {
                     add_export(linker, it.link_name(db));
                 },
                 | _ => {},
@@ -207,6 +280,22 @@ fn add_exports(db: &dyn CodegenDatabase, linker: &mut dyn Linker, lib: hir::Lib)
     }
 }
 
+pub(crate) fn transitive_deps(db: &dyn hir::db::HirDatabase, lib: hir::Lib) -> Vec<hir::Lib> {
+    let mut deps = Vec::new();
+    let mut queue = vec![lib];
+
+    while let Some(lib) = queue.pop() {
+        for dep in lib.dependencies(db) {
+            if !deps.contains(&dep.lib) {
+                deps.push(dep.lib);
+                queue.push(dep.lib);
+            }
+        }
+    }
+
+    deps
+}
+
 fn add_export(linker: &mut dyn Linker, (symbol, mangle): (hir::Name, bool)) {
     let mut symbol = symbol.to_string();
 
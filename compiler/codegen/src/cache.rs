@@ -0,0 +1,305 @@
+//! Object files are cached per module under `target/cache/<lib>`, named after a hash of
+//! everything the generated code depends on: the MIR of the module itself, the signatures of
+//! the modules it imports, the members of all modules, and the source text when debug info is
+//! enabled, as well as the compiler generating it. Interned ids are not stable between runs,
+//! so everything is hashed through its textual representation.
+
+use std::fs;
+use std::hash::{Hash, Hasher};
+
+use base_db::target::CompilerTarget;
+use hir::db::HirDatabase;
+use hir::id::DefWithBodyId;
+use hir::HirDisplay;
+use mir::db::MirDatabase;
+use paths::AbsPathBuf;
+use rustc_hash::{FxHashSet, FxHasher};
+
+use crate::db::CodegenDatabase;
+
+pub(crate) fn module_hash(db: &dyn CodegenDatabase, module: hir::Module) -> u64 {
+    let mut hasher = FxHasher::default();
+
+    hash_compiler(&mut hasher);
+    format!("{:?}", db.target()).hash(&mut hasher);
+    format!("{:?}", db.optimization()).hash(&mut hasher);
+    db.debug_info().hash(&mut hasher);
+    module.lib().is_test(db.upcast()).hash(&mut hasher);
+
+    match db.target() {
+        | CompilerTarget::Javascript => {
+            // The javascript backend works on the HIR and only emits reachable definitions.
+            let hdb: &dyn HirDatabase = db.upcast();
+
+            db.file_text(module.file_id(hdb)).hash(&mut hasher);
+            hash_reachable(db, module, &mut hasher);
+        },
+        | CompilerTarget::Native(_) => {
+            hash_module_mir(db, module, &mut hasher);
+            hash_debug_info(db, module, &mut hasher);
+        },
+    }
+
+    // Interned ids are not stable between runs, so the order of the modules isn't either.
+    let mut deps = imported_modules(db, module)
+        .into_iter()
+        .map(|dep| db.module_signature_hash(dep))
+        .chain(dependency_modules(db, module.lib()).into_iter().map(|dep| db.module_members_hash(dep)))
+        .collect::<Vec<_>>();
+
+    deps.sort_unstable();
+    deps.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash the signatures of the definitions in `module`, not including its children. Generic
+/// functions and constants are instantiated in the modules using them, so their bodies are
+/// part of the signature.
+pub(crate) fn module_signature_hash(db: &dyn CodegenDatabase, module: hir::Module) -> u64 {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut hasher = FxHasher::default();
+
+    module.path(hdb).to_string().hash(&mut hasher);
+
+    if db.debug_info() {
+        db.file_text(module.file_id(hdb)).hash(&mut hasher);
+    }
+
+    for def in module.declarations(hdb) {
+        match def {
+            | hir::ModuleDef::Func(func) => {
+                func.link_name(hdb).0.to_string().hash(&mut hasher);
+                func.ty(hdb).display(hdb).to_string().hash(&mut hasher);
+
+                if func.is_generic(hdb) && func.has_body(hdb) {
+                    hash_body(db, hir::id::FuncId::from(func).into(), &mut hasher);
+                }
+            },
+            | hir::ModuleDef::Static(static_) => {
+                let id = hir::id::StaticId::from(static_);
+
+                static_.link_name(hdb).0.to_string().hash(&mut hasher);
+                db.infer(id.into())
+                    .self_type
+                    .ty
+                    .display(hdb)
+                    .to_string()
+                    .hash(&mut hasher);
+            },
+            | hir::ModuleDef::Const(const_) => {
+                const_.path(hdb).to_string().hash(&mut hasher);
+                hash_body(db, hir::id::ConstId::from(const_).into(), &mut hasher);
+            },
+            | hir::ModuleDef::Ctor(ctor) => {
+                ctor.path(hdb).to_string().hash(&mut hasher);
+
+                for ty in ctor.types(hdb) {
+                    ty.display(hdb).to_string().hash(&mut hasher);
+                }
+            },
+            | _ => {},
+        }
+    }
+
+    hasher.finish()
+}
+
+/// Hash the members of `module` and its children. Members are found by their types rather
+/// than imported by name, so every module can use them. The bodies of generic members are
+/// instantiated in the modules using them, so they are hashed as well.
+pub(crate) fn module_members_hash(db: &dyn CodegenDatabase, module: hir::Module) -> u64 {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut hasher = FxHasher::default();
+
+    hash_debug_info(db, module, &mut hasher);
+
+    for module in with_children(db, module) {
+        for member in module.members(hdb) {
+            let is_generic = is_generic_member(db, member);
+
+            member.link_name(hdb).to_string().hash(&mut hasher);
+
+            for item in member.items(hdb) {
+                if let hir::AssocItem::Func(func) = item {
+                    func.ty(hdb).display(hdb).to_string().hash(&mut hasher);
+
+                    if func.has_body(hdb) && (is_generic || func.is_generic(hdb)) {
+                        hash_body(db, hir::id::FuncId::from(func).into(), &mut hasher);
+                    }
+                }
+            }
+        }
+    }
+
+    hasher.finish()
+}
+
+/// The path of the cached object file of `module`, removing the files of earlier versions of
+/// the module.
+pub(crate) fn object_path(db: &dyn CodegenDatabase, module: hir::Module, hash: u64) -> AbsPathBuf {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let dir = db.target_dir().join("cache").join(module.lib().name(hdb).to_string());
+    let name = module.name(hdb).to_string().replace(hir::Path::SEPARATOR, ".");
+    let prefix = format!("{}-", name);
+    let file = format!("{}{:016x}.o", prefix, hash);
+
+    if let Err(e) = fs::create_dir_all(&dir) {
+        base_db::Error::throw(format!("failed to create {}: {}", dir.display(), e));
+    }
+
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+
+            if file_name.starts_with(&prefix) && file_name != file.as_str() {
+                let rest = &file_name[prefix.len()..];
+
+                // Don't remove the objects of modules whose name starts with this one.
+                if rest.len() == 18 && rest.ends_with(".o") {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+
+    dir.join(file)
+}
+
+/// Objects generated by another build of the compiler may differ even when the version is the
+/// same, so the size and modification time of the executable identify the build.
+fn hash_compiler(hasher: &mut FxHasher) {
+    env!("CARGO_PKG_VERSION").hash(hasher);
+
+    if let Ok(meta) = std::env::current_exe().and_then(fs::metadata) {
+        meta.len().hash(hasher);
+        meta.modified().ok().hash(hasher);
+    }
+}
+
+fn hash_module_mir(db: &dyn CodegenDatabase, module: hir::Module, hasher: &mut FxHasher) {
+    let hdb: &dyn HirDatabase = db.upcast();
+
+    for module in with_children(db, module) {
+        for def in module.declarations(hdb) {
+            match def {
+                | hir::ModuleDef::Func(func) if func.has_body(hdb) && !func.is_generic(hdb) => {
+                    hash_body(db, hir::id::FuncId::from(func).into(), hasher);
+                    func.attrs(hdb).by_key("main").exists().hash(hasher);
+                    func.is_test(hdb).hash(hasher);
+                },
+                | hir::ModuleDef::Static(static_) if static_.is_c_export(hdb) && static_.has_body(hdb) => {
+                    hash_body(db, hir::id::StaticId::from(static_).into(), hasher);
+                },
+                | _ => {},
+            }
+        }
+
+        for member in module.members(hdb) {
+            for item in member.items(hdb) {
+                if let hir::AssocItem::Func(func) = item {
+                    if func.has_body(hdb) && !func.is_generic(hdb) {
+                        hash_body(db, hir::id::FuncId::from(func).into(), hasher);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Hash the optimized MIR of `def` together with the bodies of the closures it creates, which
+/// are generated alongside it.
+fn hash_body(db: &dyn CodegenDatabase, def: DefWithBodyId, hasher: &mut FxHasher) {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mdb: &dyn MirDatabase = db.upcast();
+    let body = db.optimized_body(db.body_mir(def));
+
+    for body in std::iter::once(body).chain(body.closures(mdb)) {
+        let body = db.lookup_intern_body(db.optimized_body(body));
+
+        body.display(hdb).to_string().hash(hasher);
+    }
+}
+
+/// Debug info refers to the lines and columns of the source, which can change without
+/// changing the MIR, so the source text is hashed as well when it is enabled.
+fn hash_debug_info(db: &dyn CodegenDatabase, module: hir::Module, hasher: &mut FxHasher) {
+    let hdb: &dyn HirDatabase = db.upcast();
+
+    if !db.debug_info() {
+        return;
+    }
+
+    for module in with_children(db, module) {
+        db.file_text(module.file_id(hdb)).hash(hasher);
+    }
+}
+
+fn hash_reachable(db: &dyn CodegenDatabase, module: hir::Module, hasher: &mut FxHasher) {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let reachable = db.js_reachable();
+
+    for module in with_children(db, module) {
+        for def in module.declarations(hdb) {
+            reachable.has_def(def).hash(hasher);
+        }
+
+        for member in module.members(hdb) {
+            reachable.has_member(member).hash(hasher);
+        }
+    }
+}
+
+fn is_generic_member(db: &dyn CodegenDatabase, member: hir::Member) -> bool {
+    !db.lower_member(member.into()).member.vars.is_empty()
+}
+
+/// `module`, its children and the modules they import, transitively, since the generic bodies
+/// of an imported module are instantiated together with the code using them.
+fn imported_modules(db: &dyn CodegenDatabase, module: hir::Module) -> Vec<hir::Module> {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut seen = FxHashSet::default();
+    let mut stack = with_children(db, module);
+    let mut modules = Vec::new();
+
+    while let Some(module) = stack.pop() {
+        if seen.insert(module) {
+            modules.push(module);
+            stack.extend(module.imports(hdb));
+        }
+    }
+
+    modules
+}
+
+fn with_children(db: &dyn CodegenDatabase, module: hir::Module) -> Vec<hir::Module> {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut modules = vec![module];
+    let mut i = 0;
+
+    while i < modules.len() {
+        let children = modules[i].children(hdb);
+
+        modules.extend(children);
+        i += 1;
+    }
+
+    modules
+}
+
+/// The top level modules of `lib` and of all libraries it depends on.
+fn dependency_modules(db: &dyn CodegenDatabase, lib: hir::Lib) -> Vec<hir::Module> {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut seen = FxHashSet::default();
+    let mut stack = vec![lib];
+    let mut modules = Vec::new();
+
+    while let Some(lib) = stack.pop() {
+        if seen.insert(lib) {
+            modules.extend(lib.modules(hdb));
+            stack.extend(lib.dependencies(hdb).into_iter().map(|d| d.lib));
+        }
+    }
+
+    modules
+}
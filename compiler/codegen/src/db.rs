@@ -14,6 +14,18 @@ pub trait CodegenDatabase: MirDatabase + Upcast<dyn MirDatabase> {
     #[salsa::invoke(crate::build_assembly)]
     fn lib_assembly(&self, lib: hir::Lib) -> Arc<Assembly>;
 
+    #[salsa::invoke(crate::js_reachable)]
+    fn js_reachable(&self) -> Arc<backend_js::Reachable>;
+
+    #[salsa::invoke(crate::cache::module_hash)]
+    fn module_hash(&self, module: hir::Module) -> u64;
+
+    #[salsa::invoke(crate::cache::module_signature_hash)]
+    fn module_signature_hash(&self, module: hir::Module) -> u64;
+
+    #[salsa::invoke(crate::cache::module_members_hash)]
+    fn module_members_hash(&self, module: hir::Module) -> u64;
+
     #[salsa::invoke(crate::codegen_module)]
     fn codegen_module(&self, module: hir::Module) -> Arc<ObjectFile>;
 }
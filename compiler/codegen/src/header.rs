@@ -0,0 +1,288 @@
+//! C headers declaring the functions a library exports with `@export`.
+
+use std::fmt::Write;
+use std::fs;
+
+use base_db::libs::LibKind;
+use hir::db::HirDatabase;
+use mir::instance::Instance;
+use mir::layout::{Abi, ReprAndLayout};
+use mir::repr::{Integer, Primitive, Repr, Signature};
+use paths::{AbsPath, AbsPathBuf};
+
+use crate::db::CodegenDatabase;
+
+/// The functions of `lib` exported with `@export`.
+pub fn c_exports(db: &dyn CodegenDatabase, lib: hir::Lib) -> Vec<hir::Func> {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut modules = lib.modules(hdb);
+    let mut funcs = Vec::new();
+
+    while let Some(module) = modules.pop() {
+        modules.extend(module.children(hdb));
+
+        for def in module.declarations(hdb) {
+            if let hir::ModuleDef::Func(func) = def {
+                if func.is_c_export(hdb) && func.has_body(hdb) && !func.is_generic(hdb) {
+                    funcs.push(func);
+                }
+            }
+        }
+    }
+
+    funcs.sort_by_key(|func| func.link_name(hdb).0.to_string());
+    funcs
+}
+
+/// The statics of `lib` exported with `@export`.
+pub fn c_export_statics(db: &dyn CodegenDatabase, lib: hir::Lib) -> Vec<hir::Static> {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut modules = lib.modules(hdb);
+    let mut statics = Vec::new();
+
+    while let Some(module) = modules.pop() {
+        modules.extend(module.children(hdb));
+
+        for def in module.declarations(hdb) {
+            if let hir::ModuleDef::Static(static_) = def {
+                if static_.is_c_export(hdb) && static_.has_body(hdb) {
+                    statics.push(static_);
+                }
+            }
+        }
+    }
+
+    statics.sort_by_key(|static_| static_.link_name(hdb).0.to_string());
+    statics
+}
+
+/// Write a header declaring the exported functions and statics of a static or dynamic library to
+/// `<target_dir>/<lib>.h`. Nothing is written when the library doesn't export anything.
+pub fn write_header(db: &dyn CodegenDatabase, lib: hir::Lib, target_dir: &AbsPath) -> Option<AbsPathBuf> {
+    let hdb: &dyn HirDatabase = db.upcast();
+
+    if db.libs()[lib.into()].kind == LibKind::Executable {
+        return None;
+    }
+
+    let funcs = c_exports(db, lib);
+    let statics = c_export_statics(db, lib);
+
+    if funcs.is_empty() && statics.is_empty() {
+        return None;
+    }
+
+    let name = lib.name(hdb).to_string();
+    let guard = format!(
+        "FI_{}_H",
+        name.to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    );
+    let mut text = String::new();
+
+    writeln!(
+        text,
+        "/* Generated by fi from the library `{}`, do not edit. */\n",
+        name
+    )
+    .unwrap();
+    let extra = extra_libraries(db, lib);
+
+    if !extra.is_empty() {
+        writeln!(
+            text,
+            "/* Programs using this library must also link with: {} */\n",
+            extra.join(" ")
+        )
+        .unwrap();
+    }
+
+    writeln!(text, "#ifndef {}\n#define {}\n", guard, guard).unwrap();
+    writeln!(text, "#include <stdbool.h>\n#include <stdint.h>\n").unwrap();
+    writeln!(text, "#ifdef __cplusplus\nextern \"C\" {{\n#endif\n").unwrap();
+
+    for static_ in statics {
+        writeln!(text, "{};", static_declaration(db, static_)).unwrap();
+    }
+
+    for func in funcs {
+        writeln!(text, "{};", declaration(db, func)).unwrap();
+    }
+
+    writeln!(text, "\n#ifdef __cplusplus\n}}\n#endif\n").unwrap();
+    writeln!(text, "#endif /* {} */", guard).unwrap();
+
+    let path = target_dir.join(format!("{}.h", name));
+
+    if let Err(e) = fs::write(&path, text) {
+        base_db::Error::throw(format!("failed to write {}: {}", path.display(), e));
+    }
+
+    Some(path)
+}
+
+/// The libraries a static library can't bundle: its dynamic dependencies and the system
+/// libraries it links with.
+fn extra_libraries(db: &dyn CodegenDatabase, lib: hir::Lib) -> Vec<String> {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let mut libs = Vec::new();
+
+    if db.libs()[lib.into()].kind != LibKind::Static {
+        return libs;
+    }
+
+    for dep in crate::assembly::transitive_deps(hdb, lib) {
+        if db.libs()[dep.into()].kind == LibKind::Dynamic {
+            libs.push(format!("-l{}", dep.name(hdb)));
+        }
+    }
+
+    for link in &db.libs()[lib.into()].links {
+        if !matches!(link.extension().and_then(|e| e.to_str()), Some("a" | "o")) {
+            libs.push(link.display().to_string());
+        }
+    }
+
+    libs
+}
+
+fn declaration(db: &dyn CodegenDatabase, func: hir::Func) -> String {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let name = func.link_name(hdb).0.to_string();
+    let sig = db.func_signature(Instance::mono(func.into()));
+
+    match function_declaration(db, &sig, &name) {
+        | Ok(decl) => decl,
+        | Err(what) => base_db::Error::throw(format!(
+            "the {} of the exported function `{}` has no C representation",
+            what, name
+        )),
+    }
+}
+
+fn static_declaration(db: &dyn CodegenDatabase, static_: hir::Static) -> String {
+    let hdb: &dyn HirDatabase = db.upcast();
+    let name = static_.link_name(hdb).0.to_string();
+    let layout = db.layout_of(db.repr_of(static_.ty(hdb)));
+
+    // The pointer itself is constant, not what it points to.
+    let decl = match &layout.abi {
+        | Abi::Scalar(scalar) if scalar.value == Primitive::Pointer => {
+            c_declaration(db, &layout, &format!("const {}", name))
+        },
+        | _ => c_declaration(db, &layout, &name).map(|decl| format!("const {}", decl)),
+    };
+
+    match decl {
+        | Some(decl) => format!("extern {}", decl),
+        | None => base_db::Error::throw(format!(
+            "the type of the exported static `{}` has no C representation",
+            name
+        )),
+    }
+}
+
+/// The C declaration of a function named `name` with the signature `sig`, or the part of the
+/// signature which has no C representation.
+pub fn function_declaration(db: &dyn CodegenDatabase, sig: &Signature, name: &str) -> Result<String, String> {
+    signature_declaration(db, sig, name, true)
+}
+
+/// The parameters of function pointers are left unnamed.
+fn signature_declaration(
+    db: &dyn CodegenDatabase,
+    sig: &Signature,
+    declarator: &str,
+    named: bool,
+) -> Result<String, String> {
+    let mut params = Vec::new();
+
+    for (i, param) in sig.params.iter().enumerate() {
+        let layout = db.layout_of(param.clone());
+        let name = match named {
+            | true => format!("arg{}", i),
+            | false => String::new(),
+        };
+
+        // Zero sized arguments are not passed at all.
+        if layout.is_zst() {
+            continue;
+        }
+
+        match c_declaration(db, &layout, &name) {
+            | Some(param) => params.push(param),
+            | None => return Err(format!("parameter {}", i + 1)),
+        }
+    }
+
+    if params.is_empty() {
+        params.push(String::from("void"));
+    }
+
+    let declarator = format!("{}({})", declarator, params.join(", "));
+    let ret = db.layout_of(sig.ret.clone());
+
+    match c_declaration(db, &ret, &declarator) {
+        | Some(decl) => Ok(decl),
+        | None if ret.is_zst() || ret.abi == Abi::Uninhabited => Ok(join("void", &declarator)),
+        | None => Err(String::from("return type")),
+    }
+}
+
+/// The C declaration of `declarator` for a value passed as a single scalar. C declarations are
+/// written inside out: a pointer to `int32_t` named `x` is `int32_t *x`.
+fn c_declaration(db: &dyn CodegenDatabase, layout: &ReprAndLayout, declarator: &str) -> Option<String> {
+    let scalar = match &layout.abi {
+        | Abi::Scalar(scalar) => scalar,
+        | _ => return None,
+    };
+
+    if layout.is_bool() {
+        return Some(join("bool", declarator));
+    }
+
+    let ty = match scalar.value {
+        | Primitive::Int(Integer::Int, true) => "intptr_t",
+        | Primitive::Int(Integer::Int, false) => "uintptr_t",
+        | Primitive::Int(Integer::I8, true) => "int8_t",
+        | Primitive::Int(Integer::I8, false) => "uint8_t",
+        | Primitive::Int(Integer::I16, true) => "int16_t",
+        | Primitive::Int(Integer::I16, false) => "uint16_t",
+        | Primitive::Int(Integer::I32, true) => "int32_t",
+        | Primitive::Int(Integer::I32, false) => "uint32_t",
+        | Primitive::Int(Integer::I64, true) => "int64_t",
+        | Primitive::Int(Integer::I64, false) => "uint64_t",
+        | Primitive::Int(Integer::I128, true) => "__int128",
+        | Primitive::Int(Integer::I128, false) => "unsigned __int128",
+        | Primitive::Float => "float",
+        | Primitive::Double => "double",
+        | Primitive::Pointer => return pointer_declaration(db, &layout.repr, declarator),
+    };
+
+    Some(join(ty, declarator))
+}
+
+/// Pointers point to the C type of their element, or to `void` when it has none. Thin function
+/// pointers are declared with their signature.
+fn pointer_declaration(db: &dyn CodegenDatabase, repr: &Repr, declarator: &str) -> Option<String> {
+    let pointer = format!("*{}", declarator);
+
+    match repr {
+        | Repr::Ptr(_, true, _) => None,
+        | Repr::Ptr(elem, false, _) | Repr::Box(elem) => {
+            let elem = db.layout_of((**elem).clone());
+
+            c_declaration(db, &elem, &pointer).or_else(|| Some(join("void", &pointer)))
+        },
+        | Repr::Func(sig, false) => signature_declaration(db, sig, &format!("({})", pointer), false)
+            .ok()
+            .or_else(|| Some(join("void", &pointer))),
+        | _ => Some(join("void", &pointer)),
+    }
+}
+
+fn join(ty: &str, declarator: &str) -> String {
+    match declarator {
+        | "" => ty.to_string(),
+        | _ => format!("{} {}", ty, declarator),
+    }
+}
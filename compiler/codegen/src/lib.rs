@@ -1,7 +1,10 @@
 pub mod assembly;
+mod cache;
 pub mod db;
+pub mod header;
 pub mod linker;
 
+use std::fs;
 use std::sync::Arc;
 
 use base_db::target::CompilerTarget;
@@ -17,19 +20,59 @@ pub(crate) fn build_assembly(db: &dyn db::CodegenDatabase, lib: hir::Lib) -> Arc
         objects.push(db.codegen_module(module));
     }
 
+    if lib.is_test(db.upcast()) {
+        objects.push(codegen_test_harness(db, lib));
+    }
+
     Arc::new(assembly::Assembly::new(lib, objects))
 }
 
+/// The test harness is small and depends on every module of the library, so it is generated
+/// again on every build instead of being cached.
+fn codegen_test_harness(db: &dyn db::CodegenDatabase, lib: hir::Lib) -> Arc<assembly::ObjectFile> {
+    let dir = db.target_dir().join("cache").join(lib.name(db.upcast()).to_string());
+    let path = dir.join("test_harness.o");
+
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut file = fs::File::create(&path).unwrap();
+
+    match db.target() {
+        | CompilerTarget::Javascript => base_db::Error::throw("tests are not supported for the javascript target"),
+        | CompilerTarget::Native(_) => backend_llvm::codegen_test_harness(db.upcast(), lib, &mut file),
+    }
+
+    Arc::new(assembly::ObjectFile::new(path, false))
+}
+
+pub(crate) fn js_reachable(db: &dyn db::CodegenDatabase) -> Arc<backend_js::Reachable> {
+    Arc::new(backend_js::Reachable::new(db.upcast()))
+}
+
 pub(crate) fn codegen_module(db: &dyn db::CodegenDatabase, module: hir::Module) -> Arc<assembly::ObjectFile> {
-    let mut file = tempfile::Builder::new()
-        .suffix(".o")
-        .tempfile_in(db.target_dir())
-        .unwrap();
+    let hash = db.module_hash(module);
+    let path = cache::object_path(db, module, hash);
+
+    if fs::metadata(&path).is_ok() {
+        tracing::debug!("using cached object {}", path.display());
+        return Arc::new(assembly::ObjectFile::new(path, true));
+    }
+
+    let dir = path.parent().unwrap();
+    let mut file = match tempfile::Builder::new().suffix(".o").tempfile_in(dir) {
+        | Ok(file) => file,
+        | Err(e) => base_db::Error::throw(format!("failed to create a file in {}: {}", dir.display(), e)),
+    };
 
     match db.target() {
-        | CompilerTarget::Javascript => backend_js::codegen(db.upcast(), module, &mut file),
+        | CompilerTarget::Javascript => {
+            backend_js::codegen(db.upcast(), module, &db.js_reachable(), &mut file);
+        },
         | CompilerTarget::Native(_) => backend_llvm::codegen(db.upcast(), module, &mut file),
     }
 
-    Arc::new(assembly::ObjectFile::new(file))
+    if let Err(e) = file.persist(&path) {
+        base_db::Error::throw(format!("failed to write {}: {}", path.display(), e.error));
+    }
+    Arc::new(assembly::ObjectFile::new(path, false))
 }
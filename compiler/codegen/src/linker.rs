@@ -1,4 +1,5 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
+use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -10,12 +11,12 @@ use crate::CompilerTarget;
 pub fn create(target: CompilerTarget) -> Box<dyn Linker> {
     match target {
         | CompilerTarget::Javascript => Box::new(JsLinker::new()),
+        | CompilerTarget::Native(ref triple) if target.is_wasm() => Box::new(WasmLinker::new(triple)),
         | CompilerTarget::Native(triple) => {
             match triple.operating_system {
                 | target_lexicon::OperatingSystem::Windows => create_linker_windows(&triple),
                 | target_lexicon::OperatingSystem::MacOSX { .. } => todo!(),
                 | target_lexicon::OperatingSystem::Ios => todo!(),
-                | target_lexicon::OperatingSystem::Wasi => todo!(),
                 // | _ => Box::new(ElfLinker::new()),
                 | _ => Box::new(CcLinker::new()),
             }
@@ -56,10 +57,19 @@ impl From<io::Error> for LinkError {
     }
 }
 
+/// Where a module passed to the linker comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleKind {
+    /// A module of the library emitted by the backend.
+    Object,
+    /// A file the library links with, or a system library.
+    Link,
+}
+
 pub trait Linker: std::fmt::Debug {
     fn runtime_path(&mut self, rpath: &Path);
     fn add_path(&mut self, path: &Path);
-    fn add_module(&mut self, path: &Path);
+    fn add_module(&mut self, path: &Path, kind: ModuleKind);
     fn add_lib(&mut self, kind: LibKind, lib: &str, path: &Path);
     fn add_export(&mut self, symbol: &str);
     fn subsystem(&mut self, subsystem: &str);
@@ -95,7 +105,7 @@ impl Linker for ElfLinker {
         self.arg(path);
     }
 
-    fn add_module(&mut self, path: &Path) {
+    fn add_module(&mut self, path: &Path, _: ModuleKind) {
         self.arg(path);
     }
 
@@ -110,7 +120,9 @@ impl Linker for ElfLinker {
         self.arg(lib);
     }
 
-    fn add_export(&mut self, _: &str) {
+    fn add_export(&mut self, symbol: &str) {
+        self.arg("--export-dynamic-symbol");
+        self.arg(symbol);
     }
 
     fn subsystem(&mut self, _: &str) {
@@ -140,13 +152,66 @@ impl Linker for ElfLinker {
 #[derive(Debug)]
 pub struct CcLinker {
     cmd: Command,
+    kind: LibKind,
+    objects: Vec<PathBuf>,
+    /// The static libraries which are bundled into the output when it is an archive.
+    archives: Vec<PathBuf>,
+    out: PathBuf,
 }
 
 impl CcLinker {
     pub fn new() -> Self {
         Self {
             cmd: Command::new("cc"),
+            kind: LibKind::Executable,
+            objects: Vec::new(),
+            archives: Vec::new(),
+            out: PathBuf::new(),
+        }
+    }
+
+    /// Static libraries are archives of the object files, created with `ar` instead of `cc`.
+    /// The archives of static dependencies are bundled in, so programs only need to link with
+    /// the dynamic libraries listed in the generated header. Their members are extracted to a
+    /// directory per archive, since the members of different archives can have the same name.
+    fn archive(&mut self) -> Result<(), LinkError> {
+        let dir = tempfile::tempdir()?;
+        let current_dir = std::env::current_dir()?;
+        let mut members = Vec::new();
+
+        for (i, archive) in self.archives.iter().enumerate() {
+            let dir = dir.path().join(i.to_string());
+
+            let mut cmd = Command::new("ar");
+
+            fs::create_dir(&dir)?;
+            cmd.arg("x").arg(current_dir.join(archive)).current_dir(&dir);
+            run_ar(&mut cmd)?;
+
+            let mut extracted = fs::read_dir(&dir)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+
+            extracted.sort();
+            members.extend(extracted);
         }
+
+        // `ar r` adds to an existing archive, which still has the members of the last build.
+        match fs::remove_file(&self.out) {
+            | Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            | _ => {},
+        }
+
+        run_ar(&mut self.archive_command(&members))
+    }
+
+    /// The `ar` command which creates the archive from the object files and the `members`
+    /// extracted from the archives.
+    fn archive_command(&self, members: &[PathBuf]) -> Command {
+        let mut cmd = Command::new("ar");
+
+        cmd.arg("rcs").arg(&self.out).args(&self.objects).args(members);
+        cmd
     }
 
     fn arg(&mut self, arg: impl AsRef<std::ffi::OsStr>) -> &mut Self {
@@ -155,6 +220,16 @@ impl CcLinker {
     }
 }
 
+fn run_ar(cmd: &mut Command) -> Result<(), LinkError> {
+    let output = cmd.output()?;
+
+    if !output.status.success() {
+        return Err(LinkError::Linker(String::from_utf8(output.stderr).unwrap()));
+    }
+
+    Ok(())
+}
+
 impl Linker for CcLinker {
     fn runtime_path(&mut self, rpath: &Path) {
         self.arg("-Wl,-rpath");
@@ -166,11 +241,24 @@ impl Linker for CcLinker {
         self.arg(path);
     }
 
-    fn add_module(&mut self, path: &Path) {
+    fn add_module(&mut self, path: &Path, kind: ModuleKind) {
+        match (kind, path.extension().and_then(|e| e.to_str())) {
+            | (ModuleKind::Object, _) | (ModuleKind::Link, Some("o")) => self.objects.push(path.to_path_buf()),
+            | (ModuleKind::Link, Some("a")) => self.archives.push(path.to_path_buf()),
+            | (ModuleKind::Link, _) => {},
+        }
+
         self.arg(path);
     }
 
-    fn add_lib(&mut self, kind: LibKind, lib: &str, _: &Path) {
+    fn add_lib(&mut self, kind: LibKind, lib: &str, path: &Path) {
+        let archive = path.join(format!("lib{}.a", lib));
+
+        // Dependencies are added once for every library depending on them.
+        if kind == LibKind::Static && !self.archives.contains(&archive) {
+            self.archives.push(archive);
+        }
+
         match kind {
             | LibKind::Dynamic => self.arg("-Bdynamic"),
             | LibKind::Static => self.arg("-Bstatic"),
@@ -181,13 +269,16 @@ impl Linker for CcLinker {
         self.arg(lib);
     }
 
-    fn add_export(&mut self, _: &str) {
+    fn add_export(&mut self, symbol: &str) {
+        self.arg(format!("-Wl,--export-dynamic-symbol={symbol}"));
     }
 
     fn subsystem(&mut self, _: &str) {
     }
 
     fn out_kind(&mut self, kind: LibKind, _: &Path) {
+        self.kind = kind;
+
         match kind {
             | LibKind::Dynamic => self.arg("-shared"),
             | LibKind::Static => self.arg("-static"),
@@ -196,11 +287,16 @@ impl Linker for CcLinker {
     }
 
     fn build(&mut self, out: &Path) {
+        self.out = out.to_path_buf();
         self.arg("-o");
         self.arg(out);
     }
 
     fn run(&mut self) -> Result<(), LinkError> {
+        if let LibKind::Static = self.kind {
+            return self.archive();
+        }
+
         let output = self.cmd.output()?;
 
         if !output.status.success() {
@@ -237,7 +333,7 @@ impl Linker for MsvcLinker {
         self.arg(format!("/LIBPATH:{}", path.display()));
     }
 
-    fn add_module(&mut self, path: &Path) {
+    fn add_module(&mut self, path: &Path, _: ModuleKind) {
         self.arg(path);
     }
 
@@ -299,10 +395,118 @@ impl Linker for MsvcLinker {
     }
 }
 
+#[derive(Debug)]
+pub struct WasmLinker {
+    cmd: Command,
+    wasi: bool,
+    exports: Vec<String>,
+}
+
+impl WasmLinker {
+    pub fn new(triple: &target_lexicon::Triple) -> Self {
+        let wasi = triple.operating_system == target_lexicon::OperatingSystem::Wasi;
+        let mut cmd = Command::new("wasm-ld");
+
+        // The memory below the global base is left to the runtime, see `lib/wasi`.
+        cmd.arg("--global-base=1024");
+
+        // Without a libc imports are resolved by the host, so undefined symbols are expected.
+        if !wasi {
+            cmd.arg("--allow-undefined");
+        }
+
+        Self {
+            cmd,
+            wasi,
+            exports: Vec::new(),
+        }
+    }
+
+    fn arg(&mut self, arg: impl AsRef<std::ffi::OsStr>) -> &mut Self {
+        self.cmd.arg(arg);
+        self
+    }
+}
+
+impl Linker for WasmLinker {
+    fn runtime_path(&mut self, _: &Path) {
+    }
+
+    fn add_path(&mut self, path: &Path) {
+        self.arg("-L");
+        self.arg(path);
+    }
+
+    fn add_module(&mut self, path: &Path, _: ModuleKind) {
+        self.arg(path);
+    }
+
+    fn add_lib(&mut self, kind: LibKind, lib: &str, path: &Path) {
+        match kind {
+            | LibKind::Static | LibKind::Dynamic => {
+                let file = path.join(format!("{lib}.wasm"));
+
+                if file.exists() {
+                    self.arg(file);
+                } else {
+                    self.arg(format!("-l{lib}"));
+                }
+            },
+            | LibKind::Executable => panic!("linking with executable"),
+        }
+    }
+
+    fn add_export(&mut self, symbol: &str) {
+        self.exports.push(symbol.to_string());
+    }
+
+    fn subsystem(&mut self, _: &str) {
+    }
+
+    fn out_kind(&mut self, kind: LibKind, _: &Path) {
+        match kind {
+            // There is no dynamic linking between wasm modules, so libraries are emitted as
+            // relocatable objects which get linked into the final module. Their exports are
+            // added when linking the executable.
+            | LibKind::Dynamic | LibKind::Static => {
+                self.arg("--relocatable");
+                return;
+            },
+            | LibKind::Executable if self.wasi => {
+                self.arg("--entry=_start");
+            },
+            | LibKind::Executable => {
+                self.arg("--no-entry");
+                self.arg("--export=main");
+            },
+        };
+
+        for symbol in std::mem::take(&mut self.exports) {
+            self.arg(format!("--export={symbol}"));
+        }
+    }
+
+    fn build(&mut self, out: &Path) {
+        self.arg("-o");
+        self.arg(out);
+    }
+
+    fn run(&mut self) -> Result<(), LinkError> {
+        let output = self.cmd.output()?;
+
+        if !output.status.success() {
+            return Err(LinkError::Linker(String::from_utf8(output.stderr).unwrap()));
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct JsLinker {
-    rpath: PathBuf,
-    files: Vec<PathBuf>,
+    modules: Vec<PathBuf>,
+    links: Vec<PathBuf>,
+    deps: BTreeSet<String>,
     out_file: PathBuf,
     emit_main: bool,
 }
@@ -310,42 +514,70 @@ pub struct JsLinker {
 impl JsLinker {
     pub fn new() -> Self {
         Self {
-            rpath: PathBuf::new(),
-            files: Vec::new(),
+            modules: Vec::new(),
+            links: Vec::new(),
+            deps: BTreeSet::new(),
             out_file: PathBuf::new(),
             emit_main: false,
         }
     }
 
-    fn add_file(&mut self, path: impl AsRef<Path>) {
-        let abs = path.as_ref().canonicalize().unwrap();
+    /// Write the module to the output directory, using the file name from its header.
+    fn write_module(&self, dir: &Path, path: &Path) -> Result<String, LinkError> {
+        let text = std::fs::read_to_string(path)?;
+        let (header, code) = text.split_once('\n').unwrap_or((&text, ""));
+        let name = match header.strip_prefix("//- ") {
+            | Some(name) => name.trim().to_string(),
+            | None => return Err(LinkError::Linker(format!("{} is not a module", path.display()))),
+        };
+
+        std::fs::write(dir.join(&name), code)?;
+        Ok(name)
+    }
+
+    fn package_json(&self, name: &str, main: &str) -> String {
+        let main = format!("./{}", main);
+        let mut json = serde_json::json!({
+            "name": name,
+            "private": true,
+            "type": "module",
+            "main": main,
+            "exports": main,
+        });
+
+        if !self.deps.is_empty() {
+            let deps = self
+                .deps
+                .iter()
+                .map(|dep| (dep.clone(), serde_json::Value::from(format!("file:../{}", dep))))
+                .collect::<serde_json::Map<_, _>>();
 
-        if !self.files.contains(&abs) {
-            self.files.push(abs);
+            json["dependencies"] = serde_json::Value::Object(deps);
         }
+
+        let mut json = serde_json::to_string_pretty(&json).unwrap();
+
+        json.push('\n');
+        json
     }
 }
 
 impl Linker for JsLinker {
-    fn runtime_path(&mut self, rpath: &Path) {
-        self.rpath = rpath.to_path_buf();
+    fn runtime_path(&mut self, _rpath: &Path) {
     }
 
     fn add_path(&mut self, _path: &Path) {
     }
 
-    fn add_module(&mut self, path: &Path) {
-        self.add_file(path);
+    fn add_module(&mut self, path: &Path, kind: ModuleKind) {
+        match kind {
+            | ModuleKind::Object => self.modules.push(path.to_path_buf()),
+            | ModuleKind::Link => self.links.push(path.to_path_buf()),
+        }
     }
 
     fn add_lib(&mut self, _kind: LibKind, lib: &str, _: &Path) {
-        let mut path = Path::new(lib).to_path_buf().with_extension("js");
-
-        if !path.exists() {
-            path = self.rpath.join(path);
-        }
-
-        self.add_file(path);
+        self.deps.insert(lib.to_string());
     }
 
     fn add_export(&mut self, _: &str) {
@@ -365,53 +597,171 @@ impl Linker for JsLinker {
     }
 
     fn run(&mut self) -> Result<(), LinkError> {
-        use std::io::{BufRead, Write};
-        std::fs::create_dir_all(self.out_file.parent().unwrap())?;
-        let mut emitted = HashSet::new();
-        let mut out = std::fs::File::create(&self.out_file)?;
-
-        for filename in self.files.drain(..) {
-            if emitted.contains(&filename) {
-                continue;
-            }
+        use std::io::Write;
+        let dir = self.out_file.parent().unwrap();
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
 
-            writeln!(out, "//- {}", filename.display())?;
-            let file = std::fs::File::open(&filename)?;
-            let mut file = std::io::BufReader::new(file);
-            let mut line = String::new();
-            let mut skip = false;
+        std::fs::create_dir_all(dir)?;
 
-            while let Ok(n) = file.read_line(&mut line) {
-                if n == 0 {
-                    break;
-                }
+        let mut index = std::fs::File::create(dir.join("index.js"))?;
 
-                if line.starts_with("//- ") {
-                    skip = false;
+        for module in &self.modules {
+            let file = self.write_module(dir, module)?;
 
-                    if emitted.iter().any(|e| e == Path::new(&line[3..])) {
-                        skip = true;
-                        line.clear();
-                        continue;
-                    } else {
-                        emitted.insert(Path::new(&line[3..]).to_path_buf());
-                    }
-                }
+            writeln!(index, "export * from \"./{}\";", file)?;
+        }
 
-                if !skip {
-                    out.write_all(line.as_bytes())?;
-                }
+        if !self.links.is_empty() {
+            let mut runtime = std::fs::File::create(dir.join(backend_js::RUNTIME_FILE_NAME))?;
 
-                line.clear();
+            for link in &self.links {
+                writeln!(runtime, "//- {}", link.display())?;
+                runtime.write_all(&std::fs::read(link)?)?;
             }
-
-            emitted.insert(filename);
         }
 
         if self.emit_main {
-            out.write_all(b"console.log($main());\n")?;
+            let mut main = std::fs::File::create(&self.out_file)?;
+
+            writeln!(main, "import {{ $main }} from \"./index.js\";\n")?;
+            writeln!(main, "console.log($main());")?;
         }
 
+        let main = self.out_file.file_name().unwrap().to_string_lossy();
+
+        std::fs::write(dir.join("package.json"), self.package_json(&name, &main))?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wasm_args(triple: &str, kind: LibKind) -> Vec<String> {
+        let mut linker = WasmLinker::new(&triple.parse().unwrap());
+
+        linker.add_module(Path::new("main.o"), ModuleKind::Object);
+        linker.add_export("exported");
+        linker.out_kind(kind, Path::new("out.wasm"));
+        linker.build(Path::new("out.wasm"));
+        linker
+            .cmd
+            .get_args()
+            .map(|arg| arg.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn wasi_executable_is_fully_linked() {
+        let args = wasm_args("wasm32-wasi", LibKind::Executable);
+
+        assert!(args.contains(&"--entry=_start".to_string()));
+        assert!(args.contains(&"--export=exported".to_string()));
+        assert!(!args.contains(&"--allow-undefined".to_string()));
+    }
+
+    #[test]
+    fn bare_wasm_executable_imports_from_host() {
+        let args = wasm_args("wasm32-unknown-unknown", LibKind::Executable);
+
+        assert!(args.contains(&"--allow-undefined".to_string()));
+        assert!(args.contains(&"--no-entry".to_string()));
+        assert!(args.contains(&"--export=main".to_string()));
+        assert!(args.contains(&"--export=exported".to_string()));
+    }
+
+    #[test]
+    fn wasm_library_is_relocatable() {
+        for kind in [LibKind::Static, LibKind::Dynamic] {
+            let args = wasm_args("wasm32-wasi", kind);
+
+            assert!(args.contains(&"--relocatable".to_string()));
+            assert!(!args.iter().any(|arg| arg.starts_with("--export")));
+        }
+    }
+
+    #[test]
+    fn static_archive_bundles_static_dependencies() {
+        let mut linker = CcLinker::new();
+
+        linker.add_module(Path::new("target/cache/Main.o"), ModuleKind::Object);
+        linker.add_path(Path::new("target"));
+        linker.add_lib(LibKind::Static, "core", Path::new("target"));
+        linker.add_lib(LibKind::Dynamic, "gfx", Path::new("target"));
+        linker.add_lib(LibKind::Static, "core", Path::new("target"));
+        linker.add_module(Path::new("native/libfoo.a"), ModuleKind::Link);
+        linker.add_module(Path::new("native/bar.o"), ModuleKind::Link);
+        linker.add_module(Path::new("m"), ModuleKind::Link);
+        linker.out_kind(LibKind::Static, Path::new("target/libmain.a"));
+        linker.build(Path::new("target/libmain.a"));
+
+        let members = [PathBuf::from("/tmp/0/core.o")];
+        let cmd = linker.archive_command(&members);
+        let args = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(cmd.get_program(), "ar");
+        assert_eq!(args, [
+            "rcs",
+            "target/libmain.a",
+            "target/cache/Main.o",
+            "native/bar.o",
+            "/tmp/0/core.o",
+        ]);
+        assert_eq!(linker.archives, [PathBuf::from("target/libcore.a"), PathBuf::from("native/libfoo.a")]);
+    }
+
+    #[test]
+    fn static_archive_paths_can_have_spaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("with spaces");
+        let ar = |args: &[&str]| {
+            let status = Command::new("ar").args(args).current_dir(&dir).status().unwrap();
+
+            assert!(status.success());
+        };
+
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("main module.o"), "main").unwrap();
+        fs::write(dir.join("dep.o"), "dep").unwrap();
+        ar(&["rcs", "libdep.a", "dep.o"]);
+
+        let mut linker = CcLinker::new();
+
+        linker.add_module(&dir.join("main module.o"), ModuleKind::Object);
+        linker.add_module(&dir.join("libdep.a"), ModuleKind::Link);
+        linker.out_kind(LibKind::Static, &dir.join("libmain.a"));
+        linker.build(&dir.join("libmain.a"));
+        linker.run().unwrap();
+
+        let output = Command::new("ar").arg("t").arg(dir.join("libmain.a")).output().unwrap();
+
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "main module.o\ndep.o\n");
+    }
+
+    #[test]
+    fn js_modules_are_split_by_kind() {
+        let mut linker = JsLinker::new();
+
+        linker.add_module(Path::new("target/Main.js"), ModuleKind::Object);
+        linker.add_module(Path::new("src/ffi.js"), ModuleKind::Link);
+
+        assert_eq!(linker.modules, vec![PathBuf::from("target/Main.js")]);
+        assert_eq!(linker.links, vec![PathBuf::from("src/ffi.js")]);
+    }
+
+    #[test]
+    fn package_json_escapes_names() {
+        let mut linker = JsLinker::new();
+
+        linker.deps.insert("quoted \"dep\"".into());
+
+        let json = linker.package_json("lib", "main.js");
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+
+        assert_eq!(json["name"], "lib");
+        assert_eq!(json["main"], "./main.js");
+        assert_eq!(json["exports"], "./main.js");
+        assert_eq!(json["dependencies"]["quoted \"dep\""], "file:../quoted \"dep\"");
+    }
+}
@@ -1,3 +1,5 @@
+use hir::diagnostic::Diagnostic as _;
+
 use super::*;
 
 pub struct DuplicateDeclaration<'db, 'd, DB: hir::db::HirDatabase> {
@@ -6,6 +8,10 @@ pub struct DuplicateDeclaration<'db, 'd, DB: hir::db::HirDatabase> {
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for DuplicateDeclaration<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
     fn title(&self) -> String {
         format!("`{}` is defined multiple times", self.diag.name)
     }
@@ -0,0 +1,90 @@
+//! Long-form explanations of diagnostic codes, printed by `fi explain <code>`.
+
+macro_rules! explanations {
+    ($($code:literal),* $(,)?) => {
+        /// All diagnostic codes in use, in order.
+        pub const CODES: &[&str] = &[$($code),*];
+
+        /// The explanation of `code`, with an example of code producing the diagnostic.
+        pub fn explain(code: &str) -> Option<&'static str> {
+            match code.to_uppercase().as_str() {
+                $(| $code => Some(include_str!(concat!("../explain/", $code, ".md"))),)*
+                | _ => None,
+            }
+        }
+    };
+}
+
+explanations! {
+    "E0001", "E0002", "E0003", "E0004", "E0005", "E0006", "E0007", "E0008", "E0009", "E0010",
+    "E0011", "E0012", "E0013", "E0014", "E0015", "E0016", "E0017", "E0018", "E0019", "E0020",
+    "W0001", "W0002", "W0003", "W0004",
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    /// The codes passed to `DiagnosticCode(..)` in the sources of the compiler.
+    fn used_codes(dir: &Path, codes: &mut Vec<String>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+
+            if path.is_dir() {
+                used_codes(&path, codes);
+            } else if path.extension().map_or(false, |e| e == "rs") {
+                let text = fs::read_to_string(&path).unwrap();
+
+                for (i, m) in text.match_indices("DiagnosticCode(\"") {
+                    let start = i + m.len();
+
+                    codes.push(text[start..start + 5].to_string());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_code_has_an_explanation() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("explain");
+        let mut files = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path().file_stem().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        files.sort();
+        assert_eq!(files, CODES);
+
+        for code in CODES {
+            let text = explain(code).unwrap();
+
+            assert!(text.contains("```fi\n"), "{} has no example", code);
+        }
+    }
+
+    #[test]
+    fn every_used_code_is_explained() {
+        let mut codes = Vec::new();
+
+        used_codes(&Path::new(env!("CARGO_MANIFEST_DIR")).join(".."), &mut codes);
+        assert!(codes.contains(&String::from("E0020")));
+
+        for code in codes {
+            assert!(CODES.contains(&code.as_str()), "{} is not explained", code);
+        }
+    }
+
+    #[test]
+    fn codes_are_case_insensitive() {
+        assert_eq!(explain("e0020"), explain("E0020"));
+    }
+
+    #[test]
+    fn unknown_codes_have_no_explanation() {
+        assert_eq!(explain("E9999"), None);
+        assert_eq!(explain(""), None);
+    }
+}
@@ -3,14 +3,29 @@ mod inference_cycle;
 mod mismatched_kind;
 mod mismatched_type;
 mod private_operator;
+mod shadowed_name;
+mod suggestions;
 mod unresolved_operator;
+mod unresolved_type;
+mod unresolved_value;
 mod unsolved_constraint;
+mod unused_binding;
+mod unused_import;
+mod unused_item;
 mod value_hole;
 
+pub mod explain;
+
+pub use hir::diagnostic::DiagnosticCode;
 use hir::InFile;
-use syntax::{ast, AstNode, NameOwner, Parsed, SyntaxKind, SyntaxNodePtr, TextRange};
+use syntax::{ast, AstNode, NameOwner, Parsed, SyntaxKind, SyntaxNodePtr, TextRange, TextSize};
+
+/// The code of syntax errors, which are reported by the parser instead of as a [`Diagnostic`].
+pub const SYNTAX_ERROR: DiagnosticCode = DiagnosticCode("E0020");
 
 pub trait Diagnostic {
+    fn code(&self) -> DiagnosticCode;
+
     fn title(&self) -> String;
 
     fn range(&self) -> TextRange;
@@ -30,6 +45,12 @@ pub trait Diagnostic {
     fn notes(&self) -> Vec<String> {
         Vec::new()
     }
+
+    /// Fixes that can be applied without any further input, used by `fi fix` and offered as
+    /// quick fixes by the language server.
+    fn suggestions(&self) -> Vec<Suggestion> {
+        Vec::new()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -51,6 +72,19 @@ pub struct SecondaryAnnotation {
     pub message: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String,
+    pub edits: Vec<TextEdit>,
+}
+
+/// Replace the text in `range` with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: InFile<TextRange>,
+    pub text: String,
+}
+
 pub trait DiagnosticFor {
     fn with_diagnostic<R, F: FnOnce(&dyn Diagnostic) -> R>(&self, f: F) -> R;
 }
@@ -68,6 +102,86 @@ impl From<SecondaryAnnotation> for SourceAnnotation {
     }
 }
 
+impl TextEdit {
+    pub fn new(range: InFile<TextRange>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert(at: InFile<TextSize>, text: impl Into<String>) -> Self {
+        Self::new(at.map(TextRange::empty), text)
+    }
+
+    pub fn delete(range: InFile<TextRange>) -> Self {
+        Self::new(range, String::new())
+    }
+
+    /// Whether applying both edits is ambiguous: their ranges intersect, or they insert text at
+    /// the same position.
+    fn conflicts_with(&self, other: &Self) -> bool {
+        let (a, b) = (self.range.value, other.range.value);
+
+        if self.range.file_id != other.range.file_id {
+            return false;
+        }
+
+        (a.start() < b.end() && b.start() < a.end()) || (a.is_empty() && b.is_empty() && a.start() == b.start())
+    }
+}
+
+/// The edits of the `suggestions` which can be applied together. Suggestions are taken in order
+/// and applied as a whole: a suggestion with an edit overlapping an edit of an earlier suggestion,
+/// or another edit of its own, is skipped entirely, so that a fix is never applied halfway. Edits equal to an earlier edit
+/// don't conflict with it.
+pub fn compatible_edits(suggestions: impl IntoIterator<Item = Suggestion>) -> Vec<TextEdit> {
+    let mut accepted = Vec::<TextEdit>::new();
+
+    for suggestion in suggestions {
+        let conflicts = suggestion.edits.iter().enumerate().any(|(i, edit)| {
+            accepted
+                .iter()
+                .chain(&suggestion.edits[..i])
+                .any(|other| edit != other && edit.conflicts_with(other))
+        });
+
+        if !conflicts {
+            accepted.extend(suggestion.edits);
+        }
+    }
+
+    accepted
+}
+
+/// Apply `edits` to `text`. Duplicate edits are applied once, and edits overlapping another edit
+/// are skipped, see [`compatible_edits`] to avoid applying only part of a suggestion.
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    let mut text = text.to_string();
+    let mut end = TextSize::of(text.as_str());
+
+    edits.sort_by(|a, b| {
+        (a.range.value.start(), a.range.value.end(), &a.text).cmp(&(
+            b.range.value.start(),
+            b.range.value.end(),
+            &b.text,
+        ))
+    });
+    edits.dedup();
+
+    for edit in edits.into_iter().rev() {
+        if edit.range.value.end() > end {
+            continue;
+        }
+
+        text.replace_range(std::ops::Range::<usize>::from(edit.range.value), &edit.text);
+        end = edit.range.value.start();
+    }
+
+    text
+}
+
 impl<DB: hir::db::HirDatabase> DiagnosticForWith<DB> for dyn hir::diagnostic::Diagnostic {
     fn with_diagnostic<R, F: FnOnce(&dyn Diagnostic) -> R>(&self, with: &DB, f: F) -> R {
         if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::MismatchedType>() {
@@ -86,6 +200,18 @@ impl<DB: hir::db::HirDatabase> DiagnosticForWith<DB> for dyn hir::diagnostic::Di
             f(&inference_cycle::InferenceCycle::new(with, v))
         } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::ValueHole>() {
             f(&value_hole::ValueHole::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::UnresolvedValue>() {
+            f(&unresolved_value::UnresolvedValue::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::UnresolvedType>() {
+            f(&unresolved_type::UnresolvedType::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::UnusedImport>() {
+            f(&unused_import::UnusedImport::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::UnusedBinding>() {
+            f(&unused_binding::UnusedBinding::new(v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::UnusedItem>() {
+            f(&unused_item::UnusedItem::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::ShadowedName>() {
+            f(&shadowed_name::ShadowedName::new(v))
         } else {
             f(&GenericDiagnostic { diagnostic: self })
         }
@@ -97,6 +223,10 @@ struct GenericDiagnostic<'d> {
 }
 
 impl<'d> Diagnostic for GenericDiagnostic<'d> {
+    fn code(&self) -> DiagnosticCode {
+        self.diagnostic.code()
+    }
+
     fn title(&self) -> String {
         self.diagnostic.message()
     }
@@ -112,6 +242,109 @@ fn item_name(ptr: SyntaxNodePtr, parse: &Parsed<ast::SourceFile>) -> TextRange {
             .and_then(|i| i.iter().next())
             .and_then(|i| Some(i.name()?.syntax().text_range()))
             .unwrap_or_else(|| ptr.range()),
+        | SyntaxKind::ITEM_TYPE => ast::ItemType::cast(ptr.to_node(parse.tree().syntax()))
+            .and_then(|i| i.iter().next())
+            .and_then(|i| Some(i.name()?.syntax().text_range()))
+            .unwrap_or_else(|| ptr.range()),
         | _ => ptr.range(),
     }
 }
+
+fn is_ident(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+}
+
+fn lint_level(level: hir::diagnostic::LintLevel) -> Level {
+    match level {
+        | hir::diagnostic::LintLevel::Deny => Level::Error,
+        | _ => Level::Warning,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base_db::input::FileId;
+
+    use super::*;
+
+    fn edit(file: u32, start: u32, end: u32, text: &str) -> TextEdit {
+        TextEdit::new(
+            InFile::new(FileId(file), TextRange::new(start.into(), end.into())),
+            text,
+        )
+    }
+
+    fn suggestion(edits: Vec<TextEdit>) -> Suggestion {
+        Suggestion {
+            message: String::new(),
+            edits,
+        }
+    }
+
+    fn fix(text: &str, suggestions: Vec<Suggestion>) -> String {
+        apply_edits(text, &compatible_edits(suggestions))
+    }
+
+    #[test]
+    fn applies_independent_suggestions() {
+        let text = "let x = 1\nlet y = 2\n";
+        let suggestions = vec![
+            suggestion(vec![edit(0, 4, 5, "_x")]),
+            suggestion(vec![edit(0, 14, 15, "_y")]),
+        ];
+
+        assert_eq!(fix(text, suggestions), "let _x = 1\nlet _y = 2\n");
+    }
+
+    #[test]
+    fn skips_conflicting_suggestion_as_a_whole() {
+        let text = "import A (a, b)\n";
+        let suggestions = vec![
+            suggestion(vec![edit(0, 10, 13, "")]),
+            suggestion(vec![edit(0, 0, 0, "-- unused\n"), edit(0, 11, 14, "c")]),
+        ];
+
+        assert_eq!(fix(text, suggestions), "import A (b)\n");
+    }
+
+    #[test]
+    fn skips_suggestion_overlapping_itself() {
+        let text = "abcdef";
+        let suggestions = vec![suggestion(vec![
+            edit(0, 0, 1, "A"),
+            edit(0, 2, 5, "X"),
+            edit(0, 3, 4, "Y"),
+        ])];
+
+        assert_eq!(fix(text, suggestions), "abcdef");
+    }
+
+    #[test]
+    fn applies_duplicate_suggestions_once() {
+        let text = "import A\nimport B\n";
+        let remove = suggestion(vec![edit(0, 0, 9, "")]);
+
+        assert_eq!(fix(text, vec![remove.clone(), remove]), "import B\n");
+    }
+
+    #[test]
+    fn inserts_at_the_same_position_conflict() {
+        let text = "f = x";
+        let suggestions = vec![
+            suggestion(vec![edit(0, 0, 0, "f : Int\n")]),
+            suggestion(vec![edit(0, 0, 0, "f : Bool\n")]),
+        ];
+
+        assert_eq!(fix(text, suggestions), "f : Int\nf = x");
+    }
+
+    #[test]
+    fn edits_in_different_files_dont_conflict() {
+        let suggestions = vec![
+            suggestion(vec![edit(0, 0, 3, "a")]),
+            suggestion(vec![edit(1, 0, 3, "b")]),
+        ];
+
+        assert_eq!(compatible_edits(suggestions).len(), 2);
+    }
+}
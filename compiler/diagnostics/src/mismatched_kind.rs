@@ -9,6 +9,10 @@ pub struct MismatchedKind<'db, 'd, DB: hir::db::HirDatabase> {
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for MismatchedKind<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
     fn title(&self) -> String {
         format!(
             "expected kind `{}`, found `{}`",
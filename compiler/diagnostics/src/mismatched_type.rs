@@ -9,6 +9,10 @@ pub struct MismatchedType<'db, 'd, DB: hir::db::HirDatabase> {
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for MismatchedType<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
     fn title(&self) -> String {
         if self.diag.found_src.is_some() {
             format!(
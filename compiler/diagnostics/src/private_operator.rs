@@ -5,11 +5,15 @@ use super::*;
 
 pub struct PrivateOperator<'db, 'd, DB: hir::db::HirDatabase> {
     _db: &'db DB,
-    _diag: &'d hir::diagnostic::PrivateOperator,
+    diag: &'d hir::diagnostic::PrivateOperator,
     location: TextRange,
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for PrivateOperator<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
     fn title(&self) -> String {
         "private operator".into()
     }
@@ -32,7 +36,7 @@ impl<'db, 'd, DB: hir::db::HirDatabase> PrivateOperator<'db, 'd, DB> {
 
         Self {
             _db: db,
-            _diag: diag,
+            diag,
             location,
         }
     }
@@ -0,0 +1,45 @@
+use hir::diagnostic::Diagnostic as _;
+
+use super::*;
+
+pub struct ShadowedName<'d> {
+    diag: &'d hir::diagnostic::ShadowedName,
+}
+
+impl<'d> Diagnostic for ShadowedName<'d> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
+    fn title(&self) -> String {
+        self.diag.message()
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.pat.syntax_node_ptr().range()
+    }
+
+    fn level(&self) -> Level {
+        lint_level(self.diag.level)
+    }
+
+    fn primary_annotation(&self) -> Option<SourceAnnotation> {
+        Some(SourceAnnotation {
+            range: self.diag.pat.syntax_node_ptr().range(),
+            message: format!("`{}` redefined here", self.diag.name),
+        })
+    }
+
+    fn secondary_annotations(&self) -> Vec<SecondaryAnnotation> {
+        vec![SecondaryAnnotation {
+            range: InFile::new(self.diag.file, self.diag.shadowed.syntax_node_ptr().range()),
+            message: format!("previous binding of `{}` here", self.diag.name),
+        }]
+    }
+}
+
+impl<'d> ShadowedName<'d> {
+    pub fn new(diag: &'d hir::diagnostic::ShadowedName) -> Self {
+        Self { diag }
+    }
+}
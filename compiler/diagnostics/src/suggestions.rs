@@ -0,0 +1,160 @@
+//! Suggestions shared by multiple diagnostics, like importing or qualifying an unresolved name.
+
+use base_db::input::FileId;
+use hir::semantics::Semantics;
+use hir::{InFile, ModuleDef, Name, Path};
+use syntax::{ast, AstNode, TextRange};
+
+use super::*;
+
+/// The module declaration in which `range` occurs and its definition.
+pub(crate) fn containing_module<DB: hir::db::HirDatabase>(
+    db: &DB,
+    range: InFile<TextRange>,
+) -> Option<(hir::Module, ast::ItemModule)> {
+    let sema = Semantics::new(db);
+    let file = sema.parse(range.file_id);
+    let decl = syntax::find_node_at_range::<ast::ItemModule>(file.syntax(), range.value)?;
+    let module = sema.to_def(&decl)?;
+
+    Some((module, decl))
+}
+
+/// The paths of the modules visible from `module` which export a definition named `name` for
+/// which `filter` returns true.
+pub(crate) fn find_exports<DB: hir::db::HirDatabase>(
+    db: &DB,
+    module: hir::Module,
+    name: &Name,
+    filter: impl Fn(ModuleDef) -> bool,
+) -> Vec<Path> {
+    let lib = module.lib();
+    let mut modules = lib.modules(db);
+    let mut paths = Vec::new();
+
+    for dep in lib.dependencies(db) {
+        modules.extend(dep.lib.modules(db));
+    }
+
+    while let Some(m) = modules.pop() {
+        modules.extend(m.children(db));
+
+        if m == module {
+            continue;
+        }
+
+        for def in m.exports(db) {
+            if let ModuleDef::Func(func) = def {
+                if func.as_assoc_item(db).is_some() {
+                    continue;
+                }
+            }
+
+            if filter(def) && def.name(db) == *name {
+                let mut path = def.path(db);
+
+                path.pop_segment();
+
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+
+    paths.sort();
+    paths
+}
+
+/// Suggest importing `name` from each of `paths`. An existing import of the module is extended
+/// when possible.
+pub(crate) fn import_suggestions(file: FileId, decl: &ast::ItemModule, paths: &[Path], name: &Name) -> Vec<Suggestion> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            let edit = import_edit(file, decl, path, name)?;
+
+            Some(Suggestion {
+                message: format!("import `{}` from `{}`", name, path),
+                edits: vec![edit],
+            })
+        })
+        .collect()
+}
+
+fn import_edit(file: FileId, decl: &ast::ItemModule, path: &Path, name: &Name) -> Option<TextEdit> {
+    let module = path.to_string();
+
+    for import in decl.imports() {
+        if import.qualify().is_some() || import.module().map(|m| m.text()).as_deref() != Some(module.as_str()) {
+            continue;
+        }
+
+        // An import without an item list already imports everything.
+        let last = import.items()?.into_iter().last()?;
+
+        return Some(TextEdit::insert(
+            InFile::new(file, last.syntax().text_range().end()),
+            format!(", {}", name),
+        ));
+    }
+
+    let import = format!("import {} ({})", path, name);
+
+    Some(match (decl.imports().last(), decl.items().next()) {
+        | (Some(last), _) => TextEdit::insert(
+            InFile::new(file, last.syntax().text_range().end()),
+            format!("\n{}", import),
+        ),
+        | (None, Some(first)) => TextEdit::insert(
+            InFile::new(file, first.syntax().text_range().start()),
+            format!("{}\n\n", import),
+        ),
+        | (None, None) => TextEdit::insert(
+            InFile::new(file, decl.syntax().text_range().end()),
+            format!("\n\n{}", import),
+        ),
+    })
+}
+
+/// Suggest replacing `name` at `range` with a path through an existing qualified import of one
+/// of `paths`.
+pub(crate) fn qualify_suggestions(
+    range: InFile<TextRange>,
+    decl: &ast::ItemModule,
+    paths: &[Path],
+    name: &Name,
+) -> Vec<Suggestion> {
+    let mut suggestions = Vec::new();
+
+    for import in decl.imports() {
+        let qualify = match import.qualify() {
+            | Some(q) => q.text(),
+            | None => continue,
+        };
+
+        let module = match import.module() {
+            | Some(m) => m.text(),
+            | None => continue,
+        };
+
+        if !paths.iter().any(|p| p.to_string() == module) {
+            continue;
+        }
+
+        if let Some(items) = import.items() {
+            if !items.into_iter().any(|i| i.text() == name.as_ref()) {
+                continue;
+            }
+        }
+
+        let text = format!("{}{}{}", qualify, Path::SEPARATOR, name);
+
+        suggestions.push(Suggestion {
+            message: format!("use `{}`", text),
+            edits: vec![TextEdit::new(range, text)],
+        });
+    }
+
+    suggestions
+}
@@ -1,15 +1,22 @@
 use hir::diagnostic::Diagnostic as _;
+use hir::{AsName, ModuleDef};
 use syntax::TextRange;
 
+use super::suggestions::{containing_module, find_exports, import_suggestions};
 use super::*;
 
 pub struct UnresolvedOperator<'db, 'd, DB: hir::db::HirDatabase> {
-    _db: &'db DB,
-    _diag: &'d hir::diagnostic::UnresolvedOperator,
+    db: &'db DB,
+    diag: &'d hir::diagnostic::UnresolvedOperator,
     location: TextRange,
+    operator: Option<String>,
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnresolvedOperator<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
     fn title(&self) -> String {
         "unknown operator".into()
     }
@@ -17,6 +24,25 @@ impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnresolvedOperator<'db, '
     fn range(&self) -> TextRange {
         self.location
     }
+
+    fn suggestions(&self) -> Vec<Suggestion> {
+        let operator = match &self.operator {
+            | Some(op) => op,
+            | None => return Vec::new(),
+        };
+
+        let (module, decl) = match containing_module(self.db, InFile::new(self.diag.file, self.location)) {
+            | Some(it) => it,
+            | None => return Vec::new(),
+        };
+
+        let name = format!("({})", operator).as_name();
+        let paths = find_exports(self.db, module, &name, |def| {
+            matches!(def, ModuleDef::Fixity(_) | ModuleDef::Func(_))
+        });
+
+        import_suggestions(self.diag.file, &decl, &paths, &name)
+    }
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> UnresolvedOperator<'db, 'd, DB> {
@@ -25,19 +51,24 @@ impl<'db, 'd, DB: hir::db::HirDatabase> UnresolvedOperator<'db, 'd, DB> {
         const SYMBOLS: [SyntaxKind; 9] = [OPERATOR, ARROW, LEFT_ARROW, DBL_DOT, COMMA, COLON, PIPE, EQUALS, AT];
 
         let parse = db.parse(diag.file);
-        let location = diag
+        let token = diag
             .src
             .to_node(&parse.syntax_node())
             .children_with_tokens()
             .filter(|n| SYMBOLS.contains(&n.kind()))
             .nth(diag.idx)
-            .map(|n| n.text_range())
+            .and_then(|n| n.into_token());
+
+        let location = token
+            .as_ref()
+            .map(|t| t.text_range())
             .unwrap_or_else(|| diag.display_source().value.range());
 
         Self {
-            _db: db,
-            _diag: diag,
+            db,
+            diag,
             location,
+            operator: token.map(|t| t.text().to_string()),
         }
     }
 }
@@ -0,0 +1,55 @@
+use hir::diagnostic::Diagnostic as _;
+use hir::{AsName, ModuleDef};
+
+use super::suggestions::{containing_module, find_exports, import_suggestions, qualify_suggestions};
+use super::*;
+
+pub struct UnresolvedType<'db, 'd, DB: hir::db::HirDatabase> {
+    db: &'db DB,
+    diag: &'d hir::diagnostic::UnresolvedType,
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnresolvedType<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
+    fn title(&self) -> String {
+        self.diag.message()
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.ty.syntax_node_ptr().range()
+    }
+
+    fn suggestions(&self) -> Vec<Suggestion> {
+        let range = InFile::new(self.diag.file, self.diag.ty.syntax_node_ptr().range());
+        let parse = self.db.parse(self.diag.file);
+        let text = self.diag.ty.to_node(&parse.syntax_node()).syntax().text().to_string();
+
+        if !is_ident(&text) {
+            return Vec::new();
+        }
+
+        let (module, decl) = match containing_module(self.db, range) {
+            | Some(it) => it,
+            | None => return Vec::new(),
+        };
+
+        let name = text.as_name();
+        let paths = find_exports(self.db, module, &name, |def| {
+            matches!(def, ModuleDef::TypeCtor(_) | ModuleDef::TypeAlias(_))
+        });
+
+        let mut suggestions = qualify_suggestions(range, &decl, &paths, &name);
+
+        suggestions.extend(import_suggestions(self.diag.file, &decl, &paths, &name));
+        suggestions
+    }
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> UnresolvedType<'db, 'd, DB> {
+    pub fn new(db: &'db DB, diag: &'d hir::diagnostic::UnresolvedType) -> Self {
+        Self { db, diag }
+    }
+}
@@ -9,6 +9,10 @@ pub struct UnsolvedConstraint<'db, 'd, DB: hir::db::HirDatabase> {
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnsolvedConstraint<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
     fn title(&self) -> String {
         format!("unsolved constraint `{}`", self.diag.ctnt.display(self.db),)
     }
@@ -59,6 +63,58 @@ impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnsolvedConstraint<'db, '
             Vec::new()
         }
     }
+
+    fn suggestions(&self) -> Vec<Suggestion> {
+        let parse = self.db.parse(self.diag.file);
+        let node = self.diag.src.to_node(&parse.syntax_node());
+        let sig = match node
+            .ancestors()
+            .find_map(ast::ItemFunc::cast)
+            .and_then(|f| f.iter().find(|f| f.ty().is_some()))
+        {
+            | Some(sig) => sig,
+            | None => return Vec::new(),
+        };
+
+        // Only constraints on the type variables of the function can be added to its signature.
+        let vars = sig
+            .type_vars()
+            .map(|vars| vars.iter().map(|v| v.text()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let types = self
+            .diag
+            .ctnt
+            .types
+            .iter()
+            .map(|&t| TyParens(t, ParenMode::App).display(self.db).to_string())
+            .collect::<Vec<_>>();
+
+        if types.is_empty() || !types.iter().all(|t| vars.contains(t)) {
+            return Vec::new();
+        }
+
+        let ctnt = format!("{} {}", self.db.class_data(self.diag.ctnt.class).name, types.join(" "));
+
+        let edit = match sig.ty() {
+            | Some(ast::Type::Where(ty)) => match ty.where_clause() {
+                | Some(clause) => TextEdit::insert(
+                    InFile::new(self.diag.file, clause.syntax().text_range().end()),
+                    format!(", {}", ctnt),
+                ),
+                | None => return Vec::new(),
+            },
+            | Some(ty) => TextEdit::insert(
+                InFile::new(self.diag.file, ty.syntax().text_range().end()),
+                format!(" where {}", ctnt),
+            ),
+            | None => return Vec::new(),
+        };
+
+        vec![Suggestion {
+            message: format!("add `{}` to the `where` clause", ctnt),
+            edits: vec![edit],
+        }]
+    }
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> UnsolvedConstraint<'db, 'd, DB> {
@@ -0,0 +1,45 @@
+use hir::diagnostic::Diagnostic as _;
+
+use super::*;
+
+pub struct UnusedBinding<'d> {
+    diag: &'d hir::diagnostic::UnusedBinding,
+}
+
+impl<'d> Diagnostic for UnusedBinding<'d> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
+    fn title(&self) -> String {
+        self.diag.message()
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.pat.syntax_node_ptr().range()
+    }
+
+    fn level(&self) -> Level {
+        lint_level(self.diag.level)
+    }
+
+    fn suggestions(&self) -> Vec<Suggestion> {
+        let ptr = self.diag.pat.syntax_node_ptr();
+
+        // Renaming a field pun would change the field it binds.
+        if ptr.kind() != SyntaxKind::PAT_BIND {
+            return Vec::new();
+        }
+
+        vec![Suggestion {
+            message: format!("prefix `{}` with an underscore", self.diag.name),
+            edits: vec![TextEdit::insert(InFile::new(self.diag.file, ptr.range().start()), "_")],
+        }]
+    }
+}
+
+impl<'d> UnusedBinding<'d> {
+    pub fn new(diag: &'d hir::diagnostic::UnusedBinding) -> Self {
+        Self { diag }
+    }
+}
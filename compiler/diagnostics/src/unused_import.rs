@@ -0,0 +1,96 @@
+use hir::diagnostic::Diagnostic as _;
+
+use super::*;
+
+pub struct UnusedImport<'db, 'd, DB: hir::db::HirDatabase> {
+    db: &'db DB,
+    diag: &'d hir::diagnostic::UnusedImport,
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnusedImport<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
+    fn title(&self) -> String {
+        self.diag.message()
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.src.range()
+    }
+
+    fn level(&self) -> Level {
+        lint_level(self.diag.level)
+    }
+
+    fn primary_annotation(&self) -> Option<SourceAnnotation> {
+        Some(SourceAnnotation {
+            range: self.diag.src.range(),
+            message: String::from("imported here but never used"),
+        })
+    }
+
+    fn suggestions(&self) -> Vec<Suggestion> {
+        let parse = self.db.parse(self.diag.file);
+        let root = parse.syntax_node();
+        let text = root.text().to_string();
+        let node = self.diag.src.to_node(&root);
+        let import = match node.ancestors().find_map(ast::ItemImport::cast) {
+            | Some(import) => import,
+            | None => return Vec::new(),
+        };
+
+        let only_item = import.items().map_or(true, |items| items.into_iter().count() == 1);
+        let range = if node.kind() == SyntaxKind::IMPORT || only_item {
+            line_range(&text, import.syntax().text_range())
+        } else {
+            list_item_range(&text, node.text_range())
+        };
+
+        vec![Suggestion {
+            message: format!("remove the unused import of `{}`", self.diag.name),
+            edits: vec![TextEdit::delete(InFile::new(self.diag.file, range))],
+        }]
+    }
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> UnusedImport<'db, 'd, DB> {
+    pub fn new(db: &'db DB, diag: &'d hir::diagnostic::UnusedImport) -> Self {
+        Self { db, diag }
+    }
+}
+
+/// Extend `range` over the line break following it.
+fn line_range(text: &str, range: TextRange) -> TextRange {
+    let after = &text[usize::from(range.end())..];
+    let len = if after.starts_with("\r\n") {
+        2
+    } else if after.starts_with('\n') {
+        1
+    } else {
+        0
+    };
+
+    TextRange::new(range.start(), range.end() + TextSize::from(len))
+}
+
+/// Extend `range` over the comma separating it from the next or previous item in a list.
+fn list_item_range(text: &str, range: TextRange) -> TextRange {
+    let start = usize::from(range.start());
+    let end = usize::from(range.end());
+    let after = text[end..].trim_start_matches(char::is_whitespace);
+
+    if let Some(rest) = after.strip_prefix(',') {
+        let rest = rest.trim_start_matches(char::is_whitespace);
+
+        return TextRange::new(range.start(), TextSize::from((text.len() - rest.len()) as u32));
+    }
+
+    let before = text[..start].trim_end_matches(char::is_whitespace);
+
+    match before.strip_suffix(',') {
+        | Some(before) => TextRange::new(TextSize::from(before.len() as u32), range.end()),
+        | None => range,
+    }
+}
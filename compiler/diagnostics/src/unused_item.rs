@@ -0,0 +1,39 @@
+use hir::diagnostic::Diagnostic as _;
+
+use super::*;
+
+pub struct UnusedItem<'db, 'd, DB: hir::db::HirDatabase> {
+    db: &'db DB,
+    diag: &'d hir::diagnostic::UnusedItem,
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnusedItem<'db, 'd, DB> {
+    fn code(&self) -> DiagnosticCode {
+        self.diag.code()
+    }
+
+    fn title(&self) -> String {
+        self.diag.message()
+    }
+
+    fn range(&self) -> TextRange {
+        item_name(self.diag.item.syntax_node_ptr(), &self.db.parse(self.diag.file))
+    }
+
+    fn level(&self) -> Level {
+        lint_level(self.diag.level)
+    }
+
+    fn notes(&self) -> Vec<String> {
+        vec![format!(
+            "`{}` is private, export it to use it outside of this module",
+            self.diag.name
+        )]
+    }
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> UnusedItem<'db, 'd, DB> {
+    pub fn new(db: &'db DB, diag: &'d hir::diagnostic::UnusedItem) -> Self {
+        Self { db, diag }
+    }
+}
@@ -1,19 +1,100 @@
-use std::io;
+use std::sync::Arc;
+use std::{fs, io};
 
-use ariadne::{Cache, Color, ColorGenerator, Label, Report, ReportKind, Source};
+use ariadne::{Cache, Color, ColorGenerator, Fmt, Label, Report, ReportKind, Source};
 use base_db::input::FileId;
 use base_db::{SourceDatabase, SourceDatabaseExt};
-use diagnostics::{DiagnosticForWith, Level};
+use diagnostics::{DiagnosticForWith, Level, SYNTAX_ERROR};
 use hir::db::HirDatabase;
 use hir::diagnostic::{Diagnostic, DiagnosticSink};
 use paths::AbsPathBuf;
+use project::build_script::BuildWarning;
 use rustc_hash::FxHashMap;
-use syntax::SyntaxError;
+use serde::Serialize;
+use syntax::{SyntaxError, TextRange};
 use vfs::VfsPath;
 
 use crate::db::RootDatabase;
 
-pub fn emit_diagnostics(db: &RootDatabase, lib: hir::Lib, mut writer: impl io::Write) -> io::Result<usize> {
+/// How diagnostics are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Rendered reports for a terminal.
+    Human,
+    /// One JSON object per line for tools, see [`JsonDiagnostic`].
+    Json,
+}
+
+impl Default for MessageFormat {
+    fn default() -> Self {
+        Self::Human
+    }
+}
+
+impl std::str::FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            | "human" => Ok(Self::Human),
+            | "json" => Ok(Self::Json),
+            | _ => Err(format!("invalid message format '{}', expected human or json", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonDiagnostic {
+    /// The diagnostic code, see `fi explain`. Warnings of build scripts don't have a code.
+    pub code: Option<&'static str>,
+    pub file: String,
+    pub level: &'static str,
+    pub title: String,
+    pub range: JsonRange,
+    pub primary: Option<JsonAnnotation>,
+    pub secondary: Vec<JsonAnnotation>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<JsonSuggestion>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonAnnotation {
+    pub file: String,
+    pub range: JsonRange,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonSuggestion {
+    pub message: String,
+    pub edits: Vec<JsonEdit>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonEdit {
+    pub file: String,
+    pub range: JsonRange,
+    pub text: String,
+}
+
+/// A range in a file, both as byte offsets and as 1-based lines and columns. Columns count
+/// characters.
+#[derive(Debug, Serialize)]
+pub struct JsonRange {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+pub fn emit_diagnostics(
+    db: &RootDatabase,
+    lib: hir::Lib,
+    format: MessageFormat,
+    mut writer: impl io::Write,
+) -> io::Result<usize> {
     let mut errors = 0;
     let mut cache = DbCache {
         db,
@@ -28,14 +109,46 @@ pub fn emit_diagnostics(db: &RootDatabase, lib: hir::Lib, mut writer: impl io::W
 
         for err in parse.errors().iter() {
             errors += 1;
-            emit_syntax_error(err, file_id, config, &mut cache, &mut writer)?;
+
+            match format {
+                | MessageFormat::Human => emit_syntax_error(err, file_id, config, &mut cache, &mut writer)?,
+                | MessageFormat::Json => {
+                    let diag = JsonDiagnostic {
+                        code: Some(SYNTAX_ERROR.as_str()),
+                        file: file_path(db, file_id),
+                        level: "error",
+                        title: String::from("syntax error"),
+                        range: json_range(db, file_id, err.range),
+                        primary: Some(JsonAnnotation {
+                            file: file_path(db, file_id),
+                            range: json_range(db, file_id, err.range),
+                            message: err.msg.to_string(),
+                        }),
+                        secondary: Vec::new(),
+                        notes: Vec::new(),
+                        suggestions: Vec::new(),
+                    };
+
+                    write_json(&diag, &mut writer)?;
+                },
+            }
         }
 
         let mut error = None;
         let mut diagnostic_sink = DiagnosticSink::new(|d| {
-            errors += 1;
+            // Lints are only counted when they are denied.
+            if d.with_diagnostic(db, |d| d.level() == Level::Error) {
+                errors += 1;
+            }
 
-            if let Err(e) = emit_hir_diagnostic(d, db, file_id, config, &mut cache, &mut writer) {
+            let res = match format {
+                | MessageFormat::Human => emit_hir_diagnostic(d, db, file_id, config, &mut cache, &mut writer),
+                | MessageFormat::Json => {
+                    d.with_diagnostic(db, |d| write_json(&json_diagnostic(db, d, file_id), &mut writer))
+                },
+            };
+
+            if let Err(e) = res {
                 error = Some(e);
             }
         });
@@ -51,6 +164,198 @@ pub fn emit_diagnostics(db: &RootDatabase, lib: hir::Lib, mut writer: impl io::W
     Ok(errors)
 }
 
+/// A source file changed by [`fix_diagnostics`].
+#[derive(Debug)]
+pub struct FixedFile {
+    pub file_id: FileId,
+    pub path: String,
+    pub text: Arc<str>,
+}
+
+/// Apply the suggestions of the diagnostics in `lib` to its source files. Diagnostics with
+/// multiple suggestions are skipped, as it is unclear which one to apply, and so are suggestions
+/// conflicting with an earlier one. Returns the files that changed, sorted by their paths.
+pub fn fix_diagnostics(db: &RootDatabase, lib: hir::Lib) -> io::Result<Vec<FixedFile>> {
+    let mut suggestions = Vec::new();
+
+    for module in lib.modules(db) {
+        let mut diagnostic_sink = DiagnosticSink::new(|d| {
+            d.with_diagnostic(db, |d| {
+                let mut suggestion = d.suggestions();
+
+                if suggestion.len() == 1 {
+                    suggestions.push(suggestion.remove(0));
+                }
+            })
+        });
+
+        module.diagnostics(db, &mut diagnostic_sink);
+    }
+
+    let mut edits = FxHashMap::<FileId, Vec<diagnostics::TextEdit>>::default();
+
+    for edit in diagnostics::compatible_edits(suggestions) {
+        edits.entry(edit.range.file_id).or_default().push(edit);
+    }
+
+    let mut changed = Vec::new();
+
+    for (file_id, edits) in edits {
+        let text = db.file_text(file_id);
+        let fixed = diagnostics::apply_edits(&text, &edits);
+        let source_root = db.source_root(db.file_source_root(file_id));
+
+        if fixed == *text {
+            continue;
+        }
+
+        if let Some(VfsPath::PathBuf(path)) = source_root.path_for_file(file_id) {
+            fs::write(path.as_os_str(), &fixed)?;
+            changed.push(FixedFile {
+                file_id,
+                path: file_path(db, file_id),
+                text: fixed.into(),
+            });
+        }
+    }
+
+    changed.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(changed)
+}
+
+pub fn json_diagnostic(db: &RootDatabase, d: &dyn diagnostics::Diagnostic, file_id: FileId) -> JsonDiagnostic {
+    let file = file_path(db, file_id);
+
+    JsonDiagnostic {
+        code: Some(d.code().as_str()),
+        level: match d.level() {
+            | Level::Error => "error",
+            | Level::Warning => "warning",
+            | Level::Info => "info",
+        },
+        title: d.title(),
+        range: json_range(db, file_id, d.range()),
+        primary: d.primary_annotation().map(|ann| JsonAnnotation {
+            file: file.clone(),
+            range: json_range(db, file_id, ann.range),
+            message: ann.message,
+        }),
+        secondary: d
+            .secondary_annotations()
+            .into_iter()
+            .map(|ann| JsonAnnotation {
+                file: file_path(db, ann.range.file_id),
+                range: json_range(db, ann.range.file_id, ann.range.value),
+                message: ann.message,
+            })
+            .collect(),
+        notes: d.notes(),
+        suggestions: d
+            .suggestions()
+            .into_iter()
+            .map(|s| JsonSuggestion {
+                message: s.message,
+                edits: s
+                    .edits
+                    .into_iter()
+                    .map(|e| JsonEdit {
+                        file: file_path(db, e.range.file_id),
+                        range: json_range(db, e.range.file_id, e.range.value),
+                        text: e.text,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        file,
+    }
+}
+
+/// Report a warning printed by a build script. Human readable warnings go to stderr and JSON
+/// diagnostics to stdout, like the diagnostics of the compiler itself.
+pub fn emit_build_warning(warning: &BuildWarning, format: MessageFormat) -> io::Result<()> {
+    match format {
+        | MessageFormat::Human => writeln!(
+            io::stderr(),
+            "{}: {}: {}",
+            "warning".fg(Color::Yellow),
+            warning.package,
+            warning.message
+        ),
+        | MessageFormat::Json => {
+            let diag = JsonDiagnostic {
+                code: None,
+                file: warning.manifest_path.display().to_string(),
+                level: "warning",
+                title: format!("build script of `{}`", warning.package),
+                range: JsonRange {
+                    start: 0,
+                    end: 0,
+                    start_line: 1,
+                    start_column: 1,
+                    end_line: 1,
+                    end_column: 1,
+                },
+                primary: None,
+                secondary: Vec::new(),
+                notes: vec![warning.message.clone()],
+                suggestions: Vec::new(),
+            };
+
+            write_json(&diag, io::stdout())
+        },
+    }
+}
+
+fn write_json(diag: &JsonDiagnostic, mut writer: impl io::Write) -> io::Result<()> {
+    serde_json::to_writer(&mut writer, diag)?;
+    writeln!(writer)
+}
+
+fn json_range(db: &RootDatabase, file_id: FileId, range: TextRange) -> JsonRange {
+    let text = db.file_text(file_id);
+    let start = usize::from(range.start());
+    let end = usize::from(range.end());
+    let (start_line, start_column) = line_column(&text, start);
+    let (end_line, end_column) = line_column(&text, end);
+
+    JsonRange {
+        start,
+        end,
+        start_line,
+        start_column,
+        end_line,
+        end_column,
+    }
+}
+
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+/// The path of a file relative to the current directory when possible.
+pub(crate) fn file_path(db: &RootDatabase, file_id: FileId) -> String {
+    let source_root = db.file_source_root(file_id);
+    let source_root = db.source_root(source_root);
+
+    match source_root.path_for_file(file_id).unwrap() {
+        | VfsPath::PathBuf(p) => {
+            let p = p.normalize();
+            let curr_dir = AbsPathBuf::assert(std::env::current_dir().unwrap());
+
+            match p.strip_prefix(&curr_dir) {
+                | Some(rel) => rel.as_ref().to_string_lossy().into_owned(),
+                | None => p.display().to_string(),
+            }
+        },
+        | VfsPath::Virtual(p) => p.clone().into(),
+    }
+}
+
 fn emit_syntax_error(
     err: &SyntaxError,
     file_id: FileId,
@@ -61,6 +366,7 @@ fn emit_syntax_error(
     let span = (file_id, usize::from(err.range.start())..usize::from(err.range.end()));
 
     Report::build(ReportKind::Error, file_id, err.range.start().into())
+        .with_code(SYNTAX_ERROR)
         .with_message("syntax error")
         .with_label(Label::new(span).with_message(&err.msg).with_color(Color::Red))
         .with_config(config)
@@ -97,6 +403,7 @@ fn emit_diagnostic(
     let mut colors = ColorGenerator::new();
     let mut builder = Report::build(report_kind, file_id, d.range().start().into())
         .with_config(config)
+        .with_code(d.code())
         .with_message(d.title());
 
     if let Some(ann) = d.primary_annotation() {
@@ -121,6 +428,10 @@ fn emit_diagnostic(
         builder = builder.with_note(note);
     }
 
+    for suggestion in d.suggestions() {
+        builder = builder.with_note(format!("help: {}", suggestion.message));
+    }
+
     builder.finish().write(cache, &mut writer)?;
     writeln!(writer)
 }
@@ -147,22 +458,6 @@ impl<'db> Cache<FileId> for DbCache<'db> {
     }
 
     fn display<'a>(&self, id: &'a FileId) -> Option<Box<dyn std::fmt::Display + 'a>> {
-        let source_root = self.db.file_source_root(*id);
-        let source_root = self.db.source_root(source_root);
-        let path = match source_root.path_for_file(*id).unwrap() {
-            | VfsPath::PathBuf(p) => {
-                let curr_dir = AbsPathBuf::assert(std::env::current_dir().unwrap());
-
-                p.normalize()
-                    .strip_prefix(&curr_dir)
-                    .unwrap()
-                    .as_ref()
-                    .to_string_lossy()
-                    .into_owned()
-            },
-            | VfsPath::Virtual(p) => p.clone().into(),
-        };
-
-        Some(Box::new(path))
+        Some(Box::new(file_path(self.db, *id)))
     }
 }
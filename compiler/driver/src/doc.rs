@@ -0,0 +1,600 @@
+//! Generating the documentation of libraries as a static HTML site, which is done by `fi doc`.
+//!
+//! Every library gets a directory with an index page and a page per module, listing the exported
+//! items of the module with their signatures and documentation. The names of types and classes
+//! in signatures link to the items they refer to, which may be part of a dependency. All items
+//! are written to a search index, which is searched in the browser by `search.js`.
+
+use std::fmt::{self, Write as _};
+use std::{fs, io};
+
+use hir::attrs::HasAttrs;
+use hir::db::HirDatabase;
+use hir::display::{DefNames, ParenMode, TyParens};
+use hir::id::TypedDefId;
+use hir::{AssocItem, HirDisplay, ModuleDef, Name};
+use markup::html::{escape, HtmlRenderer};
+use paths::AbsPath;
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+
+const STYLE: &str = include_str!("doc/style.css");
+
+/// Links in signatures are written as `LINK_START url LINK_TEXT name LINK_END` while displaying
+/// them, and turned into HTML after the signature is escaped.
+const LINK_START: char = '\u{1}';
+const LINK_TEXT: char = '\u{2}';
+const LINK_END: char = '\u{3}';
+const SEARCH: &str = include_str!("doc/search.js");
+
+/// Write the documentation of `libs` to `out_dir`. Returns the number of pages written.
+pub fn document(db: &dyn HirDatabase, libs: &[hir::Lib], out_dir: &AbsPath) -> io::Result<usize> {
+    let site = Site::new(db, libs);
+    let mut pages = 0;
+
+    fs::create_dir_all(out_dir)?;
+
+    for &lib in libs {
+        let dir = out_dir.join(lib.name(db).to_string());
+
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("index.html"), site.lib_page(lib))?;
+        pages += 1;
+
+        for module in modules(db, lib) {
+            fs::write(
+                out_dir.join(&site.urls[&ModuleDef::Module(module)]),
+                site.module_page(module),
+            )?;
+            pages += 1;
+        }
+    }
+
+    let index = serde_json::to_string(&site.search).map_err(io::Error::from)?;
+
+    fs::write(out_dir.join("index.html"), site.index_page())?;
+    fs::write(out_dir.join("style.css"), STYLE)?;
+    fs::write(out_dir.join("search.js"), SEARCH)?;
+    fs::write(
+        out_dir.join("search-index.js"),
+        format!("window.searchIndex = {};\n", index),
+    )?;
+
+    Ok(pages + 1)
+}
+
+/// An entry of the search index.
+#[derive(Serialize)]
+struct SearchEntry {
+    path: String,
+    kind: &'static str,
+    url: String,
+    /// The first paragraph of the documentation as plain text.
+    summary: String,
+}
+
+struct Site<'a> {
+    db: &'a dyn HirDatabase,
+    libs: Vec<hir::Lib>,
+    /// The url of every documented item, relative to the root of the site.
+    urls: FxHashMap<ModuleDef, String>,
+    member_urls: FxHashMap<hir::Member, String>,
+    /// The documented members of every class.
+    members: FxHashMap<hir::Class, Vec<hir::Member>>,
+    search: Vec<SearchEntry>,
+}
+
+impl<'a> Site<'a> {
+    fn new(db: &'a dyn HirDatabase, libs: &[hir::Lib]) -> Self {
+        let mut site = Self {
+            db,
+            libs: libs.to_vec(),
+            urls: FxHashMap::default(),
+            member_urls: FxHashMap::default(),
+            members: FxHashMap::default(),
+            search: Vec::new(),
+        };
+
+        for &lib in libs {
+            for module in modules(db, lib) {
+                site.collect(module);
+            }
+        }
+
+        site
+    }
+
+    /// Assign urls to the module and its exported items and add them to the search index.
+    fn collect(&mut self, module: hir::Module) {
+        let db = self.db;
+        let page = format!(
+            "{}/{}.html",
+            module.lib().name(db),
+            module.path(db).to_string().replace(':', ".")
+        );
+
+        self.add(
+            ModuleDef::Module(module),
+            module.path(db).to_string(),
+            "module",
+            page.clone(),
+            module.attrs(db).docs(),
+        );
+
+        for def in declarations(db, module) {
+            let url = format!("{}#{}", page, anchor(db, def));
+
+            match def {
+                | ModuleDef::TypeCtor(it) => {
+                    for ctor in it.ctors(db) {
+                        let def = ModuleDef::Ctor(ctor);
+
+                        self.add(
+                            def,
+                            def.path(db).to_string(),
+                            "ctor",
+                            format!("{}#{}", page, anchor(db, def)),
+                            None,
+                        );
+                    }
+                },
+                | ModuleDef::Class(it) => {
+                    for item in it.items(db) {
+                        let def = assoc_def(item);
+
+                        self.add(
+                            def,
+                            def.path(db).to_string(),
+                            "method",
+                            format!("{}#{}", page, anchor(db, def)),
+                            item.attrs(db).docs(),
+                        );
+                    }
+                },
+                | _ => {},
+            }
+
+            let docs = def.docs(db);
+
+            self.add(def, def.path(db).to_string(), kind(def), url, docs);
+        }
+
+        for (i, member) in module.members(db).into_iter().enumerate() {
+            self.member_urls.insert(member, format!("{}#member.{}", page, i));
+            self.members.entry(member.class(db)).or_default().push(member);
+        }
+    }
+
+    fn add(&mut self, def: ModuleDef, path: String, kind: &'static str, url: String, docs: Option<hir::Documentation>) {
+        self.search.push(SearchEntry {
+            path,
+            kind,
+            url: url.clone(),
+            summary: docs.map(|d| summary_text(d.as_str())).unwrap_or_default(),
+        });
+
+        self.urls.insert(def, url);
+    }
+
+    fn index_page(&self) -> String {
+        let db = self.db;
+        let mut body = String::from("<h1>Libraries</h1>\n<ul class=\"listing\">\n");
+
+        for &lib in &self.libs {
+            let name = escape(&lib.name(db).to_string());
+
+            writeln!(body, "<li><a href=\"{0}/index.html\">{0}</a></li>", name).unwrap();
+        }
+
+        body.push_str("</ul>\n");
+        page("", "Documentation", &body)
+    }
+
+    fn lib_page(&self, lib: hir::Lib) -> String {
+        let db = self.db;
+        let name = escape(&lib.name(db).to_string());
+        let mut body = format!("<h1>Library <span class=\"name\">{}</span></h1>\n", name);
+        let modules = lib.modules(db);
+
+        if !modules.is_empty() {
+            body.push_str("<h2>Modules</h2>\n");
+            self.module_listing(&mut body, &modules);
+        }
+
+        let deps = lib.dependencies(db);
+
+        if !deps.is_empty() {
+            body.push_str("<h2>Dependencies</h2>\n<ul class=\"listing\">\n");
+
+            for dep in deps {
+                let dep_name = escape(&dep.lib.name(db).to_string());
+
+                if self.libs.contains(&dep.lib) {
+                    writeln!(body, "<li><a href=\"../{0}/index.html\">{0}</a></li>", dep_name).unwrap();
+                } else {
+                    writeln!(body, "<li>{}</li>", dep_name).unwrap();
+                }
+            }
+
+            body.push_str("</ul>\n");
+        }
+
+        page("../", &name, &body)
+    }
+
+    fn module_page(&self, module: hir::Module) -> String {
+        let db = self.db;
+        let lib = escape(&module.lib().name(db).to_string());
+        let path = escape(&module.path(db).to_string());
+        let mut body = format!(
+            "<p class=\"breadcrumbs\"><a href=\"index.html\">{}</a></p>\n<h1>Module <span class=\"name\">{}</span></h1>\n",
+            lib, path
+        );
+
+        if let Some(docs) = module.attrs(db).docs() {
+            writeln!(body, "<div class=\"docs\">{}</div>", render_docs(docs.as_str())).unwrap();
+        }
+
+        let children = module.children(db);
+
+        if !children.is_empty() {
+            body.push_str("<h2>Modules</h2>\n");
+            self.module_listing(&mut body, &children);
+        }
+
+        let defs = declarations(db, module);
+        let sections = [
+            ("Types", "type"),
+            ("Type aliases", "alias"),
+            ("Classes", "class"),
+            ("Functions", "func"),
+            ("Values", "value"),
+            ("Fixities", "fixity"),
+        ];
+
+        for (title, key) in sections {
+            let defs = defs.iter().filter(|&&d| kind(d) == key).collect::<Vec<_>>();
+
+            if defs.is_empty() {
+                continue;
+            }
+
+            writeln!(body, "<h2>{}</h2>", title).unwrap();
+
+            for &def in defs {
+                self.item(&mut body, def);
+            }
+        }
+
+        let members = module
+            .members(db)
+            .into_iter()
+            .filter(|m| self.urls.contains_key(&ModuleDef::Class(m.class(db))))
+            .collect::<Vec<_>>();
+
+        if !members.is_empty() {
+            body.push_str("<h2>Members</h2>\n");
+
+            for member in members {
+                let url = &self.member_urls[&member];
+                let id = &url[url.find('#').unwrap() + 1..];
+
+                section(
+                    &mut body,
+                    id,
+                    &self.signature(member),
+                    member.attrs(db).docs(),
+                    |body| self.assoc_items(body, &member.items(db), false),
+                );
+            }
+        }
+
+        page("../", &path, &body)
+    }
+
+    fn module_listing(&self, body: &mut String, modules: &[hir::Module]) {
+        let db = self.db;
+
+        body.push_str("<ul class=\"listing\">\n");
+
+        for &module in modules {
+            let url = &self.urls[&ModuleDef::Module(module)];
+            let summary = module.attrs(db).docs().map(|d| summary(d.as_str())).unwrap_or_default();
+
+            writeln!(
+                body,
+                "<li><a href=\"../{}\">{}</a> {}</li>",
+                url,
+                escape(&module.path(db).to_string()),
+                summary
+            )
+            .unwrap();
+        }
+
+        body.push_str("</ul>\n");
+    }
+
+    fn item(&self, body: &mut String, def: ModuleDef) {
+        let db = self.db;
+        let docs = def.docs(db);
+        let id = anchor(db, def);
+        let sig = match def {
+            | ModuleDef::TypeCtor(it) => self.type_ctor_signature(it),
+            | ModuleDef::Class(it) => {
+                let vars = it.type_vars(db).into_iter().map(|tv| format!(" {}", tv.name(db)));
+
+                escape(&format!("class {}{}", it.name(db), vars.collect::<String>()))
+            },
+            | ModuleDef::Func(it) => self.signature(it),
+            | ModuleDef::Static(it) => self.signature(it),
+            | ModuleDef::Const(it) => self.signature(it),
+            | ModuleDef::TypeAlias(it) => self.signature(it),
+            | ModuleDef::Fixity(it) => escape(&it.display(db).to_string()),
+            | ModuleDef::Module(_) | ModuleDef::Ctor(_) => return,
+        };
+
+        section(body, &id, &sig, docs, |body| {
+            if let ModuleDef::Class(it) = def {
+                self.assoc_items(body, &it.items(db), true);
+                self.member_listing(body, it);
+            }
+        });
+    }
+
+    fn type_ctor_signature(&self, it: hir::TypeCtor) -> String {
+        let db = self.db;
+        let ctors = it.ctors(db);
+
+        if ctors.is_empty() {
+            return self.signature(it);
+        }
+
+        let vars = it.type_vars(db).into_iter().map(|tv| format!(" {}", tv.name(db)));
+        let mut sig = escape(&format!("type {}{} =", it.name(db), vars.collect::<String>()));
+
+        for ctor in ctors {
+            let text = ctor
+                .types(db)
+                .into_iter()
+                .map(|ty| format!(" {}", self.signature(TyParens(ty, ParenMode::Arg))))
+                .collect::<String>();
+
+            write!(
+                sig,
+                "\n    | <span id=\"{}\">{}</span>{}",
+                anchor(db, ModuleDef::Ctor(ctor)),
+                escape(&ctor.name(db).to_string()),
+                text
+            )
+            .unwrap();
+        }
+
+        sig
+    }
+
+    /// The items of a class, or the items of a member when `anchored` is false.
+    fn assoc_items(&self, body: &mut String, items: &[AssocItem], anchored: bool) {
+        let db = self.db;
+
+        for &item in items {
+            let sig = match item {
+                | AssocItem::Func(it) => self.signature(it),
+                | AssocItem::Static(it) => self.signature(it),
+            };
+
+            let id = if anchored {
+                anchor(db, assoc_def(item))
+            } else {
+                String::new()
+            };
+
+            section(body, &id, &sig, item.attrs(db).docs(), |_| {});
+        }
+    }
+
+    /// The members of `class` in all documented libraries.
+    fn member_listing(&self, body: &mut String, class: hir::Class) {
+        let db = self.db;
+        let members = match self.members.get(&class) {
+            | Some(members) => members,
+            | None => return,
+        };
+
+        body.push_str("<h4>Members</h4>\n<ul class=\"listing\">\n");
+
+        for &member in members {
+            writeln!(
+                body,
+                "<li><code>{}</code> in <a href=\"../{}\">{}</a></li>",
+                self.signature(member),
+                self.member_urls[&member],
+                escape(&member.module(db).path(db).to_string())
+            )
+            .unwrap();
+        }
+
+        body.push_str("</ul>\n");
+    }
+
+    /// Render `it` to HTML in which the type constructors, aliases and classes it refers to link
+    /// to their documentation, if they are documented.
+    fn signature(&self, it: impl HirDisplay) -> String {
+        let text = it.display_with_names(self.db, self).to_string();
+        let mut html = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+
+        while let Some(start) = rest.find(LINK_START) {
+            let (url, tail) = rest[start + 1..].split_once(LINK_TEXT).unwrap();
+            let (name, tail) = tail.split_once(LINK_END).unwrap();
+
+            html.push_str(&escape(&rest[..start]));
+            write!(html, "<a href=\"../{}\">{}</a>", url, escape(name)).unwrap();
+            rest = tail;
+        }
+
+        html.push_str(&escape(rest));
+        html
+    }
+}
+
+impl DefNames for Site<'_> {
+    fn write_def(&self, f: &mut dyn fmt::Write, def: TypedDefId, name: &Name) -> fmt::Result {
+        let def = match def {
+            | TypedDefId::TypeCtorId(id) => ModuleDef::TypeCtor(id.into()),
+            | TypedDefId::TypeAliasId(id) => ModuleDef::TypeAlias(id.into()),
+            | TypedDefId::ClassId(id) => ModuleDef::Class(id.into()),
+            | _ => return write!(f, "{}", name),
+        };
+
+        match self.urls.get(&def) {
+            | Some(url) => write!(f, "{}{}{}{}{}", LINK_START, url, LINK_TEXT, name, LINK_END),
+            | None => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Write an item with the signature `sig`, which is HTML already, and its documentation. The
+/// nested items are written by `inner`.
+fn section(body: &mut String, id: &str, sig: &str, docs: Option<hir::Documentation>, inner: impl FnOnce(&mut String)) {
+    if id.is_empty() {
+        body.push_str("<section class=\"item\">\n");
+        writeln!(body, "<pre class=\"signature\">{}</pre>", sig).unwrap();
+    } else {
+        writeln!(body, "<section class=\"item\" id=\"{}\">", id).unwrap();
+        writeln!(
+            body,
+            "<pre class=\"signature\"><a class=\"anchor\" href=\"#{}\">§</a>{}</pre>",
+            id, sig
+        )
+        .unwrap();
+    }
+
+    if let Some(docs) = docs {
+        writeln!(body, "<div class=\"docs\">{}</div>", render_docs(docs.as_str())).unwrap();
+    }
+
+    let start = body.len();
+
+    inner(body);
+
+    if body.len() > start {
+        body.insert_str(start, "<div class=\"inner\">\n");
+        body.push_str("</div>\n");
+    }
+
+    body.push_str("</section>\n");
+}
+
+fn page(root: &str, title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<link rel="stylesheet" href="{root}style.css">
+<script src="{root}search-index.js" defer></script>
+<script src="{root}search.js" defer></script>
+</head>
+<body data-root="{root}">
+<nav><a href="{root}index.html">Documentation</a><input id="search" type="search" placeholder="Search" autocomplete="off"></nav>
+<main id="content">
+{body}</main>
+<ul id="search-results" hidden></ul>
+</body>
+</html>
+"#,
+        root = root,
+        title = title,
+        body = body
+    )
+}
+
+/// All modules of `lib`, parents before their children.
+fn modules(db: &dyn HirDatabase, lib: hir::Lib) -> Vec<hir::Module> {
+    let mut modules = lib.modules(db);
+    let mut i = 0;
+
+    while i < modules.len() {
+        let children = modules[i].children(db);
+
+        modules.extend(children);
+        i += 1;
+    }
+
+    modules
+}
+
+/// The exported items declared in `module`, sorted by name. Constructors are documented with
+/// their type and modules on their own page.
+fn declarations(db: &dyn HirDatabase, module: hir::Module) -> Vec<ModuleDef> {
+    let mut defs = module
+        .declarations(db)
+        .into_iter()
+        .filter(|d| !matches!(d, ModuleDef::Module(_) | ModuleDef::Ctor(_)) && d.is_exported(db))
+        .collect::<Vec<_>>();
+
+    defs.sort_by_cached_key(|d| d.name(db).to_string());
+    defs
+}
+
+fn kind(def: ModuleDef) -> &'static str {
+    match def {
+        | ModuleDef::Module(_) => "module",
+        | ModuleDef::Fixity(_) => "fixity",
+        | ModuleDef::Func(_) => "func",
+        | ModuleDef::Static(_) | ModuleDef::Const(_) => "value",
+        | ModuleDef::TypeAlias(_) => "alias",
+        | ModuleDef::TypeCtor(_) => "type",
+        | ModuleDef::Ctor(_) => "ctor",
+        | ModuleDef::Class(_) => "class",
+    }
+}
+
+fn assoc_def(item: AssocItem) -> ModuleDef {
+    match item {
+        | AssocItem::Func(it) => ModuleDef::Func(it),
+        | AssocItem::Static(it) => ModuleDef::Static(it),
+    }
+}
+
+/// The id of the element documenting `def` on the page of its module. Characters which are not
+/// letters, digits or underscores are written as their code point, so operators get an id too.
+fn anchor(db: &dyn HirDatabase, def: ModuleDef) -> String {
+    let mut id = format!("{}.", kind(def));
+
+    for c in def.name(db).to_string().chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            id.push(c);
+        } else {
+            write!(id, "-{:x}", c as u32).unwrap();
+        }
+    }
+
+    id
+}
+
+/// The first paragraph of the documentation `docs`.
+fn first_paragraph(docs: &str) -> String {
+    docs.lines()
+        .map(str::trim)
+        .skip_while(|l| l.is_empty())
+        .take_while(|l| !l.is_empty() && !l.starts_with("```"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The first paragraph of `docs` rendered to HTML.
+fn summary(docs: &str) -> String {
+    HtmlRenderer::render(&markup::markdown::parse(&first_paragraph(docs)))
+}
+
+/// The first paragraph of `docs` as plain text for the search index.
+fn summary_text(docs: &str) -> String {
+    first_paragraph(docs).replace(|c: char| c == '`' || c == '*', "")
+}
+
+fn render_docs(docs: &str) -> String {
+    HtmlRenderer::render(&markup::markdown::parse(docs))
+}
@@ -0,0 +1,84 @@
+// Searching the items of the documentation, using the index written to `search-index.js`.
+(function () {
+    "use strict";
+
+    const MAX_RESULTS = 100;
+    const root = document.body.dataset.root;
+    const input = document.getElementById("search");
+    const content = document.getElementById("content");
+    const results = document.getElementById("search-results");
+
+    function rank(entry, query) {
+        const path = entry.path.toLowerCase();
+        const name = path.slice(path.lastIndexOf(":") + 1);
+
+        if (name === query) {
+            return 0;
+        } else if (name.startsWith(query)) {
+            return 1;
+        } else if (name.includes(query)) {
+            return 2;
+        } else if (path.includes(query)) {
+            return 3;
+        } else {
+            return -1;
+        }
+    }
+
+    function search(query) {
+        query = query.trim().toLowerCase();
+
+        if (query === "") {
+            results.hidden = true;
+            content.hidden = false;
+            return;
+        }
+
+        const found = (window.searchIndex || [])
+            .map((entry) => ({ entry, rank: rank(entry, query) }))
+            .filter((r) => r.rank >= 0)
+            .sort((a, b) => a.rank - b.rank || a.entry.path.length - b.entry.path.length)
+            .slice(0, MAX_RESULTS);
+
+        results.replaceChildren(
+            ...found.map(({ entry }) => {
+                const item = document.createElement("li");
+                const kind = document.createElement("span");
+                const link = document.createElement("a");
+
+                kind.className = "kind";
+                kind.textContent = entry.kind;
+                link.href = root + entry.url;
+                link.textContent = entry.path;
+                item.append(kind, link);
+
+                if (entry.summary) {
+                    item.append(" — " + entry.summary);
+                }
+
+                return item;
+            }),
+        );
+
+        if (found.length === 0) {
+            const item = document.createElement("li");
+
+            item.textContent = "No results";
+            results.append(item);
+        }
+
+        results.hidden = false;
+        content.hidden = true;
+    }
+
+    input.addEventListener("input", () => search(input.value));
+    document.addEventListener("keydown", (e) => {
+        if (e.key === "/" && document.activeElement !== input) {
+            e.preventDefault();
+            input.focus();
+        } else if (e.key === "Escape" && document.activeElement === input) {
+            input.value = "";
+            search("");
+        }
+    });
+})();
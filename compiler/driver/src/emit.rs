@@ -0,0 +1,270 @@
+//! Writing the intermediate representations of modules, which is done by `fi build --emit`.
+//!
+//! Every representation of a module is written to `<lib>/<Module.Path>.<ext>` in the output
+//! directory. The representations which come from the source, like the syntax tree and the types,
+//! are written for every module. The ones which come from code generation are written for the
+//! modules which are compiled to an object file.
+
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+use std::{fs, io};
+
+use base_db::target::CompilerTarget;
+use codegen::db::CodegenDatabase;
+use hir::db::HirDatabase;
+use hir::{AssocItem, DefWithBody, HirDisplay, InFile, ModuleDef};
+use mir::db::MirDatabase;
+use paths::{AbsPath, AbsPathBuf};
+use syntax::{AstNode, AstPtr};
+
+/// The longest source text shown for a pattern or expression in the types of a module.
+const MAX_SOURCE_LEN: usize = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Emit {
+    /// The syntax tree.
+    Ast,
+    /// The lowered bodies of definitions.
+    Hir,
+    /// The inferred types of definitions and of the patterns and expressions in their bodies.
+    Types,
+    /// The optimized MIR bodies of definitions.
+    Mir,
+    /// The optimized LLVM IR.
+    LlvmIr,
+    /// The assembly of the optimized LLVM IR.
+    Asm,
+    /// The generated javascript.
+    Js,
+    /// The object file, as it is linked.
+    Obj,
+}
+
+impl Emit {
+    pub fn extension(self) -> &'static str {
+        match self {
+            | Self::Ast => "ast",
+            | Self::Hir => "hir",
+            | Self::Types => "types",
+            | Self::Mir => "mir",
+            | Self::LlvmIr => "ll",
+            | Self::Asm => "s",
+            | Self::Js => "js",
+            | Self::Obj => "o",
+        }
+    }
+
+    /// Whether this representation can be written when compiling for `target`.
+    pub fn supports(self, target: &CompilerTarget) -> bool {
+        match self {
+            | Self::LlvmIr | Self::Asm | Self::Obj => matches!(target, CompilerTarget::Native(_)),
+            | Self::Js => matches!(target, CompilerTarget::Javascript),
+            | _ => true,
+        }
+    }
+
+    /// Whether this representation comes from the source, so it can be written for packages
+    /// which fail to type-check.
+    pub fn is_source(self) -> bool {
+        matches!(self, Self::Ast | Self::Hir | Self::Types)
+    }
+
+    fn is_codegen(self) -> bool {
+        matches!(self, Self::LlvmIr | Self::Asm | Self::Js | Self::Obj)
+    }
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            | "ast" => Ok(Self::Ast),
+            | "hir" => Ok(Self::Hir),
+            | "types" => Ok(Self::Types),
+            | "mir" => Ok(Self::Mir),
+            | "llvm-ir" => Ok(Self::LlvmIr),
+            | "asm" => Ok(Self::Asm),
+            | "js" => Ok(Self::Js),
+            | "obj" => Ok(Self::Obj),
+            | _ => Err(format!(
+                "invalid emit kind '{}', expected ast, hir, types, mir, llvm-ir, asm, js or obj",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Emit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            | Self::Ast => "ast",
+            | Self::Hir => "hir",
+            | Self::Types => "types",
+            | Self::Mir => "mir",
+            | Self::LlvmIr => "llvm-ir",
+            | Self::Asm => "asm",
+            | Self::Js => "js",
+            | Self::Obj => "obj",
+        })
+    }
+}
+
+/// Write the representations `kinds` of the modules of `lib` to `out_dir`. Returns the paths of
+/// the written files.
+pub fn emit(db: &dyn CodegenDatabase, lib: hir::Lib, kinds: &[Emit], out_dir: &AbsPath) -> io::Result<Vec<AbsPathBuf>> {
+    let hir_db: &dyn HirDatabase = db.upcast();
+    let dir = out_dir.join(lib.name(hir_db).to_string());
+    let mut stack = lib.modules(hir_db);
+    let mut written = Vec::new();
+
+    fs::create_dir_all(&dir)?;
+
+    while let Some(module) = stack.pop() {
+        let is_root = module.parent(hir_db).is_none();
+
+        stack.extend(module.children(hir_db));
+
+        if module.is_virtual(hir_db) {
+            continue;
+        }
+
+        let name = module.path(hir_db).to_string().replace(':', ".");
+
+        for &kind in kinds {
+            if kind.is_codegen() && !is_root {
+                continue;
+            }
+
+            let path = dir.join(format!("{}.{}", name, kind.extension()));
+
+            match kind {
+                | Emit::Ast => fs::write(&path, format!("{:#?}", module.source(hir_db).value.syntax()))?,
+                | Emit::Hir => fs::write(&path, self::hir(hir_db, module))?,
+                | Emit::Types => fs::write(&path, types(hir_db, module))?,
+                | Emit::Mir => fs::write(&path, mir(db, module))?,
+                | Emit::LlvmIr => backend_llvm::codegen_ir(db.upcast(), module, &mut fs::File::create(&path)?),
+                | Emit::Asm => backend_llvm::codegen_asm(db.upcast(), module, &mut fs::File::create(&path)?),
+                | Emit::Js | Emit::Obj => {
+                    fs::copy(db.codegen_module(module).path(), &path)?;
+                },
+            }
+
+            written.push(path);
+        }
+    }
+
+    written.sort();
+    Ok(written)
+}
+
+fn hir(db: &dyn HirDatabase, module: hir::Module) -> String {
+    let mut out = String::new();
+
+    for def in bodies(db, module) {
+        let body = db.body(def.into());
+
+        writeln!(out, "{}", signature(db, def)).unwrap();
+        writeln!(out, "{}\n", body.pretty_print(&def.name(db))).unwrap();
+    }
+
+    out
+}
+
+fn types(db: &dyn HirDatabase, module: hir::Module) -> String {
+    let mut out = String::new();
+
+    for def in bodies(db, module) {
+        let (_, source_map) = db.body_source_map(def.into());
+        let infer = db.infer(def.into());
+        let pats = infer
+            .type_of_pat
+            .iter()
+            .map(|(pat, &ty)| (source_text(db, source_map.pat_syntax(pat).left()), ty));
+        let exprs = infer
+            .type_of_expr
+            .iter()
+            .map(|(expr, &ty)| (source_text(db, source_map.expr_syntax(expr).left()), ty));
+        let rows = pats.chain(exprs).collect::<Vec<_>>();
+        let width = rows.iter().map(|(text, _)| text.chars().count()).max().unwrap_or(0);
+
+        writeln!(out, "{}", signature(db, def)).unwrap();
+
+        for (text, ty) in rows {
+            writeln!(out, "    {:<width$} :: {}", text, ty.display(db), width = width).unwrap();
+        }
+
+        out.push('\n');
+    }
+
+    out
+}
+
+/// The optimized MIR of the bodies in `module`, each followed by the bodies of the closures it
+/// creates.
+fn mir(db: &dyn CodegenDatabase, module: hir::Module) -> String {
+    let mdb: &dyn MirDatabase = db.upcast();
+    let mut out = String::new();
+
+    for def in bodies(db.upcast(), module) {
+        let body = db.optimized_body(db.body_mir(def.into()));
+
+        for body in std::iter::once(body).chain(body.closures(mdb)) {
+            let body = db.optimized_body(body);
+
+            writeln!(out, "{}\n", db.lookup_intern_body(body).display(db.upcast())).unwrap();
+        }
+    }
+
+    out
+}
+
+/// The definitions of `module` which have a body, including the items of its members.
+fn bodies(db: &dyn HirDatabase, module: hir::Module) -> Vec<DefWithBody> {
+    let decls = module.declarations(db).into_iter().filter_map(|def| match def {
+        | ModuleDef::Func(it) => Some(DefWithBody::Func(it)),
+        | ModuleDef::Static(it) => Some(DefWithBody::Static(it)),
+        | ModuleDef::Const(it) => Some(DefWithBody::Const(it)),
+        | _ => None,
+    });
+
+    let items = module
+        .members(db)
+        .into_iter()
+        .flat_map(|member| member.items(db))
+        .map(|item| match item {
+            | AssocItem::Func(it) => DefWithBody::Func(it),
+            | AssocItem::Static(it) => DefWithBody::Static(it),
+        });
+
+    decls.chain(items).filter(|def| def.has_body(db)).collect()
+}
+
+fn signature(db: &dyn HirDatabase, def: DefWithBody) -> String {
+    match def {
+        | DefWithBody::Func(it) => it.display(db).to_string(),
+        | DefWithBody::Static(it) => it.display(db).to_string(),
+        | DefWithBody::Const(it) => it.display(db).to_string(),
+    }
+}
+
+/// The source of a pattern or expression on a single line, shortened to [`MAX_SOURCE_LEN`]
+/// characters. Syntax generated while lowering has no source.
+fn source_text<N: AstNode>(db: &dyn HirDatabase, src: Option<InFile<AstPtr<N>>>) -> String {
+    let src = match src {
+        | Some(src) => src,
+        | None => return String::from("<generated>"),
+    };
+
+    let root = db.parse(src.file_id).syntax_node();
+    let text = src.value.to_node(&root).syntax().text().to_string();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() > MAX_SOURCE_LEN {
+        let short = text.chars().take(MAX_SOURCE_LEN - 3).collect::<String>();
+
+        format!("{}...", short)
+    } else {
+        text
+    }
+}
@@ -1,5 +1,11 @@
 pub mod db;
 pub mod diagnostics;
+pub mod doc;
+pub mod emit;
+pub mod test;
+
+#[cfg(test)]
+mod tests;
 
 use std::path::Path;
 use std::process::{Command, ExitCode};
@@ -13,17 +19,28 @@ use base_db::{SourceDatabase, SourceDatabaseExt};
 use cfg::{CfgOptions, CfgValue};
 use codegen::assembly::Assembly;
 use codegen::db::CodegenDatabase;
-use paths::AbsPathBuf;
+use paths::{AbsPath, AbsPathBuf};
 use project::manifest::{self, Cfg};
 use project::Workspace;
-use rustc_hash::FxHashSet;
+use rustc_hash::FxHashMap;
+use vfs::file_set::FileSet;
+use vfs::VfsPath;
+
+pub use crate::diagnostics::MessageFormat;
+pub use crate::emit::Emit;
 
 pub struct InitOpts<'a> {
     pub input: &'a Path,
     pub target: Option<&'a str>,
     pub output: Option<LibKind>,
     pub optimization: Optimization,
+    pub debug_info: bool,
     pub cfg: Cfg,
+    /// The workspace members to act on, all members when empty.
+    pub packages: Vec<&'a str>,
+    pub features: Vec<&'a str>,
+    pub no_default_features: bool,
+    pub message_format: MessageFormat,
 }
 
 pub struct InitNoManifestOpts<'a> {
@@ -32,9 +49,11 @@ pub struct InitNoManifestOpts<'a> {
     pub target: Option<&'a str>,
     pub output: LibKind,
     pub optimization: Optimization,
+    pub debug_info: bool,
     pub cfg: Cfg,
     pub links: Vec<&'a Path>,
     pub dependencies: Vec<&'a Path>,
+    pub message_format: MessageFormat,
 }
 
 pub struct Driver {
@@ -43,6 +62,9 @@ pub struct Driver {
     target_dir: AbsPathBuf,
     cfg: CfgOptions,
     vfs: vfs::VirtualFileSystem,
+    /// The number of source roots of the workspaces. The source roots of doctests come after them.
+    source_roots: u32,
+    message_format: MessageFormat,
 }
 
 impl Default for InitOpts<'_> {
@@ -52,7 +74,12 @@ impl Default for InitOpts<'_> {
             target: None,
             output: None,
             optimization: Optimization::None,
+            debug_info: false,
             cfg: Cfg::default(),
+            packages: Vec::new(),
+            features: Vec::new(),
+            no_default_features: false,
+            message_format: MessageFormat::Human,
         }
     }
 }
@@ -65,6 +92,8 @@ impl Default for Driver {
             target_dir: AbsPathBuf::assert(std::env::current_dir().unwrap()),
             cfg: CfgOptions::default(),
             vfs: vfs::VirtualFileSystem::default(),
+            source_roots: 0,
+            message_format: MessageFormat::Human,
         }
     }
 }
@@ -83,13 +112,57 @@ impl Driver {
         let cfg = manifest::parse_cfg(&opts.cfg).ok_or_else(|| anyhow::anyhow!("failed to parse cfg options"))?;
         let target = opts.target.map(|t| t.parse()).unwrap_or(Ok(Default::default()))?;
 
+        driver.message_format = opts.message_format;
         driver.init_cfg(&target);
-        driver.target_dir = input_dir(opts.input).join("target");
         driver.cfg = driver.cfg.merge(&cfg);
+
+        let ws = driver.load(opts.input)?;
+
+        if !opts.packages.is_empty() {
+            driver.workspaces[ws].select(&opts.packages)?;
+        }
+
+        // Features are unified over the selected members only.
+        if !opts.packages.is_empty() || !opts.features.is_empty() || opts.no_default_features {
+            driver.workspaces[ws].resolve_features(&opts.features, !opts.no_default_features)?;
+            driver.set_libs();
+        }
+
+        // All members of a workspace share the target directory in the workspace root.
+        driver.target_dir = driver.workspaces[ws].root_dir().join("target");
+
+        let target_name = match &target {
+            | CompilerTarget::Javascript => String::from("javascript"),
+            | CompilerTarget::Native(triple) => triple.to_string(),
+        };
+
+        let mut warnings = Vec::new();
+        let ran = driver.workspaces[ws].run_build_scripts(
+            &mut driver.vfs,
+            &driver.target_dir,
+            &target_name,
+            &mut warnings,
+        )?;
+
+        for warning in &warnings {
+            diagnostics::emit_build_warning(warning, driver.message_format)?;
+        }
+
+        if ran {
+            driver.set_libs();
+            driver.set_source_roots();
+        }
+
         driver.db.set_target_dir(driver.target_dir.clone());
         driver.db.set_target(target);
+        driver.db.set_optimization(opts.optimization);
 
-        let ws = driver.load(opts.input)?;
+        // Debug info is not per package: when a selected member asks for it, it is emitted for
+        // all packages in the build.
+        let workspace = &driver.workspaces[ws];
+        let debug_info = workspace.selected().iter().any(|&p| workspace[p].debug);
+
+        driver.db.set_debug_info(opts.debug_info || debug_info);
 
         Ok((driver, ws))
     }
@@ -100,6 +173,7 @@ impl Driver {
         let cfg = manifest::parse_cfg(&opts.cfg).ok_or_else(|| anyhow::anyhow!("failed to parse cfg options"))?;
         let target = opts.target.map(|t| t.parse()).unwrap_or(Ok(Default::default()))?;
 
+        driver.message_format = opts.message_format;
         driver.init_cfg(&target);
         driver.target_dir = current_dir.join("target");
         driver.cfg = driver.cfg.merge(&cfg);
@@ -123,6 +197,8 @@ impl Driver {
         driver.set_source_roots();
         driver.db.set_target_dir(driver.target_dir.clone());
         driver.db.set_target(target);
+        driver.db.set_optimization(opts.optimization);
+        driver.db.set_debug_info(opts.debug_info);
 
         let ws = driver.workspaces.len() - 1;
 
@@ -174,6 +250,8 @@ impl Driver {
     fn set_source_roots(&mut self) {
         let file_sets = Workspace::file_sets(&self.workspaces, &self.vfs);
 
+        self.source_roots = file_sets.len() as u32;
+
         for (idx, file_set) in file_sets.into_iter().enumerate() {
             let root_id = SourceRootId(idx as u32);
             let root = SourceRoot::new(file_set);
@@ -206,21 +284,30 @@ impl Driver {
         });
     }
 
-    pub fn check(&self) -> io::Result<bool> {
+    pub fn check(&self, ws: usize) -> io::Result<bool> {
         let start = std::time::Instant::now();
         let db = &self.db;
         let mut last_changed = false;
+        let ws = &self.workspaces[ws];
+        let libs = db.libs();
 
         for lib in hir::Lib::all(db) {
+            if !ws
+                .find_file_package(libs[lib.into()].root_file)
+                .map_or(false, |p| ws.is_selected(p))
+            {
+                continue;
+            }
+
             let id = lib.into();
             let changed = metadata::read_metadata(db, id, &self.target_dir)
                 .map(|m| m.has_changed(db))
                 .unwrap_or(true);
 
             if changed || last_changed {
-                eprintln!("  \x1B[1;32m\x1B[1mChecking\x1B[0m {}", lib.name(db));
+                self.status("Checking", lib.name(db));
 
-                if diagnostics::emit_diagnostics(db, lib, &mut io::stderr())? > 0 {
+                if self.emit_diagnostics(lib)? > 0 {
                     return Ok(false);
                 }
 
@@ -231,33 +318,233 @@ impl Driver {
 
         let elapsed = start.elapsed();
 
-        eprintln!("   \x1B[1;32m\x1B[1mFinished\x1B[0m in {:?}", elapsed);
+        self.status("Finished", format_args!("in {:?}", elapsed));
         Ok(true)
     }
 
-    pub fn build(&self, ws: usize) -> io::Result<bool> {
+    /// Apply the suggested fixes of the diagnostics in the selected packages to their source
+    /// files, and update the database with the fixed files so they can be checked again. Returns
+    /// the number of files changed.
+    pub fn fix(&mut self, ws: usize) -> io::Result<usize> {
         let db = &self.db;
-        fs::create_dir_all(&self.target_dir)?;
-        let start = std::time::Instant::now();
-        let mut done = FxHashSet::default();
-        let mut last_changed = false;
         let ws = &self.workspaces[ws];
         let libs = db.libs();
+        let mut fixed = Vec::new();
 
         for lib in hir::Lib::all(db) {
-            if ws.find_file_package(libs[lib.into()].root_file).is_none() {
+            if !ws
+                .find_file_package(libs[lib.into()].root_file)
+                .map_or(false, |p| ws.is_selected(p))
+            {
                 continue;
             }
 
+            fixed.extend(diagnostics::fix_diagnostics(db, lib)?);
+        }
+
+        let changed = fixed.len();
+
+        for file in fixed {
+            self.status("Fixed", &file.path);
+            self.db.set_file_text(file.file_id, file.text);
+        }
+
+        self.status("Finished", format_args!("fixed {} file(s)", changed));
+        Ok(changed)
+    }
+
+    /// Format the source files of the selected packages with the options in their manifest. With
+    /// `check` the files are not changed, but the ones which are not formatted are reported.
+    /// Returns false when a file could not be formatted, or with `check` when a file is not
+    /// formatted.
+    pub fn fmt(&self, ws: usize, check: bool) -> io::Result<bool> {
+        let db = &self.db;
+        let ws = &self.workspaces[ws];
+        let mut files = Vec::new();
+
+        if let Some(local) = ws.local_project() {
+            files.extend(local.files.iter().map(|&file| (file, formatter::Config::default())));
+        }
+
+        for &package in ws.selected() {
+            let data = &ws[package];
+            let dir = data.manifest_path.parent().unwrap();
+            let source_root = db.source_root(db.file_source_root(data.root_file));
+
+            for file in source_root.source_files() {
+                // Skip the files generated by build scripts.
+                match source_root.path_for_file(file) {
+                    | Some(VfsPath::PathBuf(path)) if path.starts_with(dir) && !path.starts_with(&self.target_dir) => {
+                        files.push((file, data.fmt.clone()));
+                    },
+                    | _ => {},
+                }
+            }
+        }
+
+        let mut success = true;
+        let mut changed = 0;
+
+        for (file, config) in files {
+            let text = db.file_text(file);
+            let path = diagnostics::file_path(db, file);
+            let formatted = match formatter::format(&text, &config) {
+                | Ok(formatted) if formatted == *text => continue,
+                | Ok(formatted) => formatted,
+                | Err(e) => {
+                    eprintln!("\x1B[31mError:\x1B[0m could not format {}: {}", path, e);
+                    success = false;
+                    continue;
+                },
+            };
+
+            if check {
+                eprintln!("\x1B[1;33m\x1B[1mUnformatted\x1B[0m {}", path);
+                success = false;
+            } else if let Some(VfsPath::PathBuf(abs)) = db.source_root(db.file_source_root(file)).path_for_file(file) {
+                fs::write(abs.as_os_str(), formatted)?;
+                self.status("Formatted", path);
+                changed += 1;
+            }
+        }
+
+        if !check {
+            self.status("Finished", format_args!("formatted {} file(s)", changed));
+        }
+
+        Ok(success)
+    }
+
+    /// Generate the documentation of the selected packages and their dependencies as a static
+    /// site in `target/doc`. Returns false when one of the selected packages has errors.
+    pub fn doc(&self, ws: usize) -> io::Result<bool> {
+        let start = std::time::Instant::now();
+        let db = &self.db;
+        let ws = &self.workspaces[ws];
+        let libs = db.libs();
+        let selected = hir::Lib::all(db)
+            .into_iter()
+            .filter(|&lib| {
+                ws.find_file_package(libs[lib.into()].root_file)
+                    .map_or(false, |p| ws.is_selected(p))
+            })
+            .collect::<Vec<_>>();
+
+        for &lib in &selected {
+            self.status("Documenting", lib.name(db));
+
+            if self.emit_diagnostics(lib)? > 0 {
+                return Ok(false);
+            }
+        }
+
+        let documented = hir::Lib::all(db)
+            .into_iter()
+            .filter(|&lib| selected.iter().any(|&s| libs.all_deps(s.into()).contains(&lib.into())))
+            .collect::<Vec<_>>();
+
+        let out_dir = self.target_dir.join("doc");
+        let pages = doc::document(db, &documented, &out_dir)?;
+        let elapsed = start.elapsed();
+
+        self.status("Finished", format_args!("{} page(s) in {:?}", pages, elapsed));
+        self.status("Generated", out_dir.join("index.html").display());
+        Ok(true)
+    }
+
+    /// Emit the diagnostics of `lib`, rendered to stderr or as JSON to stdout.
+    fn emit_diagnostics(&self, lib: hir::Lib) -> io::Result<usize> {
+        match self.message_format {
+            | MessageFormat::Human => diagnostics::emit_diagnostics(&self.db, lib, self.message_format, io::stderr()),
+            | MessageFormat::Json => diagnostics::emit_diagnostics(&self.db, lib, self.message_format, io::stdout()),
+        }
+    }
+
+    /// Print a status line like `Compiling core` to stderr. The verb is only coloured for
+    /// humans, tools reading `--message-format=json` get plain text.
+    fn status(&self, verb: &str, message: impl std::fmt::Display) {
+        match self.message_format {
+            | MessageFormat::Human => eprintln!("\x1B[1;32m\x1B[1m{:>11}\x1B[0m {}", verb, message),
+            | MessageFormat::Json => eprintln!("{:>11} {}", verb, message),
+        }
+    }
+
+    pub fn build(&self, ws: usize) -> io::Result<bool> {
+        let start = std::time::Instant::now();
+        let ws = &self.workspaces[ws];
+
+        if !self.compile(ws, self.selected_libs(ws), &self.target_dir)? {
+            return Ok(false);
+        }
+
+        let elapsed = start.elapsed();
+
+        self.status("Finished", format_args!("in {:?}", elapsed));
+        Ok(true)
+    }
+
+    /// Write the intermediate representations `kinds` of the modules of the selected packages
+    /// to `target/emit`. The packages must have been built already, except for the
+    /// representations which come from the source.
+    pub fn emit(&self, ws: usize, kinds: &[Emit]) -> io::Result<bool> {
+        let target = self.db.target();
+
+        if let Some(kind) = kinds.iter().find(|kind| !kind.supports(&target)) {
+            let required = match target {
+                | CompilerTarget::Javascript => "a native target",
+                | CompilerTarget::Native(_) => "the javascript target",
+            };
+
+            eprintln!("\x1B[31mError:\x1B[0m '--emit={}' requires {}", kind, required);
+            return Ok(false);
+        }
+
+        if kinds.is_empty() {
+            return Ok(true);
+        }
+
+        let out_dir = self.target_dir.join("emit");
+        let mut written = 0;
+
+        for lib in self.selected_libs(&self.workspaces[ws]) {
+            written += emit::emit(&self.db, lib, kinds, &out_dir)?.len();
+        }
+
+        self.status("Emitted", format_args!("{} file(s) to {}", written, out_dir.display()));
+        Ok(true)
+    }
+
+    /// The libraries of the selected packages of `ws`, in dependency order.
+    fn selected_libs(&self, ws: &Workspace) -> Vec<hir::Lib> {
+        let libs = self.db.libs();
+
+        hir::Lib::all(&self.db)
+            .into_iter()
+            .filter(|&lib| {
+                ws.find_file_package(libs[lib.into()].root_file)
+                    .map_or(false, |p| ws.is_selected(p))
+            })
+            .collect()
+    }
+
+    /// Compile and link `libs`, which must be in dependency order, into `target_dir`. Returns
+    /// false when one of them has errors.
+    fn compile(&self, ws: &Workspace, libs: Vec<hir::Lib>, target_dir: &AbsPath) -> io::Result<bool> {
+        let db = &self.db;
+        fs::create_dir_all(target_dir)?;
+        let mut done = FxHashMap::default();
+        let mut last_changed = false;
+
+        for lib in libs {
             let id = lib.into();
-            let changed = metadata::read_metadata(db, id, &self.target_dir)
+            let changed = metadata::read_metadata(db, id, target_dir)
                 .map(|m| m.has_changed(db))
                 .unwrap_or(true);
-            let changed = changed || fs::metadata(Assembly::dummy(lib).path(db, self.target_dir.as_ref())).is_err();
+            let changed = changed || fs::metadata(Assembly::dummy(lib).path(db, target_dir)).is_err();
 
             if changed || last_changed {
-                eprintln!("  \x1B[1;32m\x1B[1mCompiling\x1B[0m {}", lib.name(db));
-                let e = diagnostics::emit_diagnostics(db, lib, &mut io::stderr())?;
+                self.status("Compiling", lib.name(db));
+                let e = self.emit_diagnostics(lib)?;
 
                 if e == 1 {
                     eprintln!("\x1B[1;31mAborting due to previous error\x1B[0m");
@@ -267,17 +554,14 @@ impl Driver {
                     return Ok(false);
                 }
 
-                self.write_assembly(ws, lib, &mut done)?;
-                metadata::write_metadata(db, id, &self.target_dir)?;
+                self.write_assembly(ws, lib, target_dir, &mut done)?;
+                metadata::write_metadata(db, id, target_dir)?;
                 last_changed = true;
             } else {
-                done.insert(lib);
+                done.insert(lib, false);
             }
         }
 
-        let elapsed = start.elapsed();
-
-        eprintln!("   \x1B[1;32m\x1B[1mFinished\x1B[0m in {:?}", elapsed);
         Ok(true)
     }
 
@@ -285,27 +569,25 @@ impl Driver {
         if self.build(ws)? {
             let ws = &self.workspaces[ws];
             let libs = self.db.libs();
-            let lib = hir::Lib::all(&self.db)
+            let executables = hir::Lib::all(&self.db)
                 .into_iter()
-                .filter(|&lib| ws.find_file_package(libs[lib.into()].root_file).is_some())
-                .find(|&lib| libs[lib.into()].kind == LibKind::Executable)
-                .unwrap();
+                .filter(|&lib| libs[lib.into()].kind == LibKind::Executable)
+                .filter(|&lib| match ws.find_file_package(libs[lib.into()].root_file) {
+                    | Some(Some(p)) => ws.selected().contains(&p),
+                    | Some(None) => true,
+                    | None => false,
+                })
+                .collect::<Vec<_>>();
+
+            let lib = match executables[..] {
+                | [lib] => lib,
+                | [] => base_db::Error::throw("no executable to run"),
+                | _ => base_db::Error::throw("multiple executables in the workspace, select one with `-p <package>`"),
+            };
 
             let asm = Assembly::dummy(lib);
             let path = asm.path(&self.db, &self.target_dir);
-
-            let mut cmd = if self.db.target() == CompilerTarget::Javascript {
-                if !program_exists("node") {
-                    base_db::Error::throw("node must be insalled to run a javascript targeted program");
-                }
-
-                let mut cmd = Command::new("node");
-
-                cmd.arg(path.as_path().as_ref());
-                cmd
-            } else {
-                Command::new(path.as_path().as_ref())
-            };
+            let mut cmd = self.command(&path);
 
             cmd.args(args);
 
@@ -327,21 +609,265 @@ impl Driver {
         }
     }
 
-    fn write_assembly(&self, ws: &Workspace, lib: hir::Lib, done: &mut FxHashSet<hir::Lib>) -> io::Result<bool> {
-        if done.contains(&lib) {
+    /// Build the test harness of each selected package and run the tests whose path contains
+    /// one of `filters`, or all tests when there are no filters. The harnesses are built in
+    /// `target/test`, with the `test` cfg enabled only for the package being tested. Returns
+    /// whether all tests passed.
+    pub fn test(&mut self, ws: usize, filters: &[String]) -> io::Result<bool> {
+        if self.db.target() == CompilerTarget::Javascript {
+            base_db::Error::throw("tests are not supported for the javascript target");
+        }
+
+        let start = std::time::Instant::now();
+        let original = self.db.libs();
+        let target_dir = self.target_dir.join("test");
+        let workspace = &self.workspaces[ws];
+        let tested = hir::Lib::all(&self.db)
+            .into_iter()
+            .filter(
+                |&lib| match workspace.find_file_package(original[lib.into()].root_file) {
+                    | Some(Some(p)) => workspace.selected().contains(&p),
+                    | Some(None) => true,
+                    | None => false,
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let mut outcomes = Vec::new();
+        let mut summary = test::TestSummary::default();
+        let mut compiled = true;
+
+        self.db.set_target_dir(target_dir.clone());
+
+        for &lib in &tested {
+            let mut libs = (*original).clone();
+            let data = &mut libs[lib.into()];
+
+            data.kind = LibKind::Executable;
+            data.cfg_options.enable("test");
+            self.db.set_libs(libs.into());
+
+            let deps = self
+                .db
+                .libs()
+                .all_deps(lib.into())
+                .into_iter()
+                .map(Into::into)
+                .collect();
+
+            if !self.compile(workspace, deps, &target_dir)? {
+                compiled = false;
+                break;
+            }
+
+            let db = &self.db;
+            let name = lib.name(db).to_string();
+            let path = Assembly::dummy(lib).path(db, &target_dir);
+            let tests = lib.tests(db);
+
+            self.status("Running", format_args!("{} test(s) of {}", tests.len(), name));
+
+            for (index, func) in tests.into_iter().enumerate() {
+                let test_name = func.path(db).to_string();
+
+                if !test::is_selected(&test_name, filters) {
+                    summary.filtered += 1;
+                    continue;
+                }
+
+                let outcome = test::run_test(self.command(&path), &name, test_name, index)?;
+
+                if outcome.passed {
+                    summary.passed += 1;
+                } else {
+                    summary.failed += 1;
+                }
+
+                test::report_test(&outcome, self.message_format)?;
+                outcomes.push(outcome);
+            }
+        }
+
+        self.db.set_libs(original);
+        self.db.set_target_dir(self.target_dir.clone());
+
+        if !compiled {
             return Ok(false);
         }
 
+        self.doctests(ws, &tested, filters, &mut outcomes, &mut summary)?;
+        test::report_summary(&outcomes, &summary, self.message_format)?;
+
+        let elapsed = start.elapsed();
+
+        self.status("Finished", format_args!("in {:?}", elapsed));
+        Ok(summary.failed == 0)
+    }
+
+    /// Compile the doctests of `libs` in `target/doctest` and run the ones which are selected by
+    /// `filters`. Every doctest is compiled as an executable snippet library with its own source
+    /// root, which depends on the documented library and on the dependencies of that library.
+    /// Executables are skipped, as they cannot be depended on.
+    fn doctests(
+        &mut self,
+        ws: usize,
+        libs: &[hir::Lib],
+        filters: &[String],
+        outcomes: &mut Vec<test::TestOutcome>,
+        summary: &mut test::TestSummary,
+    ) -> io::Result<()> {
+        let original = self.db.libs();
+        let target_dir = self.target_dir.join("doctest");
+        let mut with_snippets = (*original).clone();
+        let mut snippets = Vec::new();
+
+        for &lib in libs {
+            let data = &original[lib.into()];
+
+            if data.kind == LibKind::Executable {
+                continue;
+            }
+
+            for case in test::doctests(&self.db, lib) {
+                if !test::is_selected(&case.name, filters) {
+                    summary.filtered += 1;
+                    continue;
+                }
+
+                if case.test.ignore {
+                    test::report_ignored(&data.name, &case.name, self.message_format)?;
+                    summary.ignored += 1;
+                    continue;
+                }
+
+                let index = snippets.len();
+                let text = test::doctest_source(&case.module, &case.test.code);
+                let path = VfsPath::new_virtual(format!("/doctest/{}/{}.fi", data.name, index));
+                let (file, _) = self.vfs.set_file_content(path.clone(), Some(text.as_bytes().into()));
+                let root_id = SourceRootId(self.source_roots + index as u32);
+                let mut file_set = FileSet::default();
+
+                file_set.insert(file, path);
+                self.db.set_file_text(file, text.into());
+                self.db.set_file_source_root(file, root_id);
+                self.db.set_source_root(root_id, SourceRoot::new(file_set).into());
+
+                let id = with_snippets.add_lib(
+                    format!("{}_doctest_{}", data.name, index),
+                    LibKind::Executable,
+                    Vec::new(),
+                    data.cfg_options.clone(),
+                    Default::default(),
+                    file,
+                );
+
+                for dep in std::iter::once(lib.into()).chain(data.deps.iter().copied()) {
+                    with_snippets
+                        .add_dep(id, dep, &CfgOptions::default())
+                        .expect("nothing depends on a doctest");
+                }
+
+                snippets.push((id, lib, case));
+            }
+        }
+
+        if snippets.is_empty() {
+            return Ok(());
+        }
+
+        let ws = &self.workspaces[ws];
+
+        self.db.set_libs(with_snippets.into());
+        self.db.set_target_dir(target_dir.clone());
+
+        self.status("Running", format_args!("{} doctest(s)", snippets.len()));
+
+        for (id, lib, case) in snippets {
+            let name = lib.name(&self.db).to_string();
+            let deps = self.db.libs().all_deps(id).into_iter().map(Into::into).collect();
+            let outcome = if !self.compile(ws, deps, &target_dir)? {
+                test::not_run(&name, case.name, false)
+            } else if case.test.no_run {
+                test::not_run(&name, case.name, true)
+            } else {
+                let path = Assembly::dummy(id.into()).path(&self.db, &target_dir);
+
+                test::run_doctest(self.command(&path), &name, case.name, case.test.should_crash)?
+            };
+
+            if outcome.passed {
+                summary.passed += 1;
+            } else {
+                summary.failed += 1;
+            }
+
+            test::report_test(&outcome, self.message_format)?;
+            outcomes.push(outcome);
+        }
+
+        self.db.set_libs(original);
+        self.db.set_target_dir(self.target_dir.clone());
+        Ok(())
+    }
+
+    /// The command running the program at `path` for the current target.
+    fn command(&self, path: &AbsPath) -> Command {
+        if self.db.target() == CompilerTarget::Javascript {
+            if !program_exists("node") {
+                base_db::Error::throw("node must be insalled to run a javascript targeted program");
+            }
+
+            let mut cmd = Command::new("node");
+
+            cmd.arg(path.as_os_str());
+            cmd
+        } else if self.db.target().is_wasm() {
+            if !program_exists("wasmtime") {
+                base_db::Error::throw("wasmtime must be installed to run a wasm targeted program");
+            }
+
+            let mut cmd = Command::new("wasmtime");
+
+            cmd.arg(path.as_os_str());
+            cmd.arg("--");
+            cmd
+        } else {
+            Command::new(path.as_os_str())
+        }
+    }
+
+    /// Link `lib` and its dependencies, returning whether `lib` was relinked. Libraries whose
+    /// modules were all loaded from the object cache are only relinked when one of their
+    /// dependencies was.
+    fn write_assembly(
+        &self,
+        ws: &Workspace,
+        lib: hir::Lib,
+        target_dir: &AbsPath,
+        done: &mut FxHashMap<hir::Lib, bool>,
+    ) -> io::Result<bool> {
+        if let Some(&linked) = done.get(&lib) {
+            return Ok(linked);
+        }
+
+        let mut deps_linked = false;
+
         for dep in lib.dependencies(&self.db) {
-            self.write_assembly(ws, dep.lib, done)?;
+            deps_linked |= self.write_assembly(ws, dep.lib, target_dir, done)?;
         }
 
         let asm = self.db.lib_assembly(lib);
+        let out = asm.path(&self.db, target_dir);
+        let linked = deps_linked || !asm.is_cached() || !asm.is_linked(&self.db, ws, target_dir);
 
-        asm.link(&self.db, ws, &self.target_dir);
-        done.insert(lib);
+        if linked {
+            asm.link(&self.db, ws, target_dir);
+        } else {
+            tracing::debug!("{} is up to date", out.display());
+        }
 
-        Ok(true)
+        done.insert(lib, linked);
+        Ok(linked)
     }
 }
 
@@ -0,0 +1,256 @@
+//! Running the `@test` functions of a library through its test harness and reporting the
+//! results.
+//!
+//! The harness runs a single test, selected by its index in [`hir::Lib::tests`]. Every test is
+//! run in its own process, so a test which crashes does not stop the tests after it.
+//!
+//! The fi code blocks in the documentation of a library are run as doctests. Every block is
+//! compiled as a snippet library, which depends on the documented library and imports the
+//! module of the documented item.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::process::{Command, Output};
+
+use hir::attrs::HasAttrs;
+use hir::db::HirDatabase;
+use hir::{AssocItem, DocTest, ModuleDef};
+use serde::Serialize;
+
+use crate::diagnostics::MessageFormat;
+
+/// The result of running one test.
+#[derive(Debug, Serialize)]
+pub struct TestOutcome {
+    pub lib: String,
+    pub name: String,
+    pub passed: bool,
+    /// Whether the test compiled, which is only false for doctests.
+    pub compiled: bool,
+    /// The exit code of the test, missing when it was killed by a signal, like after a crash, or
+    /// when it was not run.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Default, Debug, Serialize)]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub filtered: usize,
+}
+
+/// A doctest of a library.
+pub struct DocTestCase {
+    pub name: String,
+    /// The path of the module containing the documented item.
+    pub module: String,
+    pub test: DocTest,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonEvent<'a> {
+    Test(&'a TestOutcome),
+    Ignored { lib: &'a str, name: &'a str },
+    Summary(&'a TestSummary),
+}
+
+/// Whether the test named `name` is selected by `filters`. All tests are selected when there
+/// are no filters.
+pub fn is_selected(name: &str, filters: &[String]) -> bool {
+    filters.is_empty() || filters.iter().any(|f| name.contains(f.as_str()))
+}
+
+/// Run test `index` of a harness and collect its output.
+pub fn run_test(mut cmd: Command, lib: &str, name: String, index: usize) -> io::Result<TestOutcome> {
+    let output = cmd.arg(index.to_string()).output()?;
+
+    Ok(outcome(lib, name, output, false))
+}
+
+/// Run a compiled doctest and collect its output. When `should_crash` is set the doctest passes
+/// only if it fails.
+pub fn run_doctest(mut cmd: Command, lib: &str, name: String, should_crash: bool) -> io::Result<TestOutcome> {
+    let output = cmd.output()?;
+
+    Ok(outcome(lib, name, output, should_crash))
+}
+
+/// The outcome of a doctest which was not run, because it is marked `no_run` or because it did
+/// not compile.
+pub fn not_run(lib: &str, name: String, compiled: bool) -> TestOutcome {
+    TestOutcome {
+        lib: lib.to_string(),
+        name,
+        passed: compiled,
+        compiled,
+        exit_code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+    }
+}
+
+fn outcome(lib: &str, name: String, output: Output, should_crash: bool) -> TestOutcome {
+    let Output { status, stdout, stderr } = output;
+
+    TestOutcome {
+        lib: lib.to_string(),
+        name,
+        passed: status.success() != should_crash,
+        compiled: true,
+        exit_code: status.code(),
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+    }
+}
+
+/// The doctests in the documentation of the modules and items of `lib`, sorted by name.
+pub fn doctests(db: &dyn HirDatabase, lib: hir::Lib) -> Vec<DocTestCase> {
+    let mut cases = Vec::new();
+    let mut modules = lib.modules(db);
+
+    while let Some(module) = modules.pop() {
+        let path = module.path(db).to_string();
+        let mut add = |item: String, docs: Option<hir::Documentation>| {
+            let tests = docs.map(|d| d.doctests()).unwrap_or_default();
+
+            for (i, test) in tests.into_iter().enumerate() {
+                cases.push(DocTestCase {
+                    name: format!("{} (doctest {})", item, i + 1),
+                    module: path.clone(),
+                    test,
+                });
+            }
+        };
+
+        add(path.clone(), ModuleDef::Module(module).docs(db));
+
+        for def in module.declarations(db) {
+            add(def.path(db).to_string(), def.docs(db));
+
+            if let ModuleDef::Class(class) = def {
+                for item in class.items(db) {
+                    add(assoc_path(db, item), item.attrs(db).docs());
+                }
+            }
+        }
+
+        for member in module.members(db) {
+            for item in member.items(db) {
+                add(assoc_path(db, item), item.attrs(db).docs());
+            }
+        }
+
+        modules.extend(module.children(db));
+    }
+
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    cases
+}
+
+fn assoc_path(db: &dyn HirDatabase, item: AssocItem) -> String {
+    match item {
+        | AssocItem::Func(it) => it.path(db).to_string(),
+        | AssocItem::Static(it) => it.path(db).to_string(),
+    }
+}
+
+/// The source of the snippet library running the doctest `code`, which documents an item in
+/// `module`. Imports at the start of a line are moved to the top of the snippet and the other
+/// lines become the body of its `@main` function, unless the code has a `@main` function itself.
+pub fn doctest_source(module: &str, code: &str) -> String {
+    let mut source = format!("module Doctest =\n\nimport {}\n", module);
+
+    if code.lines().any(|l| l.trim() == "@main") {
+        writeln!(source, "\n{}", code).unwrap();
+        return source;
+    }
+
+    let mut body = String::new();
+
+    for line in code.lines() {
+        if line.starts_with("import ") {
+            writeln!(source, "{}", line).unwrap();
+        } else if line.trim().is_empty() {
+            body.push('\n');
+        } else {
+            writeln!(body, "    {}", line).unwrap();
+        }
+    }
+
+    write!(source, "\n@main\nmain =\n{}    ()\n", body).unwrap();
+    source
+}
+
+pub fn report_test(outcome: &TestOutcome, format: MessageFormat) -> io::Result<()> {
+    match format {
+        | MessageFormat::Human if outcome.passed => {
+            println!("test {} ... \x1B[32mok\x1B[0m", outcome.name);
+        },
+        | MessageFormat::Human => {
+            println!("test {} ... \x1B[31mFAILED\x1B[0m", outcome.name);
+        },
+        | MessageFormat::Json => write_json(&JsonEvent::Test(outcome))?,
+    }
+
+    Ok(())
+}
+
+pub fn report_ignored(lib: &str, name: &str, format: MessageFormat) -> io::Result<()> {
+    match format {
+        | MessageFormat::Human => println!("test {} ... \x1B[33mignored\x1B[0m", name),
+        | MessageFormat::Json => write_json(&JsonEvent::Ignored { lib, name })?,
+    }
+
+    Ok(())
+}
+
+/// Report the output of the failed tests followed by the number of tests passed and failed.
+pub fn report_summary(outcomes: &[TestOutcome], summary: &TestSummary, format: MessageFormat) -> io::Result<()> {
+    if format == MessageFormat::Json {
+        return write_json(&JsonEvent::Summary(summary));
+    }
+
+    let failures = outcomes.iter().filter(|o| !o.passed).collect::<Vec<_>>();
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+
+        for outcome in &failures {
+            match outcome.exit_code {
+                | _ if !outcome.compiled => println!("\n---- {} failed to compile ----", outcome.name),
+                | Some(code) => println!("\n---- {} exited with code {} ----", outcome.name, code),
+                | None => println!("\n---- {} crashed ----", outcome.name),
+            }
+
+            print!("{}{}", outcome.stdout, outcome.stderr);
+        }
+
+        println!();
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; {} failed; {} ignored; {} filtered out",
+        if summary.failed == 0 {
+            "\x1B[32mok\x1B[0m"
+        } else {
+            "\x1B[31mFAILED\x1B[0m"
+        },
+        summary.passed,
+        summary.failed,
+        summary.ignored,
+        summary.filtered
+    );
+
+    Ok(())
+}
+
+fn write_json(event: &JsonEvent) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+
+    serde_json::to_writer(&mut stdout, event)?;
+    writeln!(stdout)
+}
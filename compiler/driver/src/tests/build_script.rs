@@ -0,0 +1,55 @@
+use std::fs;
+
+use project::manifest::Cfg;
+
+use super::lib_dir;
+use crate::{Driver, InitOpts, MessageFormat, Optimization};
+
+const BUILD: &str = "module Build =
+
+import Prim
+import Intrinsics (unsafe, transmute)
+
+foreign write :: Int32 -> Ptr Int8 -> Uint -> Int
+
+@main
+main :: ()
+main = unsafe do
+    let ptr, len = transmute \"fi:cfg=generated\\n\" :: Ptr Int8, Uint
+    write 1 ptr len
+    ()
+";
+
+#[test]
+fn fi_build_script_is_compiled_with_dependencies_and_run() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let manifest = format!(
+        "[project]\nname = \"scripted\"\nversion = \"0.1.0\"\nbuild = \"build.fi\"\n\n[dependencies]\nprim = {{ path = {:?} }}\n",
+        lib_dir("prim").display().to_string()
+    );
+
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("fi.toml"), manifest).unwrap();
+    fs::write(dir.path().join("build.fi"), BUILD).unwrap();
+    fs::write(dir.path().join("src").join("main.fi"), "module Main =\n").unwrap();
+
+    let (driver, ws) = Driver::init(InitOpts {
+        input: dir.path(),
+        target: None,
+        output: None,
+        optimization: Optimization::None,
+        debug_info: false,
+        cfg: Cfg::default(),
+        packages: Vec::new(),
+        features: Vec::new(),
+        no_default_features: false,
+        message_format: MessageFormat::Human,
+    })
+    .unwrap();
+
+    let workspace = &driver.workspaces[ws];
+    let package = &workspace[workspace.selected()[0]];
+
+    assert!(package.cfg.flags().contains("generated"), "{:?}", package.cfg);
+    assert!(package.out_dir.is_some());
+}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::PathBuf;
+
+use base_db::libs::LibKind;
+use codegen::db::CodegenDatabase;
+use paths::AbsPathBuf;
+use project::manifest::Cfg;
+
+use super::lib_dir;
+use crate::{Driver, InitNoManifestOpts, MessageFormat, Optimization};
+
+const MAIN: &str =
+    "module Main =\n\nimport Prim\nimport Intrinsics (iadd)\n\nadd :: Int -> Int -> Int\nadd x y = iadd x y\n";
+const EXTRA: &str = "module Extra =\n\nimport Prim\n\none :: Int\none = 1\n";
+const USES_EXTRA: &str = "module Main =\n\nimport Prim\nimport Extra\n\ntwo :: Int\ntwo = one\n";
+const GENERIC: &str = "module Extra =
+
+import Prim
+
+class Pick t =
+    pick :: t -> t -> t
+
+member t of Pick =
+    pick x _ = x
+";
+const USES_GENERIC: &str = "module Main =\n\nimport Prim\nimport Extra\n\nfirst :: Int\nfirst = pick 1 2\n";
+
+/// A static library which is built repeatedly into the same target directory.
+struct Project {
+    dir: tempfile::TempDir,
+}
+
+/// What a build of the project reused from the previous one.
+#[derive(Debug, PartialEq, Eq)]
+struct Build {
+    /// Whether all object files were cached.
+    cached: bool,
+    /// Whether the library was still linked from the same inputs.
+    linked: bool,
+}
+
+impl Project {
+    fn new() -> Self {
+        Self {
+            dir: tempfile::TempDir::new().unwrap(),
+        }
+    }
+
+    fn build(&self, files: &[(&str, &str)], debug_info: bool) -> Build {
+        let (driver, ws, lib) = self.init(files, debug_info);
+        let asm = driver.db.lib_assembly(lib);
+        let build = Build {
+            cached: asm.is_cached(),
+            linked: asm.is_linked(&driver.db, &driver.workspaces[ws], &driver.target_dir),
+        };
+
+        assert!(driver.build(ws).unwrap());
+        build
+    }
+
+    /// Build the project and return the names of the modules which were compiled again.
+    fn compiled(&self, files: &[(&str, &str)]) -> Vec<String> {
+        let (driver, ws, lib) = self.init(files, false);
+        let mut compiled = lib
+            .modules(&driver.db)
+            .into_iter()
+            .filter(|&module| !driver.db.codegen_module(module).is_cached())
+            .map(|module| module.name(&driver.db).to_string())
+            .collect::<Vec<_>>();
+
+        compiled.sort();
+        assert!(driver.build(ws).unwrap());
+        compiled
+    }
+
+    fn init(&self, files: &[(&str, &str)], debug_info: bool) -> (Driver, usize, hir::Lib) {
+        let files = files
+            .iter()
+            .map(|(name, text)| {
+                let path = self.dir.path().join(name);
+
+                fs::write(&path, text).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+        let deps = [lib_dir("prim")];
+        let (mut driver, ws) = Driver::init_without_manifest(InitNoManifestOpts {
+            files: files.iter().map(PathBuf::as_path).collect(),
+            name: "snippet",
+            target: None,
+            output: LibKind::Static,
+            optimization: Optimization::None,
+            debug_info,
+            cfg: Cfg::default(),
+            links: Vec::new(),
+            dependencies: deps.iter().map(PathBuf::as_path).collect(),
+            message_format: MessageFormat::Human,
+        })
+        .unwrap();
+
+        driver.target_dir = AbsPathBuf::assert(self.dir.path().join("target"));
+        driver.db.set_target_dir(driver.target_dir.clone());
+
+        let lib = hir::Lib::all(&driver.db)
+            .into_iter()
+            .find(|lib| lib.name(&driver.db).to_string() == "snippet")
+            .unwrap();
+
+        (driver, ws, lib)
+    }
+}
+
+#[test]
+fn first_build_compiles_and_links() {
+    let project = Project::new();
+
+    assert_eq!(project.build(&[("main.fi", MAIN)], false), Build {
+        cached: false,
+        linked: false,
+    });
+}
+
+#[test]
+fn unchanged_build_is_cached() {
+    let project = Project::new();
+
+    project.build(&[("main.fi", MAIN)], false);
+
+    assert_eq!(project.build(&[("main.fi", MAIN)], false), Build {
+        cached: true,
+        linked: true,
+    });
+}
+
+#[test]
+fn changed_body_is_recompiled() {
+    let project = Project::new();
+    let changed = MAIN.replace("iadd x y", "iadd y x");
+
+    project.build(&[("main.fi", MAIN)], false);
+
+    assert_eq!(project.build(&[("main.fi", &changed)], false), Build {
+        cached: false,
+        linked: false,
+    });
+}
+
+#[test]
+fn moved_code_is_recompiled_with_debug_info() {
+    let project = Project::new();
+    let moved = MAIN.replace("add ::", "\nadd ::");
+
+    project.build(&[("main.fi", MAIN)], false);
+    assert!(project.build(&[("main.fi", &moved)], false).cached);
+
+    project.build(&[("main.fi", MAIN)], true);
+    assert!(!project.build(&[("main.fi", &moved)], true).cached);
+}
+
+#[test]
+fn removed_module_is_relinked() {
+    let project = Project::new();
+
+    project.build(&[("main.fi", MAIN), ("extra.fi", EXTRA)], false);
+
+    assert_eq!(project.build(&[("main.fi", MAIN)], false), Build {
+        cached: true,
+        linked: false,
+    });
+}
+
+#[test]
+fn only_the_changed_module_is_recompiled() {
+    let project = Project::new();
+    let changed = EXTRA.replace("one = 1", "one = 2");
+
+    project.build(&[("main.fi", USES_EXTRA), ("extra.fi", EXTRA)], false);
+
+    assert_eq!(project.compiled(&[("main.fi", USES_EXTRA), ("extra.fi", &changed)]), ["Extra"]);
+}
+
+#[test]
+fn changed_generic_body_recompiles_its_users() {
+    let project = Project::new();
+    let changed = GENERIC.replace("pick x _ = x", "pick _ y = y");
+
+    project.build(&[("main.fi", USES_GENERIC), ("extra.fi", GENERIC)], false);
+
+    assert_eq!(project.compiled(&[("main.fi", USES_GENERIC), ("extra.fi", &changed)]), ["Extra", "Main"]);
+}
@@ -0,0 +1,95 @@
+use std::fs;
+
+use base_db::SourceDatabase;
+use paths::AbsPathBuf;
+
+use super::Snippet;
+use crate::emit::{self, Emit};
+use crate::{Driver, InitOpts};
+
+/// Whether debug info is emitted when building the members `packages` of a workspace in which
+/// only `app` sets `debug = true`.
+fn debug_info(packages: &[&str]) -> bool {
+    let dir = tempfile::TempDir::new().unwrap();
+
+    fs::write(
+        dir.path().join("fi.toml"),
+        "[workspace]\nmembers = [\"app\", \"util\"]\n",
+    )
+    .unwrap();
+
+    for (name, debug) in [("app", true), ("util", false)] {
+        let src = dir.path().join(name).join("src");
+
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("main.fi"), "module Main =\n").unwrap();
+        fs::write(
+            dir.path().join(name).join("fi.toml"),
+            format!(
+                "[project]\nname = \"{}\"\nversion = \"0.1.0\"\ndebug = {}\n",
+                name, debug
+            ),
+        )
+        .unwrap();
+    }
+
+    let (driver, _) = Driver::init(InitOpts {
+        input: dir.path(),
+        packages: packages.to_vec(),
+        ..InitOpts::default()
+    })
+    .unwrap();
+
+    driver.db.debug_info()
+}
+
+#[test]
+fn debug_info_follows_the_selected_members() {
+    assert!(debug_info(&[]));
+    assert!(debug_info(&["app"]));
+    assert!(!debug_info(&["util"]));
+}
+
+/// The LLVM IR of a snippet, built with or without debug info.
+fn llvm_ir(debug_info: bool) -> String {
+    let mut snippet = Snippet::new(
+        "module Main =
+
+import Prim
+import Intrinsics (iadd)
+
+add :: Int -> Int -> Int
+add x y = do
+    let z = iadd x y
+    iadd z 1
+",
+    );
+
+    snippet.driver.db.set_debug_info(debug_info);
+
+    let db = &snippet.driver.db;
+    let dir = tempfile::TempDir::new().unwrap();
+    let out_dir = AbsPathBuf::assert(dir.path().to_path_buf());
+    let lib = snippet.func("add").lib(db);
+    let files = emit::emit(db, lib, &[Emit::LlvmIr], &out_dir).unwrap();
+
+    fs::read_to_string(&files[0]).unwrap()
+}
+
+#[test]
+fn llvm_ir_contains_debug_info() {
+    let ir = llvm_ir(true);
+
+    assert!(ir.contains("!DICompileUnit("), "{}", ir);
+    assert!(ir.contains("!DISubprogram("), "{}", ir);
+    assert!(ir.contains("!DILocation(line: 9,"), "{}", ir);
+    assert!(ir.contains("!DILocalVariable(name: \"z\""), "{}", ir);
+}
+
+#[test]
+fn llvm_ir_without_debug_info() {
+    let ir = llvm_ir(false);
+
+    assert!(!ir.contains("!DICompileUnit("), "{}", ir);
+    assert!(!ir.contains("!dbg"), "{}", ir);
+}
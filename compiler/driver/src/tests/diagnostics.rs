@@ -0,0 +1,93 @@
+use base_db::libs::LibKind;
+use serde_json::{json, Value};
+
+use super::Snippet;
+use crate::diagnostics::{self, MessageFormat};
+use crate::Optimization;
+
+/// The JSON diagnostics of the snippet `text`, with the directory of the snippet replaced by
+/// `$DIR`.
+fn json(text: &str) -> Vec<Value> {
+    let snippet = Snippet::with_output(text, &["prim"], Optimization::None, LibKind::Static);
+    let db = &snippet.driver.db;
+    let lib = hir::Lib::all(db)
+        .into_iter()
+        .find(|lib| lib.name(db).to_string() == "snippet")
+        .unwrap();
+    let mut out = Vec::new();
+
+    diagnostics::emit_diagnostics(db, lib, MessageFormat::Json, &mut out).unwrap();
+
+    let dir = snippet.target_dir().parent().unwrap().display().to_string();
+    let out = String::from_utf8(out).unwrap().replace(&dir, "$DIR");
+
+    out.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+fn range(start: usize, end: usize, line: usize, start_column: usize, end_column: usize) -> Value {
+    json!({
+        "start": start,
+        "end": end,
+        "start_line": line,
+        "start_column": start_column,
+        "end_line": line,
+        "end_column": end_column,
+    })
+}
+
+#[test]
+fn lint_with_related_label() {
+    // The lines end in CRLF and the string before the lambda has multi-byte characters, so
+    // byte offsets and columns differ.
+    let diags = json(
+        "module Main (run) =\r\n\r\nimport Prim (Int, Str)\r\nimport Intrinsics (iadd)\r\n\r\napply :: Str -> (Int \
+         -> Int) -> Int\r\napply _ f = f 1\r\n\r\nrun :: Int -> Int\r\nrun x = iadd x (apply \"αβγ\" (fn x -> \
+         x))\r\n",
+    );
+
+    assert_eq!(diags, [json!({
+        "code": "W0004",
+        "file": "$DIR/main.fi",
+        "level": "warning",
+        "title": "`x` shadows an earlier binding",
+        "range": range(185, 186, 10, 33, 34),
+        "primary": {
+            "file": "$DIR/main.fi",
+            "range": range(185, 186, 10, 33, 34),
+            "message": "`x` redefined here",
+        },
+        "secondary": [{
+            "file": "$DIR/main.fi",
+            "range": range(154, 155, 10, 5, 6),
+            "message": "previous binding of `x` here",
+        }],
+        "notes": [],
+        "suggestions": [],
+    })]);
+}
+
+#[test]
+fn syntax_error_schema() {
+    let diags = json("module Main =\n\nrun = (\n");
+    let diag = diags[0].as_object().unwrap();
+    let keys = diag.keys().map(String::as_str).collect::<Vec<_>>();
+
+    assert_eq!(keys, [
+        "code",
+        "file",
+        "level",
+        "notes",
+        "primary",
+        "range",
+        "secondary",
+        "suggestions",
+        "title",
+    ]);
+    assert_eq!(diag["code"], "E0020");
+    assert_eq!(diag["file"], "$DIR/main.fi");
+    assert_eq!(diag["level"], "error");
+    assert_eq!(diag["title"], "syntax error");
+    assert_eq!(diag["range"], diag["primary"]["range"]);
+    assert!(diag["primary"]["message"].is_string());
+    assert!(diag["range"]["start_line"].as_u64().unwrap() >= 3);
+}
@@ -0,0 +1,167 @@
+use std::fs;
+
+use base_db::libs::LibKind;
+use paths::AbsPathBuf;
+use serde_json::Value;
+
+use super::Snippet;
+use crate::{doc, Optimization};
+
+const SOURCE: &str = ";; The main module of the `snippet` & co.
+module Main =
+
+import Prim (Int)
+
+;; A shape with a <size>.
+type Shape =
+    | Square Int
+    | Circle Int
+
+;; A shape in a box.
+type Boxed = | Boxed Shape
+
+;; The area of a *shape*.
+;;
+;; ```fi
+;; area (Square 2)
+;; ```
+area :: Shape -> Int
+area _ = 0
+";
+
+const SHADOWED: &str = "module Main =
+
+module Inner =
+    type Shape = | Point
+
+type Shape = | Square
+
+convert :: Shape -> Inner:Shape
+convert _ = Inner:Point
+";
+
+/// The documentation of the snippet and the libraries it depends on.
+struct Site {
+    _dir: tempfile::TempDir,
+    root: AbsPathBuf,
+}
+
+impl Site {
+    fn new() -> Self {
+        Self::with_source(SOURCE)
+    }
+
+    fn with_source(source: &str) -> Self {
+        let snippet = Snippet::with_output(source, &["prim"], Optimization::None, LibKind::Static);
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = AbsPathBuf::assert(dir.path().to_path_buf());
+        let db = snippet.db();
+
+        doc::document(db, &hir::Lib::all(db), &root).unwrap();
+        Self { _dir: dir, root }
+    }
+
+    fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.root.join(path)).unwrap()
+    }
+
+    fn search_index(&self) -> Vec<Value> {
+        let text = self.read("search-index.js");
+        let json = text
+            .strip_prefix("window.searchIndex = ")
+            .and_then(|t| t.strip_suffix(";\n"))
+            .unwrap();
+
+        serde_json::from_str(json).unwrap()
+    }
+}
+
+#[test]
+fn signatures_link_to_items() {
+    let site = Site::new();
+    let page = site.read("snippet/Main.html");
+
+    assert!(
+        page.contains(
+            "<a href=\"../snippet/Main.html#type.Shape\">Shape</a> -&gt; <a \
+             href=\"../prim/Prim.html#type.Int\">Int</a>"
+        ),
+        "{}",
+        page
+    );
+    assert!(
+        page.contains("<span id=\"ctor.Boxed\">Boxed</span> <a href=\"../snippet/Main.html#type.Shape\">Shape</a>"),
+        "{}",
+        page
+    );
+}
+
+#[test]
+fn types_with_the_same_name_link_to_their_own_item() {
+    let site = Site::with_source(SHADOWED);
+    let page = site.read("snippet/Main.html");
+
+    assert!(
+        page.contains(
+            "convert :: <a href=\"../snippet/Main.html#type.Shape\">Shape</a> -&gt; <a \
+             href=\"../snippet/Main.Inner.html#type.Shape\">Shape</a>"
+        ),
+        "{}",
+        page
+    );
+}
+
+#[test]
+fn docs_are_rendered_and_escaped() {
+    let site = Site::new();
+    let page = site.read("snippet/Main.html");
+
+    assert!(
+        page.contains("<p>The main module of the <code>snippet</code> &amp; co.</p>"),
+        "{}",
+        page
+    );
+    assert!(page.contains("<p>A shape with a &lt;size&gt;.</p>"), "{}", page);
+    assert!(page.contains("<p>The area of a <em>shape</em>.</p>"), "{}", page);
+    assert!(
+        page.contains("<pre><code class=\"language-fi\">area (Square 2)</code></pre>"),
+        "{}",
+        page
+    );
+}
+
+#[test]
+fn search_index_is_valid_json() {
+    let site = Site::new();
+    let index = site.search_index();
+    let area = index.iter().find(|e| e["path"] == "Main:area").unwrap();
+
+    assert_eq!(area["kind"], "func");
+    assert_eq!(area["url"], "snippet/Main.html#func.area");
+    assert_eq!(area["summary"], "The area of a shape.");
+    assert!(index.iter().any(|e| e["path"] == "Main:Square" && e["kind"] == "ctor"));
+}
+
+#[test]
+fn search_index_urls_resolve() {
+    let site = Site::new();
+
+    for entry in site.search_index() {
+        let url = entry["url"].as_str().unwrap();
+        let (path, id) = match url.split_once('#') {
+            | Some((path, id)) => (path, Some(id)),
+            | None => (url, None),
+        };
+
+        let page = site.read(path);
+
+        if let Some(id) = id {
+            assert!(
+                page.contains(&format!("id=\"{}\"", id)),
+                "{} has no element {}",
+                path,
+                id
+            );
+        }
+    }
+}
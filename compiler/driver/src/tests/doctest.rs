@@ -0,0 +1,28 @@
+use super::Snippet;
+use crate::test::doctest_source;
+
+#[test]
+fn code_becomes_the_body_of_main() {
+    let source = doctest_source("Core:Ops", "import Prim\nlet x = 1\n\nx");
+
+    assert_eq!(
+        source,
+        "module Doctest =\n\nimport Core:Ops\nimport Prim\n\n@main\nmain =\n    let x = 1\n\n    x\n    ()\n"
+    );
+}
+
+#[test]
+fn code_with_main_is_kept() {
+    let code = "import Prim\n\n@main\nmain :: ()\nmain = ()";
+    let source = doctest_source("Core:Ops", code);
+
+    assert_eq!(source, format!("module Doctest =\n\nimport Core:Ops\n\n{}\n", code));
+}
+
+#[test]
+fn source_checks() {
+    let source = doctest_source("Prim", "import Intrinsics (iadd)\niadd 1 2");
+    let snippet = Snippet::new(&source);
+
+    assert!(snippet.driver.check(snippet.ws).unwrap(), "{}", source);
+}
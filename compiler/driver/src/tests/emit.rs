@@ -0,0 +1,136 @@
+use std::fs;
+
+use base_db::libs::LibKind;
+use codegen::db::CodegenDatabase;
+use hir::HirDisplay;
+use paths::AbsPathBuf;
+
+use super::Snippet;
+use crate::emit::{self, Emit};
+use crate::Optimization;
+
+const HEADER: &str = "module Main =\n\nimport Prim\nimport Intrinsics (iadd)\n\n";
+
+fn snippet(text: &str) -> Snippet {
+    Snippet::with_output(
+        &format!("{}{}", HEADER, text),
+        &["prim"],
+        Optimization::None,
+        LibKind::Static,
+    )
+}
+
+/// Write the representation `kind` of the snippet, which defines `func`, and return its
+/// contents.
+fn emit(snippet: &Snippet, func: &str, kind: Emit) -> Vec<u8> {
+    let db = &snippet.driver.db;
+    let dir = tempfile::TempDir::new().unwrap();
+    let out_dir = AbsPathBuf::assert(dir.path().to_path_buf());
+    let lib = snippet.func(func).lib(db);
+    let files = emit::emit(db, lib, &[kind], &out_dir).unwrap();
+
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(
+        files[0],
+        out_dir.join("snippet").join(format!("Main.{}", kind.extension()))
+    );
+
+    fs::read(&files[0]).unwrap()
+}
+
+fn emit_text(snippet: &Snippet, func: &str, kind: Emit) -> String {
+    String::from_utf8(emit(snippet, func, kind)).unwrap()
+}
+
+/// The signature of `func` as it is written before its body.
+fn signature(snippet: &Snippet, func: &str) -> String {
+    snippet.func(func).display(snippet.db()).to_string()
+}
+
+#[test]
+fn ast_is_the_syntax_tree_of_the_module() {
+    let snippet = snippet("identity :: Int -> Int\nidentity x = x\n");
+    let ast = emit_text(&snippet, "identity", Emit::Ast);
+
+    assert!(ast.starts_with("MODULE@0.."), "{}", ast);
+    assert!(ast.contains("IDENT@"), "{}", ast);
+    assert!(ast.contains("\"identity\""), "{}", ast);
+}
+
+#[test]
+fn hir_prints_lowered_bodies() {
+    let snippet = snippet("add3 :: Int -> Int -> Int -> Int\nadd3 x = fn y -> fn z -> iadd x (iadd y z)\n");
+    let hir = emit_text(&snippet, "add3", Emit::Hir);
+
+    assert_eq!(
+        hir,
+        format!(
+            "{}\nadd3 x = fn y -> fn z -> iadd x (iadd y z)\n\n",
+            signature(&snippet, "add3")
+        )
+    );
+}
+
+#[test]
+fn hir_desugars_clauses_into_a_case() {
+    let snippet = snippet("pick :: Int -> Int\npick 0 = 1\npick n = do\n    let m = iadd n 1\n    m\n");
+    let hir = emit_text(&snippet, "pick", Emit::Hir);
+
+    assert_eq!(
+        hir,
+        format!(
+            "{}\npick $arg0 = case $arg0 of\n    0 -> 1\n    n -> do\n        let m = iadd n 1\n        m\n\n",
+            signature(&snippet, "pick")
+        )
+    );
+}
+
+#[test]
+fn types_lists_patterns_and_expressions() {
+    let snippet = snippet("identity :: Int -> Int\nidentity x = x\n");
+    let types = emit_text(&snippet, "identity", Emit::Types);
+
+    assert_eq!(
+        types,
+        format!("{}\n    x :: Int\n    x :: Int\n\n", signature(&snippet, "identity"))
+    );
+}
+
+#[test]
+fn types_are_written_when_type_checking_fails() {
+    let snippet = snippet("wrong :: Int -> Int\nwrong x = \"text\"\n");
+
+    assert!(!snippet.driver.build(snippet.ws).unwrap());
+    assert!(snippet.driver.emit(snippet.ws, &[Emit::Types, Emit::Hir]).unwrap());
+
+    let dir = snippet.target_dir().join("emit").join("snippet");
+    let types = fs::read_to_string(dir.join("Main.types")).unwrap();
+    let hir = fs::read_to_string(dir.join("Main.hir")).unwrap();
+
+    assert!(types.contains("\"text\" :: "), "{}", types);
+    assert!(hir.contains("wrong x = \"text\""), "{}", hir);
+}
+
+#[test]
+fn asm_defines_the_functions_of_the_module() {
+    let snippet = snippet("add :: Int -> Int -> Int\nadd x y = iadd x y\n");
+    let asm = emit_text(&snippet, "add", Emit::Asm);
+    let (name, _) = snippet.func("add").link_name(snippet.db());
+    let label = asm
+        .lines()
+        .filter_map(|line| line.strip_suffix(':'))
+        .find(|label| label.trim_matches('"') == name.to_string());
+
+    assert!(label.is_some(), "{}", asm);
+}
+
+#[test]
+fn obj_is_the_linked_object_file() {
+    let snippet = snippet("add :: Int -> Int -> Int\nadd x y = iadd x y\n");
+    let obj = emit(&snippet, "add", Emit::Obj);
+    let module = snippet.func("add").module(snippet.db());
+    let compiled = fs::read(snippet.driver.db.codegen_module(module).path()).unwrap();
+
+    assert!(!obj.is_empty());
+    assert_eq!(obj, compiled);
+}
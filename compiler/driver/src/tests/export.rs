@@ -0,0 +1,89 @@
+use std::fs;
+use std::process::Command;
+
+use base_db::libs::LibKind;
+use codegen::header;
+use mir::repr::{Repr, Signature};
+
+use super::Snippet;
+use crate::Optimization;
+
+#[test]
+fn exported_static() {
+    let snippet = Snippet::with_output(
+        r#"module Main =
+
+import Prim
+
+@export
+static answer :: Int32
+static answer = 42
+"#,
+        &["prim"],
+        Optimization::None,
+        LibKind::Static,
+    );
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+
+    let target_dir = snippet.target_dir();
+    let header = fs::read_to_string(target_dir.join("snippet.h")).unwrap();
+
+    assert!(header.contains("extern const int32_t answer;"), "{}", header);
+
+    let nm = Command::new("nm")
+        .arg(target_dir.join("libsnippet.a"))
+        .output()
+        .unwrap();
+    let symbols = String::from_utf8(nm.stdout).unwrap();
+
+    assert!(symbols.lines().any(|line| line.ends_with(" R answer")), "{}", symbols);
+}
+
+#[test]
+fn exported_pointer_points_to_its_element() {
+    let snippet = Snippet::with_output(
+        r#"module Main =
+
+import Prim
+
+@export
+first :: Ptr Int32 -> Int32
+first _ = 0
+"#,
+        &["prim"],
+        Optimization::None,
+        LibKind::Static,
+    );
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+
+    let header = fs::read_to_string(snippet.target_dir().join("snippet.h")).unwrap();
+
+    assert!(header.contains("int32_t first(int32_t *arg0);"), "{}", header);
+}
+
+#[test]
+fn function_pointers_are_declared_with_their_signature() {
+    let snippet = Snippet::with_output("module Main =\n", &["prim"], Optimization::None, LibKind::Static);
+    let callback = Repr::Func(
+        Box::new(Signature {
+            params: Box::new([Repr::i32()]),
+            ret: Repr::i32(),
+        }),
+        false,
+    );
+    let sig = Signature {
+        params: Box::new([
+            callback.clone(),
+            Repr::Ptr(Box::new(Repr::i32()), false, true),
+            Repr::Ptr(Box::new(Repr::unit()), false, false),
+        ]),
+        ret: Repr::Ptr(Box::new(callback), false, false),
+    };
+
+    assert_eq!(
+        header::function_declaration(&snippet.driver.db, &sig, "apply").unwrap(),
+        "int32_t (**apply(int32_t (*arg0)(int32_t), int32_t *arg1, void *arg2))(int32_t)"
+    );
+}
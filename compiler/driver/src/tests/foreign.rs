@@ -0,0 +1,77 @@
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+
+use base_db::libs::LibKind;
+use hir::DefWithBody;
+use mir::db::MirDatabase;
+use mir::syntax::{Rvalue, Stmt};
+use paths::AbsPathBuf;
+
+use super::Snippet;
+use crate::emit::{self, Emit};
+use crate::Optimization;
+
+const HEADER: &str =
+    "module Main =\n\nimport Prim\nimport Intrinsics (iadd)\n\nforeign call_with :: (Int -> Int) -> Int\n\n";
+
+fn snippet(text: &str) -> Snippet {
+    Snippet::with_output(
+        &format!("{}{}", HEADER, text),
+        &["prim"],
+        Optimization::None,
+        LibKind::Static,
+    )
+}
+
+#[test]
+fn lambda_is_passed_as_plain_function() {
+    let snippet = snippet("run :: Int -> Int\nrun x = call_with (fn y -> iadd y 1)\n");
+    let db = &snippet.driver.db;
+    let body = db.body_mir(DefWithBody::Func(snippet.func("run")).into());
+    let data = db.lookup_intern_body(body);
+    let stmts = data.blocks.iter().flat_map(|(_, block)| block.stmts.iter());
+
+    assert!(!stmts
+        .clone()
+        .any(|stmt| matches!(stmt, Stmt::Assign(_, Rvalue::Closure(..)))));
+    assert_eq!(body.closures(db).len(), 1);
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let out_dir = AbsPathBuf::assert(dir.path().to_path_buf());
+    let lib = snippet.func("run").lib(db);
+    let files = emit::emit(db, lib, &[Emit::LlvmIr], &out_dir).unwrap();
+    let ir = fs::read_to_string(&files[0]).unwrap();
+    let call = ir.lines().find(|line| line.contains("@call_with(")).unwrap();
+
+    // the lambda is defined as a function of its own and passed without an environment
+    assert_eq!(
+        ir.lines().filter(|line| line.starts_with("define ")).count(),
+        2,
+        "{}",
+        ir
+    );
+    assert_eq!(call.matches('@').count(), 2, "{}", call);
+    assert!(!ir.contains("@malloc"), "{}", ir);
+}
+
+#[test]
+fn capturing_lambda_is_rejected() {
+    let snippet = snippet("run :: Int -> Int\nrun x = call_with (fn y -> iadd y x)\n");
+    let db = &snippet.driver.db;
+    let func = snippet.func("run");
+    let err = panic::catch_unwind(AssertUnwindSafe(|| db.body_mir(DefWithBody::Func(func).into()))).unwrap_err();
+    let err = err.downcast::<base_db::Error>().unwrap();
+
+    assert!(err.0.contains("can't capture local variables"), "{}", err);
+}
+
+#[test]
+fn local_function_is_rejected() {
+    let snippet = snippet("run :: (Int -> Int) -> Int\nrun f = call_with f\n");
+    let db = &snippet.driver.db;
+    let func = snippet.func("run");
+    let err = panic::catch_unwind(AssertUnwindSafe(|| db.body_mir(DefWithBody::Func(func).into()))).unwrap_err();
+    let err = err.downcast::<base_db::Error>().unwrap();
+
+    assert!(err.0.contains("only named functions and lambdas"), "{}", err);
+}
@@ -0,0 +1,105 @@
+use std::process::Command;
+
+use base_db::SourceDatabaseExt;
+
+use super::Snippet;
+use crate::test;
+
+const SOURCE: &str = "module Main =
+
+import Prim
+import Intrinsics (unsafe, transmute, ptr_write)
+
+@main
+main :: ()
+main = ()
+
+@test
+passes :: ()
+passes = ()
+
+@test
+fails :: ExitCode
+fails = ExitCode 1
+
+@test
+crashes :: ()
+crashes = unsafe do
+    let ptr = transmute (0 :: Uint) :: Ptr Int32
+    ptr_write ptr 1
+    ()
+";
+
+fn snippet_lib(snippet: &Snippet) -> hir::Lib {
+    let db = snippet.db();
+
+    hir::Lib::all(db)
+        .into_iter()
+        .find(|lib| lib.name(db).to_string() == "snippet")
+        .unwrap()
+}
+
+fn test_names(snippet: &Snippet) -> Vec<String> {
+    let db = snippet.db();
+
+    snippet_lib(snippet)
+        .tests(db)
+        .into_iter()
+        .map(|func| func.name(db).to_string())
+        .collect()
+}
+
+#[test]
+fn tests_are_stripped_without_the_test_cfg() {
+    let snippet = Snippet::new(SOURCE);
+
+    assert!(snippet.driver.check(snippet.ws).unwrap());
+    assert!(!snippet_lib(&snippet).is_test(snippet.db()));
+    assert!(test_names(&snippet).is_empty());
+}
+
+#[test]
+fn tests_are_discovered_in_path_order() {
+    let mut snippet = Snippet::new(SOURCE);
+    let lib = snippet_lib(&snippet);
+    let mut libs = (*snippet.driver.db.libs()).clone();
+
+    libs[lib.into()].cfg_options.enable("test");
+    snippet.driver.db.set_libs(libs.into());
+
+    assert!(lib.is_test(snippet.db()));
+    assert_eq!(test_names(&snippet), ["crashes", "fails", "passes"]);
+}
+
+#[test]
+fn harness_reports_each_test() {
+    let mut snippet = Snippet::new(SOURCE);
+
+    // One of the tests fails and one crashes.
+    assert!(!snippet.driver.test(snippet.ws, &[]).unwrap());
+
+    let harness = snippet.target_dir().join("test").join("snippet");
+    let run = |index| test::run_test(Command::new(&harness), "snippet", String::new(), index).unwrap();
+    let crashes = run(0);
+    let fails = run(1);
+    let passes = run(2);
+    let missing = run(3);
+
+    assert!(!crashes.passed);
+    assert_eq!(crashes.exit_code, None);
+    assert!(!fails.passed);
+    assert_eq!(fails.exit_code, Some(1));
+    assert!(passes.passed);
+    assert_eq!(passes.exit_code, Some(0));
+    assert!(!missing.passed);
+    assert_eq!(missing.exit_code, Some(2));
+}
+
+#[test]
+fn filtered_tests_are_not_run() {
+    let mut snippet = Snippet::new(SOURCE);
+
+    assert!(snippet.driver.test(snippet.ws, &["passes".to_string()]).unwrap());
+    assert!(test::is_selected("Main:passes", &[]));
+    assert!(!test::is_selected("Main:fails", &["passes".to_string()]));
+}
@@ -0,0 +1,106 @@
+use hir::DefWithBody;
+use mir::db::MirDatabase;
+use mir::syntax::{LocalKind, Stmt};
+
+use super::Snippet;
+use crate::Optimization;
+
+const SOURCE: &str = "module Main =
+
+import Prim
+import Intrinsics (iadd)
+
+small :: Int -> Int
+small x = iadd x 1
+
+medium :: Int -> Int
+medium x = iadd (iadd (iadd (iadd (iadd (iadd (iadd (iadd x 1) 2) 3) 4) 5) 6) 7) 8
+
+count :: Int -> Int
+count x = count (iadd x 1)
+
+call_small :: Int -> Int
+call_small x = small x
+
+call_medium :: Int -> Int
+call_medium x = medium x
+
+call_count :: Int -> Int
+call_count x = count x
+";
+
+/// The number of calls left in the optimized body of `name`.
+fn calls(snippet: &Snippet, name: &str) -> usize {
+    let db = &snippet.driver.db;
+    let body = db.optimized_body(db.body_mir(DefWithBody::Func(snippet.func(name)).into()));
+
+    db.lookup_intern_body(body)
+        .blocks
+        .iter()
+        .flat_map(|(_, block)| block.stmts.iter())
+        .filter(|stmt| matches!(stmt, Stmt::Call { .. }))
+        .count()
+}
+
+/// The number of arguments among the locals of the optimized body of `name`.
+fn args(snippet: &Snippet, name: &str) -> usize {
+    let db = &snippet.driver.db;
+    let body = db.optimized_body(db.body_mir(DefWithBody::Func(snippet.func(name)).into()));
+
+    db.lookup_intern_body(body)
+        .locals
+        .iter()
+        .filter(|(_, local)| local.kind == LocalKind::Arg)
+        .count()
+}
+
+fn snippet(optimization: Optimization) -> Snippet {
+    Snippet::with_opts(SOURCE, &["prim"], optimization)
+}
+
+#[test]
+fn no_inlining_without_optimization() {
+    let snippet = snippet(Optimization::None);
+
+    assert_eq!(calls(&snippet, "call_small"), 1);
+    assert_eq!(calls(&snippet, "call_medium"), 1);
+}
+
+#[test]
+fn size_only_inlines_small_functions() {
+    let snippet = snippet(Optimization::Size);
+
+    assert_eq!(calls(&snippet, "call_small"), 0);
+    assert_eq!(calls(&snippet, "call_medium"), 1);
+}
+
+#[test]
+fn default_inlines_larger_functions() {
+    let snippet = snippet(Optimization::Default);
+
+    assert_eq!(calls(&snippet, "call_small"), 0);
+    assert_eq!(calls(&snippet, "call_medium"), 0);
+}
+
+#[test]
+fn recursive_function_is_not_inlined_into_itself() {
+    let snippet = snippet(Optimization::Aggressive);
+
+    assert_eq!(calls(&snippet, "count"), 1);
+}
+
+#[test]
+fn calls_in_inlined_bodies_are_not_inlined_again() {
+    let snippet = snippet(Optimization::Aggressive);
+
+    // `count` is inlined once, the recursive call it contains is kept
+    assert_eq!(calls(&snippet, "call_count"), 1);
+}
+
+#[test]
+fn inlined_parameters_are_not_arguments() {
+    let snippet = snippet(Optimization::Default);
+
+    assert_eq!(calls(&snippet, "call_medium"), 0);
+    assert_eq!(args(&snippet, "call_medium"), 1);
+}
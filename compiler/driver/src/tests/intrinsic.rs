@@ -0,0 +1,350 @@
+use std::process::Command;
+
+use base_db::libs::LibKind;
+
+use super::{require, Snippet};
+use crate::Optimization;
+
+/// The imports and helpers which are the same on every backend. `show64` prints a float and
+/// `show_bool` a bool, integers are printed as floats.
+const COMMON: &str = "import Core
+import Intrinsics (transmute, fadd, fsub, fmul, fdiv, frem, feq, fne, flt, fle, fgt, fge)
+import Intrinsics (fconvert, itof, ftoi, floor, ceil, round, sqrt, isub)
+
+show_i32 :: Int32 -> ()
+show_i32 n = show64 (itof n)
+
+show_i64 :: Int64 -> ()
+show_i64 n = show64 (itof n)
+
+show_u8 :: Uint8 -> ()
+show_u8 n = show64 (itof n)
+
+show_bool :: Bool -> ()
+show_bool True  = print \"true\"
+show_bool False = print \"false\"
+
+; unlike `fsub 0.0 x`, this also negates zero
+neg :: Float64 -> Float64
+neg x = fmul x (fsub 0.0 1.0)
+
+nan :: Float64
+nan = fdiv 0.0 0.0
+";
+
+/// Build and run `main`, which formats floats with `Data:Float:to_str`, returning what it printed.
+fn run(main: &str) -> String {
+    let text = format!(
+        "module Main =
+
+{COMMON}
+foreign write :: Int32 -> Ptr Int8 -> Uint -> Int
+
+@main
+main :: ()
+main = unsafe do
+{main}
+    ()
+
+show64 :: Float64 -> ()
+show64 f = unsafe do
+    let s = Data:Float:to_str f
+    print s
+    Data:Float:free_str s
+
+show32 :: Float32 -> ()
+show32 f = unsafe do
+    let s = Data:Float:to_str f
+    print s
+    Data:Float:free_str s
+
+print :: Str -> ()
+print s = unsafe do
+    let ptr, len = transmute s :: Ptr Int8, Uint
+    write 1 ptr len
+    let nl, nl_len = transmute \"\\n\" :: Ptr Int8, Uint
+    write 1 nl nl_len
+    ()
+"
+    );
+
+    let snippet = Snippet::with_opts(&text, &["prim", "core"], Optimization::None);
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+
+    let run = Command::new(snippet.target_dir().join("snippet")).output().unwrap();
+
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    String::from_utf8(run.stdout).unwrap()
+}
+
+#[test]
+fn to_str_is_shortest_round_trip() {
+    let out = run("    show64 0.1
+    show64 (fdiv 1.0 3.0)
+    show64 100.0
+    show64 (transmute (1 :: Uint64))
+    show64 (transmute (9218868437227405311 :: Uint64))
+");
+
+    assert_eq!(out, "0.1\n0.3333333333333333\n100\n5e-324\n1.7976931348623157e+308\n");
+}
+
+#[test]
+fn to_str_uses_single_precision() {
+    let out = run("    show32 0.1
+    show32 (fdiv 1.0 3.0)
+    show32 16777216.0
+");
+
+    assert_eq!(out, "0.1\n0.33333334\n16777216\n");
+}
+
+#[test]
+fn to_str_prints_special_values_like_javascript() {
+    let out = run("    show64 (fdiv 0.0 0.0)
+    show64 (fdiv 1.0 0.0)
+    show32 (fdiv (fsub 0.0 1.0) 0.0)
+");
+
+    assert_eq!(out, "NaN\nInfinity\n-Infinity\n");
+}
+
+/// Build `main` for javascript and run it with node, returning what it printed.
+fn run_js(main: &str) -> String {
+    let text = format!(
+        "module Main =
+
+{COMMON}
+@link_name = \"console.log\"
+foreign print :: Str -> ()
+
+@main
+main :: ()
+main = unsafe do
+{main}
+    ()
+
+show64 :: Float64 -> ()
+show64 f = print (Data:Float:to_str f)
+"
+    );
+
+    let snippet = Snippet::with_target(
+        &text,
+        &["prim", "core"],
+        Optimization::None,
+        LibKind::Executable,
+        Some("javascript"),
+    );
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+
+    require("node");
+
+    let run = Command::new("node")
+        .arg(snippet.target_dir().join("snippet").join("main.js"))
+        .output()
+        .unwrap();
+
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    String::from_utf8(run.stdout).unwrap()
+}
+
+#[test]
+fn to_str_is_the_same_for_llvm_and_javascript() {
+    let main = "    show64 0.000001
+    show64 0.00001
+    show64 0.0000001
+    show64 0.0000012345
+    show64 150000000000000000000.0
+    show64 123456789012345680000.0
+    show64 1000000000000000000000.0
+    show64 (fmul 0.0 (fsub 0.0 1.0))
+    show64 (fdiv 1.0 3.0)
+";
+    let expected = "0.000001
+0.00001
+1e-7
+0.0000012345
+150000000000000000000
+123456789012345680000
+1e+21
+0
+0.3333333333333333
+";
+
+    assert_eq!(run_all(main), expected);
+}
+
+/// Run `main` on both backends, which must print the same.
+fn run_all(main: &str) -> String {
+    let out = run(main);
+
+    assert_eq!(run_js(main), out);
+    out
+}
+
+#[test]
+fn float_arithmetic() {
+    let main = "    show64 (fadd 0.1 0.2)
+    show64 (fsub 0.1 0.3)
+    show64 (fmul 1.1 1.1)
+    show64 (fdiv 1.0 3.0)
+    show64 (fdiv 1.0 (neg 0.0))
+    show64 (frem 5.5 2.0)
+    show64 (frem (neg 5.5) 2.0)
+    show64 (frem 1.0 0.0)
+    show64 (fconvert (fadd (0.1 :: Float32) 0.2))
+    show64 (fconvert (fmul (16777217.0 :: Float32) 1.0))
+";
+    let expected = "0.30000000000000004
+-0.19999999999999998
+1.2100000000000002
+0.3333333333333333
+-Infinity
+1.5
+-1.5
+NaN
+0.30000001192092896
+16777216
+";
+
+    assert_eq!(run_all(main), expected);
+}
+
+#[test]
+fn float_comparisons_are_false_for_nan() {
+    let main = "    show_bool (flt (1.0 :: Float64) 2.0)
+    show_bool (flt (2.0 :: Float64) 1.0)
+    show_bool (feq (0.0 :: Float64) (neg 0.0))
+    show_bool (feq nan nan)
+    show_bool (fne nan nan)
+    show_bool (flt nan 1.0)
+    show_bool (flt 1.0 nan)
+    show_bool (fle nan nan)
+    show_bool (fgt nan 1.0)
+    show_bool (fge 1.0 nan)
+";
+    let expected = "true\nfalse\ntrue\nfalse\ntrue\nfalse\nfalse\nfalse\nfalse\nfalse\n";
+
+    assert_eq!(run_all(main), expected);
+}
+
+#[test]
+fn float_int_conversions_saturate() {
+    let main = "    show64 (itof (7 :: Int32))
+    show64 (itof (isub 0 3 :: Int32))
+    show_i32 (ftoi (2.9 :: Float64))
+    show_i32 (ftoi (neg 2.9))
+    show_i32 (ftoi (10000000000.0 :: Float64))
+    show_i32 (ftoi (neg 10000000000.0))
+    show_i32 (ftoi nan)
+    show_i32 (ftoi (fdiv 1.0 0.0 :: Float64))
+    show_i64 (ftoi (fdiv (neg 1.0) 0.0))
+    show_u8 (ftoi (300.0 :: Float64))
+    show_u8 (ftoi (neg 1.0))
+";
+    let expected = "7
+-3
+2
+-2
+2147483647
+-2147483648
+0
+2147483647
+-9223372036854776000
+255
+0
+";
+
+    assert_eq!(run_all(main), expected);
+}
+
+#[test]
+fn float_rounding_rounds_halfway_away_from_zero() {
+    let main = "    show64 (floor 2.5)
+    show64 (floor (neg 2.5))
+    show64 (ceil 2.5)
+    show64 (ceil (neg 2.5))
+    show64 (round 2.5)
+    show64 (round (neg 2.5))
+    show64 (round 0.5)
+    show64 (round (neg 0.5))
+    show64 (round (neg 0.4))
+    show64 (round 2.4)
+    show64 (sqrt 2.0)
+    show64 (sqrt 16.0)
+    show64 (sqrt (neg 1.0))
+    show64 (fconvert (sqrt (2.0 :: Float32)))
+";
+    let expected = "2
+-3
+3
+-2
+3
+-3
+1
+-1
+0
+2
+1.4142135623730951
+4
+NaN
+1.4142135381698608
+";
+
+    assert_eq!(run_all(main), expected);
+}
+
+#[test]
+fn float_members_of_core() {
+    let main = "    let a = 1.5 :: Float64
+    let b = 0.25 :: Float64
+    show64 (a + b)
+    show64 (a - b)
+    show64 (a * b)
+    show64 (a / b)
+    show64 (a % b)
+    show_bool (a == b)
+    show_bool (a != b)
+    show_bool (a < b)
+    show_bool (a >= b)
+    show_bool (nan == nan)
+    show_bool (nan != nan)
+    show64 (default :: Float64)
+    show64 (Data:Float:floor (neg a))
+    show64 (Data:Float:ceil (neg a))
+    show64 (Data:Float:round (neg 2.5))
+    show64 (Data:Float:sqrt 0.25)
+    show_i32 (Data:Float:truncate (neg 7.9))
+    show64 (Data:Float:to_float (5 :: Int32))
+    show_i32 (cast (neg 2.9))
+    show64 (cast (3 :: Int32))
+    show64 (cast (fconvert (0.5 :: Float64) :: Float32))
+";
+    let expected = "1.75
+1.25
+0.375
+6
+0
+false
+true
+false
+true
+false
+true
+0
+-2
+-1
+-3
+0.5
+-7
+5
+-2
+3
+0.5
+";
+
+    assert_eq!(run_all(main), expected);
+}
@@ -0,0 +1,197 @@
+use std::fs;
+use std::process::Command;
+
+use base_db::libs::LibKind;
+
+use super::{require, Snippet};
+use crate::Optimization;
+
+const SOURCE: &str = "module Main =
+
+import Prim
+import Intrinsics (iadd, isub)
+
+@link_name = \"console.log\"
+foreign log :: Int -> ()
+
+type Nat =
+    | Zero
+    | Succ Nat
+
+class Fold t =
+    fold :: forall b. t -> b -> (b -> b) -> b
+
+member Nat of Fold =
+    fold Zero acc _ = acc
+    fold (Succ n) acc f = fold n (f acc) f
+
+build :: Int -> Nat -> Nat
+build 0 acc = acc
+build n acc = build (isub n 1) (Succ acc)
+
+even :: Int -> Int
+even 0 = 1
+even n = odd (isub n 1)
+
+odd :: Int -> Int
+odd 0 = 0
+odd n = even (isub n 1)
+
+@main
+main :: ()
+main =
+    log (fold (build 100000 Zero) 0 (fn n -> iadd n 1))
+    log (even 100001)
+    ()
+";
+
+fn snippet() -> Snippet {
+    let snippet = Snippet::with_target(
+        SOURCE,
+        &["prim"],
+        Optimization::None,
+        LibKind::Executable,
+        Some("javascript"),
+    );
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+    snippet
+}
+
+/// The generated modules of the snippet, without the entry point and the index.
+fn output(snippet: &Snippet) -> String {
+    lib_output(snippet, "snippet")
+}
+
+/// The generated modules of the library `lib`, without the entry point and the index.
+fn lib_output(snippet: &Snippet, lib: &str) -> String {
+    let mut out = String::new();
+
+    for entry in fs::read_dir(snippet.target_dir().join(lib)).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy();
+
+        if name.ends_with(".js") && name != "main.js" && name != "index.js" {
+            out.push_str(&fs::read_to_string(&path).unwrap());
+        }
+    }
+
+    out
+}
+
+/// Only `main` is exported, everything else is emitted only when `main` reaches it.
+const REACHABLE_SOURCE: &str = "module Main (main) =
+
+import Core
+import Intrinsics (isub)
+
+@link_name = \"console.log\"
+foreign log :: Int -> ()
+
+unused :: Int -> Int
+unused n = isub n 1
+
+even :: Int -> Int
+even 0 = 1
+even n = odd (isub n 1)
+
+odd :: Int -> Int
+odd 0 = 0
+odd n = even (isub n 1)
+
+ping :: Int -> Int
+ping 0 = 0
+ping n = pong (isub n 1)
+
+pong :: Int -> Int
+pong 0 = 1
+pong n = ping (isub n 1)
+
+pick :: Int -> Int -> Int
+pick a b if a < b = even a
+         else     = 0
+
+@main
+main :: ()
+main = log (pick 1 2)
+";
+
+fn reachable_snippet() -> Snippet {
+    let snippet = Snippet::with_target(
+        REACHABLE_SOURCE,
+        &["prim", "core"],
+        Optimization::None,
+        LibKind::Executable,
+        Some("javascript"),
+    );
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+    snippet
+}
+
+#[test]
+fn unreachable_definitions_are_not_emitted() {
+    let snippet = reachable_snippet();
+    let out = output(&snippet);
+    let core = lib_output(&snippet, "core");
+
+    assert!(out.contains("Main$pick"), "{}", out);
+    assert!(!out.contains("Main$unused"), "{}", out);
+    assert!(!out.contains("Main$ping"), "{}", out);
+
+    // `<` reaches `lt` and the `Ord` member of integers, nothing else of `core` is used.
+    assert!(core.contains("Core$Cmp$lt"), "{}", core);
+    assert!(!core.contains("Core$Cmp$le"), "{}", core);
+    assert!(!core.contains("Data$Int$to_be"), "{}", core);
+    assert!(core.contains("Ord$2"), "{}", core);
+    assert!(!core.contains("Sub$2"), "{}", core);
+    assert!(!core.contains("Default$2"), "{}", core);
+}
+
+#[test]
+fn definitions_used_by_members_are_emitted() {
+    let core = lib_output(&reachable_snippet(), "core");
+
+    // `Gt` is only used in the `cmp` item of the `Ord` member, `lt` itself only matches `Lt`.
+    assert!(core.contains("Core$Cmp$Gt"), "{}", core);
+}
+
+#[test]
+fn reachable_recursive_functions_keep_their_trampolines() {
+    let out = output(&reachable_snippet());
+
+    assert!(out.contains("Main$even = $trampoline("), "{}", out);
+    assert!(out.contains("Main$odd = $trampoline("), "{}", out);
+    assert_eq!(out.matches("= $trampoline(").count(), 2, "{}", out);
+}
+
+#[test]
+fn self_recursion_loops() {
+    let out = output(&snippet());
+
+    // Both `build` and the `fold` item of the member call themselves.
+    assert_eq!(out.matches("continue").count(), 2, "{}", out);
+}
+
+#[test]
+fn mutual_recursion_is_trampolined() {
+    let out = output(&snippet());
+
+    assert_eq!(out.matches("= $trampoline(").count(), 2, "{}", out);
+    assert!(out.contains("new $TailCall("), "{}", out);
+}
+
+#[test]
+fn deep_recursion_does_not_overflow() {
+    require("node");
+
+    let snippet = snippet();
+    let run = Command::new("node")
+        .arg(snippet.target_dir().join("snippet").join("main.js"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(run.stdout).unwrap();
+
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert!(stdout.starts_with("100000\n0\n"), "{}", stdout);
+}
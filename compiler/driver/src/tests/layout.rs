@@ -0,0 +1,118 @@
+use std::process::Command;
+
+use super::Snippet;
+use crate::Optimization;
+
+/// Constructs every variant of enums whose tag is stored in a niche of their data and matches on
+/// them, printing which variant was found.
+const SOURCE: &str = "module Main =
+
+import Core
+import Intrinsics (transmute, addr_of, ptr_read, ieq)
+
+foreign write :: Int32 -> Ptr Int8 -> Uint -> Int
+
+type Choice =
+    | Left
+    | Right
+    | Both Bool
+
+@main
+main :: ()
+main = unsafe do
+    let x = 42 :: Int32
+    let ptr = addr_of x :: Ptr Int32
+    print (show_ptr (Some ptr))
+    print (show_ptr None)
+    print (show_bool (Some True))
+    print (show_bool (Some False))
+    print (show_bool None)
+    print (show_nested (Some (Some True)))
+    print (show_nested (Some (Some False)))
+    print (show_nested (Some None))
+    print (show_nested None)
+    print (show_nested (wrap (ieq x 42)))
+    print (show_choice Left)
+    print (show_choice Right)
+    print (show_choice (Both True))
+    print (show_choice (Both False))
+    ()
+
+show_ptr :: Option (Ptr Int32) -> Str
+show_ptr opt = case opt of
+    Some p -> show_int (unsafe (ptr_read p))
+    None -> \"None\"
+
+show_int :: Int32 -> Str
+show_int 42 = \"Some 42\"
+show_int _  = \"Some ?\"
+
+show_bool :: Option Bool -> Str
+show_bool opt = case opt of
+    Some True -> \"Some True\"
+    Some False -> \"Some False\"
+    None -> \"None\"
+
+show_nested :: Option (Option Bool) -> Str
+show_nested opt = case opt of
+    Some (Some True) -> \"Some (Some True)\"
+    Some (Some False) -> \"Some (Some False)\"
+    Some None -> \"Some None\"
+    None -> \"None\"
+
+;; constructs the variants at runtime
+wrap :: Bool -> Option (Option Bool)
+wrap b = Some (Some b)
+
+show_choice :: Choice -> Str
+show_choice c = case c of
+    Left -> \"Left\"
+    Right -> \"Right\"
+    Both True -> \"Both True\"
+    Both False -> \"Both False\"
+
+print :: Str -> ()
+print s = unsafe do
+    let ptr, len = transmute s :: Ptr Int8, Uint
+    write 1 ptr len
+    let nl, nl_len = transmute \"\\n\" :: Ptr Int8, Uint
+    write 1 nl nl_len
+    ()
+";
+
+const OUTPUT: &str = "Some 42
+None
+Some True
+Some False
+None
+Some (Some True)
+Some (Some False)
+Some None
+None
+Some (Some True)
+Left
+Right
+Both True
+Both False
+";
+
+fn run(optimization: Optimization) -> String {
+    let snippet = Snippet::with_opts(SOURCE, &["prim", "core"], optimization);
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+
+    let run = Command::new(snippet.target_dir().join("snippet")).output().unwrap();
+
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    String::from_utf8(run.stdout).unwrap()
+}
+
+#[test]
+fn niche_encoded_enums_match_every_variant() {
+    assert_eq!(run(Optimization::None), OUTPUT);
+}
+
+#[test]
+fn niche_encoded_enums_match_every_variant_when_optimized() {
+    assert_eq!(run(Optimization::Aggressive), OUTPUT);
+}
@@ -0,0 +1,166 @@
+use base_db::libs::LibKind;
+use diagnostics::{DiagnosticForWith, Level};
+use hir::diagnostic::DiagnosticSink;
+
+use super::Snippet;
+use crate::Optimization;
+
+/// The code, message and level of every lint reported in the snippet `text`, sorted.
+fn lints(text: &str) -> Vec<(String, String, Level)> {
+    let snippet = Snippet::with_output(text, &["prim"], Optimization::None, LibKind::Static);
+    let db = &snippet.driver.db;
+    let lib = snippet.func("run").lib(db);
+    let mut lints = Vec::new();
+
+    for module in lib.modules(db) {
+        let mut sink = DiagnosticSink::new(|d| {
+            if d.code().as_str().starts_with('W') {
+                lints.push((d.code().to_string(), d.message(), d.with_diagnostic(db, |d| d.level())));
+            }
+        });
+
+        module.diagnostics(db, &mut sink);
+    }
+
+    lints.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+    lints
+}
+
+fn lint(code: &str, message: &str, level: Level) -> (String, String, Level) {
+    (code.to_string(), message.to_string(), level)
+}
+
+#[test]
+fn unused_imports() {
+    let lints = lints(
+        "module Main (run) =
+
+import Prim (Int)
+import Intrinsics (iadd, isub)
+
+run :: Int -> Int
+run x = iadd x 1
+",
+    );
+
+    assert_eq!(lints, [lint("W0001", "unused import `isub`", Level::Warning)]);
+}
+
+#[test]
+fn unused_bindings() {
+    let lints = lints(
+        "module Main (run) =
+
+import Prim (Int)
+
+run :: Int -> Int -> Int -> Int
+run x y _z = x
+",
+    );
+
+    assert_eq!(lints, [lint("W0002", "unused binding `y`", Level::Warning)]);
+}
+
+#[test]
+fn unused_items() {
+    let lints = lints(
+        "module Main (run) =
+
+import Prim (Int)
+
+run :: Int -> Int
+run x = helper x
+
+helper :: Int -> Int
+helper x = x
+
+unused :: Int -> Int
+unused x = x
+
+type Unused = | Unused
+",
+    );
+
+    assert_eq!(lints, [
+        lint("W0003", "function `unused` is never used", Level::Warning),
+        lint("W0003", "type `Unused` is never used", Level::Warning),
+    ]);
+}
+
+#[test]
+fn shadowed_names() {
+    let lints = lints(
+        "module Main (run) =
+
+import Prim (Int)
+import Intrinsics (iadd)
+
+run :: Int -> Int
+run x = do
+    let x = iadd x 1
+    let _x = x
+    x
+",
+    );
+
+    assert_eq!(lints, [lint("W0004", "`x` shadows an earlier binding", Level::Warning)]);
+}
+
+#[test]
+fn names_used_from_nested_modules_are_used() {
+    let lints = lints(
+        "module Main (run) =
+
+import Prim (Int)
+import Intrinsics (iadd, isub)
+
+run :: Int -> Int
+run x = iadd x 1
+
+helper :: Int -> Int
+helper x = iadd x 2
+
+module Child (twice) =
+    twice :: Int -> Int
+    twice x = helper (isub x 1)
+",
+    );
+
+    assert!(lints.is_empty(), "{:?}", lints);
+}
+
+#[test]
+fn lint_levels_are_set_by_attributes() {
+    let lints = lints(
+        "module Main (run) =
+
+import Prim (Int)
+
+@deny(unused_bindings)
+run :: Int -> Int -> Int
+run x y = x
+
+@allow(unused_items)
+allowed :: Int -> Int
+allowed x = x
+
+@deny(unused_items)
+module Child (used) =
+    used :: Int -> Int
+    used x = x
+
+    denied :: Int -> Int
+    denied x = x
+
+    @warn(unused_items)
+    warned :: Int -> Int
+    warned x = x
+",
+    );
+
+    assert_eq!(lints, [
+        lint("W0002", "unused binding `y`", Level::Error),
+        lint("W0003", "function `denied` is never used", Level::Error),
+        lint("W0003", "function `warned` is never used", Level::Warning),
+    ]);
+}
@@ -0,0 +1,135 @@
+use std::fs;
+
+use hir::{DefWithBody, HirDisplay};
+use mir::db::MirDatabase;
+use mir::syntax::{Body, Rvalue, Stmt};
+use paths::AbsPathBuf;
+
+use super::Snippet;
+use crate::emit::{self, Emit};
+
+const HEADER: &str = "module Main =\n\nimport Prim\nimport Intrinsics (iadd)\n\n";
+
+fn snippet(text: &str) -> Snippet {
+    Snippet::new(&format!("{}{}", HEADER, text))
+}
+
+/// The number of values captured by every closure created in `body`, in order.
+fn captures(db: &dyn MirDatabase, body: Body) -> Vec<usize> {
+    let data = db.lookup_intern_body(body);
+
+    data.blocks
+        .iter()
+        .flat_map(|(_, block)| block.stmts.iter())
+        .filter_map(|stmt| match stmt {
+            | Stmt::Assign(_, Rvalue::Closure(_, captures)) => Some(captures.len()),
+            | _ => None,
+        })
+        .collect()
+}
+
+fn body_of(snippet: &Snippet, name: &str) -> Body {
+    snippet.driver.db.body_mir(DefWithBody::Func(snippet.func(name)).into())
+}
+
+#[test]
+fn lambda_captures_used_locals() {
+    let snippet = snippet("adder :: Int -> Int -> Int\nadder x = fn y -> iadd x y\n");
+    let body = body_of(&snippet, "adder");
+
+    assert_eq!(captures(&snippet.driver.db, body), vec![1]);
+}
+
+#[test]
+fn lambda_captures_each_local_once() {
+    let snippet = snippet("twice :: Int -> Int -> Int\ntwice x = fn y -> iadd (iadd x y) x\n");
+    let body = body_of(&snippet, "twice");
+
+    assert_eq!(captures(&snippet.driver.db, body), vec![1]);
+}
+
+#[test]
+fn lambda_without_locals_captures_nothing() {
+    let snippet = snippet("second :: Int -> Int -> Int\nsecond x = fn y -> y\n");
+    let body = body_of(&snippet, "second");
+
+    assert_eq!(captures(&snippet.driver.db, body), vec![0]);
+}
+
+#[test]
+fn nested_lambda_captures_outer_params() {
+    let snippet = snippet("add3 :: Int -> Int -> Int -> Int\nadd3 x = fn y -> fn z -> iadd x (iadd y z)\n");
+    let db = &snippet.driver.db;
+    let body = body_of(&snippet, "add3");
+    let closures = body.closures(db);
+
+    assert_eq!(captures(db, body), vec![1]);
+    assert_eq!(closures.len(), 2);
+    assert_eq!(captures(db, closures[0]), vec![2]);
+}
+
+#[test]
+fn lambda_params_are_curried() {
+    let snippet = snippet("add :: Int -> Int -> Int\nadd = fn x y -> iadd x y\n");
+    let db = &snippet.driver.db;
+    let body = body_of(&snippet, "add");
+    let closures = body.closures(db);
+
+    // the first step adds `x` to the environment of the closure taking `y`
+    assert_eq!(captures(db, body), vec![0]);
+    assert_eq!(closures.len(), 2);
+    assert_eq!(captures(db, closures[0]), vec![1]);
+}
+
+#[test]
+fn emitted_mir_includes_closure_bodies() {
+    let snippet = snippet("add3 :: Int -> Int -> Int -> Int\nadd3 x = fn y -> fn z -> iadd x (iadd y z)\n");
+    let db = &snippet.driver.db;
+    let dir = tempfile::TempDir::new().unwrap();
+    let out_dir = AbsPathBuf::assert(dir.path().to_path_buf());
+    let lib = snippet.func("add3").lib(db);
+    let files = emit::emit(db, lib, &[Emit::Mir], &out_dir).unwrap();
+    let text = fs::read_to_string(&files[0]).unwrap();
+    let closures = db.optimized_body(body_of(&snippet, "add3")).closures(db);
+
+    assert_eq!(closures.len(), 2);
+
+    for closure in closures {
+        let closure = db.lookup_intern_body(db.optimized_body(closure));
+
+        assert!(text.contains(&closure.display(db).to_string()));
+    }
+}
+
+/// The locals dropped in the optimized `body`.
+fn drops(db: &dyn MirDatabase, body: Body) -> usize {
+    let data = db.lookup_intern_body(db.optimized_body(body));
+
+    data.blocks
+        .iter()
+        .flat_map(|(_, block)| block.stmts.iter())
+        .filter(|stmt| matches!(stmt, Stmt::Drop(_)))
+        .count()
+}
+
+#[test]
+fn borrowed_closure_is_dropped() {
+    let snippet =
+        snippet("apply :: (Int -> Int) -> Int\napply f = f 1\n\nadd :: Int -> Int\nadd x = apply (fn y -> iadd x y)\n");
+
+    assert_eq!(drops(&snippet.driver.db, body_of(&snippet, "add")), 1);
+}
+
+#[test]
+fn closure_without_captures_is_not_dropped() {
+    let snippet = snippet("apply :: (Int -> Int) -> Int\napply f = f 1\n\ninc :: Int\ninc = apply (fn y -> iadd y 1)\n");
+
+    assert_eq!(drops(&snippet.driver.db, body_of(&snippet, "inc")), 0);
+}
+
+#[test]
+fn escaping_closure_is_not_dropped() {
+    let snippet = snippet("adder :: Int -> Int -> Int\nadder x = fn y -> iadd x y\n");
+
+    assert_eq!(drops(&snippet.driver.db, body_of(&snippet, "adder")), 0);
+}
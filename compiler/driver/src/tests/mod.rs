@@ -0,0 +1,122 @@
+mod build_script;
+mod cache;
+mod debug_info;
+mod diagnostics;
+mod doc;
+mod doctest;
+mod emit;
+mod export;
+mod foreign;
+mod harness;
+mod inline;
+mod intrinsic;
+mod js;
+mod layout;
+mod lints;
+mod mir;
+mod scaffold;
+mod wasm;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use base_db::libs::LibKind;
+use codegen::db::CodegenDatabase;
+use hir::db::HirDatabase;
+use hir::ModuleDef;
+use paths::AbsPathBuf;
+use project::manifest::Cfg;
+
+use crate::{Driver, InitNoManifestOpts, MessageFormat, Optimization};
+
+/// A single source file compiled as a library named `snippet`, which depends on the
+/// libraries of the repository. Its artifacts are written to a temporary target directory.
+pub struct Snippet {
+    pub driver: Driver,
+    pub ws: usize,
+    dir: tempfile::TempDir,
+}
+
+impl Snippet {
+    pub fn new(text: &str) -> Self {
+        Self::with_opts(text, &["prim"], Optimization::None)
+    }
+
+    pub fn with_opts(text: &str, deps: &[&str], optimization: Optimization) -> Self {
+        Self::with_output(text, deps, optimization, LibKind::Executable)
+    }
+
+    pub fn with_output(text: &str, deps: &[&str], optimization: Optimization, output: LibKind) -> Self {
+        Self::with_target(text, deps, optimization, output, None)
+    }
+
+    pub fn with_target(
+        text: &str,
+        deps: &[&str],
+        optimization: Optimization,
+        output: LibKind,
+        target: Option<&str>,
+    ) -> Self {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("main.fi");
+        let deps = deps.iter().map(|d| lib_dir(d)).collect::<Vec<_>>();
+
+        fs::write(&file, text).unwrap();
+
+        let (mut driver, ws) = Driver::init_without_manifest(InitNoManifestOpts {
+            files: vec![&file],
+            name: "snippet",
+            target,
+            output,
+            optimization,
+            debug_info: false,
+            cfg: Cfg::default(),
+            links: Vec::new(),
+            dependencies: deps.iter().map(PathBuf::as_path).collect(),
+            message_format: MessageFormat::Human,
+        })
+        .unwrap();
+
+        driver.target_dir = AbsPathBuf::assert(dir.path().join("target"));
+        driver.db.set_target_dir(driver.target_dir.clone());
+
+        Self { driver, ws, dir }
+    }
+
+    /// The directory the artifacts of the snippet are written to.
+    pub fn target_dir(&self) -> PathBuf {
+        self.dir.path().join("target")
+    }
+
+    pub fn db(&self) -> &dyn HirDatabase {
+        &self.driver.db
+    }
+
+    /// The function of the snippet named `name`.
+    pub fn func(&self, name: &str) -> hir::Func {
+        let db = self.db();
+
+        hir::Lib::all(db)
+            .into_iter()
+            .filter(|lib| lib.name(db).to_string() == "snippet")
+            .flat_map(|lib| lib.modules(db))
+            .flat_map(|module| module.declarations(db))
+            .find_map(|def| match def {
+                | ModuleDef::Func(func) if func.name(db).to_string() == name => Some(func),
+                | _ => None,
+            })
+            .unwrap_or_else(|| panic!("no function named `{}`", name))
+    }
+}
+
+/// Fails the test when `program` is not installed, rather than passing without running it.
+pub fn require(program: &str) {
+    if Command::new(program).arg("--version").output().is_err() {
+        panic!("this test requires `{}` to be installed", program);
+    }
+}
+
+fn lib_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../lib").join(name)
+}
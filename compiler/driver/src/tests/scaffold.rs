@@ -0,0 +1,102 @@
+use std::fs;
+
+use base_db::libs::LibKind;
+use base_db::target::CompilerTarget;
+use paths::AbsPathBuf;
+use project::lockfile::Lockfile;
+use project::manifest::{Cfg, Dependency, Manifest};
+use project::scaffold::{self, Template};
+
+use crate::{Driver, InitOpts, MessageFormat, Optimization};
+
+/// Create a project from the template in a temporary directory, check its manifest and load
+/// and check the project, which resolves its registry dependency without a registry.
+fn create(lib: bool, target: CompilerTarget) {
+    let dir = tempfile::TempDir::new().unwrap();
+    let template = Template {
+        lib,
+        target: target.clone(),
+    };
+
+    let created = scaffold::create(dir.path(), "my-app", &template).unwrap();
+
+    assert_eq!(created.len(), 3, "{:?}", created);
+
+    let manifest = Manifest::parse(&fs::read_to_string(dir.path().join(Manifest::FILE_NAME)).unwrap()).unwrap();
+    let project = manifest.project().unwrap();
+    let (output, std) = match (lib, &target) {
+        | (false, CompilerTarget::Javascript) => (LibKind::Executable, "js"),
+        | (false, CompilerTarget::Native(_)) => (LibKind::Executable, "core"),
+        | (true, CompilerTarget::Javascript) => (LibKind::Dynamic, "js"),
+        | (true, CompilerTarget::Native(_)) => (LibKind::Dynamic, "core"),
+    };
+
+    assert_eq!(project.name, "my-app");
+    assert_eq!(project.output, output);
+    assert_eq!(manifest.dependencies.len(), 1);
+    assert!(
+        matches!(&manifest.dependencies[std], Dependency::Registry { version, .. } if version == "0.1"),
+        "{:?}",
+        manifest.dependencies
+    );
+
+    let target = match target {
+        | CompilerTarget::Javascript => Some("javascript"),
+        | CompilerTarget::Native(_) => None,
+    };
+
+    // No registry is set up, so the standard library resolves to the one in the repository.
+    let (driver, ws) = Driver::init(InitOpts {
+        input: dir.path(),
+        target,
+        output: None,
+        optimization: Optimization::None,
+        debug_info: false,
+        cfg: Cfg::default(),
+        packages: Vec::new(),
+        features: Vec::new(),
+        no_default_features: false,
+        message_format: MessageFormat::Human,
+    })
+    .unwrap();
+
+    assert!(driver.check(ws).unwrap());
+
+    let lockfile = Lockfile::load(&AbsPathBuf::assert(dir.path().to_path_buf())).unwrap();
+
+    assert_eq!(lockfile.get(std, "std").unwrap().version.as_deref(), Some("0.1.0"));
+}
+
+#[test]
+fn native_executable() {
+    create(false, CompilerTarget::default());
+}
+
+#[test]
+fn native_library() {
+    create(true, CompilerTarget::default());
+}
+
+#[test]
+fn javascript_executable() {
+    create(false, CompilerTarget::Javascript);
+}
+
+#[test]
+fn javascript_library() {
+    create(true, CompilerTarget::Javascript);
+}
+
+#[test]
+fn existing_manifest_is_rejected() {
+    let dir = tempfile::TempDir::new().unwrap();
+
+    fs::write(dir.path().join(Manifest::FILE_NAME), "").unwrap();
+
+    let template = Template {
+        lib: false,
+        target: CompilerTarget::default(),
+    };
+
+    assert!(scaffold::create(dir.path(), "app", &template).is_err());
+}
@@ -0,0 +1,149 @@
+use std::fs;
+use std::process::Command;
+
+use base_db::libs::LibKind;
+
+use super::{require, Snippet};
+use crate::Optimization;
+
+const SOURCE: &str = "module Main =
+
+import Prim
+import Intrinsics (unsafe, transmute, fdiv)
+
+@main
+main :: ()
+main = unsafe do
+    print_float 0.1
+    print_float (fdiv 1.0 3.0)
+    print_float 100.0
+    print_float 0.000001
+    print_float (transmute (1 :: Uint64))
+    print_float (transmute (9218868437227405311 :: Uint64))
+
+print_float :: Float64 -> ()
+print_float f = unsafe do
+    let s = Data:Float:to_str f
+    let ptr, len = transmute s :: Ptr Int8, Uint
+    LibC:write LibC:STDOUT_FILENO ptr len
+    Data:Float:free_str s
+    let nl, nl_len = transmute \"\\n\" :: Ptr Int8, Uint
+    LibC:write LibC:STDOUT_FILENO nl nl_len
+    ()
+";
+
+const OUTPUT: &str = "0.1
+0.3333333333333333
+100
+0.000001
+5e-324
+1.7976931348623157e+308
+";
+
+/// The module of the snippet built for `target`, which must be valid.
+fn build(source: &str, deps: &[&str], target: &str) -> (Snippet, Vec<u8>) {
+    require("wasm-ld");
+
+    let snippet = Snippet::with_target(source, deps, Optimization::None, LibKind::Executable, Some(target));
+
+    assert!(snippet.driver.build(snippet.ws).unwrap());
+
+    let path = snippet.target_dir().join("snippet.wasm");
+    let module = fs::read(&path).unwrap();
+
+    if let Err(e) = wasmparser::validate(&module) {
+        panic!("{} is not a valid module: {}", path.display(), e);
+    }
+
+    (snippet, module)
+}
+
+#[test]
+fn wasi_executable_is_valid() {
+    let (snippet, _) = build(SOURCE, &["prim", "core", "wasi"], "wasm32-wasi");
+
+    require("wasmtime");
+
+    let run = Command::new("wasmtime")
+        .arg(snippet.target_dir().join("snippet.wasm"))
+        .output()
+        .unwrap();
+
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), OUTPUT);
+}
+
+const BARE_SOURCE: &str = "module Main =
+
+import Prim
+import Intrinsics (iadd)
+
+@wasm_import_module = \"env\"
+@link_name = \"log\"
+foreign log :: Int32 -> ()
+
+@main
+main :: ()
+main = log (iadd 1 2)
+";
+
+#[test]
+fn bare_executable_imports_from_host() {
+    let (_, module) = build(BARE_SOURCE, &["prim"], "wasm32-unknown-unknown");
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+
+    for payload in wasmparser::Parser::new(0).parse_all(&module) {
+        match payload.unwrap() {
+            | wasmparser::Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import.unwrap();
+
+                    imports.push(format!("{}.{}", import.module, import.name));
+                }
+            },
+            | wasmparser::Payload::ExportSection(reader) => {
+                for export in reader {
+                    exports.push(export.unwrap().name.to_string());
+                }
+            },
+            | _ => {},
+        }
+    }
+
+    assert!(imports.contains(&"env.log".to_string()), "{:?}", imports);
+    assert!(exports.contains(&"main".to_string()), "{:?}", exports);
+}
+
+const LAMBDA_SOURCE: &str = "module Main =
+
+import Prim
+import Intrinsics (iadd)
+
+@wasm_import_module = \"env\"
+@link_name = \"log\"
+foreign log :: Int32 -> ()
+
+@main
+main :: ()
+main = do
+    let inc = (fn y -> iadd y 1) :: Int32 -> Int32
+    log (inc 2)
+";
+
+#[test]
+fn bare_executable_calls_local_lambda() {
+    // The lambda captures nothing, so it has no environment to allocate or free.
+    let (_, module) = build(LAMBDA_SOURCE, &["prim"], "wasm32-unknown-unknown");
+    let mut imports = Vec::new();
+
+    for payload in wasmparser::Parser::new(0).parse_all(&module) {
+        if let wasmparser::Payload::ImportSection(reader) = payload.unwrap() {
+            for import in reader {
+                imports.push(import.unwrap().name.to_string());
+            }
+        }
+    }
+
+    assert_eq!(imports, vec![String::from("log")]);
+}
@@ -82,9 +82,14 @@ impl Interactive {
 
     fn report(&mut self) -> bool {
         self.driver.db.set_file_text(self.file, self.text().into());
-        driver::diagnostics::emit_diagnostics(&self.driver.db, self.lib.into(), &mut std::io::stderr())
-            .map(|n| n > 0)
-            .unwrap()
+        driver::diagnostics::emit_diagnostics(
+            &self.driver.db,
+            self.lib.into(),
+            driver::MessageFormat::Human,
+            &mut std::io::stderr(),
+        )
+        .map(|n| n > 0)
+        .unwrap()
     }
 
     fn add_import(&mut self, text: &str) {
@@ -7,10 +7,12 @@ use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 use base_db::libs::LibKind;
+use base_db::target::CompilerTarget;
 use base_db::{Error, ICE};
 use clap::{Args, Parser, Subcommand};
-use driver::{Driver, InitNoManifestOpts, InitOpts, Optimization};
+use driver::{Driver, Emit, InitNoManifestOpts, InitOpts, MessageFormat, Optimization};
 use project::manifest::{Cfg, TomlValue};
+use project::scaffold::{self, Template};
 use tracing::{debug, Level};
 use tracing_subscriber::EnvFilter;
 
@@ -42,6 +44,9 @@ struct CliArgs {
     #[clap(short = 'O', global = true, value_parser = parse_optimization)]
     optimization: Option<Optimization>,
 
+    #[clap(short = 'g', long = "debug", global = true)]
+    debug_info: bool,
+
     #[clap(long = "link", short, requires = "files")]
     links: Vec<PathBuf>,
 
@@ -51,6 +56,21 @@ struct CliArgs {
     #[clap(long, global = true, value_parser = parse_cfg)]
     cfg: Vec<(String, TomlValue)>,
 
+    /// The workspace members to act on, defaults to all members.
+    #[clap(long = "package", short = 'p', global = true)]
+    packages: Vec<String>,
+
+    /// Features to enable, separated by commas or spaces.
+    #[clap(long, global = true)]
+    features: Vec<String>,
+
+    #[clap(long = "no-default-features", global = true)]
+    no_default_features: bool,
+
+    /// How diagnostics are printed: `human` or `json`, one object per line on stdout.
+    #[clap(long = "message-format", global = true, value_parser = parse_message_format)]
+    message_format: Option<MessageFormat>,
+
     #[clap(short = 'v', long, global = true, action = clap::ArgAction::Count)]
     verbose: u8,
 
@@ -61,10 +81,18 @@ struct CliArgs {
 #[derive(Subcommand, Debug, Clone)]
 enum BasicCommands {
     Check(CheckArgs),
+    /// Apply the suggested fixes of diagnostics to the source files.
+    Fix(FixArgs),
     Build(BuildArgs),
 
     #[clap(trailing_var_arg = true)]
     Run(RunArgs),
+    /// Build the test harness of the selected packages and run their `@test` functions.
+    Test(TestArgs),
+    /// Format the source files of the selected packages.
+    Fmt(FmtArgs),
+    /// Generate the documentation of the selected packages and their dependencies.
+    Doc(DocArgs),
 }
 
 #[derive(Subcommand, Debug)]
@@ -73,6 +101,11 @@ enum Commands {
     Basic(BasicCommands),
     Watch(WatchArgs),
     Lsp(LspArgs),
+    Explain(ExplainArgs),
+    /// Create a new package in a new directory.
+    New(NewArgs),
+    /// Create a new package in an existing directory.
+    Init(InitArgs),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -81,10 +114,21 @@ struct CheckArgs {
     input: PathBuf,
 }
 
+#[derive(Args, Debug, Clone)]
+struct FixArgs {
+    #[clap(default_value = ".", value_hint = clap::ValueHint::DirPath)]
+    input: PathBuf,
+}
+
 #[derive(Args, Debug, Clone)]
 struct BuildArgs {
     #[clap(default_value = ".", value_hint = clap::ValueHint::DirPath)]
     input: PathBuf,
+
+    /// Also write these representations of every module to `target/emit`, separated by commas:
+    /// `ast`, `hir`, `types`, `mir`, `llvm-ir`, `asm`, `js` or `obj`.
+    #[clap(long, value_delimiter = ',', value_parser = parse_emit)]
+    emit: Vec<Emit>,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -96,6 +140,32 @@ struct RunArgs {
     args: Vec<String>,
 }
 
+#[derive(Args, Debug, Clone)]
+struct TestArgs {
+    #[clap(default_value = ".", value_hint = clap::ValueHint::DirPath)]
+    input: PathBuf,
+
+    /// Only run the tests whose path contains one of these filters.
+    #[clap(long = "filter", short = 'f')]
+    filters: Vec<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+struct FmtArgs {
+    #[clap(default_value = ".", value_hint = clap::ValueHint::DirPath)]
+    input: PathBuf,
+
+    /// Report the files which are not formatted instead of formatting them.
+    #[clap(long)]
+    check: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+struct DocArgs {
+    #[clap(default_value = ".", value_hint = clap::ValueHint::DirPath)]
+    input: PathBuf,
+}
+
 #[derive(Args, Debug)]
 struct WatchArgs {
     #[clap(subcommand)]
@@ -108,6 +178,34 @@ struct LspArgs {
     input: PathBuf,
 }
 
+#[derive(Args, Debug)]
+struct NewArgs {
+    /// The directory to create, its name is the name of the package.
+    #[clap(value_hint = clap::ValueHint::DirPath)]
+    path: PathBuf,
+
+    /// Create a library instead of an executable.
+    #[clap(long)]
+    lib: bool,
+}
+
+#[derive(Args, Debug)]
+struct InitArgs {
+    /// The directory to create the package in, its name is the name of the package.
+    #[clap(default_value = ".", value_hint = clap::ValueHint::DirPath)]
+    input: PathBuf,
+
+    /// Create a library instead of an executable.
+    #[clap(long)]
+    lib: bool,
+}
+
+#[derive(Args, Debug)]
+struct ExplainArgs {
+    /// A diagnostic code, like `E0012`.
+    code: String,
+}
+
 fn main() -> anyhow::Result<ExitCode> {
     let cli = Cli::parse();
     let filter = cli
@@ -187,11 +285,14 @@ fn run_cli(cli: Cli) -> anyhow::Result<ExitCode> {
     match command {
         | Some(command) => match command {
             | Commands::Basic(command) => {
-                let (driver, ws) = setup_basic(args, &command)?;
-                run_basic(&driver, ws, &command).map_err(Into::into)
+                let (mut driver, ws) = setup_basic(args, &command)?;
+                run_basic(&mut driver, ws, &command).map_err(Into::into)
             },
             | Commands::Watch(watch) => run_watch(args, watch).map(|_| ExitCode::SUCCESS),
             | Commands::Lsp(lsp) => run_lsp(args, lsp).map(|_| ExitCode::SUCCESS),
+            | Commands::Explain(explain) => run_explain(explain),
+            | Commands::New(new) => run_new(args, new),
+            | Commands::Init(init) => create_package(args.target.as_deref(), &init.input, init.lib),
         },
         | None if !args.files.is_empty() => run_files(args),
         | None => {
@@ -205,35 +306,92 @@ fn setup_basic(cli: CliArgs, command: &BasicCommands) -> anyhow::Result<(Driver,
     let cfg: Cfg = cli.cfg.into_iter().collect();
     let input = match &command {
         | BasicCommands::Check(args) => &args.input,
+        | BasicCommands::Fix(args) => &args.input,
         | BasicCommands::Build(args) => &args.input,
         | BasicCommands::Run(args) => &args.input,
+        | BasicCommands::Test(args) => &args.input,
+        | BasicCommands::Fmt(args) => &args.input,
+        | BasicCommands::Doc(args) => &args.input,
     };
 
     Driver::init(InitOpts {
         target: cli.target.as_deref(),
+        optimization: cli.optimization.unwrap_or_default(),
+        debug_info: cli.debug_info,
+        packages: cli.packages.iter().map(String::as_str).collect(),
+        features: cli
+            .features
+            .iter()
+            .flat_map(|f| f.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|f| !f.is_empty())
+            .collect(),
+        no_default_features: cli.no_default_features,
+        message_format: cli.message_format.unwrap_or_default(),
         input,
         cfg,
         ..InitOpts::default()
     })
 }
 
-fn run_basic(driver: &Driver, ws: usize, command: &BasicCommands) -> io::Result<ExitCode> {
+fn run_basic(driver: &mut Driver, ws: usize, command: &BasicCommands) -> io::Result<ExitCode> {
     match command {
         | BasicCommands::Check(_) => {
-            if driver.check()? {
+            if driver.check(ws)? {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::FAILURE)
+            }
+        },
+        | BasicCommands::Fix(_) => {
+            driver.fix(ws)?;
+
+            // Check again to report the diagnostics which could not be fixed.
+            if driver.check(ws)? {
                 Ok(ExitCode::SUCCESS)
             } else {
                 Ok(ExitCode::FAILURE)
             }
         },
-        | BasicCommands::Build(_) => {
-            if driver.build(ws)? {
+        | BasicCommands::Build(args) => {
+            let built = driver.build(ws)?;
+
+            // The representations of the source are also written when the build fails, so that
+            // `--emit=types` can be used to find out why code fails to type-check.
+            let kinds = args
+                .emit
+                .iter()
+                .copied()
+                .filter(|kind| built || kind.is_source())
+                .collect::<Vec<_>>();
+
+            if driver.emit(ws, &kinds)? && built {
                 Ok(ExitCode::SUCCESS)
             } else {
                 Ok(ExitCode::FAILURE)
             }
         },
         | BasicCommands::Run(args) => driver.run(ws, args.args.iter()),
+        | BasicCommands::Test(args) => {
+            if driver.test(ws, &args.filters)? {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::FAILURE)
+            }
+        },
+        | BasicCommands::Fmt(args) => {
+            if driver.fmt(ws, args.check)? {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::FAILURE)
+            }
+        },
+        | BasicCommands::Doc(_) => {
+            if driver.doc(ws)? {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::FAILURE)
+            }
+        },
     }
 }
 
@@ -249,6 +407,8 @@ fn run_files(cli: CliArgs) -> anyhow::Result<ExitCode> {
         links: cli.links.iter().map(|p| p.as_path()).collect(),
         dependencies: cli.dependencies.iter().map(|p| p.as_path()).collect(),
         optimization,
+        debug_info: cli.debug_info,
+        message_format: cli.message_format.unwrap_or_default(),
         output,
         cfg,
     })?;
@@ -260,6 +420,54 @@ fn run_files(cli: CliArgs) -> anyhow::Result<ExitCode> {
     }
 }
 
+fn run_explain(args: ExplainArgs) -> anyhow::Result<ExitCode> {
+    match diagnostics::explain::explain(&args.code) {
+        | Some(text) => {
+            println!("{}", text.trim_end());
+            Ok(ExitCode::SUCCESS)
+        },
+        | None => {
+            eprintln!("\x1B[31mError:\x1B[0m '{}' is not a diagnostic code", args.code);
+            Ok(ExitCode::FAILURE)
+        },
+    }
+}
+
+fn run_new(cli: CliArgs, args: NewArgs) -> anyhow::Result<ExitCode> {
+    if args.path.exists() {
+        eprintln!("\x1B[31mError:\x1B[0m {} already exists", args.path.display());
+        return Ok(ExitCode::FAILURE);
+    }
+
+    create_package(cli.target.as_deref(), &args.path, args.lib)
+}
+
+fn create_package(target: Option<&str>, dir: &Path, lib: bool) -> anyhow::Result<ExitCode> {
+    let target = match target.map(str::parse::<CompilerTarget>).transpose() {
+        | Ok(target) => target.unwrap_or_default(),
+        | Err(e) => {
+            eprintln!("\x1B[31mError:\x1B[0m invalid target '{}': {}", target.unwrap(), e);
+            return Ok(ExitCode::FAILURE);
+        },
+    };
+
+    let created =
+        scaffold::dir_name(dir).and_then(|name| scaffold::create(dir, &name, &Template { lib, target }).map(|_| name));
+
+    match created {
+        | Ok(name) => {
+            let kind = if lib { "library" } else { "executable" };
+
+            eprintln!("    \x1B[1;32m\x1B[1mCreated\x1B[0m {} package `{}`", kind, name);
+            Ok(ExitCode::SUCCESS)
+        },
+        | Err(e) => {
+            eprintln!("\x1B[31mError:\x1B[0m {}", e);
+            Ok(ExitCode::FAILURE)
+        },
+    }
+}
+
 fn run_lsp(_cli: CliArgs, _args: LspArgs) -> anyhow::Result<()> {
     language_server::run()
 }
@@ -271,8 +479,12 @@ fn run_watch(cli: CliArgs, args: WatchArgs) -> anyhow::Result<()> {
     if let Ok(_driver) = setup_basic(cli, &args.command) {
         let input = match &args.command {
             | BasicCommands::Check(args) => &args.input,
+            | BasicCommands::Fix(args) => &args.input,
             | BasicCommands::Build(args) => &args.input,
             | BasicCommands::Run(args) => &args.input,
+            | BasicCommands::Test(args) => &args.input,
+            | BasicCommands::Fmt(args) => &args.input,
+            | BasicCommands::Doc(args) => &args.input,
         };
 
         let (tx, rx) = std::sync::mpsc::channel();
@@ -311,10 +523,15 @@ fn parse_output(s: &str) -> Result<LibKind, String> {
 }
 
 fn parse_optimization(s: &str) -> Result<Optimization, String> {
-    match s.to_lowercase().as_str() {
-        | "0" | "none" => Ok(Optimization::None),
-        | _ => Err(format!("`{}` isn't a valid optimization", s)),
-    }
+    s.parse()
+}
+
+fn parse_emit(s: &str) -> Result<Emit, String> {
+    s.parse()
+}
+
+fn parse_message_format(s: &str) -> Result<MessageFormat, String> {
+    s.parse()
 }
 
 fn parse_cfg(s: &str) -> Result<(String, TomlValue), String> {
@@ -332,3 +549,25 @@ fn parse_cfg(s: &str) -> Result<(String, TomlValue), String> {
         Ok((s.into(), TomlValue::Boolean(true)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain(code: &str) -> ExitCode {
+        match Cli::try_parse_from(["fi", "explain", code]).unwrap().command {
+            | Some(Commands::Explain(args)) => run_explain(args).unwrap(),
+            | command => panic!("expected the explain command, found {:?}", command),
+        }
+    }
+
+    #[test]
+    fn explain_known_code() {
+        assert_eq!(explain("E0020"), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn explain_unknown_code_fails() {
+        assert_eq!(explain("E9999"), ExitCode::FAILURE);
+    }
+}
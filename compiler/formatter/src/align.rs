@@ -0,0 +1,109 @@
+//! Aligning the arrows of case arms and guards and the equals signs of function clauses.
+//!
+//! Only arms, guards and clauses whose arrow or equals sign is on their first line are aligned,
+//! and a run of them is broken by one which is not.
+
+use rustc_hash::FxHashMap;
+use syntax::{AstNode, SyntaxKind, SyntaxNode, SyntaxToken};
+
+/// Align the arms, guards and clauses in `text`, which has already been printed. Returns `None`
+/// when nothing needs to be aligned.
+pub(crate) fn align(text: &str) -> Option<String> {
+    let file = crate::parse(text).ok()?;
+    let mut runs = Vec::new();
+
+    for node in file.syntax().descendants() {
+        let (kind, sep) = match node.kind() {
+            | SyntaxKind::EXPR_CASE => (SyntaxKind::CASE_ARM, None),
+            | SyntaxKind::CASE_GUARDED => (SyntaxKind::CASE_GUARD, None),
+            | SyntaxKind::ITEM_FUNC => (SyntaxKind::ONE_FUNC, Some(SyntaxKind::EQUALS)),
+            | _ => continue,
+        };
+
+        let mut run = Vec::new();
+
+        for child in node.children().filter(|c| c.kind() == kind) {
+            let sep = match (kind, sep) {
+                | (SyntaxKind::CASE_ARM, _) => child
+                    .children()
+                    .find(|c| c.kind() == SyntaxKind::CASE_VALUE)
+                    .and_then(|c| token(&c, SyntaxKind::ARROW)),
+                | (_, Some(sep)) => token(&child, sep),
+                | _ => token(&child, SyntaxKind::ARROW).or_else(|| token(&child, SyntaxKind::EQUALS)),
+            };
+
+            match sep.filter(|sep| starts_line(text, &child, sep)) {
+                | Some(sep) => run.push(sep),
+                | None if kind == SyntaxKind::ONE_FUNC && token(&child, SyntaxKind::DBL_COLON).is_some() => {},
+                | None => runs.push(std::mem::take(&mut run)),
+            }
+        }
+
+        runs.push(run);
+    }
+
+    let mut lines = FxHashMap::<usize, usize>::default();
+
+    for sep in runs.iter().flatten() {
+        *lines
+            .entry(crate::line_start(text, sep.text_range().start().into()))
+            .or_default() += 1;
+    }
+
+    let mut inserts = Vec::new();
+
+    for run in runs {
+        let run = run
+            .into_iter()
+            .filter(|sep| lines[&crate::line_start(text, sep.text_range().start().into())] == 1)
+            .map(|sep| (usize::from(sep.text_range().start()), column(text, &sep)))
+            .collect::<Vec<_>>();
+
+        if run.len() < 2 {
+            continue;
+        }
+
+        let target = run.iter().map(|&(_, col)| col).max().unwrap();
+
+        inserts.extend(
+            run.into_iter()
+                .filter(|&(_, col)| col < target)
+                .map(|(offset, col)| (offset, target - col)),
+        );
+    }
+
+    if inserts.is_empty() {
+        return None;
+    }
+
+    let mut aligned = text.to_string();
+
+    inserts.sort();
+
+    for (offset, spaces) in inserts.into_iter().rev() {
+        aligned.insert_str(offset, &" ".repeat(spaces));
+    }
+
+    crate::is_equivalent(&file, &aligned).then(|| aligned)
+}
+
+/// The first token of kind `kind` directly in `node`.
+fn token(node: &SyntaxNode, kind: SyntaxKind) -> Option<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(|c| c.into_token())
+        .find(|t| t.kind() == kind)
+}
+
+/// Whether `node` starts its line and `sep` is on the same line.
+fn starts_line(text: &str, node: &SyntaxNode, sep: &SyntaxToken) -> bool {
+    let start = usize::from(node.text_range().start());
+    let line = crate::line_start(text, start);
+
+    text[line..start].trim().is_empty() && !text[start..sep.text_range().start().into()].contains('\n')
+}
+
+fn column(text: &str, token: &SyntaxToken) -> usize {
+    let offset = usize::from(token.text_range().start());
+
+    text[crate::line_start(text, offset)..offset].chars().count()
+}
@@ -0,0 +1,175 @@
+//! Sorting imports.
+//!
+//! The imports of a module are sorted by module name within each group of consecutive imports.
+//! A blank line or another item ends a group. Comments on the lines directly above an import
+//! and at the end of its line move along with it. The names imported by an import are sorted as
+//! well, unless they span multiple lines.
+
+use syntax::{ast, AstNode, NodeOrToken, SyntaxElement, SyntaxKind, TextRange};
+
+/// An import together with its comments.
+struct Unit {
+    range: TextRange,
+    module: String,
+    text: String,
+}
+
+/// Sort the imports of every module in `text`, which has already been printed. Returns `None`
+/// when the imports are sorted already.
+pub(crate) fn sort_imports(text: &str) -> Option<String> {
+    let file = crate::parse(text).ok()?;
+    let mut edits = Vec::new();
+
+    for module in file.syntax().descendants().filter_map(ast::ItemModule::cast) {
+        let mut group = Vec::<Unit>::new();
+
+        for child in module.syntax().children() {
+            let unit = match ast::ItemImport::cast(child) {
+                | Some(import) => unit(text, &import),
+                | None => {
+                    sort_group(text, &group, &mut edits);
+                    group.clear();
+                    continue;
+                },
+            };
+
+            let between = group
+                .last()
+                .map(|last| &text[TextRange::new(last.range.end(), unit.range.start())]);
+
+            if !between.map_or(true, |b| b.trim().is_empty() && b.matches('\n').count() == 1) {
+                sort_group(text, &group, &mut edits);
+                group.clear();
+            }
+
+            group.push(unit);
+        }
+
+        sort_group(text, &group, &mut edits);
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    let mut sorted = text.to_string();
+
+    edits.sort_by_key(|(range, _)| range.start());
+
+    for (range, replacement) in edits.into_iter().rev() {
+        sorted.replace_range(std::ops::Range::<usize>::from(range), &replacement);
+    }
+
+    // Moving a comment to the start of a block adds a separator after it, so separators are not
+    // compared.
+    let new_file = crate::parse(&sorted).ok()?;
+    let tokens = |file| {
+        crate::tokens(file)
+            .into_iter()
+            .filter(|(kind, _)| *kind != SyntaxKind::LYT_SEP)
+            .collect::<Vec<_>>()
+    };
+
+    let mut before = tokens(&file);
+    let mut after = tokens(&new_file);
+
+    before.sort();
+    after.sort();
+
+    (before == after).then(|| sorted)
+}
+
+fn sort_group(text: &str, group: &[Unit], edits: &mut Vec<(TextRange, String)>) {
+    let (first, last) = match (group.first(), group.last()) {
+        | (Some(first), Some(last)) => (first, last),
+        | _ => return,
+    };
+
+    let range = TextRange::new(first.range.start(), last.range.end());
+    let separator = match group.get(1) {
+        | Some(second) => &text[TextRange::new(first.range.end(), second.range.start())],
+        | None => "",
+    };
+
+    let mut units = group.iter().collect::<Vec<_>>();
+
+    units.sort_by(|a, b| (&a.module, &a.text).cmp(&(&b.module, &b.text)));
+
+    let sorted = units
+        .iter()
+        .map(|u| u.text.as_str())
+        .collect::<Vec<_>>()
+        .join(separator);
+
+    if sorted != text[range] {
+        edits.push((range, sorted));
+    }
+}
+
+/// The import `import` with the comments above it and after it, and with the names it imports
+/// sorted.
+fn unit(text: &str, import: &ast::ItemImport) -> Unit {
+    let node = import.syntax();
+    let mut start = node.text_range().start();
+    let mut end = node.text_range().end();
+    let mut prev = node.prev_sibling_or_token();
+
+    while let Some(ws) = prev.as_ref().filter(|p| is_space(p) && newlines(p) == 1) {
+        let comment = match ws.prev_sibling_or_token() {
+            | Some(c) if c.kind() == SyntaxKind::COMMENT => c,
+            | _ => break,
+        };
+
+        prev = comment.prev_sibling_or_token();
+
+        if !prev.as_ref().map_or(true, |p| is_space(p) && newlines(p) > 0) {
+            break;
+        }
+
+        start = comment.text_range().start();
+    }
+
+    let mut next = node.next_sibling_or_token();
+
+    if next.as_ref().map_or(false, |n| is_space(n) && newlines(n) == 0) {
+        next = next.and_then(|n| n.next_sibling_or_token());
+    }
+
+    if let Some(comment) = next.filter(|n| n.kind() == SyntaxKind::COMMENT) {
+        end = comment.text_range().end();
+    }
+
+    let mut unit_text = text[TextRange::new(start, end)].to_string();
+    let names = import.items().into_iter().flatten().collect::<Vec<_>>();
+
+    if let (Some(first), Some(last)) = (names.first(), names.last()) {
+        let range = TextRange::new(first.syntax().text_range().start(), last.syntax().text_range().end());
+
+        if !text[range].contains('\n') {
+            let mut sorted = names.iter().map(|n| n.syntax().text().to_string()).collect::<Vec<_>>();
+
+            sorted.sort();
+            unit_text.replace_range(std::ops::Range::<usize>::from(range - start), &sorted.join(", "));
+        }
+    }
+
+    Unit {
+        range: TextRange::new(start, end),
+        module: import.module().map(|m| m.text()).unwrap_or_default(),
+        text: unit_text,
+    }
+}
+
+fn is_space(element: &SyntaxElement) -> bool {
+    matches!(
+        element.kind(),
+        SyntaxKind::WHITESPACE | SyntaxKind::LYT_START | SyntaxKind::LYT_SEP | SyntaxKind::LYT_END
+    )
+}
+
+fn newlines(element: &SyntaxElement) -> usize {
+    match element {
+        | NodeOrToken::Token(t) => t.text().matches('\n').count(),
+        | NodeOrToken::Node(_) => 0,
+    }
+}
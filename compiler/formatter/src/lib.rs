@@ -0,0 +1,324 @@
+//! An opinionated formatter for fi source files, run by `fi fmt` and the language server.
+//!
+//! Formatting happens in passes over the lossless syntax tree. The [`printer`] re-indents the
+//! layout blocks and normalizes the spacing between tokens, after which [`imports`] sorts the
+//! imports of each module and [`align`] aligns the arrows of case arms and the equals signs of
+//! function clauses. The tokens of the result are compared with those of the input after every
+//! pass, so formatting never changes the meaning of a file.
+
+mod align;
+mod imports;
+mod printer;
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use syntax::{ast, AstNode, SyntaxError, SyntaxKind, TextRange, TextSize};
+
+/// The `[fmt]` table of the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// The number of spaces per level of indentation.
+    pub indent_width: usize,
+    /// The maximum number of consecutive blank lines which are kept.
+    pub max_blank_lines: usize,
+    /// Sort the imports in each group of imports not separated by a blank line, and the names
+    /// imported by each of them.
+    pub sort_imports: bool,
+    /// Align the arrows of case arms and guards and the equals signs of function clauses.
+    pub align: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            max_blank_lines: 2,
+            sort_imports: true,
+            align: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// The source does not parse. Files with syntax errors are left as they are.
+    Syntax(SyntaxError),
+    /// The formatted source has different tokens than the original one.
+    Unstable,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | FormatError::Syntax(e) => write!(f, "syntax error: {}", e),
+            | FormatError::Unstable => f.write_str("formatting would change the meaning of the file"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {
+}
+
+/// Format the source file `text`.
+pub fn format(text: &str, config: &Config) -> Result<String, FormatError> {
+    let mut text = printer::print(text, config)?.text;
+
+    if config.sort_imports {
+        if let Some(sorted) = imports::sort_imports(&text) {
+            text = sorted;
+        }
+    }
+
+    if config.align {
+        if let Some(aligned) = align::align(&text) {
+            text = aligned;
+        }
+    }
+
+    Ok(text)
+}
+
+/// Format the lines of `text` overlapping `range`. Returns the range of the original lines and
+/// their formatted text, or `None` when they are already formatted. Imports are neither sorted
+/// nor aligned, as that could change lines outside of the range.
+pub fn format_range(text: &str, range: TextRange, config: &Config) -> Result<Option<(TextRange, String)>, FormatError> {
+    let printed = printer::print(text, config)?;
+    let mut tokens = printed.tokens.iter().filter(|t| t.orig.intersect(range).is_some());
+    let first = match tokens.next() {
+        | Some(first) => first,
+        | None => return Ok(None),
+    };
+
+    let last = tokens.last().unwrap_or(first);
+    let orig_start = line_start(text, first.orig.start().into());
+    let orig_end = line_end(text, last.orig.end().into());
+    let out_start = line_start(&printed.text, first.out.start().into());
+    let out_end = line_end(&printed.text, last.out.end().into());
+    let formatted = &printed.text[out_start..out_end];
+
+    if formatted == &text[orig_start..orig_end] {
+        return Ok(None);
+    }
+
+    let range = TextRange::new(TextSize::from(orig_start as u32), TextSize::from(orig_end as u32));
+
+    Ok(Some((range, formatted.to_string())))
+}
+
+/// Parse `text`, failing on the first syntax error.
+fn parse(text: &str) -> Result<ast::SourceFile, FormatError> {
+    let parsed = ast::SourceFile::parse(text);
+
+    match parsed.errors().first() {
+        | Some(e) => Err(FormatError::Syntax(SyntaxError::new(e.msg.clone(), e.range))),
+        | None => Ok(parsed.tree()),
+    }
+}
+
+/// The tokens of `file` which must be preserved by formatting: everything except whitespace.
+/// Layout tokens are compared by kind only, as their text is the whitespace they replaced.
+fn tokens(file: &ast::SourceFile) -> Vec<(SyntaxKind, String)> {
+    file.syntax()
+        .descendants_with_tokens()
+        .filter_map(|e| e.into_token())
+        .filter_map(|t| match t.kind() {
+            | SyntaxKind::WHITESPACE => None,
+            | SyntaxKind::LYT_START | SyntaxKind::LYT_SEP | SyntaxKind::LYT_END => Some((t.kind(), String::new())),
+            | SyntaxKind::COMMENT => Some((t.kind(), t.text().trim_end().to_string())),
+            | _ => Some((t.kind(), t.text().to_string())),
+        })
+        .collect()
+}
+
+/// Whether `formatted` parses without errors into the same tokens as `original`.
+fn is_equivalent(original: &ast::SourceFile, formatted: &str) -> bool {
+    match parse(formatted) {
+        | Ok(file) => tokens(original) == tokens(&file),
+        | Err(_) => false,
+    }
+}
+
+fn line_start(text: &str, offset: usize) -> usize {
+    text[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn line_end(text: &str, offset: usize) -> usize {
+    text[offset..].find('\n').map_or(text.len(), |i| offset + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(before: &str, after: &str) {
+        let formatted = format(before, &Config::default()).unwrap();
+
+        assert_eq!(formatted, after);
+        assert_eq!(format(&formatted, &Config::default()).unwrap(), formatted);
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let text = "\
+module Main =
+
+import Data:Option (Some,None,Option)
+import Core
+
+type Shape =
+  | Circle Int
+  | Rect Int Int
+
+area :: Shape -> Int
+area (Circle r)   = r*r*3
+area (Rect w h) = w * h
+
+main =
+   case Some 1 of
+      Some x -> do
+          let y = x+1
+          y
+      None    -> 0
+";
+        let once = format(text, &Config::default()).unwrap();
+        let twice = format(&once, &Config::default()).unwrap();
+
+        assert_ne!(once, text);
+        assert_eq!(twice, once);
+    }
+
+    #[test]
+    fn comments_are_preserved() {
+        check(
+            "\
+module Main =
+
+; the entry point
+main =
+  ; a comment in a block
+  f 1   ; trailing comment
+; f 2
+",
+            "\
+module Main =
+
+; the entry point
+main =
+    ; a comment in a block
+    f 1 ; trailing comment
+; f 2
+",
+        );
+    }
+
+    #[test]
+    fn constructors_are_indented_one_level() {
+        check(
+            "\
+module Main =
+
+type Shape =
+  | Circle Int
+      | Rect Int Int
+  | Empty
+",
+            "\
+module Main =
+
+type Shape =
+    | Circle Int
+    | Rect Int Int
+    | Empty
+",
+        );
+    }
+
+    #[test]
+    fn arms_and_clauses_are_aligned() {
+        check(
+            "\
+module Main =
+
+area (Circle r) = r
+area Empty = 0
+
+main =
+    case x of
+        Some x -> x
+        None -> 0
+",
+            "\
+module Main =
+
+area (Circle r) = r
+area Empty      = 0
+
+main =
+    case x of
+        Some x -> x
+        None   -> 0
+",
+        );
+    }
+
+    #[test]
+    fn imports_are_sorted_within_groups() {
+        check(
+            "\
+module Main =
+
+import Data:Option (Some, None, Option)
+; the core library
+import Core
+import Data:List (List)
+
+import Intrinsics
+import Data:Range
+",
+            "\
+module Main =
+
+; the core library
+import Core
+import Data:List (List)
+import Data:Option (None, Option, Some)
+
+import Data:Range
+import Intrinsics
+",
+        );
+    }
+
+    #[test]
+    fn imports_are_not_sorted_when_disabled() {
+        let text = "module Main =\n\nimport Data:Option (Some, None)\nimport Core\n";
+        let config = Config {
+            sort_imports: false,
+            ..Config::default()
+        };
+
+        assert_eq!(format(text, &config).unwrap(), text);
+    }
+
+    #[test]
+    fn changing_the_layout_is_unstable() {
+        // Without indentation the statements of the do block would become items of the module.
+        let text = "module Main =\n\nmain = do\n    a\n    b\n\nc = 1\n";
+        let config = Config {
+            indent_width: 0,
+            ..Config::default()
+        };
+
+        assert!(matches!(format(text, &config), Err(FormatError::Unstable)));
+    }
+
+    #[test]
+    fn syntax_errors_are_reported() {
+        assert!(matches!(
+            format("module Main =\n\nmain = (\n", &Config::default()),
+            Err(FormatError::Syntax(_))
+        ));
+    }
+}
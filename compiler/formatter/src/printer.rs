@@ -0,0 +1,293 @@
+//! Printing a source file with canonical indentation and spacing.
+//!
+//! The printer walks the tokens of the syntax tree, keeping a stack of the layout blocks opened
+//! by `LYT_START` tokens. A block which starts on a new line is indented one level deeper than
+//! the line opening it, while a block which starts on the same line is aligned with its first
+//! token. Every item of a block starts at the indentation of the block. Lines continuing an item
+//! keep their indentation relative to the first line of the item, except for the constructors of
+//! a type, which are indented one level deeper.
+
+use syntax::{AstNode, SyntaxKind, SyntaxToken, TextRange, TextSize};
+
+use crate::{Config, FormatError};
+
+pub(crate) struct Printed {
+    pub(crate) text: String,
+    /// The ranges of all non-trivia tokens in the original and in the printed text.
+    pub(crate) tokens: Vec<PrintedToken>,
+}
+
+pub(crate) struct PrintedToken {
+    pub(crate) orig: TextRange,
+    pub(crate) out: TextRange,
+}
+
+/// Print `text`, failing when it has syntax errors or when printing changed its tokens.
+pub(crate) fn print(text: &str, config: &Config) -> Result<Printed, FormatError> {
+    let file = crate::parse(text)?;
+    let mut printer = Printer::new(text, config);
+
+    for token in file.syntax().descendants_with_tokens().filter_map(|e| e.into_token()) {
+        printer.token(token);
+    }
+
+    let printed = printer.finish();
+
+    if !crate::is_equivalent(&file, &printed.text) {
+        return Err(FormatError::Unstable);
+    }
+
+    Ok(printed)
+}
+
+struct Printer<'a> {
+    config: &'a Config,
+    text: &'a str,
+    out: String,
+    tokens: Vec<PrintedToken>,
+    blocks: Vec<Block>,
+    /// The trivia and layout tokens since the last printed token.
+    gap: Vec<SyntaxToken>,
+    prev: Option<SyntaxToken>,
+    /// The line of the last printed token.
+    line: Line,
+    col: usize,
+}
+
+struct Block {
+    /// The column of the block in the original and in the printed text.
+    orig: usize,
+    indent: usize,
+    /// The first line of the last item in this block.
+    item: Line,
+}
+
+/// The indentation of a line in the original text and in the printed text.
+#[derive(Clone, Copy)]
+struct Line {
+    orig: usize,
+    new: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(text: &'a str, config: &'a Config) -> Self {
+        let root = Line { orig: 0, new: 0 };
+
+        Self {
+            config,
+            text,
+            out: String::with_capacity(text.len()),
+            tokens: Vec::new(),
+            blocks: vec![Block {
+                orig: 0,
+                indent: 0,
+                item: root,
+            }],
+            gap: Vec::new(),
+            prev: None,
+            line: root,
+            col: 0,
+        }
+    }
+
+    fn token(&mut self, token: SyntaxToken) {
+        match token.kind() {
+            | SyntaxKind::WHITESPACE
+            | SyntaxKind::COMMENT
+            | SyntaxKind::LYT_START
+            | SyntaxKind::LYT_SEP
+            | SyntaxKind::LYT_END => self.gap.push(token),
+            | _ => self.print(token),
+        }
+    }
+
+    fn finish(mut self) -> Printed {
+        if self.gap.iter().any(|t| t.kind() == SyntaxKind::COMMENT) {
+            self.trivia(0);
+        }
+
+        let mut text = self.out.trim_end().to_string();
+
+        if !text.is_empty() {
+            text.push('\n');
+        }
+
+        Printed {
+            text,
+            tokens: self.tokens,
+        }
+    }
+
+    fn print(&mut self, token: SyntaxToken) {
+        let offset = usize::from(token.text_range().start());
+        let orig_col = offset - crate::line_start(self.text, offset);
+        let newline = self.prev.is_none() || self.gap.iter().any(|t| t.text().contains('\n'));
+        let space = self.prev.as_ref().map_or(false, |prev| {
+            has_space(prev, &token, self.gap.iter().any(|t| !t.text().is_empty()))
+        });
+
+        let mut starts_item = self.prev.is_none();
+
+        for t in std::mem::take(&mut self.gap) {
+            match t.kind() {
+                | SyntaxKind::LYT_START => {
+                    let indent = if newline && orig_col > self.line.orig {
+                        // A nested block must stay to the right of the blocks it was to the
+                        // right of, such as guards aligned after the head of a function.
+                        self.blocks
+                            .iter()
+                            .filter(|b| b.orig < orig_col)
+                            .map(|b| b.indent + self.config.indent_width)
+                            .fold(self.line.new + self.config.indent_width, usize::max)
+                    } else if newline {
+                        self.line.new
+                    } else {
+                        self.col + space as usize
+                    };
+
+                    self.blocks.push(Block {
+                        orig: orig_col,
+                        indent,
+                        item: Line {
+                            orig: orig_col,
+                            new: indent,
+                        },
+                    });
+
+                    starts_item = true;
+                },
+                | SyntaxKind::LYT_SEP => starts_item = true,
+                | SyntaxKind::LYT_END => {
+                    if self.blocks.len() > 1 {
+                        self.blocks.pop();
+                    }
+
+                    starts_item = false;
+                },
+                | _ => {},
+            }
+
+            self.gap.push(t);
+        }
+
+        if newline {
+            let block = self.blocks.last_mut().unwrap();
+            let indent = if starts_item {
+                block.indent
+            } else if is_ctor(&token) {
+                block.item.new + self.config.indent_width
+            } else {
+                (block.item.new + orig_col).saturating_sub(block.item.orig)
+            };
+
+            if starts_item {
+                block.item = Line {
+                    orig: orig_col,
+                    new: indent,
+                };
+            }
+
+            self.trivia(indent);
+            self.line = Line {
+                orig: orig_col,
+                new: indent,
+            };
+        } else {
+            self.trivia(0);
+
+            if space {
+                self.push(" ");
+            }
+        }
+
+        let start = self.out.len();
+
+        self.push(token.text());
+        self.tokens.push(PrintedToken {
+            orig: token.text_range(),
+            out: TextRange::new(TextSize::from(start as u32), TextSize::from(self.out.len() as u32)),
+        });
+
+        self.prev = Some(token);
+    }
+
+    /// Print the comments and newlines in the gap before the next token, which is indented by
+    /// `indent` when it starts a new line.
+    fn trivia(&mut self, indent: usize) {
+        let mut newlines = 0;
+
+        for t in std::mem::take(&mut self.gap) {
+            if t.kind() != SyntaxKind::COMMENT {
+                newlines += t.text().matches('\n').count();
+                continue;
+            }
+
+            if newlines == 0 && !self.out.is_empty() {
+                self.push(" ");
+            } else {
+                self.newlines(newlines, indent);
+            }
+
+            self.push(t.text().trim_end());
+            newlines = 0;
+        }
+
+        if newlines > 0 || self.out.is_empty() {
+            self.newlines(newlines, indent);
+        }
+    }
+
+    fn newlines(&mut self, count: usize, indent: usize) {
+        if !self.out.is_empty() {
+            let trimmed = self.out.trim_end_matches(' ').len();
+
+            self.out.truncate(trimmed);
+
+            for _ in 0..count.clamp(1, self.config.max_blank_lines + 1) {
+                self.out.push('\n');
+            }
+        }
+
+        self.out.extend(std::iter::repeat(' ').take(indent));
+        self.col = indent;
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+
+        match text.rfind('\n') {
+            | Some(i) => self.col = text[i + 1..].chars().count(),
+            | None => self.col += text.chars().count(),
+        }
+    }
+}
+
+/// Whether `token` starts a constructor of a type declaration.
+fn is_ctor(token: &SyntaxToken) -> bool {
+    token.kind() == SyntaxKind::PIPE && token.parent().kind() == SyntaxKind::ONE_TYPE
+}
+
+/// Whether to print a space between `prev` and `next` on the same line. `had_space` is whether
+/// they were separated in the original text.
+fn has_space(prev: &SyntaxToken, next: &SyntaxToken, had_space: bool) -> bool {
+    use SyntaxKind::*;
+
+    match (prev.kind(), next.kind()) {
+        | (L_PAREN | L_BRACKET, _) => false,
+        | (_, R_PAREN | R_BRACKET | COMMA) => false,
+        | (COMMA, _) => true,
+        | (AT | PATH_SEP, _) | (_, PATH_SEP) => false,
+        | (EQUALS | ARROW | LEFT_ARROW | DBL_COLON | PIPE, _) | (_, EQUALS | ARROW | LEFT_ARROW | DBL_COLON | PIPE) => {
+            true
+        },
+        | _ if is_infix_op(prev) || is_infix_op(next) => true,
+        | _ => had_space,
+    }
+}
+
+/// Whether `token` is the operator of an infix expression, pattern or type.
+fn is_infix_op(token: &SyntaxToken) -> bool {
+    use SyntaxKind::*;
+
+    matches!(token.kind(), OPERATOR | COLON) && matches!(token.parent().kind(), EXPR_INFIX | PAT_INFIX | TYPE_INFIX)
+}
@@ -1,3 +1,4 @@
+pub use base_db::lints::LintLevel;
 pub use hir_def::diagnostic::*;
 pub use hir_def::diagnostics::*;
 pub use hir_ty::diagnostics::*;
@@ -39,29 +39,48 @@ impl HirDisplay for Func {
 
         let infer = f.db.infer(self.id.into());
 
-        write!(f, "{} :: {}", self.name(f.db), infer.self_type.ty.display(f.db))
+        write!(f, "{} :: ", self.name(f.db))?;
+        infer.self_type.ty.hir_fmt(f)
+    }
+}
+
+impl HirDisplay for Static {
+    fn hir_fmt(&self, f: &mut HirFormatter) -> std::fmt::Result {
+        if self.is_foreign(f.db) {
+            write!(f, "foreign ")?;
+        }
+
+        write!(f, "static {} :: ", self.name(f.db))?;
+        self.ty(f.db).hir_fmt(f)
+    }
+}
+
+impl HirDisplay for Const {
+    fn hir_fmt(&self, f: &mut HirFormatter) -> std::fmt::Result {
+        write!(f, "const {} :: ", self.name(f.db))?;
+        self.ty(f.db).hir_fmt(f)
     }
 }
 
 impl HirDisplay for TypeAlias {
     fn hir_fmt(&self, f: &mut HirFormatter) -> std::fmt::Result {
         let lower = f.db.type_for_alias(self.id);
-        let ty = lower.ty.ty.display(f.db);
 
-        write!(f, "type {} = {}", self.name(f.db), ty)
+        write!(f, "type {} = ", self.name(f.db))?;
+        lower.ty.ty.hir_fmt(f)
     }
 }
 
 impl HirDisplay for TypeCtor {
     fn hir_fmt(&self, f: &mut HirFormatter) -> std::fmt::Result {
         let kind = f.db.kind_for_ctor(self.id);
-        let kind = kind.ty.ty.display(f.db);
 
         if self.is_foreign(f.db) {
             write!(f, "foreign ")?;
         }
 
-        write!(f, "type {} :: {}", self.name(f.db), kind)
+        write!(f, "type {} :: ", self.name(f.db))?;
+        kind.ty.ty.hir_fmt(f)
     }
 }
 
@@ -71,11 +90,17 @@ impl HirDisplay for Class {
     }
 }
 
+impl HirDisplay for Member {
+    fn hir_fmt(&self, f: &mut HirFormatter) -> std::fmt::Result {
+        f.db.lower_member(self.id).member.hir_fmt(f)
+    }
+}
+
 impl HirDisplay for TypeVar {
     fn hir_fmt(&self, f: &mut HirFormatter) -> std::fmt::Result {
         let kind = self.kind(f.db);
-        let kind = kind.display(f.db);
 
-        write!(f, "type {} :: {}", self.name(f.db), kind)
+        write!(f, "type {} :: ", self.name(f.db))?;
+        kind.hir_fmt(f)
     }
 }
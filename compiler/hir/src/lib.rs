@@ -10,7 +10,7 @@ mod source_to_def;
 use base_db::input::FileId;
 use base_db::libs::LibId;
 use either::Either;
-pub use hir_def::attrs::Documentation;
+pub use hir_def::attrs::{DocTest, Documentation};
 pub use hir_def::body::Body;
 use hir_def::body::{PatSource, SyntheticSyntax};
 pub use hir_def::data::FixityKind;
@@ -25,6 +25,7 @@ pub use hir_def::name::{AsName, Name};
 pub use hir_def::pat::{Pat, PatId};
 pub use hir_def::path::Path;
 pub use hir_def::resolver::{HasResolver, Resolver, TypeNs, ValueNs};
+use hir_def::source::HasSource;
 use hir_def::visibility::Visibility;
 pub use hir_ty::class::ClassEnvPath;
 use hir_ty::db::HirDatabase;
@@ -32,6 +33,9 @@ pub use hir_ty::display::HirDisplay;
 pub use hir_ty::infer::{InferenceResult, MethodSource};
 pub use hir_ty::ty;
 use hir_ty::ty::{Ty, TyKind};
+use syntax::AstNode;
+
+use crate::attrs::HasAttrs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Lib {
@@ -79,6 +83,28 @@ impl Lib {
     pub fn all(db: &dyn HirDatabase) -> Vec<Lib> {
         db.libs().toposort(None).into_iter().map(|id| Lib { id }).collect()
     }
+
+    /// Whether the library is compiled as a test harness, which is the case when the `test` cfg
+    /// is enabled. The harness replaces the `@main` function of the library.
+    pub fn is_test(self, db: &dyn HirDatabase) -> bool {
+        db.libs()[self.id].cfg_options.flags().contains("test")
+    }
+
+    /// The `@test` functions of the library, sorted by path.
+    pub fn tests(self, db: &dyn HirDatabase) -> Vec<Func> {
+        let def_map = db.def_map(self.id);
+        let mut tests = def_map
+            .modules()
+            .flat_map(|(id, _)| Module::from(def_map.module_id(id)).declarations(db))
+            .filter_map(|def| match def {
+                | ModuleDef::Func(func) if func.is_test(db) => Some(func),
+                | _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        tests.sort_by_cached_key(|func| func.path(db));
+        tests
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -108,6 +134,13 @@ impl Module {
         def_map[local_id].origin.file_id().unwrap()
     }
 
+    /// The declaration of the module, or of its parent for virtual modules.
+    pub fn source(self, db: &dyn HirDatabase) -> InFile<syntax::ast::ItemModule> {
+        let def_map = db.def_map(self.id.lib);
+
+        def_map[self.id.local_id].origin.declaration(db.upcast(), &def_map)
+    }
+
     pub fn parent(self, db: &dyn HirDatabase) -> Option<Module> {
         let def_map = db.def_map(self.id.lib);
 
@@ -120,7 +153,8 @@ impl Module {
         def_map[self.id.local_id].origin.is_virtual()
     }
 
-    pub fn path_to_name(self, db: &dyn HirDatabase, name: Name) -> Path {
+    /// The full path of the module, including the names of its parents.
+    pub fn path(self, db: &dyn HirDatabase) -> Path {
         std::iter::successors(Some(self), |m| m.parent(db))
             .collect::<Vec<_>>()
             .into_iter()
@@ -133,10 +167,16 @@ impl Module {
             })
             .rev()
             .flatten()
-            .chain(std::iter::once(name))
             .collect()
     }
 
+    pub fn path_to_name(self, db: &dyn HirDatabase, name: Name) -> Path {
+        let mut path = self.path(db);
+
+        path.push_segment(name);
+        path
+    }
+
     pub fn is_exported(self, db: &dyn HirDatabase, name: Name, ns: ExportNs) -> bool {
         let def_map = db.def_map(self.id.lib);
         let def = def_map[self.id.local_id].scope.get(&name);
@@ -175,6 +215,24 @@ impl Module {
         def_map[self.id.local_id].scope.members().map(Member::from).collect()
     }
 
+    /// The modules defining the items in scope of this module, other than the module itself.
+    pub fn imports(self, db: &dyn HirDatabase) -> Vec<Module> {
+        let def_map = db.def_map(self.id.lib);
+        let mut modules = Vec::new();
+
+        for (_, def) in def_map[self.id.local_id].scope.entries() {
+            for (def, _) in def.types.into_iter().chain(def.values).chain(def.modules) {
+                let module = Module::from(def.module(db.upcast()));
+
+                if module != self && !modules.contains(&module) {
+                    modules.push(module);
+                }
+            }
+        }
+
+        modules
+    }
+
     pub fn exports(self, db: &dyn HirDatabase) -> Vec<ModuleDef> {
         self.declarations(db)
             .into_iter()
@@ -195,6 +253,8 @@ impl Module {
             diag.add_to(db.upcast(), self.id.local_id, sink);
         }
 
+        hir_def::lints::check_module(db.upcast(), self.id, sink);
+
         for decl in self.declarations(db) {
             decl.diagnostics(db, sink);
         }
@@ -289,6 +349,20 @@ impl ModuleDef {
         }
     }
 
+    pub fn docs(self, db: &dyn HirDatabase) -> Option<Documentation> {
+        match self {
+            | ModuleDef::Module(it) => it.attrs(db).docs(),
+            | ModuleDef::Fixity(it) => it.attrs(db).docs(),
+            | ModuleDef::Func(it) => it.attrs(db).docs(),
+            | ModuleDef::Static(it) => it.attrs(db).docs(),
+            | ModuleDef::Const(it) => it.attrs(db).docs(),
+            | ModuleDef::TypeAlias(it) => it.attrs(db).docs(),
+            | ModuleDef::TypeCtor(it) => it.attrs(db).docs(),
+            | ModuleDef::Class(it) => it.attrs(db).docs(),
+            | ModuleDef::Ctor(_) => None,
+        }
+    }
+
     pub fn link_name(self, db: &dyn HirDatabase) -> Option<(Name, bool)> {
         match self {
             | ModuleDef::Func(it) => Some(it.link_name(db)),
@@ -445,10 +519,32 @@ impl Func {
         db.attrs(self.id.into()).by_key("intrinsic").exists()
     }
 
+    /// Whether the function is exported with the C calling convention using `@export`.
+    pub fn is_c_export(self, db: &dyn HirDatabase) -> bool {
+        db.attrs(self.id.into()).by_key("export").exists()
+    }
+
+    /// Whether the function is `@variadic`. Its last parameter receives the `va_list` of the
+    /// variadic arguments it is called with from C.
+    pub fn is_variadic(self, db: &dyn HirDatabase) -> bool {
+        db.attrs(self.id.into()).by_key("variadic").exists()
+    }
+
+    pub fn is_test(self, db: &dyn HirDatabase) -> bool {
+        db.attrs(self.id.into()).by_key("test").exists()
+    }
+
     pub fn is_identity(self, db: &dyn HirDatabase) -> bool {
         db.attrs(self.id.into()).by_key("identity").exists()
     }
 
+    pub fn wasm_import_module(self, db: &dyn HirDatabase) -> Option<String> {
+        let attrs = db.attrs(self.id.into());
+        let mut module = attrs.by_key("wasm_import_module").string_value();
+
+        module.next().map(|m| m.to_string())
+    }
+
     pub fn is_generic(self, db: &dyn HirDatabase) -> bool {
         let ty = db.value_ty(ValueTyDefId::FuncId(self.id)).ty;
 
@@ -461,7 +557,7 @@ impl Func {
 
         if let Some(name) = link_name.next() {
             (name.as_name(), false)
-        } else if attrs.by_key("no_mangle").exists() {
+        } else if attrs.by_key("no_mangle").exists() || attrs.by_key("export").exists() {
             (self.name(db), false)
         } else if self.is_foreign(db) {
             (self.name(db), false)
@@ -488,7 +584,9 @@ impl Func {
     }
 
     pub fn is_exported(self, db: &dyn HirDatabase) -> bool {
-        if let Some(assoc) = self.as_assoc_item(db) {
+        if self.is_c_export(db) {
+            true
+        } else if let Some(assoc) = self.as_assoc_item(db) {
             matches!(assoc.container(db), AssocItemContainer::Member(_))
         } else {
             self.module(db).is_exported(db, self.name(db), ExportNs::Values)
@@ -528,6 +626,32 @@ impl Func {
         // }
 
         infer.add_diagnostics(db, self.id.into(), sink);
+
+        if data.has_body {
+            hir_def::lints::check_body(db.upcast(), self.id.into(), sink);
+        }
+
+        if self.is_c_export(db) {
+            let reason = if self.as_assoc_item(db).is_some() {
+                Some("class members cannot be exported")
+            } else if !data.has_body {
+                Some("it has no body")
+            } else if self.is_generic(db) {
+                Some("generic functions cannot be exported")
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                let src = self.id.lookup(db.upcast()).source(db.upcast());
+
+                sink.push(hir_def::diagnostics::InvalidExport {
+                    file: src.file_id,
+                    item: syntax::ptr::SyntaxNodePtr::new(src.value.syntax()),
+                    reason,
+                });
+            }
+        }
     }
 }
 
@@ -559,6 +683,10 @@ impl Static {
         self.module(db).path_to_name(db, self.name(db))
     }
 
+    pub fn ty(self, db: &dyn HirDatabase) -> ty::Ty {
+        db.value_ty(self.id.into()).ty
+    }
+
     pub fn has_body(self, db: &dyn HirDatabase) -> bool {
         !db.static_data(self.id).is_foreign
     }
@@ -569,7 +697,7 @@ impl Static {
 
         if let Some(name) = link_name.next() {
             (name.as_name(), false)
-        } else if attrs.by_key("no_mangle").exists() {
+        } else if attrs.by_key("no_mangle").exists() || attrs.by_key("export").exists() {
             (self.name(db), false)
         } else if self.is_foreign(db) {
             (self.name(db), false)
@@ -599,8 +727,15 @@ impl Static {
         db.static_data(self.id).is_foreign
     }
 
+    /// Whether the static is exported as a C global using `@export`.
+    pub fn is_c_export(self, db: &dyn HirDatabase) -> bool {
+        db.attrs(self.id.into()).by_key("export").exists()
+    }
+
     pub fn is_exported(self, db: &dyn HirDatabase) -> bool {
-        if let Some(assoc) = self.as_assoc_item(db) {
+        if self.is_c_export(db) {
+            true
+        } else if let Some(assoc) = self.as_assoc_item(db) {
             matches!(assoc.container(db), AssocItemContainer::Member(_))
         } else {
             self.module(db).is_exported(db, self.name(db), ExportNs::Values)
@@ -611,6 +746,27 @@ impl Static {
         let infer = db.infer(self.id.into());
 
         infer.add_diagnostics(db, self.id.into(), sink);
+        hir_def::lints::check_body(db.upcast(), self.id.into(), sink);
+
+        if self.is_c_export(db) {
+            let reason = if self.as_assoc_item(db).is_some() {
+                Some("class members cannot be exported")
+            } else if self.is_foreign(db) {
+                Some("it has no body")
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                let src = self.id.lookup(db.upcast()).source(db.upcast());
+
+                sink.push(hir_def::diagnostics::InvalidExport {
+                    file: src.file_id,
+                    item: syntax::ptr::SyntaxNodePtr::new(src.value.syntax()),
+                    reason,
+                });
+            }
+        }
     }
 }
 
@@ -642,10 +798,15 @@ impl Const {
         self.module(db).path_to_name(db, self.name(db))
     }
 
+    pub fn ty(self, db: &dyn HirDatabase) -> ty::Ty {
+        db.value_ty(self.id.into()).ty
+    }
+
     pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
         let infer = db.infer(self.id.into());
 
         infer.add_diagnostics(db, self.id.into(), sink);
+        hir_def::lints::check_body(db.upcast(), self.id.into(), sink);
     }
 }
 
@@ -677,6 +838,10 @@ impl TypeAlias {
         self.module(db).path_to_name(db, self.name(db))
     }
 
+    pub fn ty(self, db: &dyn HirDatabase) -> Ty {
+        db.type_for_alias(self.id).ty.ty
+    }
+
     pub fn type_vars(self, db: &dyn HirDatabase) -> Vec<TypeVar> {
         db.type_alias_data(self.id)
             .type_vars
@@ -860,6 +1025,19 @@ impl Class {
         self.module(db).is_exported(db, self.name(db), ExportNs::Types)
     }
 
+    pub fn type_vars(self, db: &dyn HirDatabase) -> Vec<TypeVar> {
+        db.class_data(self.id)
+            .type_vars
+            .iter()
+            .map(|&local_id| TypeVar {
+                id: TypeVarId {
+                    owner: TypeVarOwner::TypedDefId(TypedDefId::ClassId(self.id)),
+                    local_id,
+                },
+            })
+            .collect()
+    }
+
     pub fn constraints(self, _db: &dyn HirDatabase) -> Vec<ty::Constraint> {
         Vec::new()
     }
@@ -922,6 +1100,13 @@ impl Member {
         lower.member.class.into()
     }
 
+    /// The types the class is implemented for.
+    pub fn types(self, db: &dyn HirDatabase) -> Vec<Ty> {
+        let lower = db.lower_member(self.id);
+
+        lower.member.types.to_vec()
+    }
+
     pub fn item(self, db: &dyn HirDatabase, name: &Name) -> Option<AssocItem> {
         db.member_data(self.id).item(name).map(Into::into)
     }
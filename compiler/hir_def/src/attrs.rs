@@ -1,6 +1,7 @@
 use std::ops::Deref;
 use std::sync::Arc;
 
+use base_db::lints::{Lint, LintLevel};
 use cfg::{Cfg, CfgAtom, CfgValue};
 use either::Either;
 use syntax::ast;
@@ -13,6 +14,7 @@ use crate::item_tree::{ItemTreeId, ItemTreeNode};
 use crate::name::{AsName, Name};
 
 const CFG_ATTR: &'static str = "cfg";
+const TEST_ATTR: &'static str = "test";
 const DOC_ATTR: &'static str = "doc";
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -55,6 +57,18 @@ pub struct AttrQuery<'a> {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Documentation(String);
 
+/// A fenced code block in documentation, which `fi test` compiles and runs as a doctest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DocTest {
+    pub code: String,
+    /// The block is marked `ignore`: it is neither compiled nor run.
+    pub ignore: bool,
+    /// The block is marked `no_run`: it is compiled but not run.
+    pub no_run: bool,
+    /// The block is marked `should_crash`: it passes when it crashes or exits with an error.
+    pub should_crash: bool,
+}
+
 impl Attrs {
     pub const EMPTY: Self = Self(RawAttrs::EMPTY);
 
@@ -66,6 +80,19 @@ impl Attrs {
         cfg_parse(self)
     }
 
+    /// The level of `lint` set with `@allow(..)`, `@warn(..)` or `@deny(..)`. When a lint is
+    /// named by multiple attributes the last one is used.
+    pub fn lint_level(&self, lint: Lint) -> Option<LintLevel> {
+        self.iter().rev().find_map(|attr| {
+            let level = match attr.name.as_ref().parse::<LintLevel>() {
+                | Ok(level) => level,
+                | Err(_) => return None,
+            };
+
+            attr.group()?.ident(lint.name()).then(|| level)
+        })
+    }
+
     pub fn docs(&self) -> Option<Documentation> {
         let docs = self.by_key(DOC_ATTR).attrs().filter_map(|attr| attr.string_value());
         let indent = doc_indent(self);
@@ -321,6 +348,51 @@ impl Documentation {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// The fenced code blocks containing fi code. A block contains fi code when its info string
+    /// only consists of `fi` and the annotations of [`DocTest`], separated by commas or spaces.
+    pub fn doctests(&self) -> Vec<DocTest> {
+        let mut tests = Vec::new();
+        let mut lines = self.0.lines();
+
+        while let Some(text) = lines.next() {
+            let info = match text.trim_start().strip_prefix("```") {
+                | Some(info) => info,
+                | None => continue,
+            };
+
+            let code = lines
+                .by_ref()
+                .take_while(|l| !l.trim_start().starts_with("```"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let mut test = DocTest {
+                code,
+                ignore: false,
+                no_run: false,
+                should_crash: false,
+            };
+
+            let mut is_fi = true;
+
+            for annotation in info.split(|c: char| c == ',' || c.is_whitespace()) {
+                match annotation {
+                    | "" | "fi" => {},
+                    | "ignore" => test.ignore = true,
+                    | "no_run" => test.no_run = true,
+                    | "should_crash" => test.should_crash = true,
+                    | _ => is_fi = false,
+                }
+            }
+
+            if is_fi {
+                tests.push(test);
+            }
+        }
+
+        tests
+    }
 }
 
 fn doc_indent(attrs: &Attrs) -> usize {
@@ -358,7 +430,12 @@ fn collect_attrs(owner: &dyn ast::AttrsOwner) -> impl Iterator<Item = Either<ast
 }
 
 fn cfg_parse(attrs: &Attrs) -> Option<Cfg> {
-    let mut cfgs = attrs.by_key(CFG_ATTR).groups().map(cfg_parse_attr);
+    // Tests are only compiled into the test harness, as if they were marked `@cfg(test)`.
+    let test = attrs
+        .by_key(TEST_ATTR)
+        .exists()
+        .then(|| Cfg::Atom(CfgAtom::Flag(TEST_ATTR.into())));
+    let mut cfgs = attrs.by_key(CFG_ATTR).groups().map(cfg_parse_attr).chain(test);
     let a = cfgs.next()?;
 
     match cfgs.next() {
@@ -425,3 +502,77 @@ impl Deref for AttrsWithOwner {
         &self.attrs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doctests(docs: &str) -> Vec<DocTest> {
+        Documentation::new(docs.to_string()).doctests()
+    }
+
+    fn doctest(code: &str) -> DocTest {
+        DocTest {
+            code: code.to_string(),
+            ignore: false,
+            no_run: false,
+            should_crash: false,
+        }
+    }
+
+    #[test]
+    fn fi_and_untagged_blocks_are_doctests() {
+        let tests = doctests("Adds one.\n\n```fi\nadd_one 1\n```\n\n```\nadd_one 2\nadd_one 3\n```\n");
+
+        assert_eq!(tests, [doctest("add_one 1"), doctest("add_one 2\nadd_one 3")]);
+    }
+
+    #[test]
+    fn annotations() {
+        let tests = doctests(
+            "```fi,ignore\na\n```\n```no_run\nb\n```\n```fi should_crash\nc\n```\n```fi, no_run ignore\nd\n```\n",
+        );
+
+        assert_eq!(tests, [
+            DocTest {
+                ignore: true,
+                ..doctest("a")
+            },
+            DocTest {
+                no_run: true,
+                ..doctest("b")
+            },
+            DocTest {
+                should_crash: true,
+                ..doctest("c")
+            },
+            DocTest {
+                ignore: true,
+                no_run: true,
+                ..doctest("d")
+            },
+        ]);
+    }
+
+    #[test]
+    fn other_languages_are_skipped() {
+        let tests =
+            doctests("```rust\nfn main() {}\n```\n```fi,text\na\n```\n```toml\n[project]\n```\n```fi\nb\n```\n");
+
+        assert_eq!(tests, [doctest("b")]);
+    }
+
+    #[test]
+    fn indented_fences() {
+        let tests = doctests("- item\n\n  ```fi\n  a\n  ```\n");
+
+        assert_eq!(tests, [doctest("  a")]);
+    }
+
+    #[test]
+    fn unterminated_block_runs_to_the_end() {
+        let tests = doctests("```fi\na\n\nb");
+
+        assert_eq!(tests, [doctest("a\n\nb")]);
+    }
+}
@@ -1,4 +1,5 @@
 mod lower;
+mod pretty;
 
 use std::sync::Arc;
 
@@ -158,6 +159,14 @@ impl Body {
         self.body_expr
     }
 
+    pub fn exprs(&self) -> impl Iterator<Item = (ExprId, &Expr)> + '_ {
+        self.exprs.iter()
+    }
+
+    pub fn pats(&self) -> impl Iterator<Item = (PatId, &Pat)> + '_ {
+        self.pats.iter()
+    }
+
     pub fn type_map(&self) -> &TypeMap {
         &self.type_map
     }
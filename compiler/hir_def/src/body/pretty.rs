@@ -0,0 +1,429 @@
+//! Printing lowered bodies, for `fi build --emit=hir`.
+//!
+//! The output looks like source code, but every compound expression, pattern and type is
+//! wrapped in parentheses so that the structure of the lowered body is visible. Operators are
+//! printed as they were written, because their fixity is only resolved during type inference.
+
+use std::fmt::Write;
+
+use super::Body;
+use crate::expr::{CaseValue, Expr, ExprId, Literal, Stmt};
+use crate::name::Name;
+use crate::pat::{Pat, PatId};
+use crate::path::Path;
+use crate::type_ref::{LocalTypeRefId, TypeRef};
+
+impl Body {
+    /// Print the body as the definition of `name`.
+    pub fn pretty_print(&self, name: &Name) -> String {
+        let mut p = Printer {
+            body: self,
+            buf: String::new(),
+            indent: 0,
+        };
+
+        write!(p.buf, "{}", name).unwrap();
+
+        for &param in self.params() {
+            p.buf.push(' ');
+            p.pat_atom(param);
+        }
+
+        p.buf.push_str(" = ");
+        p.expr(self.body_expr());
+        p.buf
+    }
+}
+
+struct Printer<'a> {
+    body: &'a Body,
+    buf: String,
+    indent: usize,
+}
+
+impl Printer<'_> {
+    fn newline(&mut self) {
+        self.buf.push('\n');
+
+        for _ in 0..self.indent {
+            self.buf.push_str("    ");
+        }
+    }
+
+    fn block(&mut self, f: impl FnOnce(&mut Self)) {
+        self.indent += 1;
+        f(self);
+        self.indent -= 1;
+    }
+
+    fn expr_atom(&mut self, expr: ExprId) {
+        match &self.body[expr] {
+            | Expr::Missing
+            | Expr::Hole
+            | Expr::Unit
+            | Expr::Path { .. }
+            | Expr::Lit { .. }
+            | Expr::Field { .. }
+            | Expr::Index { .. }
+            | Expr::Record { .. }
+            | Expr::Array { .. }
+            | Expr::Recur => self.expr(expr),
+            | _ => {
+                self.buf.push('(');
+                self.expr(expr);
+                self.buf.push(')');
+            },
+        }
+    }
+
+    fn expr(&mut self, expr: ExprId) {
+        match &self.body[expr] {
+            | Expr::Missing => self.buf.push_str("{missing}"),
+            | Expr::Hole => self.buf.push('_'),
+            | Expr::Unit => self.buf.push_str("()"),
+            | Expr::Recur => self.buf.push_str("recur"),
+            | Expr::Path { path } => self.path(path),
+            | Expr::Lit { lit } => self.lit(lit),
+            | Expr::Typed { expr, ty } => {
+                self.expr_atom(*expr);
+                self.buf.push_str(" :: ");
+                self.type_ref(*ty);
+            },
+            | Expr::Infix { exprs, ops } => {
+                for (i, &expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push(' ');
+                        self.path(&ops[i - 1]);
+                        self.buf.push(' ');
+                    }
+
+                    self.expr_atom(expr);
+                }
+            },
+            | Expr::App { base, arg } => {
+                match self.body[*base] {
+                    | Expr::App { .. } => self.expr(*base),
+                    | _ => self.expr_atom(*base),
+                }
+
+                self.buf.push(' ');
+                self.expr_atom(*arg);
+            },
+            | Expr::Field { base, field } => {
+                self.expr_atom(*base);
+                write!(self.buf, ".{}", field).unwrap();
+            },
+            | Expr::Index { base, index } => {
+                self.expr_atom(*base);
+                self.buf.push('[');
+                self.expr(*index);
+                self.buf.push(']');
+            },
+            | Expr::Record { fields } => {
+                self.buf.push_str("{ ");
+
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push_str(", ");
+                    }
+
+                    write!(self.buf, "{} = ", field.name).unwrap();
+                    self.expr(field.val);
+                }
+
+                self.buf.push_str(" }");
+            },
+            | Expr::Array { exprs } => {
+                self.buf.push('[');
+
+                for (i, &expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push_str(", ");
+                    }
+
+                    self.expr(expr);
+                }
+
+                self.buf.push(']');
+            },
+            | Expr::Do { stmts } => self.stmts("do", stmts),
+            | Expr::Try { stmts } => self.stmts("try", stmts),
+            | Expr::Lambda { pats, body } => {
+                self.buf.push_str("fn");
+
+                for &pat in pats.iter() {
+                    self.buf.push(' ');
+                    self.pat_atom(pat);
+                }
+
+                self.buf.push_str(" -> ");
+                self.expr(*body);
+            },
+            | Expr::If { cond, then, else_ } => {
+                self.buf.push_str("if ");
+                self.expr(*cond);
+                self.block(|p| {
+                    p.newline();
+                    p.buf.push_str("then ");
+                    p.expr(*then);
+
+                    if let Some(else_) = else_ {
+                        p.newline();
+                        p.buf.push_str("else ");
+                        p.expr(*else_);
+                    }
+                });
+            },
+            | Expr::Case { pred, arms } => {
+                self.buf.push_str("case ");
+                self.expr(*pred);
+                self.buf.push_str(" of");
+                self.block(|p| {
+                    for arm in arms.iter() {
+                        p.newline();
+                        p.pat(arm.pat);
+
+                        match &arm.value {
+                            | CaseValue::Normal(expr) => {
+                                p.buf.push_str(" -> ");
+                                p.expr(*expr);
+                            },
+                            | CaseValue::Guarded(guards, exprs) => p.block(|p| {
+                                for (i, &expr) in exprs.iter().enumerate() {
+                                    p.newline();
+
+                                    match guards.get(i) {
+                                        | Some(&guard) => {
+                                            p.buf.push_str("| ");
+                                            p.expr(guard);
+                                        },
+                                        | None => p.buf.push_str("| else"),
+                                    }
+
+                                    p.buf.push_str(" -> ");
+                                    p.expr(expr);
+                                }
+                            }),
+                        }
+                    }
+                });
+            },
+            | Expr::Return { expr } => {
+                self.buf.push_str("return ");
+                self.expr(*expr);
+            },
+        }
+    }
+
+    fn stmts(&mut self, keyword: &str, stmts: &[Stmt]) {
+        self.buf.push_str(keyword);
+        self.block(|p| {
+            for stmt in stmts {
+                p.newline();
+
+                match *stmt {
+                    | Stmt::Let { pat, val } => {
+                        p.buf.push_str("let ");
+                        p.pat(pat);
+                        p.buf.push_str(" = ");
+                        p.expr(val);
+                    },
+                    | Stmt::Bind { pat, val } => {
+                        p.pat(pat);
+                        p.buf.push_str(" <- ");
+                        p.expr(val);
+                    },
+                    | Stmt::Expr { expr } => p.expr(expr),
+                }
+            }
+        });
+    }
+
+    fn pat_atom(&mut self, pat: PatId) {
+        match &self.body[pat] {
+            | Pat::Missing
+            | Pat::Wildcard
+            | Pat::Unit
+            | Pat::Path { .. }
+            | Pat::Bind { subpat: None, .. }
+            | Pat::Record { .. }
+            | Pat::Lit { .. } => self.pat(pat),
+            | _ => {
+                self.buf.push('(');
+                self.pat(pat);
+                self.buf.push(')');
+            },
+        }
+    }
+
+    fn pat(&mut self, pat: PatId) {
+        match &self.body[pat] {
+            | Pat::Missing => self.buf.push_str("{missing}"),
+            | Pat::Wildcard => self.buf.push('_'),
+            | Pat::Unit => self.buf.push_str("()"),
+            | Pat::Path { path } => self.path(path),
+            | Pat::Lit { lit } => self.lit(lit),
+            | Pat::Typed { pat, ty } => {
+                self.pat_atom(*pat);
+                self.buf.push_str(" :: ");
+                self.type_ref(*ty);
+            },
+            | Pat::Infix { pats, ops } => {
+                for (i, &pat) in pats.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push(' ');
+                        self.path(&ops[i - 1]);
+                        self.buf.push(' ');
+                    }
+
+                    self.pat_atom(pat);
+                }
+            },
+            | Pat::App { base, args } => {
+                self.pat_atom(*base);
+
+                for &arg in args.iter() {
+                    self.buf.push(' ');
+                    self.pat_atom(arg);
+                }
+            },
+            | Pat::Bind { name, subpat } => {
+                write!(self.buf, "{}", name).unwrap();
+
+                if let Some(subpat) = subpat {
+                    self.buf.push_str(" @ ");
+                    self.pat_atom(*subpat);
+                }
+            },
+            | Pat::Record { fields, has_rest } => {
+                self.buf.push_str("{ ");
+
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push_str(", ");
+                    }
+
+                    write!(self.buf, "{} = ", field.name).unwrap();
+                    self.pat(field.val);
+                }
+
+                if *has_rest {
+                    self.buf.push_str(if fields.is_empty() { ".." } else { ", .." });
+                }
+
+                self.buf.push_str(" }");
+            },
+        }
+    }
+
+    fn type_ref(&mut self, ty: LocalTypeRefId) {
+        let map = self.body.type_map();
+
+        match &map[ty] {
+            | TypeRef::Error => self.buf.push_str("{error}"),
+            | TypeRef::Placeholder => self.buf.push('_'),
+            | TypeRef::Unit => self.buf.push_str("()"),
+            | TypeRef::Figure(i) => write!(self.buf, "{}", i).unwrap(),
+            | TypeRef::Symbol(s) => write!(self.buf, "{:?}", s).unwrap(),
+            | TypeRef::Path(path) => self.path(path),
+            | TypeRef::App(base, arg) => {
+                self.buf.push('(');
+                self.type_ref(*base);
+                self.buf.push(' ');
+                self.type_ref(*arg);
+                self.buf.push(')');
+            },
+            | TypeRef::Infix(types, ops) => {
+                self.buf.push('(');
+
+                for (i, &ty) in types.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push(' ');
+                        self.path(&ops[i - 1]);
+                        self.buf.push(' ');
+                    }
+
+                    self.type_ref(ty);
+                }
+
+                self.buf.push(')');
+            },
+            | TypeRef::Record(fields, tail) | TypeRef::Row(fields, tail) => {
+                let (open, close) = match map[ty] {
+                    | TypeRef::Record(..) => ("{ ", " }"),
+                    | _ => ("(", ")"),
+                };
+
+                self.buf.push_str(open);
+
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push_str(", ");
+                    }
+
+                    write!(self.buf, "{} :: ", field.name).unwrap();
+                    self.type_ref(field.ty);
+                }
+
+                if let Some(tail) = tail {
+                    self.buf.push_str(" | ");
+                    self.type_ref(*tail);
+                }
+
+                self.buf.push_str(close);
+            },
+            | TypeRef::Forall(vars, inner) => {
+                self.buf.push_str("forall");
+
+                for &var in vars.iter() {
+                    write!(self.buf, " {}", map[var].name).unwrap();
+                }
+
+                self.buf.push_str(". ");
+                self.type_ref(*inner);
+            },
+            | TypeRef::Where(clause, inner) => {
+                self.type_ref(*inner);
+                self.buf.push_str(" where ");
+
+                for (i, ctnt) in clause.constraints.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push_str(", ");
+                    }
+
+                    self.path(&ctnt.class);
+
+                    for &ty in ctnt.types.iter() {
+                        self.buf.push(' ');
+                        self.type_ref(ty);
+                    }
+                }
+
+                for (i, kind) in clause.type_var_kinds.iter().enumerate() {
+                    if i > 0 || !clause.constraints.is_empty() {
+                        self.buf.push_str(", ");
+                    }
+
+                    self.type_ref(kind.type_var);
+                    self.buf.push_str(" :: ");
+                    self.type_ref(kind.kind);
+                }
+            },
+        }
+    }
+
+    fn path(&mut self, path: &Path) {
+        write!(self.buf, "{}", path).unwrap();
+    }
+
+    fn lit(&mut self, lit: &Literal) {
+        let res = match lit {
+            | Literal::String(s) => write!(self.buf, "{:?}", s),
+            | Literal::Char(c) => write!(self.buf, "{:?}", c),
+            | Literal::Int(i) => write!(self.buf, "{}", i),
+            | Literal::Float(bits) => write!(self.buf, "{:?}", f64::from_bits(*bits)),
+        };
+
+        res.unwrap();
+    }
+}
@@ -6,11 +6,29 @@ use syntax::ptr::SyntaxNodePtr;
 use crate::in_file::InFile;
 
 pub trait Diagnostic: Any + Send + Sync + fmt::Debug + 'static {
+    fn code(&self) -> DiagnosticCode;
     fn message(&self) -> String;
     fn display_source(&self) -> InFile<SyntaxNodePtr>;
     fn as_any(&self) -> &(dyn Any + Send + 'static);
 }
 
+/// A stable identifier of a kind of diagnostic, like `E0012`. Codes are never reused, the
+/// explanations of all codes are printed by `fi explain <code>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DiagnosticCode(pub &'static str);
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
 pub struct DiagnosticSink<'a> {
     callbacks: Vec<Box<dyn FnMut(&dyn Diagnostic) -> Result<(), ()> + 'a>>,
     default_callback: Box<dyn FnMut(&dyn Diagnostic) + 'a>,
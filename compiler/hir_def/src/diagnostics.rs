@@ -1,12 +1,13 @@
 use std::any::Any;
 
 use base_db::input::FileId;
+use base_db::lints::LintLevel;
 use syntax::ast;
 use syntax::ptr::{AstPtr, SyntaxNodePtr};
 
 use crate::ast_id::AstId;
 use crate::db::DefDatabase;
-use crate::diagnostic::{Diagnostic, DiagnosticSink};
+use crate::diagnostic::{Diagnostic, DiagnosticCode, DiagnosticSink};
 use crate::id::LocalModuleId;
 use crate::in_file::InFile;
 use crate::name::Name;
@@ -134,6 +135,10 @@ pub struct UnresolvedImport {
 }
 
 impl Diagnostic for UnresolvedImport {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0001")
+    }
+
     fn message(&self) -> String {
         "unresolved import".to_string()
     }
@@ -154,6 +159,10 @@ pub struct PrivateImport {
 }
 
 impl Diagnostic for PrivateImport {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0002")
+    }
+
     fn message(&self) -> String {
         "private import".to_string()
     }
@@ -176,6 +185,10 @@ pub struct DuplicateDeclaration {
 }
 
 impl Diagnostic for DuplicateDeclaration {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0003")
+    }
+
     fn message(&self) -> String {
         format!("the name `{}` is declared multiple times", self.name)
     }
@@ -188,3 +201,134 @@ impl Diagnostic for DuplicateDeclaration {
         self
     }
 }
+
+#[derive(Debug)]
+pub struct InvalidExport {
+    pub file: FileId,
+    pub item: SyntaxNodePtr,
+    pub reason: &'static str,
+}
+
+impl Diagnostic for InvalidExport {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0004")
+    }
+
+    fn message(&self) -> String {
+        format!("this item cannot be exported: {}", self.reason)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.item.clone())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnusedImport {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub name: String,
+    pub level: LintLevel,
+}
+
+impl Diagnostic for UnusedImport {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("W0001")
+    }
+
+    fn message(&self) -> String {
+        format!("unused import `{}`", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnusedBinding {
+    pub file: FileId,
+    pub pat: AstPtr<ast::Pat>,
+    pub name: String,
+    pub level: LintLevel,
+}
+
+impl Diagnostic for UnusedBinding {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("W0002")
+    }
+
+    fn message(&self) -> String {
+        format!("unused binding `{}`", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.pat.syntax_node_ptr())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnusedItem {
+    pub file: FileId,
+    pub item: AstPtr<ast::Item>,
+    pub kind: &'static str,
+    pub name: String,
+    pub level: LintLevel,
+}
+
+impl Diagnostic for UnusedItem {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("W0003")
+    }
+
+    fn message(&self) -> String {
+        format!("{} `{}` is never used", self.kind, self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.item.syntax_node_ptr())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct ShadowedName {
+    pub file: FileId,
+    pub pat: AstPtr<ast::Pat>,
+    pub shadowed: AstPtr<ast::Pat>,
+    pub name: String,
+    pub level: LintLevel,
+}
+
+impl Diagnostic for ShadowedName {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("W0004")
+    }
+
+    fn message(&self) -> String {
+        format!("`{}` shadows an earlier binding", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.pat.syntax_node_ptr())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
@@ -18,6 +18,7 @@ pub mod item_scope;
 pub mod item_tree;
 pub mod keys;
 pub mod lang_item;
+pub mod lints;
 pub mod name;
 pub mod pat;
 pub mod path;
@@ -0,0 +1,315 @@
+//! Lints for unused imports, bindings and items and for bindings shadowing an earlier binding.
+//!
+//! Lints are warnings by default. Their level is set with `@allow(..)`, `@warn(..)` and
+//! `@deny(..)` attributes on an item or module, or in the `[lints]` table of the manifest.
+
+use base_db::lints::{Lint, LintLevel};
+use either::Either;
+use rustc_hash::FxHashSet;
+use syntax::ptr::{AstPtr, SyntaxNodePtr};
+use syntax::{ast, AstNode, NodeOrToken, SyntaxKind, SyntaxNode};
+
+use crate::attrs::{Attrs, RawAttrs};
+use crate::db::DefDatabase;
+use crate::diagnostic::DiagnosticSink;
+use crate::diagnostics::{ShadowedName, UnusedBinding, UnusedImport, UnusedItem};
+use crate::expr::Expr;
+use crate::id::{AttrDefId, ContainerId, DefWithBodyId, Lookup, ModuleDefId, ModuleId};
+use crate::name::{AsName, Name};
+use crate::pat::Pat;
+use crate::resolver::{Resolver, ValueNs};
+use crate::source::HasSource;
+use crate::visibility::Visibility;
+
+/// Attributes marking an item as used from outside of the program.
+const USED_ATTRS: [&'static str; 6] = ["main", "test", "export", "no_mangle", "lang", "intrinsic"];
+
+/// The level of `lint` for `def`, set by an attribute on the item or one of its parents or by
+/// the library it is defined in.
+pub fn lint_level(db: &dyn DefDatabase, def: AttrDefId, lint: Lint) -> LintLevel {
+    let mut def = def;
+
+    loop {
+        if let Some(level) = db.attrs(def).lint_level(lint) {
+            return level;
+        }
+
+        def = match def {
+            | AttrDefId::FuncId(id) => container_def(id.lookup(db).container),
+            | AttrDefId::StaticId(id) => container_def(id.lookup(db).container),
+            | AttrDefId::FixityId(id) => id.lookup(db).module.into(),
+            | AttrDefId::ConstId(id) => id.lookup(db).module.into(),
+            | AttrDefId::TypeAliasId(id) => id.lookup(db).module.into(),
+            | AttrDefId::TypeCtorId(id) => id.lookup(db).module.into(),
+            | AttrDefId::ClassId(id) => id.lookup(db).module.into(),
+            | AttrDefId::MemberId(id) => id.lookup(db).module.into(),
+            | AttrDefId::ModuleId(id) => match db.def_map(id.lib)[id.local_id].parent {
+                | Some(parent) => ModuleId {
+                    lib: id.lib,
+                    local_id: parent,
+                }
+                .into(),
+                | None => return db.libs()[id.lib].lints.get(lint),
+            },
+        };
+    }
+}
+
+fn container_def(container: ContainerId) -> AttrDefId {
+    match container {
+        | ContainerId::Module(id) => id.into(),
+        | ContainerId::Class(id) => id.into(),
+        | ContainerId::Member(id) => id.into(),
+    }
+}
+
+/// Report unused imports and private items that are never used in `module`.
+pub fn check_module(db: &dyn DefDatabase, module: ModuleId, sink: &mut DiagnosticSink) {
+    let def_map = db.def_map(module.lib);
+    let data = &def_map[module.local_id];
+
+    if data.origin.is_virtual() {
+        return;
+    }
+
+    let decl = data.origin.declaration(db, &def_map);
+    let used = used_names(decl.value.syntax());
+    let import_level = lint_level(db, module.into(), Lint::UnusedImports);
+
+    for import in decl.value.imports() {
+        let level = Attrs(RawAttrs::new(&import))
+            .lint_level(Lint::UnusedImports)
+            .unwrap_or(import_level);
+
+        if level == LintLevel::Allow {
+            continue;
+        }
+
+        if let Some(qualify) = import.qualify() {
+            let name = qualify.as_name();
+
+            if !used.contains(&name) {
+                sink.push(UnusedImport {
+                    file: decl.file_id,
+                    src: SyntaxNodePtr::new(import.syntax()),
+                    name: name.to_string(),
+                    level,
+                });
+            }
+        } else if let Some(items) = import.items() {
+            for item in items {
+                let name = item.as_name();
+
+                if !used.contains(&name) && !uses_members(db, module, &name, &used) {
+                    sink.push(UnusedImport {
+                        file: decl.file_id,
+                        src: SyntaxNodePtr::new(item.syntax()),
+                        name: name.to_string(),
+                        level,
+                    });
+                }
+            }
+        }
+    }
+
+    for def in data.scope.declarations() {
+        let (name, kind, ctors) = match def {
+            | ModuleDefId::FuncId(id) => {
+                let data = db.func_data(id);
+
+                if data.is_foreign || !data.has_body {
+                    continue;
+                }
+
+                (data.name.clone(), "function", Vec::new())
+            },
+            | ModuleDefId::TypeCtorId(id) => {
+                let data = db.type_ctor_data(id);
+
+                if data.is_foreign {
+                    continue;
+                }
+
+                let ctors = data.ctors.iter().map(|(_, c)| c.name.clone()).collect();
+
+                (data.name.clone(), "type", ctors)
+            },
+            | ModuleDefId::TypeAliasId(id) => (db.type_alias_data(id).name.clone(), "type alias", Vec::new()),
+            | _ => continue,
+        };
+
+        let per_ns = data.scope.get(&name);
+        let vis = match def {
+            | ModuleDefId::FuncId(_) => per_ns.values,
+            | _ => per_ns.types,
+        };
+
+        if !matches!(vis, Some((d, Visibility::Module(_))) if d == def) {
+            continue;
+        }
+
+        if used.contains(&name) || ctors.iter().any(|c| used.contains(c)) {
+            continue;
+        }
+
+        let attr_def = match def {
+            | ModuleDefId::FuncId(id) => id.into(),
+            | ModuleDefId::TypeCtorId(id) => id.into(),
+            | ModuleDefId::TypeAliasId(id) => id.into(),
+            | _ => unreachable!(),
+        };
+
+        let attrs = db.attrs(attr_def);
+
+        if USED_ATTRS.iter().any(|&key| attrs.by_key(key).exists()) {
+            continue;
+        }
+
+        let level = lint_level(db, attr_def, Lint::UnusedItems);
+
+        if level == LintLevel::Allow {
+            continue;
+        }
+
+        let src = def.source(db);
+
+        sink.push(UnusedItem {
+            file: src.file_id,
+            item: AstPtr::new(&src.value),
+            kind,
+            name: name.to_string(),
+            level,
+        });
+    }
+}
+
+/// Report unused bindings and bindings shadowing an earlier binding in the body of `owner`.
+pub fn check_body(db: &dyn DefDatabase, owner: DefWithBodyId, sink: &mut DiagnosticSink) {
+    let (body, source_map) = db.body_source_map(owner);
+    let unused_level = lint_level(db, owner.into(), Lint::UnusedBindings);
+    let shadow_level = lint_level(db, owner.into(), Lint::ShadowedNames);
+
+    if unused_level != LintLevel::Allow {
+        let mut used = FxHashSet::default();
+
+        for (expr, e) in body.exprs() {
+            if let Expr::Path { path } = e {
+                if !path.is_ident() {
+                    continue;
+                }
+
+                let resolver = Resolver::for_expr(db, owner, expr);
+
+                if let Some((ValueNs::Local(pat), _)) = resolver.resolve_value_fully(db, path) {
+                    used.insert(pat);
+                }
+            }
+        }
+
+        for (pat, p) in body.pats() {
+            if let Pat::Bind { name, .. } = p {
+                if used.contains(&pat) || is_ignored(name) {
+                    continue;
+                }
+
+                if let Either::Left(src) = source_map.pat_syntax(pat) {
+                    sink.push(UnusedBinding {
+                        file: src.file_id,
+                        pat: src.value,
+                        name: name.to_string(),
+                        level: unused_level,
+                    });
+                }
+            }
+        }
+    }
+
+    if shadow_level != LintLevel::Allow {
+        let scopes = db.expr_scopes(owner);
+
+        for scope in scopes.iter() {
+            for entry in scopes.entries(scope) {
+                if is_ignored(entry.name()) {
+                    continue;
+                }
+
+                let shadowed = scopes
+                    .scope_chain(scopes.parent(scope))
+                    .flat_map(|s| scopes.entries(s))
+                    .find(|e| e.name() == entry.name() && e.pat() != entry.pat());
+
+                if let Some(shadowed) = shadowed {
+                    let src = source_map.pat_syntax(entry.pat());
+                    let shadowed = source_map.pat_syntax(shadowed.pat());
+
+                    if let (Either::Left(src), Either::Left(shadowed)) = (src, shadowed) {
+                        sink.push(ShadowedName {
+                            file: src.file_id,
+                            pat: src.value,
+                            shadowed: shadowed.value,
+                            name: entry.name().to_string(),
+                            level: shadow_level,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Bindings starting with an underscore are never reported, nor are the bindings introduced
+/// while lowering the body.
+fn is_ignored(name: &Name) -> bool {
+    let name = name.to_string();
+
+    name.starts_with('_') || name.starts_with('$')
+}
+
+/// Whether any constructor or class member brought into scope by importing `name` is used.
+fn uses_members(db: &dyn DefDatabase, module: ModuleId, name: &Name, used: &FxHashSet<Name>) -> bool {
+    let def_map = db.def_map(module.lib);
+
+    match def_map[module.local_id].scope.get(name).types {
+        | Some((ModuleDefId::TypeCtorId(id), _)) => {
+            db.type_ctor_data(id).ctors.iter().any(|(_, c)| used.contains(&c.name))
+        },
+        | Some((ModuleDefId::ClassId(id), _)) => db.class_data(id).items.iter().any(|(n, _)| used.contains(n)),
+        | _ => false,
+    }
+}
+
+/// All names referred to in a module, excluding imports. Nested modules are included, as they
+/// resolve names through the scope of their parents.
+fn used_names(node: &SyntaxNode) -> FxHashSet<Name> {
+    fn collect(node: &SyntaxNode, names: &mut FxHashSet<Name>) {
+        for child in node.children_with_tokens() {
+            match child {
+                | NodeOrToken::Node(node) => match node.kind() {
+                    | SyntaxKind::IMPORT => {},
+                    | SyntaxKind::NAME_REF => {
+                        names.insert(ast::NameRef::cast(node).unwrap().as_name());
+                    },
+                    | _ => collect(&node, names),
+                },
+                | NodeOrToken::Token(token) => {
+                    use SyntaxKind::*;
+
+                    if matches!(
+                        token.kind(),
+                        OPERATOR | ARROW | LEFT_ARROW | DBL_DOT | COMMA | COLON | PIPE | EQUALS | AT
+                    ) {
+                        names.insert(format!("({})", token.text()).as_name());
+                    }
+                },
+            }
+        }
+    }
+
+    let mut names = FxHashSet::default();
+
+    for child in node.children() {
+        collect(&child, &mut names);
+    }
+
+    names
+}
@@ -94,6 +94,14 @@ impl ExprScopes {
         &self.scopes_by_expr
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = ExprScopeId> + '_ {
+        self.scopes.iter().map(|(id, _)| id)
+    }
+
+    pub fn parent(&self, id: ExprScopeId) -> Option<ExprScopeId> {
+        self.scopes[id].parent
+    }
+
     pub fn entries(&self, id: ExprScopeId) -> &[ExprScopeEntry] {
         &self.scopes[id].entries
     }
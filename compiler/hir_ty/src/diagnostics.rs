@@ -2,7 +2,7 @@ use std::any::Any;
 use std::sync::Arc;
 
 use base_db::input::FileId;
-use hir_def::diagnostic::Diagnostic;
+use hir_def::diagnostic::{Diagnostic, DiagnosticCode};
 use hir_def::id::DefWithBodyId;
 use hir_def::in_file::InFile;
 use hir_def::resolver::ValueNs;
@@ -18,6 +18,10 @@ pub struct UnresolvedValue {
 }
 
 impl Diagnostic for UnresolvedValue {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0005")
+    }
+
     fn message(&self) -> String {
         "unknown value".into()
     }
@@ -38,6 +42,10 @@ pub struct UnresolvedType {
 }
 
 impl Diagnostic for UnresolvedType {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0006")
+    }
+
     fn message(&self) -> String {
         "unknown type".into()
     }
@@ -58,6 +66,10 @@ pub struct UnresolvedClass {
 }
 
 impl Diagnostic for UnresolvedClass {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0007")
+    }
+
     fn message(&self) -> String {
         "unknown class".into()
     }
@@ -79,6 +91,10 @@ pub struct UnresolvedOperator {
 }
 
 impl Diagnostic for UnresolvedOperator {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0008")
+    }
+
     fn message(&self) -> String {
         "unknown operator".into()
     }
@@ -99,6 +115,10 @@ pub struct PrivateValue {
 }
 
 impl Diagnostic for PrivateValue {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0009")
+    }
+
     fn message(&self) -> String {
         "value is not exported".to_string()
     }
@@ -119,6 +139,10 @@ pub struct PrivateType {
 }
 
 impl Diagnostic for PrivateType {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0010")
+    }
+
     fn message(&self) -> String {
         "type is not exported".to_string()
     }
@@ -139,6 +163,10 @@ pub struct PrivateClass {
 }
 
 impl Diagnostic for PrivateClass {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0011")
+    }
+
     fn message(&self) -> String {
         "class is not exported".to_string()
     }
@@ -160,6 +188,10 @@ pub struct PrivateOperator {
 }
 
 impl Diagnostic for PrivateOperator {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0012")
+    }
+
     fn message(&self) -> String {
         "operator is not exported".to_string()
     }
@@ -179,6 +211,10 @@ pub struct UninferredType {
 }
 
 impl Diagnostic for UninferredType {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0013")
+    }
+
     fn message(&self) -> String {
         "could not infer type".into()
     }
@@ -201,6 +237,10 @@ pub struct MismatchedKind {
 }
 
 impl Diagnostic for MismatchedKind {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0014")
+    }
+
     fn message(&self) -> String {
         "mismatched kinds".into()
     }
@@ -225,6 +265,10 @@ pub struct MismatchedType {
 }
 
 impl Diagnostic for MismatchedType {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0015")
+    }
+
     fn message(&self) -> String {
         "mismatched types".into()
     }
@@ -247,6 +291,10 @@ pub struct UnsolvedConstraint {
 }
 
 impl Diagnostic for UnsolvedConstraint {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0016")
+    }
+
     fn message(&self) -> String {
         "unresolved constraint".into()
     }
@@ -267,6 +315,10 @@ pub struct RecursiveTypeAlias {
 }
 
 impl Diagnostic for RecursiveTypeAlias {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0017")
+    }
+
     fn message(&self) -> String {
         "recursive type alias".into()
     }
@@ -287,6 +339,10 @@ pub struct InferenceCycle {
 }
 
 impl Diagnostic for InferenceCycle {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0018")
+    }
+
     fn message(&self) -> String {
         "cycle when inferring type".into()
     }
@@ -310,6 +366,10 @@ pub struct ValueHole {
 }
 
 impl Diagnostic for ValueHole {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("E0019")
+    }
+
     fn message(&self) -> String {
         "value hole".into()
     }
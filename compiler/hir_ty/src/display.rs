@@ -1,8 +1,9 @@
 use std::fmt;
 
 pub use fmt::{Result, Write};
-use hir_def::id::Lookup;
+use hir_def::id::{Lookup, TypedDefId};
 use hir_def::lang_item::LangItem;
+use hir_def::name::Name;
 
 use crate::class::{Class, FunDep, Member, Members};
 use crate::db::HirDatabase;
@@ -26,6 +27,23 @@ pub trait HirDisplay {
             t: self,
             max_size,
             display_target,
+            def_names: None,
+        }
+    }
+
+    /// Display `self` with the names of type constructors, aliases and classes written by
+    /// `def_names`.
+    fn display_with_names<'a>(
+        &'a self,
+        db: &'a dyn HirDatabase,
+        def_names: &'a dyn DefNames,
+    ) -> HirDisplayWrapper<'a, Self>
+    where
+        Self: Sized,
+    {
+        HirDisplayWrapper {
+            def_names: Some(def_names),
+            ..self.display(db)
         }
     }
 
@@ -51,6 +69,13 @@ pub struct HirFormatter<'a> {
     curr_size: usize,
     max_size: Option<usize>,
     display_target: DisplayTarget,
+    def_names: Option<&'a dyn DefNames>,
+}
+
+/// Writes the names of type constructors, aliases and classes, for example as links to their
+/// documentation.
+pub trait DefNames {
+    fn write_def(&self, f: &mut dyn fmt::Write, def: TypedDefId, name: &Name) -> fmt::Result;
 }
 
 #[derive(Clone, Copy)]
@@ -64,6 +89,7 @@ pub struct HirDisplayWrapper<'a, T> {
     t: &'a T,
     max_size: Option<usize>,
     display_target: DisplayTarget,
+    def_names: Option<&'a dyn DefNames>,
 }
 
 impl<'a> HirFormatter<'a> {
@@ -82,6 +108,17 @@ impl<'a> HirFormatter<'a> {
         Ok(())
     }
 
+    /// Write the `name` of the type constructor, alias or class `def`.
+    pub fn write_def(&mut self, def: TypedDefId, name: &Name) -> fmt::Result {
+        match self.def_names {
+            | Some(def_names) => {
+                self.curr_size += name.as_ref().len();
+                def_names.write_def(&mut *self.fmt, def, name)
+            },
+            | None => write!(self, "{}", name),
+        }
+    }
+
     pub fn should_truncate(&self) -> bool {
         if let Some(max_size) = self.max_size {
             self.curr_size >= max_size
@@ -122,6 +159,7 @@ where
             curr_size: 0,
             max_size: self.max_size,
             display_target: self.display_target,
+            def_names: self.def_names,
         })
     }
 }
@@ -303,8 +341,16 @@ impl HirDisplay for Ty {
 
                 write!(f, ")")
             },
-            | TyKind::Ctor(id) => write!(f, "{}", f.db.type_ctor_data(id).name),
-            | TyKind::Alias(id) => write!(f, "{}", f.db.type_alias_data(id).name),
+            | TyKind::Ctor(id) => {
+                let name = f.db.type_ctor_data(id).name.clone();
+
+                f.write_def(id.into(), &name)
+            },
+            | TyKind::Alias(id) => {
+                let name = f.db.type_alias_data(id).name.clone();
+
+                f.write_def(id.into(), &name)
+            },
             | TyKind::App(base, args) => {
                 TyParens(base, ParenMode::App).hir_fmt(f)?;
 
@@ -381,9 +427,9 @@ impl HirDisplay for WhereClause<Constraint> {
 
 impl HirDisplay for Constraint {
     fn hir_fmt(&self, f: &mut HirFormatter) -> fmt::Result {
-        let class_name = &f.db.class_data(self.class).name;
+        let class_name = f.db.class_data(self.class).name.clone();
 
-        write!(f, "{}", class_name)?;
+        f.write_def(self.class.into(), &class_name)?;
 
         for &ty in self.types.iter() {
             write!(f, " ")?;
@@ -445,7 +491,10 @@ impl HirDisplay for Member<Ty, Constraint> {
         }
 
         if self.class != hir_def::id::ClassId::dummy() {
-            write!(f, " of {}", f.db.class_data(self.class).name)?;
+            let class_name = f.db.class_data(self.class).name.clone();
+
+            write!(f, " of ")?;
+            f.write_def(self.class.into(), &class_name)?;
         }
 
         if !self.where_clause.constraints.is_empty() {
@@ -69,7 +69,9 @@ pub(crate) fn infer_query(db: &dyn HirDatabase, def: DefWithBodyId) -> Arc<Infer
                 ty = lcx.types.insert(TyInfo::ForAll(kinds, ty, scope, Some(id.into())), src);
             }
 
-            if db.attrs(id.into()).by_key("main").exists() {
+            let attrs = db.attrs(id.into());
+
+            if attrs.by_key("main").exists() || attrs.by_key("test").exists() {
                 lcx.main_fn(ty, db.body(id.into()).body_expr());
             }
 
@@ -59,6 +59,10 @@ impl AnalysisSnapshot {
         self.with_db(|db| db.line_index(file_id))
     }
 
+    pub fn file_text(&self, file_id: FileId) -> Result<Arc<str>, Cancelled> {
+        self.with_db(|db| db.file_text(file_id))
+    }
+
     pub fn hover(&self, file_offset: InFile<TextSize>) -> Result<Option<RangeInfo<hover::HoverInfo>>, Cancelled> {
         self.with_db(|db| hover::hover(db, file_offset))
     }
@@ -1,11 +1,11 @@
 use base_db::input::FileId;
 use base_db::{FileLoader, SourceDatabase, SourceDatabaseExt, Upcast};
 use crossbeam_channel::Sender;
-use diagnostics::{DiagnosticForWith, Level, SecondaryAnnotation};
+use diagnostics::{DiagnosticCode, DiagnosticForWith, Level, SecondaryAnnotation, Suggestion, SYNTAX_ERROR};
 use hir::db::DefDatabase;
 use lsp_server::Notification;
 use lsp_types::notification::{Notification as _, PublishDiagnostics};
-use lsp_types::{DiagnosticRelatedInformation, DiagnosticSeverity, PublishDiagnosticsParams};
+use lsp_types::{DiagnosticRelatedInformation, DiagnosticSeverity, NumberOrString, PublishDiagnosticsParams};
 use syntax::TextRange;
 
 use crate::db::LspDatabase;
@@ -13,11 +13,13 @@ use crate::state::{LspState, LspStateSnapshot, Task};
 use crate::util;
 
 pub struct Diagnostic {
+    pub code: DiagnosticCode,
     pub range: TextRange,
     pub message: String,
     pub severity: DiagnosticSeverity,
     pub secondary: Vec<SecondaryAnnotation>,
     pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
 }
 
 impl LspState {
@@ -45,6 +47,7 @@ impl LspStateSnapshot {
             lsp_diagnostics.push(lsp_types::Diagnostic {
                 range: util::lsp_range(&line_index, d.range),
                 severity: Some(d.severity),
+                code: Some(NumberOrString::String(d.code.to_string())),
                 message: d.message,
                 related_information: {
                     let mut annotations = Vec::with_capacity(d.secondary.len());
@@ -87,16 +90,19 @@ pub fn file_diagnostics(db: &LspDatabase, file_id: FileId) -> Vec<Diagnostic> {
     let mut result = Vec::new();
 
     result.extend(parsed.errors().iter().map(|d| Diagnostic {
+        code: SYNTAX_ERROR,
         range: d.range(),
         severity: lsp_types::DiagnosticSeverity::ERROR,
         message: d.msg.clone(),
         secondary: Vec::new(),
         notes: Vec::new(),
+        suggestions: Vec::new(),
     }));
 
     let mut sink = hir::diagnostic::DiagnosticSink::new(|d| {
         d.with_diagnostic(db, |d| {
             result.push(Diagnostic {
+                code: d.code(),
                 range: d.range(),
                 severity: match d.level() {
                     | Level::Error => lsp_types::DiagnosticSeverity::ERROR,
@@ -106,6 +112,7 @@ pub fn file_diagnostics(db: &LspDatabase, file_id: FileId) -> Vec<Diagnostic> {
                 message: d.title(),
                 secondary: d.secondary_annotations(),
                 notes: d.notes(),
+                suggestions: d.suggestions(),
             });
         })
     });
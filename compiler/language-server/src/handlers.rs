@@ -1,4 +1,12 @@
-use lsp_types::{Hover, HoverParams};
+use std::collections::HashMap;
+
+use base_db::input::FileId;
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionResponse, DocumentFormattingParams,
+    DocumentRangeFormattingParams, Hover, HoverParams, TextEdit, WorkspaceEdit,
+};
+use syntax::{TextRange, TextSize};
+use vfs::VfsPath;
 
 use crate::state::LspStateSnapshot;
 use crate::util;
@@ -24,3 +32,108 @@ pub fn handle_hover(snap: LspStateSnapshot, params: HoverParams) -> anyhow::Resu
 
     Ok(Some(hover))
 }
+
+pub fn handle_code_action(
+    snap: LspStateSnapshot,
+    params: CodeActionParams,
+) -> anyhow::Result<Option<CodeActionResponse>> {
+    let file_id = util::file_id(&snap, &params.text_document.uri)?;
+    let line_index = snap.line_index(file_id)?;
+    let range = util::text_range(&line_index, params.range);
+    let mut actions = Vec::new();
+
+    for d in snap.analysis.diagnostics(file_id)? {
+        if d.range.intersect(range).is_none() {
+            continue;
+        }
+
+        for suggestion in d.suggestions {
+            let mut changes = HashMap::<_, Vec<_>>::new();
+
+            for edit in suggestion.edits {
+                let line_index = snap.line_index(edit.range.file_id)?;
+
+                changes
+                    .entry(util::uri(&snap, edit.range.file_id)?)
+                    .or_default()
+                    .push(lsp_types::TextEdit {
+                        range: util::lsp_range(&line_index, edit.range.value),
+                        new_text: edit.text,
+                    });
+            }
+
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: suggestion.message,
+                kind: Some(CodeActionKind::QUICKFIX),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(changes),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+        }
+    }
+
+    Ok(Some(actions))
+}
+
+pub fn handle_formatting(
+    snap: LspStateSnapshot,
+    params: DocumentFormattingParams,
+) -> anyhow::Result<Option<Vec<TextEdit>>> {
+    let file_id = util::file_id(&snap, &params.text_document.uri)?;
+    let text = snap.analysis.file_text(file_id)?;
+
+    // Files with syntax errors are not formatted.
+    let formatted = match formatter::format(&text, &fmt_config(&snap, file_id)) {
+        | Ok(formatted) => formatted,
+        | Err(_) => return Ok(None),
+    };
+
+    if formatted == *text {
+        return Ok(Some(Vec::new()));
+    }
+
+    let line_index = snap.line_index(file_id)?;
+
+    Ok(Some(vec![TextEdit {
+        range: util::lsp_range(&line_index, TextRange::up_to(TextSize::of(&*text))),
+        new_text: formatted,
+    }]))
+}
+
+pub fn handle_range_formatting(
+    snap: LspStateSnapshot,
+    params: DocumentRangeFormattingParams,
+) -> anyhow::Result<Option<Vec<TextEdit>>> {
+    let file_id = util::file_id(&snap, &params.text_document.uri)?;
+    let text = snap.analysis.file_text(file_id)?;
+    let line_index = snap.line_index(file_id)?;
+    let range = util::text_range(&line_index, params.range);
+
+    match formatter::format_range(&text, range, &fmt_config(&snap, file_id)) {
+        | Ok(Some((range, new_text))) => Ok(Some(vec![TextEdit {
+            range: util::lsp_range(&line_index, range),
+            new_text,
+        }])),
+        | Ok(None) => Ok(Some(Vec::new())),
+        | Err(_) => Ok(None),
+    }
+}
+
+/// The formatter options of the package containing `file_id`.
+fn fmt_config(snap: &LspStateSnapshot, file_id: FileId) -> formatter::Config {
+    let vfs = snap.vfs.read();
+    let path = match vfs.file_path(file_id) {
+        | VfsPath::PathBuf(path) => path.clone(),
+        | _ => return formatter::Config::default(),
+    };
+
+    snap.workspaces
+        .iter()
+        .flat_map(|ws| ws.packages().map(move |p| &ws[p]))
+        .filter(|data| path.starts_with(data.manifest_path.parent().unwrap()))
+        .max_by_key(|data| data.manifest_path.as_os_str().len())
+        .map(|data| data.fmt.clone())
+        .unwrap_or_default()
+}
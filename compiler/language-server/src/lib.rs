@@ -85,6 +85,9 @@ fn server_capabilities(_client_args: &ClientCapabilities) -> ServerCapabilities
             },
         )),
         hover_provider: Some(lsp_types::HoverProviderCapability::Simple(true)),
+        code_action_provider: Some(lsp_types::CodeActionProviderCapability::Simple(true)),
+        document_formatting_provider: Some(lsp_types::OneOf::Left(true)),
+        document_range_formatting_provider: Some(lsp_types::OneOf::Left(true)),
         ..Default::default()
     }
 }
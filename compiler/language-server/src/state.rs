@@ -12,7 +12,7 @@ use lsp_server::{ErrorCode, Message, Notification, ReqQueue, Request, Response};
 use lsp_types::notification::{
     self, DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
 };
-use lsp_types::request::{HoverRequest, Shutdown};
+use lsp_types::request::{CodeActionRequest, Formatting, HoverRequest, RangeFormatting, Shutdown};
 use parking_lot::RwLock;
 use paths::AbsPathBuf;
 use project::Workspace;
@@ -165,6 +165,9 @@ impl LspState {
                 Ok(())
             })?
             .on::<HoverRequest>(crate::handlers::handle_hover)?
+            .on::<CodeActionRequest>(crate::handlers::handle_code_action)?
+            .on::<Formatting>(crate::handlers::handle_formatting)?
+            .on::<RangeFormatting>(crate::handlers::handle_range_formatting)?
             .finish();
         Ok(())
     }
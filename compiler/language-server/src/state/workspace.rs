@@ -4,7 +4,7 @@ use std::sync::Arc;
 
 use base_db::libs::LibSet;
 use paths::{AbsPath, AbsPathBuf};
-use project::manifest::Manifest;
+use project::manifest::{self, Manifest};
 use project::Workspace;
 use vfs::file_set::FileSetConfig;
 use vfs::VfsPath;
@@ -22,7 +22,7 @@ impl LspState {
             .config
             .workspaces
             .iter()
-            .filter_map(|path| Workspace::load(path.clone(), &mut vfs, &cfg).ok())
+            .filter_map(|path| Workspace::load_offline(path.clone(), &mut vfs, &cfg).ok())
             .collect::<Vec<_>>();
 
         if workspaces == *self.workspaces {
@@ -96,10 +96,13 @@ pub fn discover(path: &AbsPath) -> Option<AbsPathBuf> {
     None
 }
 
+/// Discover the projects containing `paths`, replacing members of a workspace with the root
+/// of the workspace.
 pub fn discover_all(paths: &[AbsPathBuf]) -> Vec<AbsPathBuf> {
     let mut res = paths
         .iter()
         .filter_map(|it| discover(it.as_ref()))
+        .map(|dir| manifest::find_workspace_root(&dir))
         .collect::<HashSet<_>>()
         .into_iter()
         .collect::<Vec<_>>();
@@ -2,8 +2,13 @@ mod server;
 
 use lsp_server::Message;
 use lsp_types::notification::{DidOpenTextDocument, Notification, PublishDiagnostics};
-use lsp_types::{DidOpenTextDocumentParams, TextDocumentItem};
+use lsp_types::request::Formatting;
+use lsp_types::{DidOpenTextDocumentParams, DocumentFormattingParams, FormattingOptions, TextDocumentItem};
+use project::Workspace;
 use server::Project;
+use vfs::VirtualFileSystem;
+
+use crate::state::workspace::discover_all;
 
 #[test]
 fn initialization() {
@@ -80,3 +85,133 @@ main = )
         | _ => false,
     });
 }
+
+#[test]
+fn workspace() {
+    let project = Project::new()
+        .with_file(
+            "fi.toml",
+            r#"
+[workspace]
+members = ["./app", "util"]
+"#,
+        )
+        .with_file(
+            "app/fi.toml",
+            r#"
+[project]
+name = "app"
+version = "0.1.0"
+output = "executable"
+
+[dependencies]
+util = { path = "../util" }
+"#,
+        )
+        .with_file(
+            "app/src/main.fi",
+            r#"
+module Main =
+
+import Util (unit)
+
+main = unit
+"#,
+        )
+        .with_file(
+            "util/fi.toml",
+            r#"
+[project]
+name = "util"
+version = "0.1.0"
+output = "static"
+"#,
+        )
+        .with_file(
+            "util/src/util.fi",
+            r#"
+module Util =
+
+unit = ()
+"#,
+        );
+
+    let server = project.server().wait_until_workspace_loaded();
+    let root = server.path("");
+    let members = [server.path("app"), server.path("util")];
+
+    // Both members belong to the workspace at the root, even though `app` is not normalized.
+    assert_eq!(discover_all(&members), [root.clone()]);
+
+    let mut ws = Workspace::load_offline(root.clone(), &mut VirtualFileSystem::default(), &Default::default()).unwrap();
+    let names = |ws: &Workspace, packages: &[project::Package]| {
+        packages.iter().map(|&p| ws[p].name.clone()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(&ws, ws.members()), ["app", "util"]);
+    assert_eq!(names(&ws, ws.selected()), ["app", "util"]);
+
+    // `-p app` selects only `app`, but still needs `util` as its dependency.
+    ws.select(&["app"]).unwrap();
+
+    let util = ws.members()[1];
+
+    assert_eq!(names(&ws, ws.selected()), ["app"]);
+    assert!(ws.is_selected(Some(util)));
+    assert!(ws.select(&["missing"]).is_err());
+
+    // The members share the target directory in the root of the workspace.
+    assert_eq!(ws.root_dir(), root.as_path());
+}
+
+#[test]
+fn formatting() {
+    let project = Project::new()
+        .with_file(
+            "fi.toml",
+            r#"
+[project]
+name = "test"
+version = "0.1.0"
+output = "executable"
+
+[fmt]
+indent-width = 2
+"#,
+        )
+        .with_file(
+            "src/main.fi",
+            r#"module Main =
+
+import Core:Ops (index, Index)
+import Core:Cmp
+
+main =
+        let x = 1+2
+        x
+"#,
+        );
+
+    let server = project.server().wait_until_workspace_loaded();
+    let edits = server
+        .request::<Formatting>(DocumentFormattingParams {
+            text_document: server.doc_id("src/main.fi"),
+            options: FormattingOptions::default(),
+            work_done_progress_params: Default::default(),
+        })
+        .unwrap();
+
+    assert_eq!(edits.len(), 1);
+    assert_eq!(
+        edits[0].new_text,
+        r#"module Main =
+
+import Core:Cmp
+import Core:Ops (Index, index)
+
+main =
+  let x = 1 + 2
+  x
+"#
+    );
+}
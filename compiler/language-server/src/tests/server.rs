@@ -28,7 +28,7 @@ impl<'a> Project<'a> {
     }
 
     pub fn server(self) -> Server {
-        let tmp_dir = tempdir::TempDir::new("testdir").unwrap();
+        let tmp_dir = tempfile::TempDir::new().unwrap();
 
         for (path, text) in self.files {
             let path = tmp_dir.path().join(path);
@@ -54,11 +54,11 @@ pub struct Server {
     messages: RefCell<Vec<Message>>,
     worker: Option<JoinHandle<()>>,
     client: Connection,
-    tmp_dir: tempdir::TempDir,
+    tmp_dir: tempfile::TempDir,
 }
 
 impl Server {
-    pub fn new(tmp_dir: tempdir::TempDir, config: Config) -> Self {
+    pub fn new(tmp_dir: tempfile::TempDir, config: Config) -> Self {
         let (connection, client) = Connection::memory();
         let worker = std::thread::spawn(move || {
             crate::main_loop(connection, config).unwrap();
@@ -73,6 +73,10 @@ impl Server {
         }
     }
 
+    pub fn path(&self, rel_path: &str) -> AbsPathBuf {
+        AbsPathBuf::assert(self.tmp_dir.path().join(rel_path))
+    }
+
     pub fn doc_id(&self, rel_path: &str) -> lsp_types::TextDocumentIdentifier {
         let path = self.tmp_dir.path().join(rel_path);
 
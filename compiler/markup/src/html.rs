@@ -0,0 +1,143 @@
+//! Rendering markup to HTML.
+
+use crate::{Markup, MarkupRenderer, Styles};
+
+#[derive(Default)]
+pub struct HtmlRenderer {
+    out: String,
+}
+
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render `markup` to a string of HTML.
+    pub fn render(markup: &Markup) -> String {
+        let mut renderer = Self::new();
+
+        renderer.render_markup(markup);
+        renderer.finish()
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+impl MarkupRenderer for HtmlRenderer {
+    fn render_text(&mut self, text: &String, styles: Styles) {
+        let tags = [
+            (styles.code(), "code"),
+            (styles.bold(), "strong"),
+            (styles.italic(), "em"),
+            (styles.underline(), "u"),
+        ];
+
+        for (_, tag) in tags.iter().filter(|(set, _)| *set) {
+            self.out.push_str(&format!("<{}>", tag));
+        }
+
+        self.out.push_str(&escape(text));
+
+        for (_, tag) in tags.iter().rev().filter(|(set, _)| *set) {
+            self.out.push_str(&format!("</{}>", tag));
+        }
+    }
+
+    fn render_header(&mut self, text: &String, level: u8) {
+        let level = level.clamp(1, 6);
+
+        self.out.push_str(&format!("<h{0}>{1}</h{0}>", level, escape(text)));
+    }
+
+    fn render_line(&mut self) {
+        self.out.push_str("<hr>");
+    }
+
+    fn render_newline(&mut self) {
+        self.out.push('\n');
+    }
+
+    fn render_paragraph(&mut self, inner: &Markup) {
+        self.out.push_str("<p>");
+        self.render_markup(inner);
+        self.out.push_str("</p>");
+    }
+
+    fn render_code(&mut self, code: &String, lang: Option<&str>) {
+        match lang {
+            | Some(lang) => self
+                .out
+                .push_str(&format!("<pre><code class=\"language-{}\">", escape(lang))),
+            | None => self.out.push_str("<pre><code>"),
+        }
+
+        self.out.push_str(&escape(code));
+        self.out.push_str("</code></pre>");
+    }
+}
+
+/// Escape the characters of `text` which have a meaning in HTML.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            | '&' => escaped.push_str("&amp;"),
+            | '<' => escaped.push_str("&lt;"),
+            | '>' => escaped.push_str("&gt;"),
+            | '"' => escaped.push_str("&quot;"),
+            | '\'' => escaped.push_str("&#39;"),
+            | _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown;
+
+    fn html(text: &str) -> String {
+        HtmlRenderer::render(&markdown::parse(text))
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(
+            escape("<a href=\"x\">'&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            html("# Title & <more>\n\nSome text\ncontinued.\n\n```fi,ignore\nx < \"y\"\n```\n---\n- item\n"),
+            "<h1>Title &amp; &lt;more&gt;</h1>\n<p>Some text continued.</p>\n<pre><code \
+             class=\"language-fi\">x &lt; &quot;y&quot;</code></pre>\n<hr>\n<p>• item</p>"
+        );
+    }
+
+    #[test]
+    fn inline_styles() {
+        assert_eq!(
+            html("Use `a < b`, **bold**, *italic* and _emphasis_ in snake_case_names."),
+            "<p>Use <code>a &lt; b</code>, <strong>bold</strong>, <em>italic</em> and <em>emphasis</em> in \
+             snake_case_names.</p>"
+        );
+    }
+
+    #[test]
+    fn unbalanced_delimiters_are_text() {
+        assert_eq!(html("2 * 3 and `x"), "<p>2 * 3 and `x</p>");
+    }
+
+    #[test]
+    fn code_without_language() {
+        assert_eq!(html("```\n<tag>\n```"), "<pre><code>&lt;tag&gt;</code></pre>");
+    }
+}
@@ -1,3 +1,9 @@
+//! A small document model for rendering documentation and messages to different outputs, like
+//! the terminal or [HTML](html).
+
+pub mod html;
+pub mod markdown;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Markup {
     parts: Vec<Part>,
@@ -9,6 +15,8 @@ enum Part {
     Header(String, u8),
     Paragraph(Markup),
     Line,
+    /// A block of code with the language it is written in.
+    Code(String, Option<String>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -29,6 +37,16 @@ pub trait MarkupRenderer {
         self.render_markup(inner);
     }
 
+    fn render_code(&mut self, code: &String, _lang: Option<&str>) {
+        for (i, line) in code.lines().enumerate() {
+            if i > 0 {
+                self.render_newline();
+            }
+
+            self.render_text(&line.to_string(), Styles::CODE);
+        }
+    }
+
     fn render_markup(&mut self, markup: &Markup) {
         let mut start = true;
         let mut block = false;
@@ -67,6 +85,14 @@ pub trait MarkupRenderer {
                     self.render_paragraph(inner);
                     block = true;
                 },
+                | Part::Code(code, lang) => {
+                    if !start {
+                        self.render_newline();
+                    }
+
+                    self.render_code(code, lang.as_deref());
+                    block = true;
+                },
             }
 
             start = false;
@@ -94,6 +120,15 @@ impl Markup {
         self
     }
 
+    pub fn code(mut self, code: impl Into<String>, lang: Option<String>) -> Self {
+        self.parts.push(Part::Code(code.into(), lang));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
     pub fn paragraph(self) -> ParagraphBuilder {
         ParagraphBuilder {
             markup: self,
@@ -131,6 +166,7 @@ impl Styles {
     pub const BOLD: Self = Styles(1 << 1);
     pub const ITALIC: Self = Styles(1 << 2);
     pub const UNDERLINE: Self = Styles(1 << 3);
+    pub const CODE: Self = Styles(1 << 4);
 
     pub fn bold(self) -> bool {
         self.0 & (1 << 1) != 0
@@ -143,6 +179,10 @@ impl Styles {
     pub fn underline(self) -> bool {
         self.0 & (1 << 3) != 0
     }
+
+    pub fn code(self) -> bool {
+        self.0 & (1 << 4) != 0
+    }
 }
 
 impl std::ops::BitOr for Styles {
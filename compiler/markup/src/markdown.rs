@@ -0,0 +1,132 @@
+//! Parsing the Markdown of documentation comments.
+//!
+//! Only the subset used in documentation is supported: headers, paragraphs, list items,
+//! horizontal rules, fenced code blocks, and inline code, bold and italic text. Anything else is
+//! kept as plain text.
+
+use crate::{Markup, ParagraphBuilder, Styles};
+
+/// Parse the Markdown in `text`.
+pub fn parse(text: &str) -> Markup {
+    let mut markup = Markup::new();
+    let mut para = Vec::new();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if let Some(info) = trimmed.strip_prefix("```") {
+            markup = paragraph(markup, &mut para);
+
+            let code = lines
+                .by_ref()
+                .take_while(|l| !l.trim_start().starts_with("```"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            markup = markup.code(code, code_lang(info).map(str::to_string));
+        } else if trimmed.is_empty() {
+            markup = paragraph(markup, &mut para);
+        } else if let Some((level, text)) = header(trimmed) {
+            markup = paragraph(markup, &mut para);
+            markup = markup.header(text, level);
+        } else if trimmed.len() >= 3 && (trimmed.chars().all(|c| c == '-') || trimmed.chars().all(|c| c == '*')) {
+            markup = paragraph(markup, &mut para);
+            markup = markup.line();
+        } else if let Some(item) = list_item(trimmed) {
+            markup = paragraph(markup, &mut para);
+            para.push(item);
+        } else {
+            para.push(trimmed.to_string());
+        }
+    }
+
+    paragraph(markup, &mut para)
+}
+
+/// The language of a fenced code block with the info string `info`, like `fi` in `fi,ignore`.
+pub fn code_lang(info: &str) -> Option<&str> {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .next()
+        .filter(|lang| !lang.is_empty())
+}
+
+fn paragraph(markup: Markup, lines: &mut Vec<String>) -> Markup {
+    if lines.is_empty() {
+        return markup;
+    }
+
+    let text = lines.join(" ");
+
+    lines.clear();
+    inline(markup.paragraph(), &text).finish()
+}
+
+fn header(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+
+    (1..=6).contains(&level).then(|| (level as u8, text.trim()))
+}
+
+fn list_item(line: &str) -> Option<String> {
+    if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|b| line.strip_prefix(b)) {
+        return Some(format!("• {}", item));
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+
+    (digits > 0 && line[digits..].starts_with(". ")).then(|| line.to_string())
+}
+
+/// Split `text` into runs of inline code, bold, italic and plain text.
+fn inline(mut para: ParagraphBuilder, text: &str) -> ParagraphBuilder {
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let span = match c {
+            | '`' => delimited(rest, "`").map(|(inner, len)| (inner, len, Styles::CODE)),
+            | '*' if rest.starts_with("**") => delimited(rest, "**").map(|(inner, len)| (inner, len, Styles::BOLD)),
+            | '*' => delimited(rest, "*").map(|(inner, len)| (inner, len, Styles::ITALIC)),
+            | '_' if !plain.ends_with(|c: char| c.is_alphanumeric()) => {
+                delimited(rest, "_").map(|(inner, len)| (inner, len, Styles::ITALIC))
+            },
+            | _ => None,
+        };
+
+        match span {
+            | Some((inner, len, styles)) => {
+                if !plain.is_empty() {
+                    para = para.text(std::mem::take(&mut plain), Styles::NONE);
+                }
+
+                para = para.text(inner.to_string(), styles);
+                rest = &rest[len..];
+            },
+            | None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            },
+        }
+    }
+
+    if !plain.is_empty() {
+        para = para.text(plain, Styles::NONE);
+    }
+
+    para
+}
+
+/// The text between `delim` at the start of `text` and the next `delim`, and the length of the
+/// whole span.
+fn delimited<'a>(text: &'a str, delim: &str) -> Option<(&'a str, usize)> {
+    let inner = &text[delim.len()..];
+    let end = inner.find(delim)?;
+
+    if end == 0 || inner.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some((&inner[..end], end + delim.len() * 2))
+}
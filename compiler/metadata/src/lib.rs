@@ -133,5 +133,19 @@ fn hash_cfg(cfg: &CfgOptions) -> u64 {
         value.hash(&mut hasher);
     }
 
+    // The values are unordered, so their hashes are combined independent of the order.
+    let mut values = 0u64;
+
+    for (key, set) in cfg.values().iter() {
+        for value in set {
+            let mut hasher = rustc_hash::FxHasher::default();
+
+            key.hash(&mut hasher);
+            value.hash(&mut hasher);
+            values = values.wrapping_add(hasher.finish());
+        }
+    }
+
+    values.hash(&mut hasher);
     hasher.finish()
 }
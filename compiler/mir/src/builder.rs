@@ -1,4 +1,4 @@
-use hir::Ctor;
+use hir::{Ctor, ExprId, PatId};
 
 use crate::instance::Instance;
 use crate::repr::Repr;
@@ -7,6 +7,7 @@ use crate::syntax::*;
 pub struct Builder {
     body: BodyData,
     block: Option<Block>,
+    source: Option<ExprId>,
 }
 
 impl Builder {
@@ -16,8 +17,10 @@ impl Builder {
                 origin,
                 locals: Default::default(),
                 blocks: Default::default(),
+                vars: Vec::new(),
             },
             block: None,
+            source: None,
         }
     }
 
@@ -45,6 +48,7 @@ impl Builder {
             params: Vec::new(),
             stmts: Vec::new(),
             term: Term::None,
+            sources: Vec::new(),
         });
 
         id
@@ -58,6 +62,15 @@ impl Builder {
         self.body.blocks[block.0].params.push(param);
     }
 
+    /// Set the expression new statements are lowered from, returning the previous one.
+    pub fn set_source(&mut self, source: Option<ExprId>) -> Option<ExprId> {
+        std::mem::replace(&mut self.source, source)
+    }
+
+    pub fn add_var(&mut self, local: Local, pat: PatId) {
+        self.body.vars.push((local, pat));
+    }
+
     pub fn add_local(&mut self, kind: LocalKind, repr: Repr) -> Local {
         let id = Local(self.body.locals.next_idx());
 
@@ -70,14 +83,20 @@ impl Builder {
     }
 
     fn stmt(&mut self, stmt: Stmt) {
-        self.block().stmts.push(stmt);
+        let source = self.source;
+        let block = self.block();
+
+        block.sources.insert(block.stmts.len(), source);
+        block.stmts.push(stmt);
     }
 
     fn term(&mut self, term: Term) {
+        let source = self.source;
         let block = self.block();
 
         if let Term::None = block.term {
             block.term = term;
+            block.sources.push(source);
         }
     }
 
@@ -153,6 +172,10 @@ impl Builder {
         self.stmt(Stmt::Assign(res, Rvalue::InstanceRef(instance)));
     }
 
+    pub fn closure(&mut self, res: Place, instance: Instance, captures: impl Into<Vec<Operand>>) {
+        self.stmt(Stmt::Assign(res, Rvalue::Closure(instance, captures.into())));
+    }
+
     pub fn binop(&mut self, res: Place, op: BinOp, lhs: impl Into<Operand>, rhs: impl Into<Operand>) {
         self.stmt(Stmt::Assign(res, Rvalue::BinOp(op, lhs.into(), rhs.into())));
     }
@@ -162,43 +185,11 @@ impl Builder {
     }
 
     pub fn place_repr(&self, place: &Place) -> Repr {
-        let mut repr = self.body.locals[place.local.0].repr.clone();
-
-        for proj in place.projection.iter() {
-            match *proj {
-                | Projection::Deref => match repr {
-                    | Repr::Box(inner) => repr = *inner,
-                    | Repr::Ptr(inner, false, _) => repr = *inner,
-                    | _ => unreachable!(),
-                },
-                | Projection::Field(i) => match repr {
-                    | Repr::Struct(fields) => repr = fields[i].clone(),
-                    | _ => unreachable!(),
-                },
-                | Projection::Index(_) => match repr {
-                    | Repr::Array(_, e) => repr = *e.clone(),
-                    | _ => unreachable!(),
-                },
-                | Projection::Slice(_, _) => match repr {
-                    | Repr::Array(_, e) => repr = Repr::Ptr(e.clone(), true, true),
-                    | Repr::Ptr(_, true, _) => {},
-                    | _ => unreachable!(),
-                },
-                | Projection::Downcast(ctor) => match repr {
-                    | Repr::Enum(e) => repr = e[ctor.idx()].clone(),
-                    | _ => unreachable!(),
-                },
-            }
-        }
-
-        repr
+        self.body.place_repr(place)
     }
 
     pub fn operand_repr(&self, op: &Operand) -> Repr {
-        match op {
-            | Operand::Copy(place) | Operand::Move(place) => self.place_repr(place),
-            | Operand::Const(_, repr) => repr.clone(),
-        }
+        self.body.operand_repr(op)
     }
 }
 
@@ -19,6 +19,9 @@ pub trait MirDatabase: HirDatabase + Upcast<dyn HirDatabase> {
     #[salsa::invoke(crate::lower::lower_body)]
     fn body_mir(&self, def: DefWithBodyId) -> Body;
 
+    #[salsa::invoke(crate::transform::optimized_body)]
+    fn optimized_body(&self, body: Body) -> Body;
+
     #[salsa::invoke(crate::lower::mir_main_shim)]
     fn mir_main_shim(&self, main_fn: hir::Func) -> Body;
 
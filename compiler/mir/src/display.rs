@@ -242,6 +242,19 @@ impl HirDisplay for Rvalue {
             | Self::Discriminant(p) => write!(f, "discriminant {}", p.display(f.db)),
             | Self::Cast(kind, op) => write!(f, "cast {} ({:?})", op.display(f.db), kind),
             | Self::InstanceRef(i) => write!(f, "instance {}", i.display(f.db)),
+            | Self::Closure(i, captures) => {
+                write!(f, "closure {} [", i.display(f.db))?;
+
+                for (idx, op) in captures.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    op.hir_fmt(f)?;
+                }
+
+                f.write_char(']')
+            },
             | Self::BinOp(op, lhs, rhs) => write!(f, "{} {} {}", lhs.display(f.db), op, rhs.display(f.db)),
             | Self::NullOp(op, repr) => write!(f, "{} {}", op, repr.display(f.db)),
         }
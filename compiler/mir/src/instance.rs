@@ -3,6 +3,7 @@ use std::sync::Arc;
 use arena::ArenaMap;
 use hir::ty::{Ty, TyKind, TypeVarScopeId};
 use hir::{DefWithBody, HirDisplay, MethodSource, Name};
+use salsa::InternKey;
 
 use crate::db::MirDatabase;
 use crate::repr::{ArrayLen, Repr, Signature};
@@ -132,19 +133,16 @@ impl InstanceDef {
             },
             | Self::Body(id) => {
                 let origin = db.lookup_intern_body(id).origin;
-                let (name, mut mangle) = def_name(db, origin.def.into());
+                let (name, _) = def_name(db, origin.def.into());
                 let mut name = name.to_string();
 
                 if let Some(expr) = origin.expr {
                     name.push_str(&format!("#{}", u32::from(expr.into_raw())));
-                    mangle = true;
                 }
 
-                if mangle {
-                    name = mangling::mangle(name.bytes());
-                }
-
-                name
+                // multiple closure bodies can originate from the same expression
+                name.push_str(&format!("${}", id.as_intern_id().as_u32()));
+                mangling::mangle(name.bytes())
             },
         };
 
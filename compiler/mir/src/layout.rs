@@ -102,6 +102,16 @@ pub fn layout_of(db: &dyn MirDatabase, repr: &Repr) -> Layout {
 }
 
 pub fn _layout_of(db: &dyn MirDatabase, triple: &Triple, repr: &Repr) -> Layout {
+    match repr {
+        | Repr::ReprOf(ty) => _layout_of(db, triple, &db.repr_of(*ty)),
+        | Repr::Discr(repr) => discr_layout(&db.layout_of((**repr).clone()), triple),
+        | _ => shape_layout(triple, repr, &|r| _layout_of(db, triple, r)),
+    }
+}
+
+/// The layout of `repr`, which must not refer to types, where `inner` computes the layouts of its
+/// elements, fields and variants.
+fn shape_layout(triple: &Triple, repr: &Repr, inner: &dyn Fn(&Repr) -> Layout) -> Layout {
     match repr {
         | Repr::Opaque => {
             let mut layout = Layout::UNIT;
@@ -114,7 +124,6 @@ pub fn _layout_of(db: &dyn MirDatabase, triple: &Triple, repr: &Repr) -> Layout
             layout
         },
         | Repr::Scalar(scalar) => Layout::scalar(scalar.clone(), &triple),
-        | Repr::ReprOf(ty) => _layout_of(db, triple, &db.repr_of(*ty)),
         | Repr::Ptr(_, false, false) => Layout::scalar(Scalar::new(Primitive::Pointer, triple), triple),
         | Repr::Ptr(_, true, nonnull) => {
             let mut scalar = Scalar::new(Primitive::Pointer, triple);
@@ -127,10 +136,15 @@ pub fn _layout_of(db: &dyn MirDatabase, triple: &Triple, repr: &Repr) -> Layout
             scalar.valid_range = 1..=*scalar.valid_range.end();
             Layout::scalar(scalar, triple)
         },
-        | Repr::Func(_, true) => todo!(),
+        | Repr::Func(_, true) => {
+            let mut func = Scalar::new(Primitive::Pointer, triple);
+            func.valid_range = 1..=*func.valid_range.end();
+            let env = Scalar::new(Primitive::Pointer, triple);
+            scalar_pair(func, env, triple)
+        },
         | Repr::Array(ArrayLen::Const(len), el) => {
             let len = *len as u64;
-            let elem = _layout_of(db, triple, el);
+            let elem = inner(el);
 
             Layout {
                 size: elem.stride * len,
@@ -146,7 +160,7 @@ pub fn _layout_of(db: &dyn MirDatabase, triple: &Triple, repr: &Repr) -> Layout
             }
         },
         | Repr::Array(ArrayLen::TypeVar(_), el) => {
-            let elem = _layout_of(db, triple, el);
+            let elem = inner(el);
 
             Layout {
                 size: Size::ZERO,
@@ -165,7 +179,7 @@ pub fn _layout_of(db: &dyn MirDatabase, triple: &Triple, repr: &Repr) -> Layout
             let layouts = fields
                 .iter()
                 .map(|f| {
-                    let layout = _layout_of(db, triple, f);
+                    let layout = inner(f);
                     ReprAndLayout {
                         layout,
                         repr: f.clone(),
@@ -179,21 +193,34 @@ pub fn _layout_of(db: &dyn MirDatabase, triple: &Triple, repr: &Repr) -> Layout
             let layouts = variants
                 .iter()
                 .map(|v| {
-                    let layout = _layout_of(db, triple, v);
+                    let layout = inner(v);
                     (v.clone(), layout)
                 })
                 .collect();
 
             enum_layout(layouts, triple)
         },
-        | Repr::Discr(repr) => {
-            let layout = db.layout_of((**repr).clone());
+        | Repr::ReprOf(_) | Repr::Discr(_) => unreachable!(),
+    }
+}
 
-            match layout.variants {
-                | Variants::Multiple { ref tag, .. } => Layout::scalar(tag.clone(), triple),
-                | _ => todo!(),
-            }
+/// The layout of the discriminant of an enum with the layout `layout`.
+fn discr_layout(layout: &Layout, triple: &Triple) -> Layout {
+    match layout.variants {
+        | Variants::Multiple {
+            ref tag,
+            tag_encoding: TagEncoding::Direct,
+            ..
+        } => Layout::scalar(tag.clone(), triple),
+        | Variants::Multiple { ref variants, .. } => {
+            let discr = Scalar {
+                value: Primitive::Int(Integer::fit_unsigned(variants.len() as u128 - 1), false),
+                valid_range: 0..=variants.len() as u128 - 1,
+            };
+
+            Layout::scalar(discr, triple)
         },
+        | _ => todo!(),
     }
 }
 
@@ -247,11 +274,15 @@ fn struct_layout(lyts: Vec<ReprAndLayout>, triple: &Triple) -> Layout {
     let mut niches = Vec::new();
 
     for (i, lyt) in lyts.into_iter().enumerate() {
+        offset = offset.align_to(lyt.align);
+
         if let Some(niche) = &lyt.largest_niche {
-            niches.push(niche.clone());
+            niches.push(Niche {
+                offset: offset + niche.offset,
+                scalar: niche.scalar.clone(),
+            });
         }
 
-        offset = offset.align_to(lyt.align);
         align = align.max(lyt.align);
         fields[i].0 = offset;
         offset = offset + lyt.size;
@@ -279,15 +310,14 @@ fn enum_layout(mut lyts: Vec<(Repr, Layout)>, triple: &Triple) -> Layout {
     } else if lyts.len() == 1 {
         lyts.pop().unwrap().1
     } else {
-        let largest_niche = lyts
-            .iter()
-            .filter_map(|v| v.1.largest_niche.clone())
-            .max_by_key(|n| n.available(triple));
-
         for (i, lyt) in lyts.iter_mut().enumerate() {
             lyt.1.variants = Variants::Single { index: i };
         }
 
+        if let Some(layout) = niche_layout(&lyts, triple) {
+            return layout;
+        }
+
         let largest = &lyts.iter().max_by_key(|l| l.1.size).unwrap().1;
         let align = largest.align;
         let mut size = largest.size;
@@ -350,18 +380,9 @@ fn enum_layout(mut lyts: Vec<(Repr, Layout)>, triple: &Triple) -> Layout {
             }
         };
 
-        let (tag, fields, variants) = if let Some(niche) = largest_niche {
-            if niche.available(triple) >= lyts.len() as u128 {
-                // @TODO: implement niches
-                no_niche(lyts)
-            } else {
-                no_niche(lyts)
-            }
-        } else {
-            no_niche(lyts)
-        };
-
+        let (tag, fields, variants) = no_niche(lyts);
         let stride = size.align_to(align);
+        let largest_niche = Niche::from_scalar(triple, Size::ZERO, tag.clone());
 
         if tag.value.size(triple) == size {
             Layout {
@@ -371,7 +392,7 @@ fn enum_layout(mut lyts: Vec<(Repr, Layout)>, triple: &Triple) -> Layout {
                 abi: Abi::Scalar(tag),
                 fields,
                 variants,
-                largest_niche: None,
+                largest_niche,
             }
         } else {
             Layout {
@@ -381,12 +402,71 @@ fn enum_layout(mut lyts: Vec<(Repr, Layout)>, triple: &Triple) -> Layout {
                 abi: Abi::Aggregate { sized: true },
                 fields,
                 variants,
-                largest_niche: None,
+                largest_niche,
             }
         }
     }
 }
 
+/// Try to store the discriminant of the enum in the invalid values of a scalar inside its only dataful variant.
+/// This only succeeds if all other variants are zero-sized and the niche has enough room to encode them.
+fn niche_layout(lyts: &[(Repr, Layout)], triple: &Triple) -> Option<Layout> {
+    let mut dataful = lyts.iter().enumerate().filter(|(_, l)| !l.1.is_zst());
+    let (dataful_variant, (_, dataful_layout)) = dataful.next()?;
+
+    if dataful.next().is_some() {
+        return None;
+    }
+
+    let niche = dataful_layout.largest_niche.as_ref()?;
+    let mut niche_indices = (0..lyts.len()).filter(|&i| i != dataful_variant);
+    let first = niche_indices.next()?;
+    let last = niche_indices.last().unwrap_or(first);
+    let count = (last - first + 1) as u128;
+    let (niche_start, tag) = niche.reserve(triple, count)?;
+    let size = dataful_layout.size;
+    let align = dataful_layout.align;
+    let stride = size.align_to(align);
+    let variants = lyts
+        .iter()
+        .cloned()
+        .map(|(repr, mut layout)| {
+            layout.size = size;
+            layout.stride = stride;
+            Arc::new(ReprAndLayout { repr, layout })
+        })
+        .collect();
+
+    // The niche lives in one of the scalars of a scalar pair, which is replaced by the tag.
+    let abi = match &dataful_layout.abi {
+        | Abi::Scalar(_) => Abi::Scalar(tag.clone()),
+        | Abi::ScalarPair(_, b) if niche.offset == Size::ZERO => Abi::ScalarPair(tag.clone(), b.clone()),
+        | Abi::ScalarPair(a, _) => Abi::ScalarPair(a.clone(), tag.clone()),
+        | _ => Abi::Aggregate { sized: true },
+    };
+
+    Some(Layout {
+        size,
+        align,
+        stride,
+        abi,
+        fields: Fields::Arbitrary {
+            offsets: vec![niche.offset],
+        },
+        variants: Variants::Multiple {
+            tag: tag.clone(),
+            tag_encoding: TagEncoding::Niche {
+                dataful_variant,
+                niche_variants: first..=last,
+                niche_start,
+            },
+            tag_field: 0,
+            variants,
+        },
+        largest_niche: Niche::from_scalar(triple, niche.offset, tag),
+    })
+}
+
 impl ReprAndLayout {
     pub fn elem(&self, db: &dyn MirDatabase) -> Option<Arc<ReprAndLayout>> {
         let el = match &self.repr {
@@ -409,6 +489,11 @@ impl ReprAndLayout {
                 | 1 => Some(db.layout_of(Repr::usize())),
                 | _ => unreachable!(),
             },
+            | Repr::Func(sig, true) => match field {
+                | 0 => Some(db.layout_of(Repr::Func(sig.clone(), false))),
+                | 1 => Some(db.layout_of(Repr::closure_env())),
+                | _ => unreachable!(),
+            },
             | Repr::Enum(reprs) => match self.variants {
                 | Variants::Single { index } => db.layout_of(reprs[index].clone()).field(db, field),
                 | Variants::Multiple { ref tag, .. } => {
@@ -700,7 +785,7 @@ impl Niche {
 
         let bits = value.size(triple).bits();
         assert!(bits <= 128);
-        let max_value = !0128 >> (128 - bits);
+        let max_value = !0u128 >> (128 - bits);
 
         if count > max_value {
             return None;
@@ -759,6 +844,17 @@ impl Primitive {
 }
 
 impl Integer {
+    /// The smallest fixed size integer that can hold the given value.
+    pub fn fit_unsigned(value: u128) -> Self {
+        match 128 - value.leading_zeros() {
+            | 0..=8 => Integer::I8,
+            | 9..=16 => Integer::I16,
+            | 17..=32 => Integer::I32,
+            | 33..=64 => Integer::I64,
+            | _ => Integer::I128,
+        }
+    }
+
     pub fn size(self, triple: &Triple) -> Size {
         match self {
             | Integer::Int => match triple.pointer_width() {
@@ -779,3 +875,101 @@ impl Integer {
         Align::from_bytes(self.size(triple).bytes())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(repr: &Repr) -> Layout {
+        let triple = "x86_64-unknown-linux-gnu".parse().unwrap();
+
+        shape_layout(&triple, repr, &layout)
+    }
+
+    fn ptr() -> Repr {
+        Repr::Ptr(Box::new(Repr::unit()), false, true)
+    }
+
+    /// A pointer with an `Int` as metadata, which is a scalar pair.
+    fn fat_ptr() -> Repr {
+        Repr::Ptr(Box::new(Repr::unit()), true, true)
+    }
+
+    fn option(repr: Repr) -> Repr {
+        Repr::Enum(Box::new([Repr::unit(), Repr::Struct(Box::new([repr]))]))
+    }
+
+    fn tag_encoding(layout: &Layout) -> &TagEncoding {
+        match &layout.variants {
+            | Variants::Multiple { tag_encoding, .. } => tag_encoding,
+            | Variants::Single { .. } => panic!("expected multiple variants"),
+        }
+    }
+
+    #[test]
+    fn option_ptr_uses_null() {
+        let layout = layout(&option(ptr()));
+
+        assert_eq!(layout.size, Size::from_bytes(8));
+        assert_eq!(tag_encoding(&layout), &TagEncoding::Niche {
+            dataful_variant: 1,
+            niche_variants: 0..=0,
+            niche_start: 0,
+        });
+    }
+
+    #[test]
+    fn option_bool_uses_invalid_values() {
+        let layout = layout(&option(Repr::bool()));
+
+        assert_eq!(layout.size, Size::from_bytes(1));
+        assert_eq!(tag_encoding(&layout), &TagEncoding::Niche {
+            dataful_variant: 1,
+            niche_variants: 0..=0,
+            niche_start: 2,
+        });
+    }
+
+    #[test]
+    fn nested_options_share_niche() {
+        let inner = layout(&option(Repr::bool()));
+        let outer = layout(&option(option(Repr::bool())));
+
+        assert_eq!(outer.size, Size::from_bytes(1));
+        assert_eq!(inner.largest_niche.as_ref().unwrap().scalar.valid_range, 0..=2);
+        assert_eq!(tag_encoding(&outer), &TagEncoding::Niche {
+            dataful_variant: 1,
+            niche_variants: 0..=0,
+            niche_start: 3,
+        });
+    }
+
+    #[test]
+    fn option_int_without_niche_uses_tag() {
+        let layout = layout(&option(Repr::usize()));
+
+        assert_eq!(layout.size, Size::from_bytes(16));
+        assert_eq!(tag_encoding(&layout), &TagEncoding::Direct);
+    }
+
+    #[test]
+    fn option_scalar_pair_keeps_scalar_pair_abi() {
+        let layout = layout(&Repr::Enum(Box::new([Repr::unit(), fat_ptr()])));
+
+        assert_eq!(layout.size, Size::from_bytes(16));
+        assert_eq!(tag_encoding(&layout), &TagEncoding::Niche {
+            dataful_variant: 1,
+            niche_variants: 0..=0,
+            niche_start: 0,
+        });
+
+        match &layout.abi {
+            | Abi::ScalarPair(tag, meta) => {
+                assert_eq!(tag.value, Primitive::Pointer);
+                assert_eq!(*tag.valid_range.start(), 0);
+                assert_eq!(meta.value, Primitive::Int(Integer::Int, false));
+            },
+            | abi => panic!("expected a scalar pair, found {:?}", abi),
+        }
+    }
+}
@@ -9,4 +9,5 @@ pub mod layout;
 pub mod lower;
 pub mod repr;
 pub mod syntax;
+pub mod transform;
 pub mod visitor;
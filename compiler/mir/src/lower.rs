@@ -1,3 +1,4 @@
+mod closure;
 mod expr;
 mod intrinsic;
 mod pat;
@@ -0,0 +1,364 @@
+use hir::{Expr, HasResolver, Resolver, ValueNs};
+
+use super::*;
+use crate::repr::Signature;
+use crate::syntax::Body;
+
+/// What a closure does once all of its parameters have been applied.
+pub enum ClosureTarget {
+    /// Call the function with all captured values and parameters.
+    Func(Instance),
+
+    /// Construct a value using all captured values and parameters as its fields.
+    Ctor(hir::Ctor),
+
+    /// Evaluate the body of a lambda with the given locals captured.
+    Lambda {
+        captures: Vec<PatId>,
+        pats: Box<[PatId]>,
+        body: hir::ExprId,
+    },
+}
+
+impl BodyLowerCtx<'_> {
+    pub fn lower_lambda(
+        &mut self,
+        expr: hir::ExprId,
+        pats: &[PatId],
+        body: hir::ExprId,
+        store_in: &mut Option<Place>,
+    ) -> Operand {
+        let mut captures = Vec::new();
+
+        self.collect_captures(body, &mut captures);
+
+        let env = captures
+            .iter()
+            .map(|&pat| Operand::Copy(self.locals[pat].clone()))
+            .collect();
+        let params = pats
+            .iter()
+            .map(|&pat| self.db.repr_of(self.infer.type_of_pat[pat]))
+            .collect();
+        let ret = self.db.repr_of(self.infer.type_of_expr[body]);
+        let target = ClosureTarget::Lambda {
+            captures,
+            pats: pats.into(),
+            body,
+        };
+
+        self.lower_closure(expr, target, env, params, ret, store_in)
+    }
+
+    /// Lower a lambda passed to a foreign function to a plain function taking all parameters of
+    /// `sig` at once. Foreign code calls it without an environment, so it can't capture locals.
+    pub fn lower_foreign_lambda(
+        &mut self,
+        expr: hir::ExprId,
+        pats: &[PatId],
+        body: hir::ExprId,
+        sig: &Signature,
+    ) -> Operand {
+        let mut captures = Vec::new();
+
+        self.collect_captures(body, &mut captures);
+
+        if !captures.is_empty() {
+            base_db::Error::throw("a lambda passed to a foreign function can't capture local variables");
+        }
+
+        let reprs = pats
+            .iter()
+            .map(|&pat| self.db.repr_of(self.infer.type_of_pat[pat]))
+            .collect::<Vec<_>>();
+
+        if *reprs != *sig.params || self.db.repr_of(self.infer.type_of_expr[body]) != sig.ret {
+            base_db::Error::throw("a lambda passed to a foreign function must match the signature of the callback");
+        }
+
+        let origin = BodyOrigin {
+            def: self.builder.origin().def,
+            expr: Some(expr),
+        };
+
+        let mut builder = Builder::new(origin);
+
+        builder.set_source(Some(expr));
+
+        let entry = builder.create_block();
+        let params = reprs
+            .into_iter()
+            .map(|repr| {
+                let param = builder.add_local(LocalKind::Arg, repr);
+
+                builder.add_block_param(entry, param);
+                param
+            })
+            .collect::<Vec<_>>();
+
+        builder.switch_block(entry);
+
+        let mut bcx = BodyLowerCtx {
+            cx: LowerCtx {
+                db: self.db,
+                builder,
+                infer: self.infer.clone(),
+            },
+            body: self.body.clone(),
+            locals: ArenaMap::default(),
+        };
+
+        for (&pat, param) in pats.iter().zip(params) {
+            bcx.define_pat(pat, Place::new(param));
+        }
+
+        let res = bcx.lower_expr(body, &mut None);
+
+        bcx.builder.return_(res);
+
+        let func = self.db.intern_body(Arc::new(bcx.cx.builder.build()));
+
+        self.instance_ref(Instance::mono(func.into()))
+    }
+
+    /// Find all locals of the current body that are referenced inside of `expr`.
+    fn collect_captures(&self, expr: hir::ExprId, captures: &mut Vec<PatId>) {
+        if let Expr::Path { ref path } = self.body[expr] {
+            let resolver = Resolver::for_expr(self.db.upcast(), self.builder.origin().def, expr);
+
+            if let Some((ValueNs::Local(pat), _)) = resolver.resolve_value_fully(self.db.upcast(), path) {
+                if self.locals.get(pat).is_some() && !captures.contains(&pat) {
+                    captures.push(pat);
+                }
+            }
+        }
+
+        self.body[expr].walk(|e| self.collect_captures(e, captures));
+    }
+
+    /// Create a curried closure taking `params` one at a time. Every parameter but the last
+    /// returns a new closure with the parameter added to its environment, the last one
+    /// passes the environment and all parameters to `target`.
+    pub fn lower_closure(
+        &mut self,
+        expr: hir::ExprId,
+        target: ClosureTarget,
+        env: Vec<Operand>,
+        params: Vec<Repr>,
+        ret: Repr,
+        store_in: &mut Option<Place>,
+    ) -> Operand {
+        let env_reprs = env.iter().map(|op| self.builder.operand_repr(op)).collect::<Vec<_>>();
+        let mut next = None;
+
+        for step in (0..params.len()).rev() {
+            next = Some(self.lower_closure_step(expr, &target, &env_reprs, &params, &ret, step, next));
+        }
+
+        let repr = curried_repr(&params, ret);
+        let res = self.store_in_repr(store_in, repr);
+
+        self.builder
+            .closure(res.clone(), Instance::mono(next.unwrap().into()), env);
+        Operand::Move(res)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn lower_closure_step(
+        &mut self,
+        expr: hir::ExprId,
+        target: &ClosureTarget,
+        env_reprs: &[Repr],
+        params: &[Repr],
+        ret: &Repr,
+        step: usize,
+        next: Option<Body>,
+    ) -> Body {
+        let origin = BodyOrigin {
+            def: self.builder.origin().def,
+            expr: Some(expr),
+        };
+
+        let mut builder = Builder::new(origin);
+
+        builder.set_source(Some(expr));
+
+        let entry = builder.create_block();
+        let env = builder.add_local(LocalKind::Arg, Repr::closure_env());
+        let arg = builder.add_local(LocalKind::Arg, params[step].clone());
+
+        builder.add_block_param(entry, env);
+        builder.add_block_param(entry, arg);
+        builder.switch_block(entry);
+
+        let fields = env_reprs.iter().chain(&params[..step]).cloned().collect::<Vec<_>>();
+        let mut values = Vec::with_capacity(fields.len() + 1);
+
+        if !fields.is_empty() {
+            let count = fields.len();
+            let env_repr = Repr::Ptr(Box::new(Repr::Struct(fields.into_boxed_slice())), false, false);
+            let env_ptr = builder.add_local(LocalKind::Tmp, env_repr);
+
+            builder.cast(Place::new(env_ptr), CastKind::Pointer, Operand::Move(Place::new(env)));
+            values.extend((0..count).map(|i| Place::new(env_ptr).deref().field(i)));
+        }
+
+        values.push(Place::new(arg));
+
+        if let Some(next) = next {
+            let repr = curried_repr(&params[step + 1..], ret.clone());
+            let res = builder.add_local(LocalKind::Tmp, repr);
+            let values = values.into_iter().map(Operand::Copy).collect::<Vec<_>>();
+
+            builder.closure(Place::new(res), Instance::mono(next.into()), values);
+            builder.return_(Operand::Move(Place::new(res)));
+            return self.db.intern_body(Arc::new(builder.build()));
+        }
+
+        match *target {
+            | ClosureTarget::Func(ref instance) => {
+                let sig = Signature {
+                    params: values.iter().map(|v| builder.place_repr(v)).collect(),
+                    ret: ret.clone(),
+                };
+
+                let func = builder.add_local(LocalKind::Tmp, Repr::Func(Box::new(sig), false));
+                let res = builder.add_local(LocalKind::Tmp, ret.clone());
+                let args = values.into_iter().map(Operand::Copy).collect::<Vec<_>>();
+
+                builder.instance_ref(Place::new(func), instance.clone());
+                builder.call(Place::new(res), Operand::Move(Place::new(func)), args);
+                builder.return_(Operand::Move(Place::new(res)));
+            },
+            | ClosureTarget::Ctor(ctor) => {
+                let res = builder.add_local(LocalKind::Tmp, ret.clone());
+
+                builder.init(res);
+
+                let downcast = if ctor.type_ctor().ctors(self.db.upcast()).len() == 1 {
+                    Place::new(res)
+                } else {
+                    builder.set_discriminant(Place::new(res), ctor);
+                    Place::new(res).downcast(ctor)
+                };
+
+                for (i, value) in values.into_iter().enumerate() {
+                    builder.assign(downcast.clone().field(i), Operand::Copy(value));
+                }
+
+                builder.return_(Operand::Move(Place::new(res)));
+            },
+            | ClosureTarget::Lambda {
+                ref captures,
+                ref pats,
+                body,
+            } => {
+                let mut bcx = BodyLowerCtx {
+                    cx: LowerCtx {
+                        db: self.db,
+                        builder,
+                        infer: self.infer.clone(),
+                    },
+                    body: self.body.clone(),
+                    locals: ArenaMap::default(),
+                };
+
+                for (&pat, place) in captures.iter().zip(&values) {
+                    bcx.locals.insert(pat, place.clone());
+                }
+
+                for (&pat, place) in pats.iter().zip(&values[captures.len()..]) {
+                    bcx.define_pat(pat, place.clone());
+                }
+
+                let res = bcx.lower_expr(body, &mut None);
+
+                bcx.builder.return_(res);
+                builder = bcx.cx.builder;
+            },
+        }
+
+        self.db.intern_body(Arc::new(builder.build()))
+    }
+}
+
+/// The representation of a function taking `params` one at a time.
+pub fn curried_repr(params: &[Repr], ret: Repr) -> Repr {
+    params.iter().rev().fold(ret, |ret, param| {
+        let sig = Signature {
+            params: Box::new([param.clone()]),
+            ret,
+        };
+
+        Repr::Func(Box::new(sig), true)
+    })
+}
+
+/// Split the representation of a curried function into `n` parameters and its return value.
+pub fn uncurry_repr(mut repr: Repr, n: usize) -> (Vec<Repr>, Repr) {
+    let mut params = Vec::with_capacity(n);
+
+    for _ in 0..n {
+        match repr {
+            | Repr::Func(sig, _) => {
+                let Signature { params: p, ret } = *sig;
+
+                params.extend(p.into_vec());
+                repr = ret;
+            },
+            | _ => unreachable!(),
+        }
+    }
+
+    (params, repr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::foreign_repr;
+
+    fn func(params: &[Repr], ret: Repr, thick: bool) -> Repr {
+        let sig = Signature {
+            params: params.into(),
+            ret,
+        };
+
+        Repr::Func(Box::new(sig), thick)
+    }
+
+    #[test]
+    fn curried_repr_takes_one_param_at_a_time() {
+        let repr = curried_repr(&[Repr::i32(), Repr::bool()], Repr::usize());
+        let expected = func(&[Repr::i32()], func(&[Repr::bool()], Repr::usize(), true), true);
+
+        assert_eq!(repr, expected);
+        assert_eq!(curried_repr(&[], Repr::usize()), Repr::usize());
+    }
+
+    #[test]
+    fn uncurry_repr_inverts_curried_repr() {
+        let params = vec![Repr::i32(), Repr::bool(), Repr::usize()];
+        let repr = curried_repr(&params, Repr::unit());
+
+        assert_eq!(uncurry_repr(repr.clone(), 3), (params.clone(), Repr::unit()));
+        assert_eq!(
+            uncurry_repr(repr, 2),
+            (params[..2].to_vec(), func(&[Repr::usize()], Repr::unit(), true))
+        );
+    }
+
+    #[test]
+    fn foreign_repr_is_thin_and_uncurried() {
+        let callback = curried_repr(&[Repr::i32(), Repr::i32()], Repr::i32());
+        let repr = curried_repr(&[callback], Repr::unit());
+        let expected = func(
+            &[func(&[Repr::i32(), Repr::i32()], Repr::i32(), false)],
+            Repr::unit(),
+            false,
+        );
+
+        assert_eq!(foreign_repr(repr), expected);
+        assert_eq!(foreign_repr(Repr::i32()), Repr::i32());
+    }
+}
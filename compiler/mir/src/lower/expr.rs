@@ -1,8 +1,9 @@
 use hir::id::{AssocItemId, HasModule};
 use hir::{Expr, HasResolver, Literal, MethodSource, Resolver, ValueNs};
 
+use super::closure::{uncurry_repr, ClosureTarget};
 use super::*;
-use crate::repr::Repr;
+use crate::repr::{Repr, Signature};
 
 #[derive(Debug)]
 pub enum Arg {
@@ -12,6 +13,14 @@ pub enum Arg {
 
 impl BodyLowerCtx<'_> {
     pub fn lower_expr(&mut self, expr: hir::ExprId, store_in: &mut Option<Place>) -> Operand {
+        let prev = self.builder.set_source(Some(expr));
+        let op = self.lower_expr_inner(expr, store_in);
+
+        self.builder.set_source(prev);
+        op
+    }
+
+    fn lower_expr_inner(&mut self, expr: hir::ExprId, store_in: &mut Option<Place>) -> Operand {
         let body = self.body.clone();
 
         match body[expr] {
@@ -74,6 +83,7 @@ impl BodyLowerCtx<'_> {
                 else_: None,
             } => self.lower_if(expr, cond, then),
             | Expr::Case { pred, ref arms } => self.lower_case(expr, pred, arms),
+            | Expr::Lambda { ref pats, body: b } => self.lower_lambda(expr, pats, b, store_in),
             | Expr::Return { expr: e } => {
                 let repr = self.db.repr_of(self.infer.type_of_expr[expr]);
                 let val = self.lower_expr(e, &mut None);
@@ -298,9 +308,23 @@ impl BodyLowerCtx<'_> {
             }
         }
 
-        let _base = self.lower_arg(base, &mut None);
+        let mut base = self.lower_arg(base, &mut None);
+        let last = args.len() - 1;
+
+        for (i, arg) in args.into_iter().enumerate() {
+            let repr = self.app_ret_ty(&base, 1);
+            let arg = self.lower_arg(arg, &mut None);
+            let res = if i == last {
+                self.store_in_repr(store_in, repr)
+            } else {
+                self.store_in_repr(&mut None, repr)
+            };
+
+            self.builder.call(res.clone(), base, [arg]);
+            base = Operand::Move(res);
+        }
 
-        todo!()
+        base
     }
 
     pub fn lower_path_app(
@@ -313,6 +337,7 @@ impl BodyLowerCtx<'_> {
         store_in: &mut Option<Place>,
     ) -> Operand {
         let (resolved, _) = resolver.resolve_value_fully(self.db.upcast(), path).unwrap();
+        let mut foreign_params = None;
         let (mut base, params) = match resolved {
             | ValueNs::Local(id) => (Operand::Copy(self.locals[id].clone()), 1),
             | ValueNs::Fixity(id) => {
@@ -332,7 +357,43 @@ impl BodyLowerCtx<'_> {
                     return self.lower_arg(args.remove(0), store_in);
                 }
 
-                (self.lower_path(resolver, base, path, &mut None), self.func_params(id))
+                let params = self.func_params(id);
+
+                if params == 0 {
+                    (self.lower_path(resolver, base, path, &mut None), 0)
+                } else {
+                    let instance = self.func_instance(base, func, path);
+
+                    if args.len() < params {
+                        let sig = self.db.func_signature(instance.clone());
+                        let args = args
+                            .into_iter()
+                            .map(|a| self.lower_arg(a, &mut None))
+                            .collect::<Vec<_>>();
+                        let params = sig.params[args.len()..].to_vec();
+                        let target = ClosureTarget::Func(instance);
+
+                        return self.lower_closure(base.0, target, args, params, sig.ret, store_in);
+                    }
+
+                    if func.is_foreign(self.db.upcast()) {
+                        foreign_params = Some(self.db.func_signature(instance.clone()).params);
+                    }
+
+                    (self.instance_ref(instance), params)
+                }
+            },
+            | ValueNs::Ctor(id) if args.len() < self.db.type_ctor_data(id.parent).ctors[id.local_id].types.len() => {
+                let fields = self.db.type_ctor_data(id.parent).ctors[id.local_id].types.len();
+                let repr = self.db.repr_of(self.infer.type_of_expr[base.0]);
+                let (params, ret) = uncurry_repr(repr, fields);
+                let args = args
+                    .into_iter()
+                    .map(|a| self.lower_arg(a, &mut None))
+                    .collect::<Vec<_>>();
+                let params = params[args.len()..].to_vec();
+
+                return self.lower_closure(base.0, ClosureTarget::Ctor(id.into()), args, params, ret, store_in);
             },
             | ValueNs::Ctor(id) => {
                 let ty = self.infer.type_of_expr[expr];
@@ -351,14 +412,18 @@ impl BodyLowerCtx<'_> {
 
                 return Operand::Move(res);
             },
-            | _ => (self.lower_path(resolver, base, path, &mut None), args.len()),
+            | _ => (self.lower_path(resolver, base, path, &mut None), 0),
         };
 
         if params > 0 {
             let repr = self.app_ret_ty(&base, params);
             let args2 = args
                 .drain(..params)
-                .map(|a| self.lower_arg(a, &mut None))
+                .enumerate()
+                .map(|(i, a)| match &foreign_params {
+                    | Some(reprs) => self.lower_foreign_arg(a, &reprs[i]),
+                    | None => self.lower_arg(a, &mut None),
+                })
                 .collect::<Vec<_>>();
 
             if args.is_empty() {
@@ -419,99 +484,147 @@ impl BodyLowerCtx<'_> {
                     return Operand::Move(res);
                 }
 
-                todo!("reference ctor function")
+                let repr = self.db.repr_of(self.infer.type_of_expr[expr.0]);
+                let (params, ret) = uncurry_repr(repr, ctor.types.len());
+
+                self.lower_closure(
+                    expr.0,
+                    ClosureTarget::Ctor(id.into()),
+                    Vec::new(),
+                    params,
+                    ret,
+                    store_in,
+                )
             },
             | ValueNs::Func(id) => {
-                let func = hir::Func::from(id);
-                let infer = self.infer.clone();
-                let is_method = func.as_assoc_item(self.db.upcast()).is_some();
-                let mut methods = infer.methods.get(&expr).map(|m| m.iter().copied());
-                let (mut base, sig) = if is_method {
-                    let methods = methods.as_mut().expect(&format!("{:?}", expr));
-
-                    match methods.next().unwrap() {
-                        | MethodSource::Member(id) => {
-                            let member = self.db.member_data(id);
-                            let member_info = self.db.lower_member(id);
-                            let func = match member.item(path.segments().last().unwrap()).unwrap() {
-                                | AssocItemId::FuncId(id) => hir::Func::from(id),
-                                | AssocItemId::StaticId(_) => unreachable!(),
-                            };
-
-                            tracing::debug!("{} :: {}", path, func.ty(self.db.upcast()).display(self.db.upcast()));
-                            let types = infer.instances.get(&expr).map(|t| &t[..]).unwrap_or_default();
-                            tracing::debug!(
-                                "{}",
-                                types
-                                    .iter()
-                                    .map(|t| t.display(self.db.upcast()).to_string())
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
-                            );
-                            let types = member_info.member.get_instance_types(self.db.upcast(), types);
-                            tracing::debug!(
-                                "{}",
-                                types
-                                    .iter()
-                                    .map(|t| t.display(self.db.upcast()).to_string())
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
-                            );
-                            let func = Instance::new(self.db, func.into(), types, methods.collect());
-                            tracing::debug!("{}", func.display(self.db.upcast()));
-                            let sig = self.db.func_signature(func.clone());
-                            let repr = Repr::Func(Box::new(sig.clone()), false);
-                            let res = if sig.params.is_empty() {
-                                self.store_in_repr(&mut None, repr)
-                            } else {
-                                self.store_in_repr(store_in, repr)
-                            };
-
-                            self.builder.instance_ref(res.clone(), func);
-                            (Operand::Move(res), sig)
-                        },
-                        | MethodSource::Record(_idx, _p) => todo!(),
-                    }
-                } else {
-                    let types = infer.instances.get(&expr);
-                    let func = if types.is_some() || methods.is_some() {
-                        Instance::new(
-                            self.db,
-                            func.into(),
-                            types.cloned().unwrap_or_default(),
-                            methods.map(|c| c.collect()).unwrap_or_default(),
-                        )
-                    } else {
-                        Instance::mono(func.into())
-                    };
-                    tracing::debug!("{}", func.display(self.db.upcast()));
-
-                    let sig = self.db.func_signature(func.clone());
-                    let repr = Repr::Func(Box::new(sig.clone()), false);
-                    let res = if sig.params.is_empty() {
-                        self.store_in_repr(&mut None, repr)
-                    } else {
-                        self.store_in_repr(store_in, repr)
-                    };
-
-                    self.builder.instance_ref(res.clone(), func);
-                    (Operand::Move(res), sig)
-                };
+                let func = self.func_instance(expr, hir::Func::from(id), path);
+                let sig = self.db.func_signature(func.clone());
 
                 tracing::debug!("{}: {} ({:?})", path, sig.display(self.db.upcast()), expr);
 
                 if sig.params.is_empty() {
+                    let base = self.instance_ref(func);
                     let place = self.store_in_repr(store_in, sig.ret);
+
                     self.builder.call(place.clone(), base, []);
-                    base = Operand::Move(place);
+                    return Operand::Move(place);
                 }
 
-                base
+                let Signature { params, ret } = sig;
+
+                self.lower_closure(
+                    expr.0,
+                    ClosureTarget::Func(func),
+                    Vec::new(),
+                    params.into_vec(),
+                    ret,
+                    store_in,
+                )
             },
             | _ => todo!("{}", path),
         }
     }
 
+    fn func_instance(&mut self, expr: (hir::ExprId, usize), func: hir::Func, path: &hir::Path) -> Instance {
+        let infer = self.infer.clone();
+        let is_method = func.as_assoc_item(self.db.upcast()).is_some();
+        let mut methods = infer.methods.get(&expr).map(|m| m.iter().copied());
+
+        if is_method {
+            let methods = methods.as_mut().expect(&format!("{:?}", expr));
+
+            match methods.next().unwrap() {
+                | MethodSource::Member(id) => {
+                    let member = self.db.member_data(id);
+                    let member_info = self.db.lower_member(id);
+                    let func = match member.item(path.segments().last().unwrap()).unwrap() {
+                        | AssocItemId::FuncId(id) => hir::Func::from(id),
+                        | AssocItemId::StaticId(_) => unreachable!(),
+                    };
+
+                    tracing::debug!("{} :: {}", path, func.ty(self.db.upcast()).display(self.db.upcast()));
+                    let types = infer.instances.get(&expr).map(|t| &t[..]).unwrap_or_default();
+                    tracing::debug!(
+                        "{}",
+                        types
+                            .iter()
+                            .map(|t| t.display(self.db.upcast()).to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    let types = member_info.member.get_instance_types(self.db.upcast(), types);
+                    tracing::debug!(
+                        "{}",
+                        types
+                            .iter()
+                            .map(|t| t.display(self.db.upcast()).to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    let func = Instance::new(self.db, func.into(), types, methods.collect());
+                    tracing::debug!("{}", func.display(self.db.upcast()));
+                    func
+                },
+                | MethodSource::Record(_idx, _p) => todo!(),
+            }
+        } else {
+            let types = infer.instances.get(&expr);
+            let func = if types.is_some() || methods.is_some() {
+                Instance::new(
+                    self.db,
+                    func.into(),
+                    types.cloned().unwrap_or_default(),
+                    methods.map(|c| c.collect()).unwrap_or_default(),
+                )
+            } else {
+                Instance::mono(func.into())
+            };
+            tracing::debug!("{}", func.display(self.db.upcast()));
+            func
+        }
+    }
+
+    /// Function values are passed to foreign functions as plain function pointers, see
+    /// [`crate::repr::foreign_repr`]. These can be created for named functions, which are
+    /// referenced directly, and for lambdas without captures, which become plain functions.
+    /// Any other function value needs an environment, which foreign code has no way to pass.
+    fn lower_foreign_arg(&mut self, arg: Arg, repr: &Repr) -> Operand {
+        let (sig, mut expr) = match (repr, &arg) {
+            | (Repr::Func(sig, false), Arg::ExprId(expr)) => (sig, *expr),
+            | _ => return self.lower_arg(arg, &mut None),
+        };
+
+        let body = self.body.clone();
+
+        while let Expr::Typed { expr: inner, .. } = body[expr] {
+            expr = inner;
+        }
+
+        match body[expr] {
+            | Expr::Path { ref path } => {
+                let resolver = Resolver::for_expr(self.db.upcast(), self.builder.origin().def, expr);
+
+                if let Some((ValueNs::Func(id), _)) = resolver.resolve_value_fully(self.db.upcast(), path) {
+                    let instance = self.func_instance((expr, 0), id.into(), path);
+
+                    return self.instance_ref(instance);
+                }
+            },
+            | Expr::Lambda { ref pats, body: lambda } => return self.lower_foreign_lambda(expr, pats, lambda, sig),
+            | _ => {},
+        }
+
+        base_db::Error::throw("only named functions and lambdas without captures can be passed to foreign functions")
+    }
+
+    pub fn instance_ref(&mut self, instance: Instance) -> Operand {
+        let sig = self.db.func_signature(instance.clone());
+        let res = self.store_in_repr(&mut None, Repr::Func(Box::new(sig), false));
+
+        self.builder.instance_ref(res.clone(), instance);
+        Operand::Move(res)
+    }
+
     pub fn lower_infix_expr(
         &mut self,
         expr: hir::ExprId,
@@ -133,14 +133,21 @@ impl BodyLowerCtx<'_> {
             | "isub" => self.lower_intrinsic_binop(expr, BinOp::Sub, args, store_in),
             | "ieq" => self.lower_intrinsic_binop(expr, BinOp::Eq, args, store_in),
             | "ilt" => self.lower_intrinsic_binop(expr, BinOp::Lt, args, store_in),
-            | "iconvert" => {
-                let val = self.lower_arg(args.next().unwrap(), &mut None);
-                let ty = self.infer.type_of_expr[expr];
-                let res = self.store_in(store_in, ty);
-
-                self.builder.cast(res.clone(), CastKind::IntToInt, val);
-                Operand::Move(res)
-            },
+            | "fadd" => self.lower_intrinsic_binop(expr, BinOp::Add, args, store_in),
+            | "fsub" => self.lower_intrinsic_binop(expr, BinOp::Sub, args, store_in),
+            | "fmul" => self.lower_intrinsic_binop(expr, BinOp::Mul, args, store_in),
+            | "fdiv" => self.lower_intrinsic_binop(expr, BinOp::Div, args, store_in),
+            | "frem" => self.lower_intrinsic_binop(expr, BinOp::Rem, args, store_in),
+            | "feq" => self.lower_intrinsic_binop(expr, BinOp::Eq, args, store_in),
+            | "fne" => self.lower_intrinsic_binop(expr, BinOp::Ne, args, store_in),
+            | "flt" => self.lower_intrinsic_binop(expr, BinOp::Lt, args, store_in),
+            | "fle" => self.lower_intrinsic_binop(expr, BinOp::Le, args, store_in),
+            | "fgt" => self.lower_intrinsic_binop(expr, BinOp::Gt, args, store_in),
+            | "fge" => self.lower_intrinsic_binop(expr, BinOp::Ge, args, store_in),
+            | "iconvert" => self.lower_intrinsic_cast(expr, CastKind::IntToInt, args, store_in),
+            | "fconvert" => self.lower_intrinsic_cast(expr, CastKind::FloatToFloat, args, store_in),
+            | "itof" => self.lower_intrinsic_cast(expr, CastKind::IntToFloat, args, store_in),
+            | "ftoi" => self.lower_intrinsic_cast(expr, CastKind::FloatToInt, args, store_in),
             | "transmute" => {
                 let arg = self.lower_arg(args.next().unwrap(), &mut None);
                 let ty = self.infer.type_of_expr[expr];
@@ -176,6 +183,21 @@ impl BodyLowerCtx<'_> {
         Operand::Move(res)
     }
 
+    fn lower_intrinsic_cast(
+        &mut self,
+        expr: hir::ExprId,
+        kind: CastKind,
+        mut args: impl Iterator<Item = Arg>,
+        store_in: &mut Option<Place>,
+    ) -> Operand {
+        let val = self.lower_arg(args.next().unwrap(), &mut None);
+        let ty = self.infer.type_of_expr[expr];
+        let res = self.store_in(store_in, ty);
+
+        self.builder.cast(res.clone(), kind, val);
+        Operand::Move(res)
+    }
+
     fn lower_intrinsic_nullop(
         &mut self,
         expr: hir::ExprId,
@@ -23,6 +23,7 @@ pub enum MatchValue {
 #[derive(Debug)]
 pub enum Pattern {
     Switch(Place, i128),
+    Equal(Place, Operand),
     Check(hir::ExprId),
     And(Box<Pattern>, Box<Pattern>),
     #[allow(dead_code)]
@@ -135,6 +136,17 @@ impl BodyLowerCtx<'_> {
                     next_block.into(),
                 ]);
             },
+            | Pattern::Equal(ref place, ref value) => {
+                let res = self.builder.add_local(LocalKind::Tmp, Repr::bool());
+
+                self.builder.init(res);
+                self.builder
+                    .binop(Place::new(res), BinOp::Eq, Operand::Copy(place.clone()), value.clone());
+                self.builder.switch(Operand::Move(Place::new(res)), vec![0], [
+                    next_block.into(),
+                    then_block.into(),
+                ]);
+            },
             | Pattern::Check(expr) => {
                 let op = self.lower_expr(expr, &mut None);
                 self.builder.switch(op, vec![0], [next_block.into(), then_block.into()]);
@@ -224,11 +236,19 @@ impl BodyLowerCtx<'_> {
             | Pat::Missing => unreachable!(),
             | Pat::Wildcard => None,
             | Pat::Bind { subpat, .. } => {
+                if place.projection.is_empty() {
+                    self.builder.add_var(place.local, pat);
+                }
+
                 self.locals.insert(pat, place.clone());
                 subpat.and_then(|s| self.compile_pat(s, place))
             },
             | Pat::Lit { ref lit } => match *lit {
                 | Literal::Int(v) => Some(Pattern::Switch(place.clone(), v)),
+                | Literal::Float(v) => {
+                    let repr = self.builder.place_repr(&place);
+                    Some(Pattern::Equal(place.clone(), Operand::Const(Const::Float(v), repr)))
+                },
                 | _ => todo!(),
             },
             | Pat::Path { ref path } => {
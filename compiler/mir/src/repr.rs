@@ -9,6 +9,7 @@ use hir_def::lang_item;
 
 use crate::db::MirDatabase;
 use crate::instance::{Instance, InstanceDef};
+use crate::syntax::{Block, BodyData, Term};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Repr {
@@ -81,6 +82,18 @@ impl Repr {
         })
     }
 
+    pub fn bool() -> Self {
+        Self::Scalar(Scalar {
+            value: Primitive::Int(Integer::I8, false),
+            valid_range: 0..=1,
+        })
+    }
+
+    /// An untyped pointer to the environment of a closure.
+    pub fn closure_env() -> Self {
+        Self::Ptr(Box::new(Self::unit()), false, false)
+    }
+
     pub fn i32() -> Self {
         Self::Scalar(Scalar {
             value: Primitive::Int(Integer::I32, true),
@@ -286,7 +299,7 @@ fn repr_from_attrs(db: &dyn MirDatabase, group: &AttrInputGroup, args: &[Ty]) ->
                         params: Box::new([arg]),
                         ret,
                     }),
-                    false,
+                    true,
                 )
             }
         }
@@ -334,6 +347,14 @@ fn primitive_from_attr(attr: &str) -> Primitive {
 pub fn func_signature_query(db: &dyn MirDatabase, instance: Instance) -> Signature {
     let func = match instance.def {
         | InstanceDef::Def(DefWithBody::Func(f)) => f,
+        | InstanceDef::Body(body) => {
+            let sig = body_signature(&db.lookup_intern_body(body));
+
+            return match &instance.subst {
+                | Some(subst) => subst.subst_signature(db, &sig),
+                | None => sig,
+            };
+        },
         | _ => unreachable!(),
     };
 
@@ -388,8 +409,14 @@ pub fn func_signature_query(db: &dyn MirDatabase, instance: Instance) -> Signatu
         }
     }
 
-    let params = args.into_iter().map(|a| db.repr_of(a)).collect();
-    let ret = db.repr_of(ret);
+    let mut params = args.into_iter().map(|a| db.repr_of(a)).collect::<Box<[_]>>();
+    let mut ret = db.repr_of(ret);
+
+    if func.is_foreign(hir_db) && !func.is_intrinsic(hir_db) {
+        params = params.into_vec().into_iter().map(foreign_repr).collect();
+        ret = foreign_repr(ret);
+    }
+
     let sig = Signature { params, ret };
 
     if let Some(subst) = &instance.subst {
@@ -398,3 +425,50 @@ pub fn func_signature_query(db: &dyn MirDatabase, instance: Instance) -> Signatu
         sig
     }
 }
+
+/// Function values cross the boundary to foreign code as plain function pointers taking all of
+/// their parameters at once, like C expects for callbacks, instead of as curried closures.
+pub fn foreign_repr(repr: Repr) -> Repr {
+    match repr {
+        | Repr::Func(_, true) => {
+            let mut params = Vec::new();
+            let mut ret = repr;
+
+            while let Repr::Func(sig, true) = ret {
+                let Signature { params: p, ret: r } = *sig;
+
+                params.extend(p.into_vec().into_iter().map(foreign_repr));
+                ret = r;
+            }
+
+            let sig = Signature {
+                params: params.into_boxed_slice(),
+                ret: foreign_repr(ret),
+            };
+
+            Repr::Func(Box::new(sig), false)
+        },
+        | repr => repr,
+    }
+}
+
+/// The signature of a body that is not a definition (i.e. a closure) is derived from
+/// the parameters of its entry block and the value it returns.
+fn body_signature(body: &BodyData) -> Signature {
+    let params = body.blocks[Block::ENTRY.0]
+        .params
+        .iter()
+        .map(|l| body.locals[l.0].repr.clone())
+        .collect();
+
+    let ret = body
+        .blocks
+        .iter()
+        .find_map(|(_, b)| match &b.term {
+            | Term::Return(op) => Some(body.operand_repr(op)),
+            | _ => None,
+        })
+        .unwrap_or(Repr::Uninhabited);
+
+    Signature { params, ret }
+}
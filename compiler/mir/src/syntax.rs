@@ -1,11 +1,12 @@
 use arena::{Arena, Idx};
 use hir::id::DefWithBodyId;
 use hir::ty::TypeVar;
-use hir::{Ctor, ExprId, Func};
+use hir::{Ctor, ExprId, Func, PatId};
 use rustc_hash::FxHashMap;
 use salsa::{InternId, InternKey};
 
-use crate::instance::Instance;
+use crate::db::MirDatabase;
+use crate::instance::{Instance, InstanceDef};
 use crate::repr::Repr;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -53,6 +54,9 @@ pub struct BodyData {
     pub origin: BodyOrigin,
     pub locals: Arena<LocalData>,
     pub blocks: Arena<BlockData>,
+
+    /// The locals holding user defined variables and the pattern that bound them.
+    pub vars: Vec<(Local, PatId)>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -81,6 +85,9 @@ pub struct BlockData {
     pub params: Vec<Local>,
     pub stmts: Vec<Stmt>,
     pub term: Term,
+
+    /// The expression each statement was lowered from, followed by the one of the terminator.
+    pub sources: Vec<Option<ExprId>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -162,6 +169,10 @@ pub enum Rvalue {
     /// Create a reference to the given instance.
     InstanceRef(Instance),
 
+    /// Create a closure calling the given instance, with the operands stored in its environment.
+    /// The instance receives a pointer to the environment as its first argument.
+    Closure(Instance, Vec<Operand>),
+
     /// A binary operation (add, sub, mul, div, etc.).
     BinOp(BinOp, Operand, Operand),
 
@@ -267,6 +278,76 @@ pub struct Location {
     pub stmt: usize,
 }
 
+impl Body {
+    /// The bodies of the closures created in this body, including the closures created by those
+    /// bodies in turn. Lambdas passed to foreign functions are bodies without an environment.
+    pub fn closures(self, db: &dyn MirDatabase) -> Vec<Body> {
+        let mut closures = Vec::new();
+        let mut stack = vec![self];
+
+        while let Some(body) = stack.pop() {
+            let data = db.lookup_intern_body(body);
+
+            for (_, block) in data.blocks.iter() {
+                for stmt in block.stmts.iter() {
+                    if let Stmt::Assign(_, Rvalue::Closure(instance, _) | Rvalue::InstanceRef(instance)) = stmt {
+                        if let InstanceDef::Body(closure) = instance.def {
+                            if !closures.contains(&closure) {
+                                closures.push(closure);
+                                stack.push(closure);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        closures
+    }
+}
+
+impl BodyData {
+    pub fn place_repr(&self, place: &Place) -> Repr {
+        let mut repr = self.locals[place.local.0].repr.clone();
+
+        for proj in place.projection.iter() {
+            match *proj {
+                | Projection::Deref => match repr {
+                    | Repr::Box(inner) => repr = *inner,
+                    | Repr::Ptr(inner, false, _) => repr = *inner,
+                    | _ => unreachable!(),
+                },
+                | Projection::Field(i) => match repr {
+                    | Repr::Struct(fields) => repr = fields[i].clone(),
+                    | _ => unreachable!(),
+                },
+                | Projection::Index(_) => match repr {
+                    | Repr::Array(_, e) => repr = *e.clone(),
+                    | _ => unreachable!(),
+                },
+                | Projection::Slice(_, _) => match repr {
+                    | Repr::Array(_, e) => repr = Repr::Ptr(e.clone(), true, true),
+                    | Repr::Ptr(_, true, _) => {},
+                    | _ => unreachable!(),
+                },
+                | Projection::Downcast(ctor) => match repr {
+                    | Repr::Enum(e) => repr = e[ctor.idx()].clone(),
+                    | _ => unreachable!(),
+                },
+            }
+        }
+
+        repr
+    }
+
+    pub fn operand_repr(&self, op: &Operand) -> Repr {
+        match op {
+            | Operand::Copy(place) | Operand::Move(place) => self.place_repr(place),
+            | Operand::Const(_, repr) => repr.clone(),
+        }
+    }
+}
+
 impl Place {
     pub fn has_deref(&self) -> bool {
         self.projection
@@ -0,0 +1,39 @@
+mod drop;
+mod inline;
+mod simplify;
+
+use std::sync::Arc;
+
+use base_db::target::Optimization;
+
+use crate::db::MirDatabase;
+use crate::syntax::{Body, BodyData};
+
+/// Run the optimization passes enabled by the current optimization level on the body, and
+/// release the closures it creates once they are no longer used.
+pub(crate) fn optimized_body(db: &dyn MirDatabase, body: Body) -> Body {
+    let level = db.optimization();
+    let mut data = BodyData::clone(&db.lookup_intern_body(body));
+
+    if level.is_enabled() {
+        if let Some(threshold) = inline_threshold(level) {
+            inline::inline_calls(db, &mut data, threshold);
+        }
+
+        simplify::remove_unused_refs(&mut data);
+        simplify::simplify_cfg(&mut data);
+    }
+
+    drop::drop_closures(&mut data);
+    db.intern_body(Arc::new(data))
+}
+
+/// The maximum size of a function that will be inlined.
+fn inline_threshold(level: Optimization) -> Option<usize> {
+    match level {
+        | Optimization::None | Optimization::Less | Optimization::SizeMin => None,
+        | Optimization::Size => Some(8),
+        | Optimization::Default => Some(32),
+        | Optimization::Aggressive => Some(128),
+    }
+}
@@ -0,0 +1,163 @@
+use rustc_hash::FxHashMap;
+
+use crate::repr::Repr;
+use crate::syntax::*;
+use crate::visitor::{MutUseContext, PlaceContext, Visitor};
+
+/// Release the environments of closures which can't outlive the body that created them.
+///
+/// Environments are allocated when a closure with captures is created. A closure is only
+/// released when every use of it borrows it for the duration of a call: it is called, or it is
+/// passed to a function which can't keep it because everything else it receives and returns is
+/// plain data. Closures which escape in any other way are never released, see
+/// `docs/closures.md`.
+pub fn drop_closures(body: &mut BodyData) {
+    let mut uses = Uses::default();
+
+    for (id, data) in body.blocks.iter() {
+        uses.visit_block(Block(id), data);
+    }
+
+    let mut drops = Vec::new();
+
+    for (local, def) in uses.defs {
+        if uses.stores[&local] > 1 {
+            continue;
+        }
+
+        let locs = uses.uses.remove(&local).unwrap_or_default();
+
+        if !locs.iter().all(|&loc| is_borrowed(body, local, loc)) {
+            continue;
+        }
+
+        if locs.iter().all(|loc| loc.block == def.block && loc.stmt > def.stmt) {
+            let last = locs.iter().map(|loc| loc.stmt).max().unwrap_or(def.stmt);
+
+            drops.push((local, Location {
+                block: def.block,
+                stmt: last + 1,
+            }));
+        } else if def.block == Block::ENTRY && !jumps_to_entry(body) {
+            for (id, data) in body.blocks.iter() {
+                if let Term::Return(_) = data.term {
+                    drops.push((local, Location {
+                        block: Block(id),
+                        stmt: data.stmts.len(),
+                    }));
+                }
+            }
+        }
+    }
+
+    // Insert from the back so the locations of the remaining drops stay valid.
+    drops.sort_by_key(|&(_, loc)| (u32::from(loc.block.0.into_raw()), loc.stmt));
+
+    for (local, loc) in drops.into_iter().rev() {
+        let data = &mut body.blocks[loc.block.0];
+
+        data.stmts.insert(loc.stmt, Stmt::Drop(local));
+        data.sources.insert(loc.stmt, None);
+    }
+}
+
+#[derive(Default)]
+struct Uses {
+    /// The closures created in the body and where they were created.
+    defs: FxHashMap<Local, Location>,
+    stores: FxHashMap<Local, usize>,
+    uses: FxHashMap<Local, Vec<Location>>,
+}
+
+impl Visitor for Uses {
+    fn visit_assign(&mut self, place: &Place, rvalue: &Rvalue, loc: Location) {
+        // Closures without captures have no environment to release.
+        if let (true, Rvalue::Closure(_, captures)) = (place.projection.is_empty(), rvalue) {
+            if !captures.is_empty() {
+                self.defs.insert(place.local, loc);
+            }
+        }
+
+        self.super_assign(place, rvalue, loc);
+    }
+
+    fn visit_local(&mut self, local: &Local, ctx: PlaceContext, loc: Location) {
+        match ctx {
+            | PlaceContext::MutUse(MutUseContext::Store) => *self.stores.entry(*local).or_default() += 1,
+            | _ => self.uses.entry(*local).or_default().push(loc),
+        }
+    }
+}
+
+/// Whether every use of the closure `local` at `loc` only borrows it for the duration of a call.
+fn is_borrowed(body: &BodyData, local: Local, loc: Location) -> bool {
+    let data = &body.blocks[loc.block.0];
+    let (place, func, args) = match data.stmts.get(loc.stmt) {
+        | Some(Stmt::Call { place, func, args }) => (place, func, args),
+        | _ => return false,
+    };
+
+    let is_local = |op: &Operand| matches!(op, Operand::Copy(p) | Operand::Move(p) if p.local == local);
+    let is_whole = |op: &Operand| matches!(op, Operand::Copy(p) | Operand::Move(p) if p.projection.is_empty());
+
+    if place.local == local || (is_local(func) && !is_whole(func)) {
+        return false;
+    }
+
+    if !args.iter().any(is_local) {
+        return true;
+    }
+
+    // The callee could only keep the closure by returning it or by writing it to memory it
+    // received, which requires a pointer or another closure.
+    args.iter().all(|arg| match is_local(arg) {
+        | true => is_whole(arg),
+        | false => is_plain(&body.operand_repr(arg)),
+    }) && is_plain(&body.place_repr(place))
+        && matches!(body.operand_repr(func), Repr::Func(_, false))
+}
+
+/// Whether values of `repr` can't refer to a closure environment.
+fn is_plain(repr: &Repr) -> bool {
+    match repr {
+        | Repr::Uninhabited | Repr::Scalar(_) | Repr::Discr(_) | Repr::Func(_, false) => true,
+        | Repr::Struct(fields) | Repr::Enum(fields) => fields.iter().all(is_plain),
+        | Repr::Array(_, elem) => is_plain(elem),
+        | Repr::Opaque | Repr::TypeVar(_) | Repr::ReprOf(_) | Repr::Ptr(..) | Repr::Box(_) | Repr::Func(_, true) => {
+            false
+        },
+    }
+}
+
+fn jumps_to_entry(body: &BodyData) -> bool {
+    body.blocks.iter().any(|(_, data)| match &data.term {
+        | Term::Jump(target) => target.block == Block::ENTRY,
+        | Term::Switch { targets, .. } => targets.iter().any(|t| t.block == Block::ENTRY),
+        | _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::Signature;
+
+    fn func(thick: bool) -> Repr {
+        let sig = Signature {
+            params: Box::new([Repr::i32()]),
+            ret: Repr::i32(),
+        };
+
+        Repr::Func(Box::new(sig), thick)
+    }
+
+    #[test]
+    fn plain_reprs_hold_no_closures() {
+        assert!(is_plain(&Repr::i32()));
+        assert!(is_plain(&Repr::Struct(Box::new([Repr::i32(), func(false)]))));
+        assert!(!is_plain(&func(true)));
+        assert!(!is_plain(&Repr::Struct(Box::new([Repr::i32(), func(true)]))));
+        assert!(!is_plain(&Repr::Ptr(Box::new(Repr::i32()), false, false)));
+        assert!(!is_plain(&Repr::Opaque));
+    }
+}
@@ -0,0 +1,263 @@
+use arena::{ArenaMap, Idx};
+use hir::DefWithBody;
+use rustc_hash::FxHashMap;
+
+use crate::db::MirDatabase;
+use crate::instance::{Instance, InstanceDef};
+use crate::syntax::*;
+use crate::visitor::{MutVisitor, PlaceContext, Visitor};
+
+/// Replace calls to small known functions with the body of the called function.
+/// Calls inside of inlined bodies are not inlined again.
+pub fn inline_calls(db: &dyn MirDatabase, body: &mut BodyData, threshold: usize) {
+    let refs = body
+        .blocks
+        .iter()
+        .flat_map(|(_, b)| b.stmts.iter())
+        .filter_map(|stmt| match stmt {
+            | Stmt::Assign(place, Rvalue::InstanceRef(instance)) if place.projection.is_empty() => {
+                Some((place.local, instance.clone()))
+            },
+            | _ => None,
+        })
+        .collect::<FxHashMap<_, _>>();
+
+    let mut worklist = body.blocks.iter().map(|(id, _)| Block(id)).collect::<Vec<_>>();
+
+    while let Some(block) = worklist.pop() {
+        let call = body.blocks[block.0]
+            .stmts
+            .iter()
+            .enumerate()
+            .find_map(|(i, stmt)| match stmt {
+                | Stmt::Call { func, args, .. } => {
+                    let (instance, callee) = inline_candidate(db, body, &refs, func, threshold)?;
+                    let params = callee.blocks[Block::ENTRY.0].params.len();
+
+                    (params == args.len()).then_some((i, instance, callee))
+                },
+                | _ => None,
+            });
+
+        if let Some((i, instance, callee)) = call {
+            worklist.push(inline_call(db, body, block, i, &instance, &callee));
+        }
+    }
+}
+
+fn inline_candidate(
+    db: &dyn MirDatabase,
+    body: &BodyData,
+    refs: &FxHashMap<Local, Instance>,
+    func: &Operand,
+    threshold: usize,
+) -> Option<(Instance, BodyData)> {
+    let local = match func {
+        | Operand::Copy(place) | Operand::Move(place) if place.projection.is_empty() => place.local,
+        | _ => return None,
+    };
+
+    let instance = refs.get(&local)?;
+
+    match instance.def {
+        | InstanceDef::Def(DefWithBody::Func(_)) if instance.has_body(db) && !instance.is_foreign(db) => {},
+        | _ => return None,
+    }
+
+    let callee = db.lookup_intern_body(instance.body(db));
+
+    if callee.origin.def == body.origin.def {
+        return None;
+    }
+
+    let size = callee.blocks.iter().map(|(_, b)| b.stmts.len() + 1).sum::<usize>();
+
+    if size > threshold || !Inlinable::check(&callee) {
+        return None;
+    }
+
+    Some((instance.clone(), BodyData::clone(&callee)))
+}
+
+/// Inline the call at `stmt` in `block`, returning the block containing the statements
+/// following the call.
+fn inline_call(
+    db: &dyn MirDatabase,
+    body: &mut BodyData,
+    block: Block,
+    stmt: usize,
+    instance: &Instance,
+    callee: &BodyData,
+) -> Block {
+    let data = &mut body.blocks[block.0];
+    let rest = data.stmts.split_off(stmt + 1);
+    let rest_sources = data.sources.split_off(stmt + 1);
+    let (place, args) = match data.stmts.pop() {
+        | Some(Stmt::Call { place, args, .. }) => (place, args),
+        | _ => unreachable!(),
+    };
+
+    // The inlined statements are attributed to the call as their sources refer to another body.
+    let source = data.sources.pop().flatten();
+    let term = std::mem::replace(&mut data.term, Term::None);
+
+    data.sources.push(source);
+
+    let mut locals = ArenaMap::default();
+    let mut blocks = ArenaMap::default();
+
+    // The parameters of the callee become block parameters, the caller keeps its own arguments.
+    for (id, local) in callee.locals.iter() {
+        let new = Local(body.locals.next_idx());
+        let kind = match local.kind {
+            | LocalKind::Arg => LocalKind::Var,
+            | kind => kind,
+        };
+
+        body.locals.alloc(LocalData {
+            id: new,
+            kind,
+            repr: instance.subst_repr(db, &local.repr),
+        });
+
+        locals.insert(id, new);
+    }
+
+    for (id, _) in callee.blocks.iter() {
+        let new = Block(body.blocks.next_idx());
+
+        body.blocks.alloc(BlockData {
+            id: new,
+            params: Vec::new(),
+            stmts: Vec::new(),
+            term: Term::None,
+            sources: Vec::new(),
+        });
+
+        blocks.insert(id, new);
+    }
+
+    let ret = Local(body.locals.next_idx());
+    let cont = Block(body.blocks.next_idx());
+    let ret_repr = body.place_repr(&place);
+
+    body.locals.alloc(LocalData {
+        id: ret,
+        kind: LocalKind::Tmp,
+        repr: ret_repr,
+    });
+
+    let mut stmts = vec![Stmt::Assign(place, Rvalue::Use(Operand::Move(Place::new(ret))))];
+    let mut sources = vec![source];
+
+    stmts.extend(rest);
+    sources.extend(rest_sources);
+    body.blocks.alloc(BlockData {
+        id: cont,
+        params: vec![ret],
+        stmts,
+        term,
+        sources,
+    });
+
+    let mut renamer = Renamer {
+        db,
+        instance,
+        locals: &locals,
+        blocks: &blocks,
+    };
+
+    for (id, data) in callee.blocks.iter() {
+        let new = blocks[id];
+        let mut data = data.clone();
+
+        renamer.visit_block(new, &mut data);
+        data.id = new;
+        data.params = data.params.iter().map(|l| locals[l.0]).collect();
+        data.sources = vec![source; data.stmts.len() + 1];
+
+        if let Term::Return(op) = data.term {
+            data.term = Term::Jump(JumpTarget {
+                block: cont,
+                args: vec![op],
+            });
+        }
+
+        body.blocks[new.0] = data;
+    }
+
+    body.blocks[block.0].term = Term::Jump(JumpTarget {
+        block: blocks[Block::ENTRY.0],
+        args,
+    });
+
+    cont
+}
+
+/// Checks whether the body does not depend on the context it was defined in.
+struct Inlinable(bool);
+
+impl Inlinable {
+    fn check(body: &BodyData) -> bool {
+        let mut v = Self(true);
+
+        for (id, data) in body.blocks.iter() {
+            v.visit_block(Block(id), data);
+        }
+
+        v.0
+    }
+}
+
+impl Visitor for Inlinable {
+    fn visit_rvalue(&mut self, rvalue: &Rvalue, loc: Location) {
+        if let Rvalue::Closure(..) = rvalue {
+            self.0 = false;
+        }
+
+        self.super_rvalue(rvalue, loc);
+    }
+
+    fn visit_const(&mut self, const_: &Const, _loc: Location) {
+        if let Const::TypeVar(_) = const_ {
+            self.0 = false;
+        }
+    }
+}
+
+/// Maps the locals and blocks of an inlined body to their new ids.
+struct Renamer<'a> {
+    db: &'a dyn MirDatabase,
+    instance: &'a Instance,
+    locals: &'a ArenaMap<Idx<LocalData>, Local>,
+    blocks: &'a ArenaMap<Idx<BlockData>, Block>,
+}
+
+impl MutVisitor for Renamer<'_> {
+    fn visit_local(&mut self, local: &mut Local, _ctx: PlaceContext, _loc: Location) {
+        *local = self.locals[local.0];
+    }
+
+    fn visit_jump_target(&mut self, target: &mut JumpTarget, loc: Location) {
+        target.block = self.blocks[target.block.0];
+        self.super_jump_target(target, loc);
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &mut Rvalue, loc: Location) {
+        match rvalue {
+            | Rvalue::InstanceRef(instance) => *instance = self.instance.subst_instance(self.db, instance),
+            | Rvalue::NullOp(_, repr) => *repr = self.instance.subst_repr(self.db, repr),
+            | _ => {},
+        }
+
+        self.super_rvalue(rvalue, loc);
+    }
+
+    fn visit_operand(&mut self, op: &mut Operand, loc: Location) {
+        if let Operand::Const(_, repr) = op {
+            *repr = self.instance.subst_repr(self.db, repr);
+        }
+
+        self.super_operand(op, loc);
+    }
+}
@@ -0,0 +1,168 @@
+use arena::{Arena, ArenaMap, Idx};
+
+use crate::syntax::*;
+use crate::visitor::{PlaceContext, Visitor};
+
+/// Remove references to instances which are never used, as happens after inlining a call.
+pub fn remove_unused_refs(body: &mut BodyData) {
+    let mut uses = Uses::default();
+
+    for (id, data) in body.blocks.iter() {
+        uses.visit_block(Block(id), data);
+    }
+
+    for (_, data) in body.blocks.iter_mut() {
+        let mut i = 0;
+
+        while i < data.stmts.len() {
+            match &data.stmts[i] {
+                | Stmt::Assign(place, Rvalue::InstanceRef(_))
+                    if place.projection.is_empty() && uses.0.get(place.local.0).is_none() =>
+                {
+                    data.stmts.remove(i);
+                    data.sources.remove(i);
+                },
+                | _ => i += 1,
+            }
+        }
+    }
+}
+
+/// Merge blocks into their only predecessor when it unconditionally jumps to them and
+/// remove all blocks which are no longer reachable.
+pub fn simplify_cfg(body: &mut BodyData) {
+    while merge_block(body) {}
+    remove_unreachable(body);
+}
+
+fn merge_block(body: &mut BodyData) -> bool {
+    let mut preds = ArenaMap::<Idx<BlockData>, usize>::default();
+
+    for (_, data) in body.blocks.iter() {
+        for target in targets(&data.term) {
+            let count = preds.get(target.block.0).copied().unwrap_or(0);
+
+            preds.insert(target.block.0, count + 1);
+        }
+    }
+
+    let found = body.blocks.iter().find_map(|(id, data)| match &data.term {
+        | Term::Jump(target)
+            if target.block.0 != id
+                && target.block != Block::ENTRY
+                && preds[target.block.0] == 1
+                && !target
+                    .args
+                    .iter()
+                    .any(|arg| uses_params(arg, &body.blocks[target.block.0])) =>
+        {
+            Some((Block(id), target.clone()))
+        },
+        | _ => None,
+    });
+
+    let (block, target) = match found {
+        | Some(found) => found,
+        | None => return false,
+    };
+
+    let next = std::mem::replace(&mut body.blocks[target.block.0], BlockData {
+        id: target.block,
+        params: Vec::new(),
+        stmts: Vec::new(),
+        term: Term::Unreachable,
+        sources: Vec::new(),
+    });
+
+    let data = &mut body.blocks[block.0];
+    let source = data.sources.pop().flatten();
+
+    for (&param, arg) in next.params.iter().zip(target.args) {
+        data.stmts.push(Stmt::Assign(Place::new(param), Rvalue::Use(arg)));
+        data.sources.push(source);
+    }
+
+    data.stmts.extend(next.stmts);
+    data.sources.extend(next.sources);
+    data.term = next.term;
+
+    for param in next.params {
+        body.locals[param.0].kind = LocalKind::Tmp;
+    }
+
+    true
+}
+
+fn remove_unreachable(body: &mut BodyData) {
+    let mut order = vec![Block::ENTRY];
+    let mut i = 0;
+
+    while i < order.len() {
+        for target in targets(&body.blocks[order[i].0].term) {
+            if !order.contains(&target.block) {
+                order.push(target.block);
+            }
+        }
+
+        i += 1;
+    }
+
+    if order.len() == body.blocks.len() {
+        return;
+    }
+
+    let mut map = ArenaMap::default();
+
+    for (i, block) in order.iter().enumerate() {
+        map.insert(block.0, Block(Idx::from_raw((i as u32).into())));
+    }
+
+    let mut blocks = Arena::default();
+
+    for block in order {
+        let mut data = body.blocks[block.0].clone();
+
+        data.id = map[block.0];
+
+        match &mut data.term {
+            | Term::Jump(target) => target.block = map[target.block.0],
+            | Term::Switch { targets, .. } => {
+                for target in targets {
+                    target.block = map[target.block.0];
+                }
+            },
+            | _ => {},
+        }
+
+        blocks.alloc(data);
+    }
+
+    body.blocks = blocks;
+}
+
+fn targets(term: &Term) -> &[JumpTarget] {
+    match term {
+        | Term::Jump(target) => std::slice::from_ref(target),
+        | Term::Switch { targets, .. } => targets,
+        | _ => &[],
+    }
+}
+
+fn uses_params(op: &Operand, block: &BlockData) -> bool {
+    match op {
+        | Operand::Copy(place) | Operand::Move(place) => block.params.contains(&place.local),
+        | Operand::Const(_, _) => false,
+    }
+}
+
+/// Collects all locals which are used as a value.
+#[derive(Default)]
+struct Uses(ArenaMap<Idx<LocalData>, ()>);
+
+impl Visitor for Uses {
+    fn visit_local(&mut self, local: &Local, ctx: PlaceContext, _loc: Location) {
+        if let PlaceContext::Use(_) = ctx {
+            self.0.insert(local.0, ());
+        }
+    }
+}
@@ -140,6 +140,11 @@ macro_rules! make_visitor {
                     },
                     | Rvalue::NullOp(_, _) => {},
                     | Rvalue::InstanceRef(_) => {},
+                    | Rvalue::Closure(_, captures) => {
+                        for op in captures {
+                            self.visit_operand(op, loc);
+                        }
+                    },
                 }
             }
 
@@ -0,0 +1,313 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use cfg::{CfgOptions, CfgValue};
+use paths::{AbsPath, AbsPathBuf};
+use serde::{Deserialize, Serialize};
+use vfs::VirtualFileSystem;
+
+use crate::{manifest, Package, Workspace};
+
+/// A script run before a package is compiled, set with `build` in the `[project]` table.
+///
+/// The script runs in the directory of the manifest with the following environment variables:
+/// - `FI_MANIFEST_DIR`: the directory containing the manifest of the package
+/// - `FI_OUT_DIR`: a directory for the files generated by the script
+/// - `FI_TARGET_DIR`: the target directory of the workspace
+/// - `FI_TARGET`: the target triple, or `javascript`
+/// - `FI_PACKAGE`: the name of the package
+/// - `FI_FEATURE_<NAME>`: set for every enabled feature
+///
+/// Lines printed to stdout of the form `fi:<key>=<value>` instruct the compiler:
+/// - `fi:link=<path>` links the package with an extra library or object file
+/// - `fi:cfg=<key>` or `fi:cfg=<key>=<value>` sets a cfg option for the package
+/// - `fi:source=<path>` adds a generated source file to the package
+/// - `fi:warning=<message>` prints a warning
+///
+/// Relative paths are resolved against `FI_OUT_DIR`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BuildScript {
+    /// A `.fi` file which is compiled to an executable, or any other executable file. A `.fi`
+    /// script can import the dependencies of the package and is compiled with the `fi` set in
+    /// `$FI`, found next to the running compiler or in the `PATH`.
+    Path(PathBuf),
+    /// A program and its arguments.
+    Command(Vec<String>),
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct BuildOutput {
+    pub links: Vec<PathBuf>,
+    pub cfg: CfgOptions,
+    pub sources: Vec<AbsPathBuf>,
+    pub warnings: Vec<String>,
+}
+
+/// A warning printed by a build script with `fi:warning=<message>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildWarning {
+    pub package: String,
+    pub manifest_path: AbsPathBuf,
+    pub message: String,
+}
+
+impl Workspace {
+    /// Run the build scripts of the packages needed by the selected members, dependencies
+    /// first, and add their output to the packages. The warnings of the scripts are added to
+    /// `warnings` for the caller to report.
+    pub fn run_build_scripts(
+        &mut self,
+        vfs: &mut VirtualFileSystem,
+        target_dir: &AbsPath,
+        target: &str,
+        warnings: &mut Vec<BuildWarning>,
+    ) -> Result<bool> {
+        let mut ran = false;
+
+        for package in self.packages_in_build_order() {
+            if self.packages[package].build.is_none() || !self.is_selected(Some(package)) {
+                continue;
+            }
+
+            let data = &self.packages[package];
+            let out_dir = target_dir.join("build").join(&data.name);
+            let output = run(self, package, &out_dir, target_dir, target)
+                .with_context(|| format!("failed to run the build script of `{}`", data.name))?;
+
+            warnings.extend(output.warnings.into_iter().map(|message| BuildWarning {
+                package: data.name.clone(),
+                manifest_path: data.manifest_path.clone(),
+                message,
+            }));
+
+            for source in output.sources {
+                manifest::load_file(vfs, source)?;
+            }
+
+            let data = &mut self.packages[package];
+
+            data.links.extend(output.links);
+            data.cfg = data.cfg.merge(&output.cfg);
+            data.out_dir = Some(out_dir);
+            ran = true;
+        }
+
+        Ok(ran)
+    }
+
+    fn packages_in_build_order(&self) -> Vec<Package> {
+        fn visit(ws: &Workspace, package: Package, order: &mut Vec<Package>) {
+            if order.contains(&package) {
+                return;
+            }
+
+            for dep in &ws.packages[package].dependencies {
+                visit(ws, dep.package, order);
+            }
+
+            order.push(package);
+        }
+
+        let mut order = Vec::new();
+
+        for package in self.packages() {
+            visit(self, package, &mut order);
+        }
+
+        order
+    }
+}
+
+fn run(ws: &Workspace, package: Package, out_dir: &AbsPath, target_dir: &AbsPath, target: &str) -> Result<BuildOutput> {
+    let data = &ws.packages[package];
+    let manifest_dir = data.manifest_path.parent().unwrap();
+
+    fs::create_dir_all(out_dir).with_context(|| format!("failed to create {}", out_dir.display()))?;
+
+    let mut cmd = match data.build.as_ref().unwrap() {
+        | BuildScript::Path(path) if path.extension().map_or(false, |e| e == "fi") => {
+            Command::new(compile(ws, package, &manifest_dir.join(path), out_dir)?.as_os_str())
+        },
+        | BuildScript::Path(path) => Command::new(manifest_dir.join(path).as_os_str()),
+        | BuildScript::Command(args) => match args.split_first() {
+            | Some((program, args)) => {
+                let mut cmd = Command::new(program);
+
+                cmd.args(args);
+                cmd
+            },
+            | None => anyhow::bail!("the build command is empty"),
+        },
+    };
+
+    cmd.current_dir(manifest_dir)
+        .env("FI_MANIFEST_DIR", manifest_dir.as_os_str())
+        .env("FI_OUT_DIR", out_dir.as_os_str())
+        .env("FI_TARGET_DIR", target_dir.as_os_str())
+        .env("FI_TARGET", target)
+        .env("FI_PACKAGE", &data.name);
+
+    for feature in &data.enabled_features {
+        cmd.env(format!("FI_FEATURE_{}", feature.to_uppercase().replace('-', "_")), "1");
+    }
+
+    tracing::debug!("running build script: {:?}", cmd);
+
+    let output = cmd
+        .output()
+        .with_context(|| format!("failed to run {:?}", cmd.get_program()))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "build script exited with {}\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+
+    parse_output(&String::from_utf8_lossy(&output.stdout), out_dir)
+}
+
+/// Compile a `.fi` build script to an executable in `out_dir`. The script can use the
+/// dependencies of its package.
+fn compile(ws: &Workspace, package: Package, script: &AbsPath, out_dir: &AbsPath) -> Result<AbsPathBuf> {
+    let fi = compiler();
+    let mut cmd = Command::new(&fi);
+
+    cmd.current_dir(out_dir)
+        .arg(script.as_os_str())
+        .args(["--name", "build", "--output", "executable"]);
+
+    for dep in &ws.packages[package].dependencies {
+        cmd.arg("--dep")
+            .arg(ws.packages[dep.package].manifest_path.parent().unwrap().as_os_str());
+    }
+
+    tracing::debug!("compiling build script: {:?}", cmd);
+
+    let status = cmd
+        .status()
+        .with_context(|| format!("failed to run {}", fi.display()))?;
+
+    if !status.success() {
+        anyhow::bail!("failed to compile {}", script.display());
+    }
+
+    Ok(out_dir
+        .join("target")
+        .join(format!("build{}", std::env::consts::EXE_SUFFIX)))
+}
+
+/// The `fi` executable used to compile build scripts: `$FI` when it is set, otherwise the `fi`
+/// next to the current executable, or in its parent directory when it's a test in
+/// `target/debug/deps`, and finally `fi` from the `PATH`. The current executable itself may be
+/// the language server or a test.
+fn compiler() -> PathBuf {
+    if let Some(fi) = std::env::var_os("FI") {
+        return fi.into();
+    }
+
+    let name = format!("fi{}", std::env::consts::EXE_SUFFIX);
+
+    if let Ok(exe) = std::env::current_exe() {
+        for dir in exe.ancestors().skip(1).take(2) {
+            let fi = dir.join(&name);
+
+            if fi.is_file() {
+                return fi;
+            }
+        }
+    }
+
+    PathBuf::from(name)
+}
+
+pub fn parse_output(stdout: &str, out_dir: &AbsPath) -> Result<BuildOutput> {
+    let mut output = BuildOutput::default();
+
+    for line in stdout.lines() {
+        let (key, value) = match line.trim().strip_prefix("fi:").and_then(|l| l.split_once('=')) {
+            | Some(it) => it,
+            | None => continue,
+        };
+
+        match key {
+            | "link" => output.links.push(out_dir.join(value).into()),
+            | "source" => output.sources.push(out_dir.join(value)),
+            | "warning" => output.warnings.push(value.to_string()),
+            | "cfg" => match value.split_once('=') {
+                | Some((key, value)) => {
+                    let value = match value.parse::<i128>() {
+                        | Ok(i) => CfgValue::Int(i),
+                        | Err(_) => CfgValue::String(value.trim_matches('"').into()),
+                    };
+
+                    output.cfg.set(key, value);
+                },
+                | None => output.cfg.enable(value),
+            },
+            | _ => anyhow::bail!("unknown build script instruction `fi:{}`", key),
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn out_dir() -> AbsPathBuf {
+        AbsPathBuf::assert(PathBuf::from("/target/build/pkg"))
+    }
+
+    #[test]
+    fn parses_instructions() {
+        let stdout = "fi:link=libfoo.a\nfi:source=gen.fi\nfi:warning=something happened\n";
+        let output = parse_output(stdout, &out_dir()).unwrap();
+
+        assert_eq!(output.links, vec![PathBuf::from("/target/build/pkg/libfoo.a")]);
+        assert_eq!(output.sources, vec![out_dir().join("gen.fi")]);
+        assert_eq!(output.warnings, vec![String::from("something happened")]);
+    }
+
+    #[test]
+    fn keeps_absolute_paths() {
+        let output = parse_output("fi:link=/usr/lib/libz.a", &out_dir()).unwrap();
+
+        assert_eq!(output.links, vec![PathBuf::from("/usr/lib/libz.a")]);
+    }
+
+    #[test]
+    fn parses_cfg_options() {
+        let stdout = "fi:cfg=has_foo\nfi:cfg=version=3\nfi:cfg=backend=\"gl\"\n";
+        let output = parse_output(stdout, &out_dir()).unwrap();
+        let mut cfg = CfgOptions::default();
+
+        cfg.enable("has_foo");
+        cfg.set("version", CfgValue::Int(3));
+        cfg.set("backend", CfgValue::String("gl".into()));
+
+        assert_eq!(output.cfg, cfg);
+    }
+
+    #[test]
+    fn ignores_other_output() {
+        let stdout = "compiling foo.c\n  fi:warning=indented\nfi:no value\n";
+        let output = parse_output(stdout, &out_dir()).unwrap();
+
+        assert_eq!(output.warnings, vec![String::from("indented")]);
+        assert!(output.links.is_empty());
+        assert!(output.sources.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_instructions() {
+        let err = parse_output("fi:rerun=foo.c", &out_dir()).unwrap_err();
+
+        assert_eq!(err.to_string(), "unknown build script instruction `fi:rerun`");
+    }
+}
@@ -3,19 +3,27 @@ use std::path::PathBuf;
 
 use arena::{Arena, Idx};
 use base_db::libs::{LibKind, LibSet};
-use cfg::CfgOptions;
-use manifest::Manifest;
+use base_db::lints::LintLevels;
+use build_script::BuildScript;
+use cfg::{CfgOptions, CfgValue};
+use manifest::Project;
 use paths::{AbsPath, AbsPathBuf};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use vfs::file_set::{FileSet, FileSetConfig};
 use vfs::{FileId, VfsPath, VirtualFileSystem};
 
+pub mod build_script;
+pub mod lockfile;
 pub mod manifest;
+pub mod scaffold;
+pub mod sources;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Workspace {
     local: Option<LocalProject>,
     packages: Arena<PackageData>,
+    members: Vec<Package>,
+    selected: Vec<Package>,
     root_dir: AbsPathBuf,
 }
 
@@ -38,6 +46,20 @@ pub struct PackageData {
     pub output: LibKind,
     pub links: Vec<PathBuf>,
     pub dependencies: Vec<Dependency>,
+    pub debug: bool,
+    /// The features declared in the manifest.
+    pub features: FxHashMap<String, Vec<String>>,
+    /// The features enabled after unifying the features requested by all dependents.
+    pub enabled_features: FxHashSet<String>,
+    pub build: Option<BuildScript>,
+    /// The cfg options set by the build script.
+    pub cfg: CfgOptions,
+    /// The directory containing the files generated by the build script, once it has run.
+    pub out_dir: Option<AbsPathBuf>,
+    /// The lint levels set in the `[lints]` table.
+    pub lints: LintLevels,
+    /// The formatter options set in the `[fmt]` table.
+    pub fmt: formatter::Config,
 
     pub root_file: FileId,
     pub manifest_path: AbsPathBuf,
@@ -47,6 +69,8 @@ pub struct PackageData {
 pub struct Dependency {
     pub package: Package,
     pub cfg_opts: CfgOptions,
+    pub features: Vec<String>,
+    pub default_features: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
@@ -56,14 +80,51 @@ pub struct PackageRoot {
 }
 
 impl Workspace {
-    pub fn load(root_dir: AbsPathBuf, vfs: &mut VirtualFileSystem, cfg: &CfgOptions) -> anyhow::Result<Self> {
+    /// Load the workspace containing the project in `dir`. When `dir` is a member of a
+    /// workspace, the whole workspace is loaded but only `dir` is selected.
+    pub fn load(dir: AbsPathBuf, vfs: &mut VirtualFileSystem, cfg: &CfgOptions) -> anyhow::Result<Self> {
+        Self::load_with(dir, vfs, cfg, false)
+    }
+
+    /// Like [`Workspace::load`], but git dependencies are only taken from the cache and `fi.lock`
+    /// is left untouched.
+    pub fn load_offline(dir: AbsPathBuf, vfs: &mut VirtualFileSystem, cfg: &CfgOptions) -> anyhow::Result<Self> {
+        Self::load_with(dir, vfs, cfg, true)
+    }
+
+    fn load_with(
+        dir: AbsPathBuf,
+        vfs: &mut VirtualFileSystem,
+        cfg: &CfgOptions,
+        offline: bool,
+    ) -> anyhow::Result<Self> {
+        let dir = dir.normalize();
+        let root_dir = manifest::find_workspace_root(&dir);
         let mut workspace = Workspace {
             local: None,
             packages: Arena::default(),
+            members: Vec::new(),
+            selected: Vec::new(),
             root_dir: root_dir.clone(),
         };
 
-        manifest::load_project(&mut workspace, vfs, &cfg, &root_dir)?;
+        let mut sources = sources::Sources::new(&root_dir, offline)?;
+
+        workspace.members = manifest::load_workspace(&mut workspace, vfs, &mut sources, &cfg, &root_dir)?;
+        sources.finish()?;
+
+        workspace.selected = if root_dir == dir {
+            workspace.members.clone()
+        } else {
+            workspace
+                .members
+                .iter()
+                .copied()
+                .filter(|&p| workspace.packages[p].manifest_path.parent() == Some(dir.as_path()))
+                .collect()
+        };
+
+        workspace.resolve_features(&[] as &[&str], true)?;
 
         Ok(workspace)
     }
@@ -92,18 +153,27 @@ impl Workspace {
                 lib_deps: Vec::new(),
             }),
             packages: Arena::default(),
+            members: Vec::new(),
+            selected: Vec::new(),
             root_dir,
         };
 
+        let mut sources = sources::Sources::new(&workspace.root_dir, false)?;
+
         for dep in dependencies {
-            let pkg = manifest::load_project(&mut workspace, vfs, &cfg, &dep)?;
+            let pkg = manifest::load_project(&mut workspace, vfs, &mut sources, &cfg, &dep)?;
 
             workspace.local.as_mut().unwrap().lib_deps.push(Dependency {
                 package: pkg,
                 cfg_opts: CfgOptions::default(),
+                features: Vec::new(),
+                default_features: true,
             });
         }
 
+        sources.finish()?;
+        workspace.resolve_features(&[] as &[&str], true)?;
+
         Ok(workspace)
     }
 
@@ -115,6 +185,204 @@ impl Workspace {
         self.local.as_ref()
     }
 
+    /// The package whose manifest is in the root directory of the workspace.
+    pub fn root_package(&self) -> Option<Package> {
+        match self.local {
+            | Some(_) => None,
+            | None => self
+                .members
+                .iter()
+                .copied()
+                .find(|&p| self.packages[p].manifest_path.parent() == Some(self.root_dir.as_path())),
+        }
+    }
+
+    pub fn members(&self) -> &[Package] {
+        &self.members
+    }
+
+    /// The members the commands act on, which are all members unless selected otherwise.
+    pub fn selected(&self) -> &[Package] {
+        &self.selected
+    }
+
+    /// Select the members with the given names, or all members if there are none.
+    pub fn select(&mut self, names: &[impl AsRef<str>]) -> anyhow::Result<()> {
+        if names.is_empty() {
+            self.selected = self.members.clone();
+            return Ok(());
+        }
+
+        self.selected = names
+            .iter()
+            .map(|name| {
+                let name = name.as_ref();
+
+                self.members
+                    .iter()
+                    .copied()
+                    .find(|&p| self.packages[p].name == name)
+                    .ok_or_else(|| anyhow::anyhow!("package `{}` is not a member of the workspace", name))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(())
+    }
+
+    /// Enable `features` and, if `default_features` is set, the default features of the selected
+    /// members. The features of the selected members and their dependencies are then unified:
+    /// a package has every feature that any of its dependents enables. Features written as `package/feature` are enabled
+    /// on the named package instead.
+    pub fn resolve_features(&mut self, features: &[impl AsRef<str>], default_features: bool) -> anyhow::Result<()> {
+        let mut enabled = FxHashMap::<Package, FxHashSet<String>>::default();
+        let mut activated = FxHashSet::default();
+        let mut to_activate = Vec::new();
+        let mut queue = Vec::new();
+
+        for feature in features {
+            let feature = feature.as_ref();
+
+            if let Some((name, feature)) = feature.split_once('/') {
+                let package = self
+                    .package_for_name(name)
+                    .ok_or_else(|| anyhow::anyhow!("package `{}` is not in the workspace", name))?;
+
+                queue.push((package, feature.to_string()));
+                continue;
+            }
+
+            let mut found = false;
+
+            for &p in &self.selected {
+                if self.packages[p].features.contains_key(feature) {
+                    queue.push((p, feature.to_string()));
+                    found = true;
+                }
+            }
+
+            if !found {
+                anyhow::bail!("none of the selected packages have the feature `{}`", feature);
+            }
+        }
+
+        // Unselected members are not built, so only the selected members request features.
+        for &p in &self.selected {
+            if default_features {
+                queue.push((p, String::from("default")));
+            }
+
+            to_activate.push(p);
+        }
+
+        if let Some(local) = &self.local {
+            for dep in &local.lib_deps {
+                Self::request_features(dep, &mut queue);
+                to_activate.push(dep.package);
+            }
+        }
+
+        loop {
+            // A package requests the features of its dependencies once it is used at all.
+            while let Some(p) = to_activate.pop() {
+                if activated.insert(p) {
+                    for dep in &self.packages[p].dependencies {
+                        Self::request_features(dep, &mut queue);
+                        to_activate.push(dep.package);
+                    }
+                }
+            }
+
+            let (p, feature) = match queue.pop() {
+                | Some(it) => it,
+                | None => break,
+            };
+
+            to_activate.push(p);
+
+            if !enabled.entry(p).or_default().insert(feature.clone()) {
+                continue;
+            }
+
+            let data = &self.packages[p];
+
+            match data.features.get(&feature) {
+                | Some(enables) => {
+                    for enable in enables {
+                        match enable.split_once('/') {
+                            | Some((name, dep_feature)) => {
+                                let dep = data
+                                    .dependencies
+                                    .iter()
+                                    .find(|d| self.packages[d.package].name == name)
+                                    .ok_or_else(|| {
+                                        anyhow::anyhow!(
+                                            "feature `{}` of `{}` refers to `{}`, which is not a dependency",
+                                            feature,
+                                            data.name,
+                                            name
+                                        )
+                                    })?;
+
+                                queue.push((dep.package, dep_feature.to_string()));
+                            },
+                            | None => queue.push((p, enable.clone())),
+                        }
+                    }
+                },
+                | None if feature == "default" => {},
+                | None => anyhow::bail!("package `{}` does not have the feature `{}`", data.name, feature),
+            }
+        }
+
+        for (p, data) in self.packages.iter_mut() {
+            data.enabled_features = enabled.remove(&p).unwrap_or_default();
+
+            if !data.features.contains_key("default") {
+                data.enabled_features.remove("default");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn request_features(dep: &Dependency, queue: &mut Vec<(Package, String)>) {
+        if dep.default_features {
+            queue.push((dep.package, String::from("default")));
+        }
+
+        for feature in &dep.features {
+            queue.push((dep.package, feature.clone()));
+        }
+    }
+
+    /// Whether the library of `package` is needed by the selected members. `None` refers to the
+    /// local project, which is always included.
+    pub fn is_selected(&self, package: Option<Package>) -> bool {
+        let package = match package {
+            | Some(package) => package,
+            | None => return true,
+        };
+
+        if self.local.is_some() {
+            return true;
+        }
+
+        let mut seen = FxHashSet::default();
+        let mut stack = self.selected.clone();
+
+        while let Some(p) = stack.pop() {
+            if p == package {
+                return true;
+            }
+
+            if seen.insert(p) {
+                stack.extend(self.packages[p].dependencies.iter().map(|d| d.package));
+            }
+        }
+
+        false
+    }
+
     pub fn packages(&self) -> impl Iterator<Item = Package> + ExactSizeIterator + '_ {
         self.packages.iter().map(|(id, _)| id)
     }
@@ -147,6 +415,7 @@ impl Workspace {
                 let package_root = data.manifest_path.parent().unwrap().to_owned();
 
                 root.include.push(package_root.clone());
+                root.include.extend(data.out_dir.clone());
 
                 root.exclude.push(package_root.join(".git"));
                 root.exclude.push(package_root.join("target"));
@@ -175,11 +444,18 @@ impl Workspace {
         let mut map = FxHashMap::default();
 
         for (id, data) in self.packages.iter() {
+            let mut cfg_opts = cfg_opts.merge(&data.cfg);
+
+            for feature in &data.enabled_features {
+                cfg_opts.insert("feature", CfgValue::String(feature.into()));
+            }
+
             let lib = libs.add_lib(
                 data.name.clone(),
                 data.output,
                 data.links.clone(),
-                cfg_opts.clone(),
+                cfg_opts,
+                data.lints.clone(),
                 data.root_file,
             );
 
@@ -203,6 +479,7 @@ impl Workspace {
                 local.lib_output,
                 local.lib_links.clone(),
                 cfg_opts.clone(),
+                LintLevels::default(),
                 local.files[0],
             );
 
@@ -225,13 +502,29 @@ impl Workspace {
             .map(|(id, _)| id)
     }
 
-    fn alloc_package(&mut self, manifest: &Manifest, manifest_path: AbsPathBuf, root_file: FileId) -> Package {
+    fn alloc_package(
+        &mut self,
+        project: &Project,
+        features: FxHashMap<String, Vec<String>>,
+        lints: LintLevels,
+        fmt: formatter::Config,
+        manifest_path: AbsPathBuf,
+        root_file: FileId,
+    ) -> Package {
         self.packages.alloc(PackageData {
-            name: manifest.project.name.clone(),
-            version: manifest.project.version.clone(),
-            output: manifest.project.output,
-            links: manifest.project.link.clone(),
+            name: project.name.clone(),
+            version: project.version.clone(),
+            output: project.output,
+            links: project.link.clone(),
             dependencies: Vec::new(),
+            debug: project.debug,
+            features,
+            enabled_features: FxHashSet::default(),
+            build: project.build.clone(),
+            cfg: CfgOptions::default(),
+            out_dir: None,
+            lints,
+            fmt,
             manifest_path,
             root_file,
         })
@@ -249,3 +542,167 @@ impl Index<Package> for Workspace {
         &self.packages[package]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use cfg::{Cfg, CfgAtom};
+
+    use super::*;
+
+    /// A workspace with the members `app` and `tool`, which both depend on `lib`. `app` requests
+    /// `lib/json` with the default features of `lib`, `tool` requests `lib/yaml` without them.
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        root: AbsPathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = tempfile::TempDir::new().unwrap();
+            let root = AbsPathBuf::assert(dir.path().to_path_buf());
+            let fixture = Self { _dir: dir, root };
+
+            fs::write(
+                fixture.root.join(manifest::Manifest::FILE_NAME),
+                "[workspace]\nmembers = [\"app\", \"tool\"]\n",
+            )
+            .unwrap();
+
+            fixture.package(
+                "app",
+                "[dependencies]\nlib = { path = \"../lib\", features = [\"json\"] }\n\n[features]\ndefault = \
+                 [\"fast\"]\nfast = []\nextra = [\"lib/xml\"]\n",
+            );
+            fixture.package(
+                "tool",
+                "[dependencies]\nlib = { path = \"../lib\", default-features = false, features = [\"yaml\"] }\n",
+            );
+            fixture.package(
+                "lib",
+                "[features]\ndefault = [\"std\"]\nstd = []\njson = []\nxml = []\nyaml = []\n",
+            );
+            fixture
+        }
+
+        fn package(&self, name: &str, rest: &str) {
+            let dir = self.root.join(name);
+
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(dir.join("src").join("main.fi"), format!("module {} =\n", name)).unwrap();
+            fs::write(
+                dir.join(manifest::Manifest::FILE_NAME),
+                format!("[project]\nname = \"{}\"\nversion = \"0.1.0\"\n\n{}", name, rest),
+            )
+            .unwrap();
+        }
+
+        fn load(&self) -> Workspace {
+            Workspace::load(
+                self.root.clone(),
+                &mut VirtualFileSystem::default(),
+                &CfgOptions::default(),
+            )
+            .unwrap()
+        }
+    }
+
+    fn enabled(ws: &Workspace, name: &str) -> Vec<String> {
+        let package = ws.package_for_name(name).unwrap();
+        let mut features = ws[package].enabled_features.iter().cloned().collect::<Vec<_>>();
+
+        features.sort();
+        features
+    }
+
+    fn feature(name: &str) -> Cfg {
+        Cfg::Atom(CfgAtom::Key("feature".into(), CfgValue::String(name.into())))
+    }
+
+    #[test]
+    fn features_are_unified_across_dependents() {
+        let ws = Fixture::new().load();
+
+        assert_eq!(enabled(&ws, "app"), ["default", "fast"]);
+        assert_eq!(enabled(&ws, "tool"), [] as [&str; 0]);
+        assert_eq!(enabled(&ws, "lib"), ["default", "json", "std", "yaml"]);
+    }
+
+    #[test]
+    fn dependency_features_are_enabled_on_the_dependency() {
+        let mut ws = Fixture::new().load();
+
+        ws.resolve_features(&["extra"], true).unwrap();
+
+        assert_eq!(enabled(&ws, "app"), ["default", "extra", "fast"]);
+        assert_eq!(enabled(&ws, "lib"), ["default", "json", "std", "xml", "yaml"]);
+
+        ws.resolve_features(&["lib/xml"], true).unwrap();
+
+        assert_eq!(enabled(&ws, "app"), ["default", "fast"]);
+        assert!(enabled(&ws, "lib").contains(&String::from("xml")));
+    }
+
+    #[test]
+    fn no_default_features_only_applies_to_selected_members() {
+        let mut ws = Fixture::new().load();
+
+        ws.select(&["app"]).unwrap();
+        ws.resolve_features(&[] as &[&str], false).unwrap();
+
+        assert_eq!(enabled(&ws, "app"), [] as [&str; 0]);
+        // `app` still requests the default features of `lib`.
+        assert_eq!(enabled(&ws, "lib"), ["default", "json", "std"]);
+
+        ws.resolve_features(&["fast"], false).unwrap();
+
+        assert_eq!(enabled(&ws, "app"), ["fast"]);
+    }
+
+    #[test]
+    fn unselected_members_do_not_enable_features() {
+        let mut ws = Fixture::new().load();
+
+        ws.select(&["app"]).unwrap();
+        ws.resolve_features(&[] as &[&str], true).unwrap();
+
+        // Only the unselected `tool` requests `lib/yaml`.
+        assert_eq!(enabled(&ws, "lib"), ["default", "json", "std"]);
+        assert_eq!(enabled(&ws, "tool"), [] as [&str; 0]);
+
+        ws.select(&["tool"]).unwrap();
+        ws.resolve_features(&[] as &[&str], true).unwrap();
+
+        assert_eq!(enabled(&ws, "lib"), ["yaml"]);
+        assert_eq!(enabled(&ws, "app"), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn unknown_features_are_rejected() {
+        let mut ws = Fixture::new().load();
+
+        assert!(ws.resolve_features(&["missing"], true).is_err());
+        assert!(ws.resolve_features(&["lib/missing"], true).is_err());
+        assert!(ws.resolve_features(&["other/json"], true).is_err());
+    }
+
+    #[test]
+    fn enabled_features_are_cfg_values() {
+        let ws = Fixture::new().load();
+        let libs = ws.to_libs(&CfgOptions::default());
+        let lib = &libs.find("lib").unwrap().cfg_options;
+
+        assert_eq!(lib.check(&feature("json")), Some(true));
+        assert_eq!(lib.check(&feature("yaml")), Some(true));
+        assert_eq!(lib.check(&feature("xml")), Some(false));
+        assert_eq!(
+            lib.check(&Cfg::All(Box::new([feature("json"), feature("yaml")]))),
+            Some(true)
+        );
+        assert_eq!(
+            libs.find("tool").unwrap().cfg_options.check(&feature("default")),
+            Some(false)
+        );
+    }
+}
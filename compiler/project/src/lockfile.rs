@@ -0,0 +1,156 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use paths::{AbsPath, AbsPathBuf};
+use serde::{Deserialize, Serialize};
+
+/// The resolved sources of the git and registry dependencies of a project, stored next to its
+/// manifest so that later builds use the same versions.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+
+    /// Either `git+<url>` or `registry+<path>`, where the path is relative to the lockfile, or
+    /// `std` for the standard libraries installed with the compiler.
+    pub source: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// The commit a git dependency is pinned to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+}
+
+impl Lockfile {
+    pub const FILE_NAME: &'static str = "fi.lock";
+
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(Into::into)
+    }
+
+    /// Load the lockfile in `dir`, or an empty one if there is none.
+    pub fn load(dir: &AbsPath) -> Result<Self> {
+        let path = dir.join(Self::FILE_NAME);
+
+        match fs::read_to_string(&path) {
+            | Ok(text) => Self::parse(&text).with_context(|| format!("failed to parse {}", path.display())),
+            | Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            | Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, dir: &AbsPath) -> Result<AbsPathBuf> {
+        let path = dir.join(Self::FILE_NAME);
+        let mut text = String::from("# This file is generated by fi, do not edit it by hand.\n\n");
+
+        text.push_str(&toml::to_string(self)?);
+        fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    pub fn get(&self, name: &str, source: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name && p.source == source)
+    }
+
+    /// Lock `package`, replacing the package with the same name and source. Packages with the
+    /// same name from different sources are locked separately.
+    pub fn insert(&mut self, package: LockedPackage) {
+        match self
+            .packages
+            .iter_mut()
+            .find(|p| p.name == package.name && p.source == package.source)
+        {
+            | Some(p) => *p = package,
+            | None => self.packages.push(package),
+        }
+
+        self.packages
+            .sort_by(|a, b| (&a.name, &a.source).cmp(&(&b.name, &b.source)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(name: &str, rev: &str) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            source: format!("git+https://example.com/{}.git", name),
+            version: None,
+            rev: Some(rev.to_string()),
+        }
+    }
+
+    fn registry(name: &str, version: &str) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            source: String::from("registry+../registry"),
+            version: Some(version.to_string()),
+            rev: None,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dir = AbsPath::assert(dir.path());
+        let mut lockfile = Lockfile::default();
+
+        lockfile.insert(registry("json", "1.2.0"));
+        lockfile.insert(git("http", "0123456789abcdef"));
+
+        let path = lockfile.save(dir).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+
+        assert!(text.starts_with("# This file is generated by fi"));
+        assert_eq!(Lockfile::load(dir).unwrap(), lockfile);
+        assert_eq!(lockfile.packages[0].name, "http");
+    }
+
+    #[test]
+    fn missing_lockfile_is_empty() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        assert_eq!(
+            Lockfile::load(AbsPath::assert(dir.path())).unwrap(),
+            Lockfile::default()
+        );
+    }
+
+    #[test]
+    fn insert_replaces_same_source() {
+        let mut lockfile = Lockfile::default();
+
+        lockfile.insert(git("http", "aaaa"));
+        lockfile.insert(git("http", "bbbb"));
+
+        assert_eq!(lockfile.packages, vec![git("http", "bbbb")]);
+    }
+
+    #[test]
+    fn insert_keeps_same_name_from_other_source() {
+        let mut lockfile = Lockfile::default();
+
+        lockfile.insert(git("http", "aaaa"));
+        lockfile.insert(registry("http", "0.3.0"));
+
+        assert_eq!(lockfile.packages.len(), 2);
+        assert_eq!(
+            lockfile.get("http", "registry+../registry"),
+            Some(&registry("http", "0.3.0"))
+        );
+        assert_eq!(
+            lockfile.get("http", "git+https://example.com/http.git"),
+            Some(&git("http", "aaaa"))
+        );
+    }
+}
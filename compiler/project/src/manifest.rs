@@ -3,6 +3,7 @@ use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 use base_db::libs::LibKind;
+use base_db::lints::{Lint, LintLevel, LintLevels};
 use cfg::{CfgOptions, CfgValue};
 use paths::{AbsPath, AbsPathBuf};
 use rustc_hash::FxHashMap;
@@ -10,14 +11,34 @@ use serde::{Deserialize, Serialize};
 pub use toml::Value as TomlValue;
 use vfs::{FileId, VfsPath, VirtualFileSystem};
 
+use crate::build_script::BuildScript;
+use crate::sources::Sources;
 use crate::{Package, Workspace};
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Manifest {
-    pub project: Project,
+    /// Only a workspace root can leave out the project.
+    #[serde(default)]
+    pub project: Option<Project>,
+
+    #[serde(default)]
+    pub workspace: Option<WorkspaceTable>,
 
     #[serde(default)]
     pub dependencies: FxHashMap<String, Dependency>,
+
+    /// Features and the features or `dependency/feature`s they enable. The `default` feature is
+    /// enabled unless the default features are disabled.
+    #[serde(default)]
+    pub features: FxHashMap<String, Vec<String>>,
+
+    /// Lint names and their level: `allow`, `warn` or `deny`.
+    #[serde(default)]
+    pub lints: FxHashMap<String, String>,
+
+    /// The options of `fi fmt`.
+    #[serde(default)]
+    pub fmt: formatter::Config,
 }
 
 pub type Cfg = FxHashMap<String, toml::Value>;
@@ -36,6 +57,22 @@ pub struct Project {
     #[serde(default)]
     #[serde(with = "lib_kind")]
     pub output: LibKind,
+
+    /// Emit debug info. Debug info is a setting of the whole build, so it is emitted for every
+    /// package that is built, including dependencies, when any of the selected members sets it.
+    #[serde(default)]
+    pub debug: bool,
+
+    #[serde(default)]
+    pub build: Option<BuildScript>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceTable {
+    /// Paths to the member projects, relative to the workspace root. A path ending in `*`
+    /// includes every directory containing a manifest.
+    #[serde(default)]
+    pub members: Vec<PathBuf>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -46,6 +83,49 @@ pub enum Dependency {
 
         #[serde(default)]
         cfg: Cfg,
+
+        #[serde(default)]
+        features: Vec<String>,
+
+        #[serde(default = "default_features", rename = "default-features")]
+        default_features: bool,
+    },
+    Git {
+        git: String,
+
+        #[serde(default)]
+        rev: Option<String>,
+
+        #[serde(default)]
+        tag: Option<String>,
+
+        #[serde(default)]
+        branch: Option<String>,
+
+        #[serde(default)]
+        cfg: Cfg,
+
+        #[serde(default)]
+        features: Vec<String>,
+
+        #[serde(default = "default_features", rename = "default-features")]
+        default_features: bool,
+    },
+    Registry {
+        version: String,
+
+        /// A directory registry, defaults to `$FI_REGISTRY` or the registry in the cache directory.
+        #[serde(default)]
+        registry: Option<PathBuf>,
+
+        #[serde(default)]
+        cfg: Cfg,
+
+        #[serde(default)]
+        features: Vec<String>,
+
+        #[serde(default = "default_features", rename = "default-features")]
+        default_features: bool,
     },
 }
 
@@ -53,16 +133,76 @@ impl Manifest {
     pub const FILE_NAME: &'static str = "fi.toml";
 
     pub fn parse(text: &str) -> Result<Self> {
-        toml::from_str(text).map_err(Into::into)
+        let manifest: Self = toml::from_str(text)?;
+
+        for (name, dep) in &manifest.dependencies {
+            dep.validate().with_context(|| format!("invalid dependency `{}`", name))?;
+        }
+
+        Ok(manifest)
     }
 
-    pub fn dep_dirs<'a>(&'a self, proj_dir: &'a AbsPath) -> impl Iterator<Item = AbsPathBuf> + 'a {
-        self.dependencies.values().map(move |d| d.get_dir(proj_dir))
+    pub fn project(&self) -> Result<&Project> {
+        self.project
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("manifest is missing a [project] table"))
+    }
+
+    pub fn dep_dirs<'a>(
+        &'a self,
+        proj_dir: &'a AbsPath,
+        sources: &'a mut Sources,
+    ) -> impl Iterator<Item = Result<AbsPathBuf>> + 'a {
+        self.dependencies
+            .iter()
+            .map(move |(name, d)| sources.resolve(name, d, proj_dir))
     }
 
     pub fn dep_cfg_opts<'a>(&'a self) -> impl Iterator<Item = &'a Cfg> + 'a {
         self.dependencies.values().map(|d| d.get_cfg())
     }
+
+    pub fn lint_levels(&self) -> Result<LintLevels> {
+        let mut levels = LintLevels::default();
+
+        for (name, level) in &self.lints {
+            let lint = name.parse::<Lint>().map_err(|e| anyhow::anyhow!("{}", e))?;
+            let level = level.parse::<LintLevel>().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+            levels.set(lint, level);
+        }
+
+        Ok(levels)
+    }
+
+    pub fn dep_features<'a>(&'a self) -> impl Iterator<Item = (&'a [String], bool)> + 'a {
+        self.dependencies.values().map(|d| d.get_features())
+    }
+}
+
+impl WorkspaceTable {
+    pub fn member_dirs(&self, root_dir: &AbsPath) -> Result<Vec<AbsPathBuf>> {
+        let mut dirs = Vec::new();
+
+        for member in &self.members {
+            if member.file_name().and_then(|n| n.to_str()) != Some("*") {
+                dirs.push(root_dir.join(member).normalize());
+                continue;
+            }
+
+            let parent = root_dir.join(member.parent().unwrap());
+            let mut found = fs::read_dir(&parent)
+                .with_context(|| format!("failed to read directory: {}", parent.display()))?
+                .filter_map(|entry| Some(AbsPathBuf::try_from(entry.ok()?.path()).ok()?.normalize()))
+                .filter(|dir| fs::metadata(dir.join(Manifest::FILE_NAME)).map_or(false, |m| m.is_file()))
+                .collect::<Vec<_>>();
+
+            found.sort();
+            dirs.extend(found);
+        }
+
+        Ok(dirs)
+    }
 }
 
 impl Project {
@@ -76,52 +216,197 @@ impl Project {
 }
 
 impl Dependency {
-    pub fn get_dir(&self, proj_dir: &AbsPath) -> AbsPathBuf {
+    pub fn get_cfg(&self) -> &Cfg {
         match self {
-            | Dependency::Path { path, .. } => proj_dir.join(path),
+            | Dependency::Path { cfg, .. } | Dependency::Git { cfg, .. } | Dependency::Registry { cfg, .. } => cfg,
         }
     }
 
-    pub fn get_cfg(&self) -> &Cfg {
+    /// Git urls and revisions are passed to git, so they can't start with `-` and be mistaken
+    /// for an option.
+    fn validate(&self) -> Result<()> {
+        if let Dependency::Git {
+            git, rev, tag, branch, ..
+        } = self
+        {
+            let values = [
+                ("git", Some(git)),
+                ("rev", rev.as_ref()),
+                ("tag", tag.as_ref()),
+                ("branch", branch.as_ref()),
+            ];
+
+            for (key, value) in values {
+                if let Some(value) = value.filter(|v| v.starts_with('-')) {
+                    anyhow::bail!("`{}` can't start with `-`: {}", key, value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The features enabled on the dependency and whether its default features are enabled.
+    pub fn get_features(&self) -> (&[String], bool) {
         match self {
-            | Dependency::Path { cfg, .. } => cfg,
+            | Dependency::Path {
+                features,
+                default_features,
+                ..
+            }
+            | Dependency::Git {
+                features,
+                default_features,
+                ..
+            }
+            | Dependency::Registry {
+                features,
+                default_features,
+                ..
+            } => (features, *default_features),
+        }
+    }
+}
+
+fn default_features() -> bool {
+    true
+}
+
+/// Find the root of the workspace containing the project in `dir`, which is `dir` itself when
+/// it is not a member of any workspace.
+pub fn find_workspace_root(dir: &AbsPath) -> AbsPathBuf {
+    let dir = dir.normalize();
+    let mut curr = Some(dir.as_path());
+
+    while let Some(root) = curr {
+        let manifest = fs::read_to_string(root.join(Manifest::FILE_NAME))
+            .ok()
+            .and_then(|text| Manifest::parse(&text).ok());
+
+        if let Some(Manifest {
+            workspace: Some(ws), ..
+        }) = manifest
+        {
+            if root == dir.as_path() || ws.member_dirs(root).map_or(false, |dirs| dirs.contains(&dir)) {
+                return root.to_path_buf();
+            }
+        }
+
+        curr = root.parent();
+    }
+
+    dir
+}
+
+/// Load the project and the workspace members of the manifest in `path`, returning the
+/// members of the workspace.
+pub(crate) fn load_workspace(
+    workspace: &mut Workspace,
+    vfs: &mut VirtualFileSystem,
+    sources: &mut Sources,
+    cfg: &CfgOptions,
+    path: &AbsPath,
+) -> Result<Vec<Package>> {
+    let manifest = read_manifest(vfs, path)?;
+    let mut members = Vec::new();
+
+    if manifest.project.is_some() {
+        members.push(load_manifest(workspace, vfs, sources, &manifest, cfg, path)?);
+    }
+
+    if let Some(ws) = &manifest.workspace {
+        for dir in ws.member_dirs(path)? {
+            let member = load_project(workspace, vfs, sources, cfg, &dir)
+                .with_context(|| format!("failed to load workspace member {}", dir.display()))?;
+
+            if !members.contains(&member) {
+                members.push(member);
+            }
         }
     }
+
+    if members.is_empty() {
+        anyhow::bail!("manifest has neither a [project] table nor workspace members");
+    }
+
+    Ok(members)
 }
 
 pub(crate) fn load_project(
     workspace: &mut Workspace,
     vfs: &mut VirtualFileSystem,
+    sources: &mut Sources,
     cfg: &CfgOptions,
     path: &AbsPath,
 ) -> Result<Package> {
+    let manifest = read_manifest(vfs, path)?;
+
+    load_manifest(workspace, vfs, sources, &manifest, cfg, path)
+}
+
+fn read_manifest(vfs: &mut VirtualFileSystem, path: &AbsPath) -> Result<Manifest> {
     let manifest_file = load_file(vfs, path.join(Manifest::FILE_NAME))?;
     let manifest_text = String::from_utf8_lossy(vfs.file_content(manifest_file).unwrap());
-    let manifest = Manifest::parse(&manifest_text).with_context(|| "failed to parse manifest")?;
 
-    load_manifest(workspace, vfs, &manifest, cfg, path)
+    Manifest::parse(&manifest_text).with_context(|| "failed to parse manifest")
 }
 
 fn load_manifest(
     workspace: &mut Workspace,
     vfs: &mut VirtualFileSystem,
+    sources: &mut Sources,
     manifest: &Manifest,
     cfg: &CfgOptions,
     path: &AbsPath,
 ) -> Result<Package> {
-    if let Some(package) = workspace.package_for_name(&manifest.project.name) {
+    let project = manifest.project()?;
+
+    let manifest_path = path.join(Manifest::FILE_NAME);
+
+    if let Some(package) = workspace.package_for_name(&project.name) {
+        let existing = &workspace[package].manifest_path;
+
+        if existing.normalize() != manifest_path.normalize() {
+            anyhow::bail!(
+                "package `{}` is loaded from both {} and {}",
+                project.name,
+                existing.parent().unwrap().display(),
+                path.display()
+            );
+        }
+
         return Ok(package);
     }
 
-    let src_dir = path.join(&manifest.project.src);
+    let src_dir = path.join(&project.src);
     let first_file = load_dir(vfs, &src_dir)?.ok_or_else(|| anyhow::anyhow!("empty source folder"))?;
-    let package = workspace.alloc_package(manifest, path.join(Manifest::FILE_NAME), first_file);
+    let package = workspace.alloc_package(
+        project,
+        manifest.features.clone(),
+        manifest
+            .lint_levels()
+            .with_context(|| "invalid [lints] table in manifest")?,
+        manifest.fmt.clone(),
+        manifest_path,
+        first_file,
+    );
+
+    let dep_dirs = manifest.dep_dirs(path, sources).collect::<Result<Vec<_>>>()?;
 
-    for (dep, cfg_opts) in manifest.dep_dirs(path).zip(manifest.dep_cfg_opts()) {
-        let dep = load_project(workspace, vfs, cfg, &dep)?;
+    for ((dep, cfg_opts), (features, default_features)) in dep_dirs
+        .into_iter()
+        .zip(manifest.dep_cfg_opts())
+        .zip(manifest.dep_features())
+    {
+        let dep = load_project(workspace, vfs, sources, cfg, &dep)?;
         let cfg_opts = parse_cfg(cfg_opts).ok_or(anyhow::anyhow!("invalid cfg in manifest"))?;
 
-        workspace.add_dependency(package, crate::Dependency { package: dep, cfg_opts });
+        workspace.add_dependency(package, crate::Dependency {
+            package: dep,
+            cfg_opts,
+            features: features.to_vec(),
+            default_features,
+        });
     }
 
     Ok(package)
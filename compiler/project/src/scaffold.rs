@@ -0,0 +1,203 @@
+//! Creating new projects for `fi new` and `fi init`.
+//!
+//! A new project consists of a manifest, a source file and a `.gitignore` for the target
+//! directory. Executables get a `src/main.fi` with an `@main` function, libraries get a source
+//! file named after the package. The standard libraries are added as registry dependencies, so
+//! they are looked up in `$FI_REGISTRY` or the registry in the cache directory, and otherwise
+//! resolve to the standard libraries installed with the compiler.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base_db::target::CompilerTarget;
+
+use crate::manifest::Manifest;
+use crate::sources::STD_LIBS;
+
+/// The version of the standard libraries new projects depend on.
+const STD_VERSION: &str = "0.1";
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    /// Create a library instead of an executable.
+    pub lib: bool,
+    pub target: CompilerTarget,
+}
+
+/// Check whether `name` can be used as the name of a package.
+pub fn validate_name(name: &str) -> Result<()> {
+    let first = match name.chars().next() {
+        | Some(c) => c,
+        | None => anyhow::bail!("the package name can't be empty"),
+    };
+
+    if !first.is_ascii_alphabetic() {
+        anyhow::bail!("the package name `{}` must start with a letter", name);
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|&c| !c.is_ascii_alphanumeric() && c != '-' && c != '_')
+    {
+        anyhow::bail!(
+            "invalid character `{}` in package name `{}`, only letters, digits, `-` and `_` are allowed",
+            c,
+            name
+        );
+    }
+
+    // A project can't be named after a standard library.
+    if STD_LIBS.contains(&name.to_ascii_lowercase().as_str()) {
+        anyhow::bail!("the package name `{}` is the name of a standard library", name);
+    }
+
+    Ok(())
+}
+
+/// The package name of a project created in `dir`, which is the name of the directory.
+pub fn dir_name(dir: &Path) -> Result<String> {
+    let dir = match dir.canonicalize() {
+        | Ok(dir) => dir,
+        | Err(_) => dir.to_path_buf(),
+    };
+
+    dir.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("can't infer a package name from {}", dir.display()))
+}
+
+/// Create a project named `name` in `dir`, which may already exist but must not contain a
+/// manifest. Files which already exist are left alone. Returns the paths of the created files.
+pub fn create(dir: &Path, name: &str, template: &Template) -> Result<Vec<PathBuf>> {
+    validate_name(name)?;
+
+    let manifest = dir.join(Manifest::FILE_NAME);
+
+    if manifest.exists() {
+        anyhow::bail!("{} already exists", manifest.display());
+    }
+
+    let (src_file, src_text) = source(name, template);
+    let files = [
+        (PathBuf::from(Manifest::FILE_NAME), self::manifest(name, template)),
+        (PathBuf::from("src").join(src_file), src_text),
+        (PathBuf::from(".gitignore"), String::from("/target/\n")),
+    ];
+
+    let mut created = Vec::new();
+
+    for (path, text) in files {
+        let path = dir.join(path);
+
+        if path.exists() {
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("failed to create directory: {}", parent.display()))?;
+        }
+
+        fs::write(&path, text).with_context(|| format!("failed to write file: {}", path.display()))?;
+        created.push(path);
+    }
+
+    Ok(created)
+}
+
+fn manifest(name: &str, template: &Template) -> String {
+    let output = if template.lib { "dynamic" } else { "executable" };
+
+    let dep = match template.target {
+        | CompilerTarget::Javascript => "js",
+        | CompilerTarget::Native(_) => "core",
+    };
+
+    format!(
+        "[project]\nname = \"{}\"\nversion = \"0.1.0\"\nsrc = \"src\"\noutput = \"{}\"\n\n[dependencies]\n{} = {{ version = \"{}\" }}\n",
+        name, output, dep, STD_VERSION
+    )
+}
+
+/// The name and contents of the source file of the project.
+fn source(name: &str, template: &Template) -> (String, String) {
+    let js = template.target == CompilerTarget::Javascript;
+
+    match (template.lib, js) {
+        | (false, false) => (
+            String::from("main.fi"),
+            String::from("module Main =\n\nimport Core\n\n@main\nmain :: ()\nmain = ()\n"),
+        ),
+        | (false, true) => (
+            String::from("main.fi"),
+            String::from(
+                "module Main =\n\nimport Core\nimport Js:Console (log)\n\n@main\nmain :: ()\nmain = log \"Hello, world!\"\n",
+            ),
+        ),
+        | (true, _) => (
+            format!("{}.fi", name.replace('-', "_")),
+            format!(
+                "module {} =\n\nimport Core\n\n;; Add two integers.\nadd :: Int -> Int -> Int\nadd a b = a + b\n",
+                module_name(name)
+            ),
+        ),
+    }
+}
+
+/// The module name of a package, like `MyLib` for `my-lib`.
+fn module_name(name: &str) -> String {
+    name.split(|c| c == '-' || c == '_')
+        .flat_map(|part| {
+            let mut chars = part.chars();
+
+            chars.next().map(|c| c.to_ascii_uppercase()).into_iter().chain(chars)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(name: &str) -> String {
+        validate_name(name).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn accepts_valid_names() {
+        for name in ["app", "my-app", "my_app2", "App"] {
+            assert!(validate_name(name).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_name() {
+        assert_eq!(error(""), "the package name can't be empty");
+    }
+
+    #[test]
+    fn rejects_leading_digit() {
+        assert_eq!(error("2d"), "the package name `2d` must start with a letter");
+        assert_eq!(error("-app"), "the package name `-app` must start with a letter");
+    }
+
+    #[test]
+    fn rejects_invalid_characters() {
+        assert_eq!(
+            error("my.app"),
+            "invalid character `.` in package name `my.app`, only letters, digits, `-` and `_` are allowed"
+        );
+        assert!(validate_name("my app").is_err());
+        assert!(validate_name("café").is_err());
+    }
+
+    #[test]
+    fn rejects_reserved_names() {
+        assert_eq!(
+            error("core"),
+            "the package name `core` is the name of a standard library"
+        );
+        assert_eq!(error("Std"), "the package name `Std` is the name of a standard library");
+    }
+}
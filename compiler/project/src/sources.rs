@@ -0,0 +1,719 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use paths::{AbsPath, AbsPathBuf};
+use semver::{Version, VersionReq};
+
+use crate::lockfile::{LockedPackage, Lockfile};
+use crate::manifest::{Dependency, Manifest};
+
+/// The standard libraries, which are installed with the compiler.
+pub(crate) const STD_LIBS: &[&str] = &["prim", "core", "std", "js", "libc", "wasi"];
+
+/// Resolves dependencies to the directories containing their manifests, fetching git
+/// dependencies into a cache directory and pinning every resolved source in `fi.lock`.
+///
+/// Nothing is fetched for sources which are already locked and cached, and git urls may point
+/// to a local mirror, so that resolution works without network access.
+///
+/// When `offline`, only the cache is used and the lockfile is never written, which is how the
+/// language server loads workspaces.
+#[derive(Debug)]
+pub struct Sources {
+    root_dir: AbsPathBuf,
+    cache_dir: AbsPathBuf,
+    offline: bool,
+    locked: Lockfile,
+    resolved: Lockfile,
+}
+
+impl Sources {
+    pub fn new(root_dir: &AbsPath, offline: bool) -> Result<Self> {
+        Self::with_cache_dir(root_dir, cache_dir(root_dir), offline)
+    }
+
+    fn with_cache_dir(root_dir: &AbsPath, cache_dir: AbsPathBuf, offline: bool) -> Result<Self> {
+        Ok(Self {
+            root_dir: root_dir.to_path_buf(),
+            cache_dir,
+            offline,
+            locked: Lockfile::load(root_dir)?,
+            resolved: Lockfile::default(),
+        })
+    }
+
+    /// Write the lockfile if any of the resolved sources changed.
+    pub fn finish(self) -> Result<()> {
+        if !self.offline && self.resolved != self.locked {
+            self.resolved.save(&self.root_dir)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn resolve(&mut self, name: &str, dep: &Dependency, proj_dir: &AbsPath) -> Result<AbsPathBuf> {
+        match dep {
+            | Dependency::Path { path, .. } => Ok(proj_dir.join(path)),
+            | Dependency::Git {
+                git, rev, tag, branch, ..
+            } => {
+                let (query, spec) = match (rev, tag, branch) {
+                    | (None, None, None) => (String::new(), String::from("HEAD")),
+                    | (Some(rev), None, None) => (format!("?rev={}", rev), rev.clone()),
+                    | (None, Some(tag), None) => (format!("?tag={}", tag), format!("refs/tags/{}", tag)),
+                    | (None, None, Some(branch)) => (format!("?branch={}", branch), format!("refs/heads/{}", branch)),
+                    | _ => anyhow::bail!("dependency `{}` specifies more than one of rev, tag or branch", name),
+                };
+
+                // Local paths are relative to the manifest, and are locked relative to the lockfile.
+                let (url, source) = match local_git_path(git, proj_dir) {
+                    | Some(path) => (path.display().to_string(), relative_path(&path, &self.root_dir)),
+                    | None => (git.clone(), git.clone()),
+                };
+
+                self.resolve_git(name, &url, &format!("git+{}{}", source, query), &spec)
+                    .with_context(|| format!("failed to resolve git dependency `{}`", name))
+            },
+            | Dependency::Registry { version, registry, .. } => {
+                let registry = match registry {
+                    | Some(registry) => proj_dir.join(registry),
+                    | None => self.registry_dir(),
+                };
+
+                // The standard libraries only need to be published to the registry to use
+                // another version than the one installed with the compiler.
+                let result = match self.std_lib_dir(name) {
+                    | Some(dir) if fs::metadata(registry.join(name)).is_err() => self.resolve_std(name, version, dir),
+                    | _ => self.resolve_registry(name, version, &registry),
+                };
+
+                result.with_context(|| format!("failed to resolve dependency `{}` {}", name, version))
+            },
+        }
+    }
+
+    fn resolve_git(&mut self, name: &str, url: &str, source: &str, spec: &str) -> Result<AbsPathBuf> {
+        let source = source.to_string();
+        let ident = format!("{}-{:016x}", name, hash(url));
+        let db = self.cache_dir.join("git").join("db").join(&ident);
+        let mut fetched = false;
+
+        if fs::metadata(&db).is_err() {
+            if self.offline {
+                anyhow::bail!("{} is not cached and can't be fetched offline", url);
+            }
+
+            fs::create_dir_all(db.parent().unwrap().as_ref())?;
+            git(&[
+                "clone".as_ref(),
+                "--bare".as_ref(),
+                "--quiet".as_ref(),
+                "--".as_ref(),
+                url.as_ref(),
+                db.as_os_str(),
+            ])?;
+            fetched = true;
+        }
+
+        let locked = self.locked.get(name, &source).and_then(|p| p.rev.clone());
+        let commit = match locked {
+            | Some(locked) => match rev_parse(&db, &locked) {
+                | Ok(commit) => commit,
+                | Err(_) if self.offline => {
+                    anyhow::bail!("revision {} is not cached and can't be fetched offline", locked)
+                },
+                | Err(_) => {
+                    fetch(&db)?;
+                    rev_parse(&db, &locked)?
+                },
+            },
+            | None => {
+                if !fetched && !self.offline {
+                    // The cached refs are still usable when the remote can't be reached.
+                    if let Err(e) = fetch(&db) {
+                        tracing::warn!("failed to update {}: {:#}", url, e);
+                    }
+                }
+
+                rev_parse(&db, spec)?
+            },
+        };
+
+        let checkout = self
+            .cache_dir
+            .join("git")
+            .join("checkouts")
+            .join(&ident)
+            .join(&commit[..commit.len().min(12)]);
+
+        if fs::metadata(&checkout).is_err() {
+            let result = git(&[
+                "clone".as_ref(),
+                "--quiet".as_ref(),
+                "--no-checkout".as_ref(),
+                "--".as_ref(),
+                db.as_os_str(),
+                checkout.as_os_str(),
+            ])
+            .and_then(|_| {
+                git(&[
+                    "-C".as_ref(),
+                    checkout.as_os_str(),
+                    "checkout".as_ref(),
+                    "--quiet".as_ref(),
+                    commit.as_ref(),
+                ])
+            });
+
+            if let Err(e) = result {
+                let _ = fs::remove_dir_all(&checkout);
+                return Err(e);
+            }
+        }
+
+        self.resolved.insert(LockedPackage {
+            name: name.to_string(),
+            source,
+            version: None,
+            rev: Some(commit),
+        });
+
+        Ok(checkout)
+    }
+
+    /// A directory registry contains a directory per package, which contains a directory per
+    /// published version of the package.
+    fn resolve_registry(&mut self, name: &str, req: &str, registry: &AbsPath) -> Result<AbsPathBuf> {
+        let source = format!("registry+{}", relative_path(registry, &self.root_dir));
+        let req = VersionReq::parse(req).with_context(|| format!("invalid version requirement: {}", req))?;
+        let dir = registry.join(name);
+        let locked = self
+            .locked
+            .get(name, &source)
+            .and_then(|p| p.version.as_deref())
+            .and_then(|v| Version::parse(v).ok())
+            .filter(|v| req.matches(v) && fs::metadata(dir.join(v.to_string())).is_ok());
+
+        let version = match locked {
+            | Some(version) => version,
+            | None => fs::read_dir(&dir)
+                .with_context(|| format!("package not found in registry {}", registry.display()))?
+                .filter_map(|entry| {
+                    let entry = entry.ok()?;
+
+                    if !entry.file_type().ok()?.is_dir() {
+                        return None;
+                    }
+
+                    Version::parse(entry.file_name().to_str()?).ok()
+                })
+                .filter(|v| req.matches(v))
+                .max()
+                .ok_or_else(|| anyhow::anyhow!("no matching version in registry {}", registry.display()))?,
+        };
+
+        self.resolved.insert(LockedPackage {
+            name: name.to_string(),
+            source,
+            version: Some(version.to_string()),
+            rev: None,
+        });
+
+        Ok(dir.join(version.to_string()))
+    }
+
+    fn resolve_std(&mut self, name: &str, req: &str, dir: AbsPathBuf) -> Result<AbsPathBuf> {
+        let req = VersionReq::parse(req).with_context(|| format!("invalid version requirement: {}", req))?;
+        let path = dir.join(Manifest::FILE_NAME);
+        let text = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let version = Manifest::parse(&text)?.project()?.version.clone();
+
+        if !Version::parse(&version).map_or(false, |v| req.matches(&v)) {
+            anyhow::bail!("the installed standard library is version {}", version);
+        }
+
+        self.resolved.insert(LockedPackage {
+            name: name.to_string(),
+            source: String::from("std"),
+            version: Some(version),
+            rev: None,
+        });
+
+        Ok(dir)
+    }
+
+    /// The directory of the standard library `name` installed with the compiler, which is in
+    /// `$FI_LIB` or in the `lib` directory above the running executable. This is the `lib`
+    /// directory of an installation or of the repository the compiler is built in.
+    fn std_lib_dir(&self, name: &str) -> Option<AbsPathBuf> {
+        if !STD_LIBS.contains(&name) {
+            return None;
+        }
+
+        let lib = match std::env::var_os("FI_LIB") {
+            | Some(dir) => self.root_dir.join(dir),
+            | None => {
+                let exe = std::env::current_exe().ok()?;
+                let lib = exe
+                    .ancestors()
+                    .skip(1)
+                    .map(|dir| dir.join("lib"))
+                    .find(|lib| lib.join("prim").join(Manifest::FILE_NAME).is_file())?;
+
+                self.root_dir.join(lib)
+            },
+        };
+
+        let dir = lib.join(name);
+
+        match dir.join(Manifest::FILE_NAME).is_file() {
+            | true => Some(dir),
+            | false => None,
+        }
+    }
+
+    fn registry_dir(&self) -> AbsPathBuf {
+        match std::env::var_os("FI_REGISTRY") {
+            | Some(dir) => self.root_dir.join(dir),
+            | None => self.cache_dir.join("registry"),
+        }
+    }
+}
+
+/// `$FI_HOME`, or `~/.fi` when it is not set.
+fn cache_dir(root_dir: &AbsPath) -> AbsPathBuf {
+    if let Some(dir) = std::env::var_os("FI_HOME") {
+        return root_dir.join(dir);
+    }
+
+    match std::env::var_os("HOME") {
+        | Some(home) => root_dir.join(PathBuf::from(home).join(".fi")),
+        | None => root_dir.join("target").join(".fi"),
+    }
+}
+
+/// The directory a git url refers to when it is a relative path, resolved against the directory
+/// of the manifest instead of the current directory. Urls with a scheme, absolute paths and
+/// scp-like urls such as `git@host:repo` are used as is.
+fn local_git_path(url: &str, proj_dir: &AbsPath) -> Option<AbsPathBuf> {
+    let is_remote = url.contains("://") || url.split('/').next().map_or(false, |first| first.contains(':'));
+
+    if is_remote || Path::new(url).is_absolute() {
+        None
+    } else {
+        Some(proj_dir.join(url).normalize())
+    }
+}
+
+/// `path` relative to `base`, with `/` separators so that the lockfile is the same on every
+/// platform. Paths on another drive stay absolute.
+fn relative_path(path: &AbsPath, base: &AbsPath) -> String {
+    let path = path.normalize();
+    let base = base.normalize();
+    let path = AsRef::<Path>::as_ref(&path).components().collect::<Vec<_>>();
+    let base = AsRef::<Path>::as_ref(&base).components().collect::<Vec<_>>();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    if common == 0 {
+        return path.iter().collect::<PathBuf>().display().to_string();
+    }
+
+    let parents = base[common..].iter().map(|_| String::from(".."));
+    let rest = path[common..].iter().map(|c| match c {
+        | Component::Normal(c) => c.to_string_lossy().to_string(),
+        | c => c.as_os_str().to_string_lossy().to_string(),
+    });
+    let parts = parents.chain(rest).collect::<Vec<_>>();
+
+    if parts.is_empty() {
+        String::from(".")
+    } else {
+        parts.join("/")
+    }
+}
+
+/// The 64-bit FNV-1a hash of `url`, which unlike the std hashers is the same across
+/// toolchains, so the cache directories stay valid between releases.
+fn hash(url: &str) -> u64 {
+    url.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn fetch(db: &AbsPath) -> Result<()> {
+    git(&[
+        "--git-dir".as_ref(),
+        db.as_os_str(),
+        "fetch".as_ref(),
+        "--quiet".as_ref(),
+        "origin".as_ref(),
+        "+refs/heads/*:refs/heads/*".as_ref(),
+        "+refs/tags/*:refs/tags/*".as_ref(),
+    ])
+    .map(|_| ())
+}
+
+fn rev_parse(db: &AbsPath, spec: &str) -> Result<String> {
+    git(&[
+        "--git-dir".as_ref(),
+        db.as_os_str(),
+        "rev-parse".as_ref(),
+        "--verify".as_ref(),
+        "--quiet".as_ref(),
+        "--end-of-options".as_ref(),
+        format!("{}^{{commit}}", spec).as_ref(),
+    ])
+    .with_context(|| format!("revision not found: {}", spec))
+}
+
+fn git(args: &[&OsStr]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .with_context(|| "failed to run git")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        root: AbsPathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = tempfile::TempDir::new().unwrap();
+            let root = AbsPathBuf::assert(dir.path().to_path_buf());
+
+            fs::create_dir_all(root.join("project")).unwrap();
+            Self { _dir: dir, root }
+        }
+
+        fn project(&self) -> AbsPathBuf {
+            self.root.join("project")
+        }
+
+        fn sources(&self) -> Sources {
+            Sources::with_cache_dir(&self.project(), self.root.join("cache"), false).unwrap()
+        }
+
+        fn offline_sources(&self) -> Sources {
+            Sources::with_cache_dir(&self.project(), self.root.join("cache"), true).unwrap()
+        }
+
+        /// Create a package with a single source file.
+        fn package(&self, dir: &str, name: &str) {
+            let dir = self.root.join(dir);
+
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(
+                dir.join("fi.toml"),
+                format!("[project]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+            )
+            .unwrap();
+            fs::write(dir.join("src").join("lib.fi"), "module Lib =\n").unwrap();
+        }
+
+        /// Create a git repository containing a package, which serves as a local mirror.
+        fn mirror(&self, name: &str) -> AbsPathBuf {
+            let repo = self.root.join(name);
+
+            fs::create_dir_all(&repo).unwrap();
+            git(&["init".as_ref(), "--quiet".as_ref(), repo.as_os_str()]).unwrap();
+            self.commit(&repo, "0.1.0");
+            repo
+        }
+
+        fn commit(&self, repo: &AbsPath, version: &str) -> String {
+            let manifest = format!("[project]\nname = \"dep\"\nversion = \"{}\"\n", version);
+
+            fs::write(repo.join("fi.toml"), manifest).unwrap();
+            repo_git(repo, &["add", "fi.toml"]);
+            repo_git(repo, &["commit", "--quiet", "-m", version]);
+            repo_git(repo, &["rev-parse", "HEAD"])
+        }
+    }
+
+    fn repo_git(repo: &AbsPath, args: &[&str]) -> String {
+        let mut all = vec![
+            "-C".as_ref(),
+            repo.as_os_str(),
+            "-c".as_ref(),
+            "user.name=fi".as_ref(),
+            "-c".as_ref(),
+            "user.email=fi@example.com".as_ref(),
+        ];
+
+        all.extend(args.iter().map(OsStr::new));
+        git(&all).unwrap()
+    }
+
+    fn git_dep(url: &AbsPath) -> Dependency {
+        Dependency::Git {
+            git: url.display().to_string(),
+            rev: None,
+            tag: None,
+            branch: None,
+            cfg: Default::default(),
+            features: Vec::new(),
+            default_features: true,
+        }
+    }
+
+    fn manifest_version(dir: &AbsPath) -> String {
+        fs::read_to_string(dir.join("fi.toml"))
+            .unwrap()
+            .lines()
+            .find_map(|l| l.strip_prefix("version = "))
+            .unwrap()
+            .trim_matches('"')
+            .to_string()
+    }
+
+    #[test]
+    fn git_dependency_is_locked_to_commit() {
+        let fixture = Fixture::new();
+        let mirror = fixture.mirror("mirror");
+        let first = repo_git(&mirror, &["rev-parse", "HEAD"]);
+        let dep = git_dep(&mirror);
+        let mut sources = fixture.sources();
+        let dir = sources.resolve("dep", &dep, &fixture.project()).unwrap();
+
+        assert_eq!(manifest_version(&dir), "0.1.0");
+        sources.finish().unwrap();
+
+        let lockfile = Lockfile::load(&fixture.project()).unwrap();
+        let source = format!("git+{}", mirror.display());
+
+        assert_eq!(lockfile.get("dep", &source).unwrap().rev.as_deref(), Some(&*first));
+
+        // A new commit in the mirror is not used while the lockfile pins the old one.
+        fixture.commit(&mirror, "0.2.0");
+
+        let mut sources = fixture.sources();
+        let dir = sources.resolve("dep", &dep, &fixture.project()).unwrap();
+
+        assert_eq!(manifest_version(&dir), "0.1.0");
+    }
+
+    #[test]
+    fn git_dependency_without_lock_uses_head() {
+        let fixture = Fixture::new();
+        let mirror = fixture.mirror("mirror");
+
+        fixture.commit(&mirror, "0.2.0");
+
+        let mut sources = fixture.sources();
+        let dir = sources.resolve("dep", &git_dep(&mirror), &fixture.project()).unwrap();
+
+        assert_eq!(manifest_version(&dir), "0.2.0");
+    }
+
+    #[test]
+    fn registry_source_is_relative() {
+        let fixture = Fixture::new();
+        let registry = fixture.root.join("registry");
+
+        for version in ["0.1.0", "0.1.3", "0.2.0"] {
+            fs::create_dir_all(registry.join("dep").join(version)).unwrap();
+        }
+
+        let dep = Dependency::Registry {
+            version: String::from("^0.1"),
+            registry: Some(PathBuf::from("../registry")),
+            cfg: Default::default(),
+            features: Vec::new(),
+            default_features: true,
+        };
+
+        let mut sources = fixture.sources();
+        let dir = sources.resolve("dep", &dep, &fixture.project()).unwrap();
+
+        assert_eq!(dir, registry.join("dep").join("0.1.3"));
+        sources.finish().unwrap();
+
+        let lockfile = Lockfile::load(&fixture.project()).unwrap();
+        let locked = lockfile.get("dep", "registry+../registry").unwrap();
+
+        assert_eq!(locked.version.as_deref(), Some("0.1.3"));
+    }
+
+    #[test]
+    fn standard_library_resolves_to_the_installed_one() {
+        let fixture = Fixture::new();
+        let dep = Dependency::Registry {
+            version: String::from("0.1"),
+            registry: None,
+            cfg: Default::default(),
+            features: Vec::new(),
+            default_features: true,
+        };
+
+        let mut sources = fixture.sources();
+        let dir = sources.resolve("core", &dep, &fixture.project()).unwrap();
+        let lib = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../lib/core");
+
+        assert_eq!(dir.as_ref().canonicalize().unwrap(), lib.canonicalize().unwrap());
+        sources.finish().unwrap();
+
+        let lockfile = Lockfile::load(&fixture.project()).unwrap();
+
+        assert_eq!(lockfile.get("core", "std").unwrap().version.as_deref(), Some("0.1.0"));
+    }
+
+    #[test]
+    fn relative_paths() {
+        let base = AbsPathBuf::assert(PathBuf::from("/work/project"));
+        let rel = |p: &str| relative_path(&AbsPathBuf::assert(PathBuf::from(p)), &base);
+
+        assert_eq!(rel("/work/project"), ".");
+        assert_eq!(rel("/work/project/vendor/registry"), "vendor/registry");
+        assert_eq!(rel("/work/registry"), "../registry");
+        assert_eq!(rel("/home/user/.fi/registry"), "../../home/user/.fi/registry");
+    }
+
+    #[test]
+    fn offline_uses_the_cache_without_locking() {
+        let fixture = Fixture::new();
+        let mirror = fixture.mirror("mirror");
+        let dep = git_dep(&mirror);
+        let mut sources = fixture.offline_sources();
+
+        // Nothing is cached yet, so the dependency can't be resolved.
+        assert!(sources.resolve("dep", &dep, &fixture.project()).is_err());
+
+        let mut sources = fixture.sources();
+
+        sources.resolve("dep", &dep, &fixture.project()).unwrap();
+        fs::remove_dir_all(&mirror).unwrap();
+
+        // The cached clone is used even though the mirror is gone, and the lockfile is not written.
+        let mut sources = fixture.offline_sources();
+        let dir = sources.resolve("dep", &dep, &fixture.project()).unwrap();
+
+        assert_eq!(manifest_version(&dir), "0.1.0");
+        sources.finish().unwrap();
+        assert!(fs::metadata(fixture.project().join(Lockfile::FILE_NAME)).is_err());
+    }
+
+    #[test]
+    fn option_like_git_arguments_are_rejected() {
+        let manifest = "[project]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\ndep = { git = \
+                        \"--upload-pack=touch pwned\" }\n";
+        let err = Manifest::parse(manifest).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("`git` can't start with `-`"), "{:#}", err);
+
+        let manifest = "[project]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\ndep = { git = \
+                        \"../dep\", branch = \"--help\" }\n";
+
+        assert!(Manifest::parse(manifest).is_err());
+
+        // Dependencies which don't come from a manifest are still passed to git as operands.
+        let fixture = Fixture::new();
+        let pwned = fixture.root.join("pwned");
+        let dep = Dependency::Git {
+            git: format!("--upload-pack=touch {}", pwned.display()),
+            rev: None,
+            tag: None,
+            branch: None,
+            cfg: Default::default(),
+            features: Vec::new(),
+            default_features: true,
+        };
+
+        assert!(fixture.sources().resolve("dep", &dep, &fixture.project()).is_err());
+        assert!(fs::metadata(&pwned).is_err());
+    }
+
+    #[test]
+    fn same_name_from_different_sources_is_an_error() {
+        let fixture = Fixture::new();
+
+        fixture.package("project", "app");
+        fixture.package("a", "dep");
+        fixture.package("b", "dep");
+        fs::write(
+            fixture.project().join("fi.toml"),
+            "[project]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\na = { path = \"../a\" }\nb = { \
+             path = \"../b\" }\n",
+        )
+        .unwrap();
+
+        let err = crate::Workspace::load(
+            fixture.project(),
+            &mut vfs::VirtualFileSystem::default(),
+            &Default::default(),
+        )
+        .unwrap_err();
+
+        assert!(
+            format!("{:#}", err).contains("package `dep` is loaded from both"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn relative_git_url_is_relative_to_the_manifest() {
+        let fixture = Fixture::new();
+
+        fixture.mirror("mirror");
+
+        let dep = Dependency::Git {
+            git: String::from("../mirror"),
+            rev: None,
+            tag: None,
+            branch: None,
+            cfg: Default::default(),
+            features: Vec::new(),
+            default_features: true,
+        };
+
+        // From the parent of the project `../mirror` is another directory.
+        let cwd = std::env::current_dir().unwrap();
+
+        std::env::set_current_dir(&fixture.root).unwrap();
+
+        let mut sources = fixture.sources();
+        let dir = sources.resolve("dep", &dep, &fixture.project());
+
+        std::env::set_current_dir(cwd).unwrap();
+        assert_eq!(manifest_version(&dir.unwrap()), "0.1.0");
+        sources.finish().unwrap();
+
+        let lockfile = Lockfile::load(&fixture.project()).unwrap();
+
+        assert!(lockfile.get("dep", "git+../mirror").is_some());
+    }
+
+    #[test]
+    fn remote_git_urls_are_not_paths() {
+        let proj_dir = AbsPathBuf::assert(PathBuf::from("/work/project"));
+
+        assert_eq!(local_git_path("https://example.com/dep.git", &proj_dir), None);
+        assert_eq!(local_git_path("git@example.com:dep.git", &proj_dir), None);
+        assert_eq!(local_git_path("/work/mirror", &proj_dir), None);
+        assert_eq!(
+            local_git_path("../mirror", &proj_dir),
+            Some(AbsPathBuf::assert(PathBuf::from("/work/mirror")))
+        );
+    }
+
+    #[test]
+    fn url_hash_is_stable() {
+        assert_eq!(hash(""), 0xcbf29ce484222325);
+        assert_eq!(hash("https://example.com/dep.git"), 0x53decc189908cd67);
+    }
+}
@@ -1,23 +1,23 @@
-function toString(x) {
+export function toString(x) {
   return x.toString();
 }
 
-function push(a, x) {
+export function push(a, x) {
   a.push(x);
   return a;
 }
 
-function concatString(a, b) {
+export function concatString(a, b) {
   return a + b.toString();
 }
 
-function pureEffect(x) {
+export function pureEffect(x) {
   return function() {
     return x;
   };
 }
 
-function bindEffect(a) {
+export function bindEffect(a) {
   return function(f) {
     return function() {
       return f(a())();
@@ -25,8 +25,10 @@ function bindEffect(a) {
   };
 }
 
-function reportEffect(r) {
+export function reportEffect(r) {
   return function(e) {
     return r.report(e());
   };
 }
+
+export const document = globalThis.document;